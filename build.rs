@@ -0,0 +1,9 @@
+//! Generates the UniFFI scaffolding for the mobile feature
+#[cfg(feature = "mobile")]
+extern crate uniffi;
+
+fn main() {
+    #[cfg(feature = "mobile")]
+    uniffi::generate_scaffolding("uniffi/blindsign.udl")
+        .expect("failed to generate the UniFFI scaffolding");
+}
@@ -0,0 +1,53 @@
+//! Runs a blind signature session between a signer and a requester over a
+//! local tokio TCP connection, driving the sans-IO state machines with
+//! TokioTransport from blocking threads.
+//!
+//! cargo run --example tokio_tcp --features tokio-transport
+extern crate blindsign;
+extern crate sha3;
+extern crate tokio;
+
+use blindsign::{
+    keypair::BlindKeypair,
+    transport::{run_requester, run_signer, TokioTransport},
+};
+use sha3::Sha3_512;
+use std::{net, thread};
+use tokio::{
+    net::TcpStream,
+    runtime::{Handle, Runtime},
+};
+
+fn main() {
+    let runtime = Runtime::new().expect("failed to start the runtime");
+    let keypair = BlindKeypair::generate().expect("failed to generate the keypair");
+    let xs = keypair.signing_key();
+
+    let listener = net::TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+    let addr = listener.local_addr().expect("failed to get the address");
+
+    let handle = runtime.handle().clone();
+    let signer = thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("failed to accept");
+        let stream = register(stream, &handle);
+        run_signer(&mut TokioTransport::new(stream, handle.clone()), &xs)
+    });
+
+    let handle = runtime.handle().clone();
+    let stream = net::TcpStream::connect(addr).expect("failed to connect");
+    let mut transport = TokioTransport::new(register(stream, &handle), handle.clone());
+    let sig = run_requester::<Sha3_512, _, _>(&mut transport, b"message")
+        .expect("requester failed");
+    signer.join().expect("signer panicked").expect("signer failed");
+
+    assert!(sig.msg_authenticate::<Sha3_512, _>(&keypair.verifying_key(), b"message"));
+    println!("received a valid blind signature over tokio TCP");
+}
+
+/// Registers a std stream with the runtime, which must be done within its
+/// context
+fn register(stream: net::TcpStream, handle: &Handle) -> TcpStream {
+    let _guard = handle.enter();
+    stream.set_nonblocking(true).expect("failed to set nonblocking");
+    TcpStream::from_std(stream).expect("failed to register the stream")
+}
@@ -0,0 +1,52 @@
+/*
+ * C declarations for the blindsign ffi feature, see src/ffi.rs.
+ *
+ * Wired keys, R', e' and S' are 32 bytes, and unblinded signatures are 96
+ * bytes. The hash algorithm is SHA3-512.
+ */
+#ifndef BLINDSIGN_H
+#define BLINDSIGN_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define BLINDSIGN_OK 0
+#define BLINDSIGN_ERR_NULL (-1)
+#define BLINDSIGN_ERR_RNG (-2)
+#define BLINDSIGN_ERR_MALFORMED (-3)
+#define BLINDSIGN_ERR_FAILED (-4)
+
+typedef struct BlindKeypair BlindKeypair;
+typedef struct BlindSession BlindSession;
+typedef struct BlindRequest BlindRequest;
+
+BlindKeypair *blindsign_keypair_generate(void);
+BlindKeypair *blindsign_keypair_from_wired(const uint8_t *private_key,
+                                           const uint8_t *public_key);
+int32_t blindsign_keypair_public(const BlindKeypair *keypair, uint8_t *out);
+int32_t blindsign_keypair_private(const BlindKeypair *keypair, uint8_t *out);
+void blindsign_keypair_free(BlindKeypair *keypair);
+
+BlindSession *blindsign_session_new(uint8_t *rp_out);
+int32_t blindsign_sign_ep(BlindSession *session, const BlindKeypair *keypair,
+                          const uint8_t *ep, uint8_t *sp_out);
+void blindsign_session_free(BlindSession *session);
+
+BlindRequest *blindsign_request_new(const uint8_t *rp, const uint8_t *msg,
+                                    size_t msg_len, uint8_t *ep_out);
+int32_t blindsign_request_unblind(BlindRequest *request, const uint8_t *sp,
+                                  uint8_t *sig_out);
+void blindsign_request_free(BlindRequest *request);
+
+int32_t blindsign_authenticate(const uint8_t *public_key, const uint8_t *sig,
+                               const uint8_t *msg, size_t msg_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,263 @@
+//! Self-signed announcements of issuer keys
+//!
+//! Clients fetch issuer keys and their metadata from mirrors or caches which
+//! may not be trusted. A KeyAnnouncement binds the verifying key to its
+//! validity window, denomination value and usage label with a plain Schnorr
+//! signature by the key itself, so that a mirror cannot alter the metadata of
+//! a key, such as extending its validity or changing its value, without it
+//! failing to verify.
+//!
+//! The self-signature is a plain Schnorr signature in the UnblindedSigData
+//! format, with the body being public key || value || not_before ||
+//! not_after || usage, and the integers being 8 byte big endian. Its
+//! challenge is H("blindsign key announcement" || Q || R || body) rather than
+//! the H(R || m) of blind signatures, so that no blind signing session can
+//! produce a valid announcement. The published form is the 96 byte wired
+//! signature followed by the body.
+//!
+//! # Note
+//!
+//! The self-signature proves only that the announcement was made by the
+//! holder of the private key, not that the key belongs to the issuer, as a
+//! mirror can announce a key of its own. Clients must still pin the issuer key,
+//! for example by its fingerprint, obtained over a trusted channel.
+//!
+//! Validity windows are expressed in whatever unit of time the caller uses
+//! for `now`, as with KeySet.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT, ristretto::RistrettoPoint, scalar::Scalar,
+};
+use denominations::Denomination;
+use digest::Digest;
+use keypair::{BlindKeypair, VerifyingKey, WiredPublicKey};
+use rng;
+use signature::{UnblindedSigData, WiredUnblindedSigData};
+use typenum::U64;
+use Error::{KeyAnnouncementInvalid, WiredLengthInvalid};
+
+/// The domain separation prefix for the self-signature challenge
+const ANNOUNCEMENT_DOMAIN: &[u8] = b"blindsign key announcement";
+
+/// The length of the wired signature
+const SIG_LEN: usize = 96;
+
+/// The length of the body before the usage label
+const FIXED_LEN: usize = 56;
+
+/// A verifying key along with its metadata, self-signed by the key.
+#[derive(Clone, Debug)]
+pub struct KeyAnnouncement {
+    key: VerifyingKey,
+    value: u64,
+    not_before: u64,
+    not_after: u64,
+    usage: Vec<u8>,
+    sig: UnblindedSigData,
+}
+
+impl KeyAnnouncement {
+    /// Announces the public key of the keypair with the provided metadata,
+    /// with the key being valid from not_before up to and including
+    /// not_after.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for the self-signature
+    ///
+    /// # Returns
+    ///
+    /// * Ok(KeyAnnouncement) on success.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * R = r*P, with r random
+    /// * e = H(domain || Q || R || body)
+    /// * S = e*Xs + r
+    pub fn new<H, U>(
+        keypair: &BlindKeypair,
+        value: u64,
+        not_before: u64,
+        not_after: u64,
+        usage: U,
+    ) -> ::Result<Self>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        U: AsRef<[u8]>,
+    {
+        let key = keypair.verifying_key();
+        let usage = usage.as_ref().to_vec();
+        let body = body(&key, value, not_before, not_after, &usage);
+        let r = Scalar::random(&mut rng::try_new()?);
+        let big_r = r * RISTRETTO_BASEPOINT_POINT;
+        let e = challenge::<H>(&key, &big_r, &body);
+        Ok(KeyAnnouncement {
+            key,
+            value,
+            not_before,
+            not_after,
+            usage,
+            sig: UnblindedSigData::new(e, e * keypair.private() + r, big_r),
+        })
+    }
+
+    /// Returns the announced verifying key
+    pub fn key(&self) -> &VerifyingKey {
+        &self.key
+    }
+
+    /// Returns the value of the denomination the key signs
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// Returns the start of the validity window
+    pub fn not_before(&self) -> u64 {
+        self.not_before
+    }
+
+    /// Returns the end of the validity window, inclusive
+    pub fn not_after(&self) -> u64 {
+        self.not_after
+    }
+
+    /// Returns the usage label of the key, as set by the issuer
+    pub fn usage(&self) -> &[u8] {
+        &self.usage
+    }
+
+    /// Returns true if the key is valid at the provided time
+    pub fn is_valid_at(&self, now: u64) -> bool {
+        self.not_before <= now && now <= self.not_after
+    }
+
+    /// Returns the announced key as a Denomination, for inserting into a
+    /// Registry once verified.
+    pub fn denomination(&self) -> Denomination {
+        Denomination::new(self.key, self.value, self.not_before, self.not_after)
+    }
+
+    /// Verifies the self-signature under the announced key.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for the self-signature
+    ///
+    /// # Returns
+    ///
+    /// * Ok(()) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the announcement was
+    /// altered, or was not signed by the announced key.
+    ///
+    /// # Mathematics
+    ///
+    /// * e == H(domain || Q || R || body)
+    /// * S*P == e*Q + R
+    pub fn verify<H>(&self) -> ::Result<()>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let e = challenge::<H>(&self.key, &self.sig.r(), &self.body());
+        if e != self.sig.e() || !self.sig.authenticate(&self.key) {
+            return Err(KeyAnnouncementInvalid);
+        }
+        Ok(())
+    }
+
+    /// Returns the announcement in its published form, signature || body.
+    pub fn publish(&self) -> Vec<u8> {
+        let mut wired = WiredUnblindedSigData::from(self.sig).0.to_vec();
+        wired.extend_from_slice(&self.body());
+        wired
+    }
+
+    /// Loads an announcement from its published form, without verifying it.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(KeyAnnouncement) on success, which must then be verified.
+    ///
+    /// * Err(::Error) on failure, which indicates that the input was too
+    /// short, or that the signature or key was malformed.
+    pub fn parse(wired: &[u8]) -> ::Result<Self> {
+        if wired.len() < SIG_LEN + FIXED_LEN {
+            return Err(WiredLengthInvalid {
+                expected: SIG_LEN + FIXED_LEN,
+                actual: wired.len(),
+            });
+        }
+        let mut sig = [0; SIG_LEN];
+        sig.copy_from_slice(&wired[..SIG_LEN]);
+        let body = &wired[SIG_LEN..];
+        Ok(KeyAnnouncement {
+            key: VerifyingKey::from_wired(WiredPublicKey::from_bytes(&body[0..32])?)?,
+            value: be_u64(&body[32..40]),
+            not_before: be_u64(&body[40..48]),
+            not_after: be_u64(&body[48..56]),
+            usage: body[FIXED_LEN..].to_vec(),
+            sig: WiredUnblindedSigData(sig).to_internal_format()?,
+        })
+    }
+
+    /// Loads an announcement from its published form and verifies it, as
+    /// with parse followed by verify.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for the self-signature
+    pub fn parse_verified<H>(wired: &[u8]) -> ::Result<Self>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let announcement = KeyAnnouncement::parse(wired)?;
+        announcement.verify::<H>()?;
+        Ok(announcement)
+    }
+
+    /// Returns public key || value || not_before || not_after || usage
+    fn body(&self) -> Vec<u8> {
+        body(
+            &self.key,
+            self.value,
+            self.not_before,
+            self.not_after,
+            &self.usage,
+        )
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Encodes public key || value || not_before || not_after || usage
+fn body(key: &VerifyingKey, value: u64, not_before: u64, not_after: u64, usage: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(FIXED_LEN + usage.len());
+    body.extend_from_slice(&key.wired());
+    body.extend_from_slice(&value.to_be_bytes());
+    body.extend_from_slice(&not_before.to_be_bytes());
+    body.extend_from_slice(&not_after.to_be_bytes());
+    body.extend_from_slice(usage);
+    body
+}
+
+/// Computes the self-signature challenge H(domain || Q || R || body)
+fn challenge<H>(key: &VerifyingKey, r: &RistrettoPoint, body: &[u8]) -> Scalar
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut hasher = H::default();
+    hasher.input(ANNOUNCEMENT_DOMAIN);
+    hasher.input(&key.wired());
+    hasher.input(r.compress().as_bytes());
+    hasher.input(body);
+    Scalar::from_hash(hasher)
+}
+
+/// Reads an 8 byte big endian integer
+fn be_u64(bytes: &[u8]) -> u64 {
+    let mut arr = [0; 8];
+    arr.copy_from_slice(bytes);
+    u64::from_be_bytes(arr)
+}
@@ -0,0 +1,320 @@
+//! A hash chained audit log of signer side sessions
+//!
+//! The AuditLog records an entry for each blind signature issued, with its
+//! timestamp, the id of the signing key, the session id and a hash of e',
+//! which is enough for accounting of issuance without revealing anything
+//! about the messages signed, as e' is blinded. Each entry includes the hash
+//! of the previous one, so that the exported log can be verified to be
+//! complete and unaltered up to its head hash, which the signer may publish
+//! or countersign periodically.
+//!
+//! The AuditedSigner wraps a SessionManager, recording an entry each time a
+//! session is finished successfully.
+//!
+//! # Note
+//!
+//! Timestamps are expressed in whatever unit of time the caller uses for
+//! `now`, as with KeySet. The same hash algorithm must be used for every
+//! entry of a log.
+use digest::Digest;
+use request::WiredEPrime;
+use session::{SessionId, SessionManager, WiredRPrime, WiredSPrime};
+use std::sync::{Mutex, MutexGuard};
+use typenum::U64;
+use Error::{AuditLogInvalid, WiredLengthInvalid};
+
+/// The domain separation prefix for hashing e'
+const EP_DOMAIN: &[u8] = b"blindsign audit e'";
+
+/// The domain separation prefix for hashing entries
+const ENTRY_DOMAIN: &[u8] = b"blindsign audit entry";
+
+/// The length of an entry in wired form
+pub const ENTRY_LEN: usize = 124;
+
+/// An entry of the audit log
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AuditEntry {
+    timestamp: u64,
+    key_id: u32,
+    session: SessionId,
+    ep_hash: [u8; 32],
+    prev: [u8; 32],
+    hash: [u8; 32],
+}
+
+impl AuditEntry {
+    /// Returns the time the session was finished
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Returns the id of the signing key
+    pub fn key_id(&self) -> u32 {
+        self.key_id
+    }
+
+    /// Returns the session id
+    pub fn session(&self) -> SessionId {
+        self.session
+    }
+
+    /// Returns the hash of the signed e'
+    pub fn ep_hash(&self) -> [u8; 32] {
+        self.ep_hash
+    }
+
+    /// Returns the hash of the previous entry, or zeroes for the first entry
+    pub fn prev(&self) -> [u8; 32] {
+        self.prev
+    }
+
+    /// Returns the hash of this entry
+    pub fn hash(&self) -> [u8; 32] {
+        self.hash
+    }
+
+    /// Returns the entry in wired form, timestamp || key id || session id ||
+    /// e' hash || previous hash || hash, with the integers being big endian
+    pub fn wired(&self) -> [u8; ENTRY_LEN] {
+        let mut arr = [0; ENTRY_LEN];
+        arr[0..92].copy_from_slice(&self.body());
+        arr[92..124].copy_from_slice(&self.hash);
+        arr
+    }
+
+    /// Loads an entry from its wired form, without verifying its hash.
+    pub fn from_wired(wired: &[u8; ENTRY_LEN]) -> Self {
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&wired[0..8]);
+        let mut key_id = [0; 4];
+        key_id.copy_from_slice(&wired[8..12]);
+        let mut session = SessionId([0; 16]);
+        session.0.copy_from_slice(&wired[12..28]);
+        AuditEntry {
+            timestamp: u64::from_be_bytes(timestamp),
+            key_id: u32::from_be_bytes(key_id),
+            session,
+            ep_hash: array32(&wired[28..60]),
+            prev: array32(&wired[60..92]),
+            hash: array32(&wired[92..124]),
+        }
+    }
+
+    /// Returns true if the hash of the entry is correct
+    pub fn verify<H>(&self) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        entry_hash::<H>(&self.body()) == self.hash
+    }
+
+    /// Encodes every field except the hash
+    fn body(&self) -> [u8; 92] {
+        let mut arr = [0; 92];
+        arr[0..8].copy_from_slice(&self.timestamp.to_be_bytes());
+        arr[8..12].copy_from_slice(&self.key_id.to_be_bytes());
+        arr[12..28].copy_from_slice(&self.session.0);
+        arr[28..60].copy_from_slice(&self.ep_hash);
+        arr[60..92].copy_from_slice(&self.prev);
+        arr
+    }
+}
+
+/// An append only, hash chained log of issued signatures, which may be
+/// shared between threads.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl AuditLog {
+    /// Creates an empty log
+    pub fn new() -> Self {
+        AuditLog {
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Appends an entry for the session, chained to the current head.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for hashing e' and the entries
+    pub fn record<H>(
+        &self,
+        timestamp: u64,
+        key_id: u32,
+        session: SessionId,
+        ep: &WiredEPrime,
+    ) -> AuditEntry
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut entries = self.entries();
+        let mut entry = AuditEntry {
+            timestamp,
+            key_id,
+            session,
+            ep_hash: hash32::<H>(&[EP_DOMAIN, ep.as_bytes()]),
+            prev: entries.last().map_or([0; 32], |last| last.hash),
+            hash: [0; 32],
+        };
+        entry.hash = entry_hash::<H>(&entry.body());
+        entries.push(entry);
+        entry
+    }
+
+    /// Returns the hash of the last entry, or zeroes if the log is empty
+    pub fn head(&self) -> [u8; 32] {
+        self.entries().last().map_or([0; 32], |last| last.hash)
+    }
+
+    /// Returns the number of entries
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Returns true if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    /// Returns a copy of every entry, oldest first
+    pub fn snapshot(&self) -> Vec<AuditEntry> {
+        self.entries().clone()
+    }
+
+    /// Exports the log as the concatenation of the wired form of every
+    /// entry, oldest first.
+    pub fn export(&self) -> Vec<u8> {
+        self.entries()
+            .iter()
+            .flat_map(|entry| entry.wired().to_vec())
+            .collect()
+    }
+
+    /// Locks the entries. The log is never left in an inconsistent state, so
+    /// it remains usable even if another thread panicked while holding the
+    /// lock.
+    fn entries(&self) -> MutexGuard<Vec<AuditEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Verifies an exported log, checking the hash of every entry and that each
+/// is chained to the one before it.
+///
+/// # Returns
+///
+/// * Ok((Vec<AuditEntry>, [u8; 32])) on success, with the [u8; 32] being the
+/// head hash of the log, for comparing against a published head.
+///
+/// * Err(::Error) on failure, which indicates that the length was not a
+/// multiple of the entry length, or the index of the first entry whose hash
+/// or chaining was wrong.
+pub fn verify_export<H>(bytes: &[u8]) -> ::Result<(Vec<AuditEntry>, [u8; 32])>
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    if bytes.len() % ENTRY_LEN != 0 {
+        return Err(WiredLengthInvalid {
+            expected: bytes.len() / ENTRY_LEN * ENTRY_LEN,
+            actual: bytes.len(),
+        });
+    }
+    let mut head = [0; 32];
+    let mut entries = Vec::with_capacity(bytes.len() / ENTRY_LEN);
+    for (i, chunk) in bytes.chunks(ENTRY_LEN).enumerate() {
+        let mut wired = [0; ENTRY_LEN];
+        wired.copy_from_slice(chunk);
+        let entry = AuditEntry::from_wired(&wired);
+        if entry.prev != head || !entry.verify::<H>() {
+            return Err(AuditLogInvalid(i));
+        }
+        head = entry.hash;
+        entries.push(entry);
+    }
+    Ok((entries, head))
+}
+
+/// A signer wrapping a SessionManager, which records every signature it
+/// issues in an AuditLog.
+pub struct AuditedSigner {
+    sessions: SessionManager,
+    log: AuditLog,
+    key_id: u32,
+}
+
+impl AuditedSigner {
+    /// Creates a signer starting sessions with the provided manager, whose
+    /// signing key is recorded in the log under key_id.
+    pub fn new(sessions: SessionManager, key_id: u32) -> Self {
+        AuditedSigner {
+            sessions,
+            log: AuditLog::new(),
+            key_id,
+        }
+    }
+
+    /// Returns the audit log
+    pub fn log(&self) -> &AuditLog {
+        &self.log
+    }
+
+    /// Starts a new session at the provided time, as for
+    /// SessionManager::start.
+    pub fn start(&self, now: u64) -> ::Result<(SessionId, WiredRPrime)> {
+        self.sessions.start(now)
+    }
+
+    /// Finishes the session with the provided id, as for
+    /// SessionManager::finish, recording an entry in the log if signing
+    /// succeeds.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for the log
+    pub fn finish<H>(&self, id: &SessionId, ep: &WiredEPrime, now: u64) -> ::Result<WiredSPrime>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let sp = self.sessions.finish(id, ep)?;
+        self.log.record::<H>(now, self.key_id, *id, ep);
+        Ok(sp)
+    }
+
+    /// Aborts the session with the provided id, as for SessionManager::abort.
+    pub fn abort(&self, id: &SessionId) -> bool {
+        self.sessions.abort(id)
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Hashes the inputs to 32 bytes
+fn hash32<H>(inputs: &[&[u8]]) -> [u8; 32]
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut hasher = H::default();
+    for input in inputs {
+        hasher.input(input);
+    }
+    array32(&hasher.result()[..32])
+}
+
+/// Hashes the body of an entry
+fn entry_hash<H>(body: &[u8; 92]) -> [u8; 32]
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    hash32::<H>(&[ENTRY_DOMAIN, body])
+}
+
+/// Copies a 32 byte slice into an array
+fn array32(bytes: &[u8]) -> [u8; 32] {
+    let mut arr = [0; 32];
+    arr.copy_from_slice(bytes);
+    arr
+}
@@ -0,0 +1,336 @@
+//! Anonymous voting with blindly signed ballots
+//!
+//! An election authority authenticates each eligible voter and blindly signs
+//! one ballot for them, without seeing the choice on it. The voters then cast
+//! their signed ballots anonymously, and the tally verifies each ballot
+//! against the authority key, counting it unless its nullifier shows that a
+//! ballot with the same serial number was already cast.
+//!
+//! 1. The voter prepares a Ballot for the election and their choice.
+//! 2. The authority starts an ordinary BlindSession for the voter, after
+//!    checking their eligibility, sending R' to the voter.
+//! 3. The voter blinds the ballot with BallotRequest::new, sending e' to the
+//!    authority, which signs it with BlindSession::sign_ep.
+//! 4. The voter unblinds S' into a SignedBallot, and casts it anonymously.
+//! 5. The tally counts the SignedBallot with Tally::cast.
+//!
+//! # Security
+//!
+//! The authority must sign at most one ballot per voter, and voters must cast
+//! their ballots over an anonymous channel, separately from being signed, for
+//! the votes to be unlinkable to the voters.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use digest::Digest;
+use envelope::PayloadType;
+use keypair::VerifyingKey;
+use rand::RngCore;
+use request::{BlindRequest, WiredEPrime};
+use rng;
+use session::{WiredRPrime, WiredSPrime};
+use signature::{UnblindedSigData, WiredUnblindedSigData};
+use spent::{Nullifier, SpentStore};
+use std::collections::BTreeMap;
+use typenum::U64;
+use Error::{AlreadySpent, BallotElectionMismatch, BallotSignatureInvalid};
+
+/// The domain separation prefix of the messages signed for ballots
+const BALLOT_DOMAIN: &[u8] = b"blindsign ballot";
+
+/// The domain separation prefix for deriving the nullifiers of ballots
+const NULLIFIER_DOMAIN: &[u8] = b"blindsign ballot nullifier";
+
+/// The length of a wired ballot, election || choice || serial
+const BALLOT_LEN: usize = 44;
+
+/// A ballot, the choice of a voter in an election along with a random serial
+/// number that makes every ballot unique
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Ballot {
+    election: u64,
+    choice: u32,
+    serial: [u8; 32],
+}
+
+impl Ballot {
+    /// Prepares a ballot for the choice in the election, with a fresh serial
+    /// number.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Ballot) on success.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    pub fn new(election: u64, choice: u32) -> ::Result<Self> {
+        let mut serial = [0; 32];
+        rng::try_new()?.fill_bytes(&mut serial);
+        Ok(Ballot {
+            election,
+            choice,
+            serial,
+        })
+    }
+
+    /// Returns the id of the election
+    pub fn election(&self) -> u64 {
+        self.election
+    }
+
+    /// Returns the choice of the voter
+    pub fn choice(&self) -> u32 {
+        self.choice
+    }
+
+    /// Returns the ballot in wired form, election || choice || serial, with
+    /// the integers being big endian
+    pub fn wired(&self) -> [u8; BALLOT_LEN] {
+        let mut arr = [0; BALLOT_LEN];
+        arr[0..8].copy_from_slice(&self.election.to_be_bytes());
+        arr[8..12].copy_from_slice(&self.choice.to_be_bytes());
+        arr[12..44].copy_from_slice(&self.serial);
+        arr
+    }
+
+    /// Loads the ballot from its wired form
+    pub fn from_wired(wired: &[u8; BALLOT_LEN]) -> Self {
+        let mut election = [0; 8];
+        election.copy_from_slice(&wired[0..8]);
+        let mut choice = [0; 4];
+        choice.copy_from_slice(&wired[8..12]);
+        let mut serial = [0; 32];
+        serial.copy_from_slice(&wired[12..44]);
+        Ballot {
+            election: u64::from_be_bytes(election),
+            choice: u32::from_be_bytes(choice),
+            serial,
+        }
+    }
+
+    /// Returns the message that is blindly signed for the ballot
+    fn msg(&self) -> Vec<u8> {
+        let mut msg = BALLOT_DOMAIN.to_vec();
+        msg.extend_from_slice(&self.wired());
+        msg
+    }
+}
+
+/// For managing the voter side of having a ballot signed.
+pub struct BallotRequest {
+    ballot: Ballot,
+    request: BlindRequest,
+}
+
+impl BallotRequest {
+    /// Blinds the ballot for the session with the provided R' from the
+    /// authority.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for generating e
+    ///
+    /// # Returns
+    ///
+    /// * Ok((WiredEPrime, BallotRequest)) on success, with the WiredEPrime
+    /// being e' for sending to the authority.
+    ///
+    /// * Err(::Error) on failure, which indicates the failure to initiate the
+    /// internal RNG.
+    pub fn new<H>(rp: &WiredRPrime, ballot: Ballot) -> ::Result<(WiredEPrime, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let (ep, request) = BlindRequest::new_specific_msg::<H, Vec<u8>>(rp, ballot.msg())?;
+        Ok((ep, BallotRequest { ballot, request }))
+    }
+
+    /// Input S' from the authority, consumes self and creates the signed
+    /// ballot. This does not verify the signature.
+    pub fn gen_signed_ballot(self, sp: &WiredSPrime) -> SignedBallot {
+        SignedBallot {
+            ballot: self.ballot,
+            sig: self.request.gen_signed_msg(sp),
+        }
+    }
+}
+
+/// A ballot along with the blind signature of the authority on it
+#[derive(Copy, Clone, Debug)]
+pub struct SignedBallot {
+    ballot: Ballot,
+    sig: UnblindedSigData,
+}
+
+impl SignedBallot {
+    /// Returns the ballot
+    pub fn ballot(&self) -> &Ballot {
+        &self.ballot
+    }
+
+    /// Returns the nullifier of the signed ballot, for duplicate detection.
+    /// It identifies the ballot by its election and serial number, rather
+    /// than by its signature, whose e can be altered without the signature
+    /// failing to verify.
+    ///
+    /// # Mathematics
+    ///
+    /// * N = H(domain || election || serial), truncated to 32 bytes
+    pub fn nullifier<H>(&self) -> Nullifier
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut hasher = H::default();
+        hasher.input(NULLIFIER_DOMAIN);
+        hasher.input(&self.ballot.election.to_be_bytes());
+        hasher.input(&self.ballot.serial);
+        let mut nullifier = [0; 32];
+        nullifier.copy_from_slice(&hasher.result()[..32]);
+        Nullifier(nullifier)
+    }
+
+    /// Authenticates the signature of the authority on the ballot.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the ballot was signed by the authority.
+    ///
+    /// * False to indicate that the signed ballot is not valid.
+    pub fn verify<H>(&self, authority: &VerifyingKey) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        self.sig
+            .msg_authenticate::<H, Vec<u8>>(authority, self.ballot.msg())
+    }
+}
+
+/// The SignedBallot in wired form, election || choice || serial || e || S || R
+pub struct WiredSignedBallot(pub [u8; 140]);
+
+impl From<SignedBallot> for WiredSignedBallot {
+    fn from(signed: SignedBallot) -> Self {
+        let mut arr = [0; 140];
+        arr[0..44].copy_from_slice(&signed.ballot.wired());
+        arr[44..140].copy_from_slice(&WiredUnblindedSigData::from(signed.sig).0);
+        WiredSignedBallot(arr)
+    }
+}
+
+impl WiredSignedBallot {
+    /// Converts the wired form into the internal SignedBallot.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(SignedBallot) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the signature was
+    /// malformed.
+    pub fn to_internal_format(&self) -> ::Result<SignedBallot> {
+        let mut ballot = [0; BALLOT_LEN];
+        ballot.copy_from_slice(&self.0[0..44]);
+        Ok(SignedBallot {
+            ballot: Ballot::from_wired(&ballot),
+            sig: WiredUnblindedSigData::from_bytes(&self.0[44..140])?.to_internal_format()?,
+        })
+    }
+}
+
+impl_wired_bytes!(WiredSignedBallot, 140);
+impl_wired_envelope!(WiredSignedBallot, PayloadType::SignedBallot);
+
+/// Verifies a batch of signed ballots, returning the indices of those that
+/// are not valid for the election under the authority key.
+pub fn verify_batch<H>(
+    election: u64,
+    authority: &VerifyingKey,
+    ballots: &[SignedBallot],
+) -> Vec<usize>
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    ballots
+        .iter()
+        .enumerate()
+        .filter(|&(_, signed)| {
+            signed.ballot.election != election || !signed.verify::<H>(authority)
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// The tally of an election, counting each valid ballot once
+pub struct Tally<S> {
+    election: u64,
+    authority: VerifyingKey,
+    store: S,
+    counts: BTreeMap<u32, u64>,
+}
+
+impl<S> Tally<S>
+where
+    S: SpentStore,
+{
+    /// Creates an empty tally for the election, recording the nullifiers of
+    /// counted ballots in the provided store.
+    pub fn new(election: u64, authority: VerifyingKey, store: S) -> Self {
+        Tally {
+            election,
+            authority,
+            store,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the number of votes for each choice that received any
+    pub fn counts(&self) -> &BTreeMap<u32, u64> {
+        &self.counts
+    }
+
+    /// Returns the total number of ballots counted
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// Counts a signed ballot.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for generating e, and for deriving the
+    /// nullifier
+    ///
+    /// # Returns
+    ///
+    /// * Ok(()) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the ballot was for another
+    /// election, that it was not signed by the authority, that it was already
+    /// cast, or that the store failed.
+    pub fn cast<H>(&mut self, signed: &SignedBallot) -> ::Result<()>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        if signed.ballot.election != self.election {
+            return Err(BallotElectionMismatch);
+        }
+        if !signed.verify::<H>(&self.authority) {
+            return Err(BallotSignatureInvalid);
+        }
+        if !self.store.insert_if_absent(&signed.nullifier::<H>())? {
+            return Err(AlreadySpent);
+        }
+        *self.counts.entry(signed.ballot.choice).or_insert(0) += 1;
+        Ok(())
+    }
+
+    /// Counts a batch of signed ballots, as for cast, returning the result of
+    /// each.
+    pub fn cast_batch<H>(&mut self, ballots: &[SignedBallot]) -> Vec<::Result<()>>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        ballots.iter().map(|signed| self.cast::<H>(signed)).collect()
+    }
+}
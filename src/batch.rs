@@ -0,0 +1,205 @@
+//! Batch signing, unblinding and authentication
+//!
+//! Each function applies the single item operation to every item of the
+//! batch, returning the results in the same order. With the parallel
+//! feature, batches of at least PARALLEL_THRESHOLD items are spread across
+//! the rayon thread pool, while smaller batches, for which the cost of
+//! distributing the work outweighs the gain, are processed sequentially.
+//!
+//! verify_batch instead authenticates a whole batch with a single combined
+//! equation, and find_invalid bisects failing batches to identify the
+//! invalid signatures, so that the valid ones of a mixed batch can still be
+//! accepted.
+//!
+//! # Note
+//!
+//! The bounds are the same with and without the parallel feature, so that
+//! enabling it never breaks a build.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT, ristretto::RistrettoPoint, scalar::Scalar,
+    traits::Identity,
+};
+use digest::Digest;
+use keypair::{BlindSignerKey, VerifyingKey};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use request::{self, BlindRequest, WiredEPrime};
+use rng::{self, SecureRng};
+use session::{BlindSession, WiredSPrime};
+use signature::UnblindedSigData;
+use typenum::U64;
+
+/// The number of items from which batches are processed in parallel, with
+/// the parallel feature. Below this, a single Ristretto scalar
+/// multiplication per item is too little work to amortize the scheduling.
+pub const PARALLEL_THRESHOLD: usize = 64;
+
+/// Signs each e' with its session, as for BlindSession::sign_ep.
+///
+/// # Returns
+///
+/// * A Vec of the result of signing each (session, e') pair.
+pub fn sign_all<K>(
+    sessions: Vec<(BlindSession, WiredEPrime)>,
+    xs: &K,
+) -> Vec<::Result<WiredSPrime>>
+where
+    K: BlindSignerKey + Sync + ?Sized,
+{
+    map(sessions, |(session, ep)| session.sign_ep(&ep, xs))
+}
+
+/// Unblinds each S' with its request, as for BlindRequest::gen_signed_msg.
+///
+/// # Returns
+///
+/// * A Vec of the signature unblinded from each (request, S') pair.
+pub fn unblind_all(requests: Vec<(BlindRequest, WiredSPrime)>) -> Vec<UnblindedSigData> {
+    map(requests, |(request, sp)| request.gen_signed_msg(&sp))
+}
+
+/// Authenticates each signature on its message under the public key, as for
+/// UnblindedSigData::msg_authenticate.
+///
+/// # Type Parameters
+///
+/// H is the hash algorithm used for generating e
+///
+/// # Returns
+///
+/// * A Vec of whether each (signature, message) pair authenticated.
+pub fn authenticate_all<H, M>(pub_key: &VerifyingKey, sigs: &[(UnblindedSigData, M)]) -> Vec<bool>
+where
+    H: Digest<OutputSize = U64> + Default,
+    M: AsRef<[u8]> + Sync,
+{
+    map(sigs.iter().collect(), |&(ref sig, ref m)| {
+        sig.msg_authenticate::<H, &[u8]>(pub_key, m.as_ref())
+    })
+}
+
+/// Authenticates every signature on its message under the public key, as for
+/// authenticate_all, but with a single combined equation, which takes about
+/// half the scalar multiplications.
+///
+/// # Type Parameters
+///
+/// H is the hash algorithm used for generating e
+///
+/// # Returns
+///
+/// * Ok(true) if every signature authenticated, and Ok(false) if any did not.
+///
+/// * Err(::Error) on failure, which can only be the failure to initiate the
+/// internal RNG.
+///
+/// # Mathematics
+///
+/// * (z1*S1 + ... + zn*Sn)P = (z1*e1 + ... + zn*en)Qs + z1*R1 + ... + zn*Rn
+/// * zi = A randomly generated scalar, so that invalid signatures cannot be
+/// crafted to cancel each other out
+pub fn verify_batch<H, M>(pub_key: &VerifyingKey, sigs: &[(UnblindedSigData, M)]) -> ::Result<bool>
+where
+    H: Digest<OutputSize = U64> + Default,
+    M: AsRef<[u8]>,
+{
+    Ok(combined::<H, M>(pub_key, sigs, &mut rng::try_new()?))
+}
+
+/// Finds the signatures which do not authenticate on their message under the
+/// public key. A failing batch is split in half and each half verified as a
+/// batch in turn, down to single signatures, so a batch with few invalid
+/// signatures costs little more than verify_batch.
+///
+/// # Type Parameters
+///
+/// H is the hash algorithm used for generating e
+///
+/// # Returns
+///
+/// * Ok(Vec<usize>) on success, which are the indices of the invalid
+/// signatures in ascending order, empty if every signature authenticated.
+///
+/// * Err(::Error) on failure, which can only be the failure to initiate the
+/// internal RNG.
+pub fn find_invalid<H, M>(
+    pub_key: &VerifyingKey,
+    sigs: &[(UnblindedSigData, M)],
+) -> ::Result<Vec<usize>>
+where
+    H: Digest<OutputSize = U64> + Default,
+    M: AsRef<[u8]>,
+{
+    let mut invalid = Vec::new();
+    bisect::<H, M>(pub_key, sigs, 0, &mut rng::try_new()?, &mut invalid);
+    Ok(invalid)
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Verifies the combined equation of verify_batch for the signatures
+fn combined<H, M>(
+    pub_key: &VerifyingKey,
+    sigs: &[(UnblindedSigData, M)],
+    rng: &mut SecureRng,
+) -> bool
+where
+    H: Digest<OutputSize = U64> + Default,
+    M: AsRef<[u8]>,
+{
+    let mut s_sum = Scalar::zero();
+    let mut e_sum = Scalar::zero();
+    let mut r_sum = RistrettoPoint::identity();
+    for &(ref sig, ref m) in sigs {
+        let z = Scalar::random(rng);
+        let e = request::generate_e_compressed::<H>(None, sig.r_compressed(), m.as_ref());
+        s_sum += z * sig.s();
+        e_sum += z * e;
+        r_sum += z * sig.r();
+    }
+    s_sum * RISTRETTO_BASEPOINT_POINT == e_sum * pub_key.point() + r_sum
+}
+
+/// Pushes the indices of the invalid signatures, offset by the index of the
+/// first signature, verifying the signatures as a batch before splitting them
+fn bisect<H, M>(
+    pub_key: &VerifyingKey,
+    sigs: &[(UnblindedSigData, M)],
+    offset: usize,
+    rng: &mut SecureRng,
+    invalid: &mut Vec<usize>,
+) where
+    H: Digest<OutputSize = U64> + Default,
+    M: AsRef<[u8]>,
+{
+    if sigs.len() == 1 {
+        let (ref sig, ref m) = sigs[0];
+        if !sig.msg_authenticate::<H, &[u8]>(pub_key, m.as_ref()) {
+            invalid.push(offset);
+        }
+        return;
+    }
+    if sigs.is_empty() || combined::<H, M>(pub_key, sigs, rng) {
+        return;
+    }
+    let (left, right) = sigs.split_at(sigs.len() / 2);
+    bisect::<H, M>(pub_key, left, offset, rng, invalid);
+    bisect::<H, M>(pub_key, right, offset + left.len(), rng, invalid);
+}
+
+
+/// Maps every item, in parallel if the batch is large enough
+fn map<T, R, F>(items: Vec<T>, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Send + Sync,
+{
+    #[cfg(feature = "parallel")]
+    {
+        if items.len() >= PARALLEL_THRESHOLD {
+            return items.into_par_iter().map(f).collect();
+        }
+    }
+    items.into_iter().map(f).collect()
+}
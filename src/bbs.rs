@@ -0,0 +1,618 @@
+//! BBS+ style multi-message signatures with blinded attributes
+//!
+//! A BBS+ signature over BLS12-381 signs a vector of messages at once. Some of
+//! the messages can be hidden from the issuer behind a Pedersen commitment
+//! made by the requester, who proves knowledge of the committed messages,
+//! while the rest are known to the issuer. The holder of a signature can then
+//! prove possession of it in zero knowledge, disclosing any subset of the
+//! messages while hiding the rest and the signature itself, so presentations
+//! are unlinkable to the issuance and to each other. This is the building
+//! block for anonymous credentials.
+//!
+//! 1. The requester commits to its hidden messages with BbsBlinding::new,
+//!    sending the BbsCommitment to the issuer.
+//! 2. The issuer verifies the commitment and signs it along with the known
+//!    messages with BbsSecretKey::blind_sign.
+//! 3. The requester unblinds the blind signature with BbsBlinding::unblind.
+//! 4. The holder presents the signature with BbsSignature::prove, which the
+//!    verifier checks with BbsProof::verify.
+//!
+//! The messages are byte strings, each identified by its index in the signed
+//! vector, and hashed to scalars.
+//!
+//! # Mathematics
+//!
+//! * b = g1 + s*h0 + m1*h1 + ... + mL*hL
+//! * A = b / (x + e)
+//! * e(A, W + e*g2) == e(b, g2), W = x*g2
+//!
+//! # Note
+//!
+//! As with the ECC protocol, this **does not** include **any** networking
+//! code.
+use bls12_381::hash_to_curve::{ExpandMsgXmd, HashToCurve};
+use bls12_381::{pairing, G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
+use envelope::PayloadType;
+use rand::RngCore;
+use rng::{self, SecureRng};
+use sha2::{Digest, Sha256, Sha512};
+use Error::{BbsIndexInvalid, BbsProofInvalid, BlsPointMalformed, WiredScalarMalformed};
+
+/// The domain separation tag for deriving the message generators
+const GENERATOR_DST: &[u8] = b"BLINDSIGN-BBS-GENERATOR-V01-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+
+/// The domain separation prefix for hashing messages to scalars
+const MESSAGE_DOMAIN: &[u8] = b"blindsign bbs message";
+
+/// The domain separation prefix for proof challenges
+const CHALLENGE_DOMAIN: &[u8] = b"blindsign bbs challenge";
+
+/// A BBS+ secret key, for the issuer
+pub struct BbsSecretKey {
+    x: Scalar,
+}
+
+impl BbsSecretKey {
+    /// Generates a new random BBS+ secret key.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BbsSecretKey) on success.
+    ///
+    /// * Err(::Error) on failure, which is only due to the failure to initiate
+    /// the internal random number generator.
+    pub fn generate() -> ::Result<Self> {
+        Ok(BbsSecretKey {
+            x: random_scalar(&mut rng::try_new()?),
+        })
+    }
+
+    /// Returns the public key associated with the secret key
+    pub fn public_key(&self) -> BbsPublicKey {
+        BbsPublicKey {
+            w: G2Affine::from(G2Affine::generator() * self.x),
+        }
+    }
+
+    /// Signs a vector of messages that are all known to the issuer.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BbsSignature) on success.
+    ///
+    /// * Err(::Error) on failure, which is only due to the failure to initiate
+    /// the internal random number generator.
+    pub fn sign(&self, msgs: &[&[u8]]) -> ::Result<BbsSignature> {
+        let mut rng = rng::try_new()?;
+        let s = random_scalar(&mut rng);
+        let known: Vec<(usize, &[u8])> = msgs.iter().cloned().enumerate().collect();
+        let b = G1Projective::generator() + generator(0) * s + msgs_sum(&known);
+        let (a, e) = self.exponentiate(b, &mut rng);
+        Ok(BbsSignature { a, e, s })
+    }
+
+    /// Signs the requester's commitment to its hidden messages along with the
+    /// messages known to the issuer, after verifying the commitment proof.
+    ///
+    /// # Arguments
+    ///
+    /// * 'known' - The messages known to the issuer, with their indices in
+    /// the signed vector. These must not overlap the hidden indices.
+    ///
+    /// * 'nonce' - The nonce sent by the issuer to the requester for the
+    /// commitment proof, which must be fresh for each issuance.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BbsBlindSignature) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that a known message index
+    /// overlaps a hidden one, that the commitment proof did not verify, or the
+    /// failure to initiate the RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * A = (g1 + C + s''*h0 + Σ mi*hi) / (x + e)
+    pub fn blind_sign(
+        &self,
+        commitment: &BbsCommitment,
+        known: &[(usize, &[u8])],
+        nonce: &[u8],
+    ) -> ::Result<BbsBlindSignature> {
+        if known
+            .iter()
+            .any(|&(index, _)| commitment.indices.contains(&index))
+        {
+            return Err(BbsIndexInvalid);
+        }
+        if !commitment.verify(nonce) {
+            return Err(BbsProofInvalid);
+        }
+        let mut rng = rng::try_new()?;
+        let s = random_scalar(&mut rng);
+        let b = G1Projective::generator() + commitment.c + generator(0) * s + msgs_sum(known);
+        let (a, e) = self.exponentiate(b, &mut rng);
+        Ok(BbsBlindSignature { a, e, s })
+    }
+
+    /// Computes A = b / (x + e) for a random e
+    fn exponentiate(&self, b: G1Projective, rng: &mut SecureRng) -> (G1Affine, Scalar) {
+        loop {
+            let e = random_scalar(rng);
+            let inv: Option<Scalar> = (self.x + e).invert().into();
+            if let Some(inv) = inv {
+                return (G1Affine::from(b * inv), e);
+            }
+        }
+    }
+}
+
+/// A BBS+ public key, W = x*g2
+#[derive(Copy, Clone, Debug)]
+pub struct BbsPublicKey {
+    w: G2Affine,
+}
+
+impl BbsPublicKey {
+    /// Loads a public key from its compressed form, rejecting points that are
+    /// not in the G2 subgroup as well as the identity.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BbsPublicKey) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the point was malformed.
+    pub fn from_wired(wired: [u8; 96]) -> ::Result<Self> {
+        let w: Option<G2Affine> = G2Affine::from_compressed(&wired).into();
+        match w {
+            Some(w) if !bool::from(w.is_identity()) => Ok(BbsPublicKey { w }),
+            _ => Err(BlsPointMalformed),
+        }
+    }
+
+    /// Returns the public key in wired form
+    pub fn wired(&self) -> [u8; 96] {
+        self.w.to_compressed()
+    }
+}
+
+/// The requester's commitment to its hidden messages, along with a proof of
+/// knowledge of them, which is sent to the issuer.
+#[derive(Clone, Debug)]
+pub struct BbsCommitment {
+    c: G1Projective,
+    indices: Vec<usize>,
+    challenge: Scalar,
+    responses: Vec<Scalar>,
+}
+
+impl BbsCommitment {
+    /// Returns the indices of the hidden messages
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Verifies the proof of knowledge of the committed messages
+    fn verify(&self, nonce: &[u8]) -> bool {
+        if self.responses.len() != self.indices.len() + 1 {
+            return false;
+        }
+        let t = self
+            .indices
+            .iter()
+            .zip(&self.responses[1..])
+            .fold(generator(0) * self.responses[0], |acc, (&index, response)| {
+                acc + generator(index + 1) * response
+            })
+            - self.c * self.challenge;
+        let c = G1Affine::from(self.c).to_compressed();
+        let t = G1Affine::from(t).to_compressed();
+        self.challenge == challenge(&[&c, &t, &index_bytes(&self.indices), nonce])
+    }
+}
+
+/// For managing the requester side of a blind issuance.
+pub struct BbsBlinding {
+    s: Scalar,
+}
+
+impl BbsBlinding {
+    /// Commits to the hidden messages with their indices in the signed vector,
+    /// proving knowledge of them for the nonce from the issuer.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((BbsCommitment, BbsBlinding)) on success, with the BbsCommitment
+    /// being sent to the issuer.
+    ///
+    /// * Err(::Error) on failure, which indicates a hidden index was repeated
+    /// or 0 based indices were not used, or the failure to initiate the RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * C = s'*h0 + Σ mi*hi
+    pub fn new(hidden: &[(usize, &[u8])], nonce: &[u8]) -> ::Result<(BbsCommitment, Self)> {
+        let indices: Vec<usize> = hidden.iter().map(|&(index, _)| index).collect();
+        check_indices(&indices)?;
+        let mut rng = rng::try_new()?;
+        let s = random_scalar(&mut rng);
+        let c = generator(0) * s + msgs_sum(hidden);
+
+        let blindings: Vec<Scalar> = (0..=hidden.len()).map(|_| random_scalar(&mut rng)).collect();
+        let t = indices
+            .iter()
+            .zip(&blindings[1..])
+            .fold(generator(0) * blindings[0], |acc, (&index, blinding)| {
+                acc + generator(index + 1) * blinding
+            });
+        let c_bytes = G1Affine::from(c).to_compressed();
+        let t_bytes = G1Affine::from(t).to_compressed();
+        let ch = challenge(&[&c_bytes, &t_bytes, &index_bytes(&indices), nonce]);
+        let secrets = Some(s)
+            .into_iter()
+            .chain(hidden.iter().map(|&(_, msg)| msg_scalar(msg)));
+        let responses = blindings
+            .iter()
+            .zip(secrets)
+            .map(|(blinding, secret)| blinding + ch * secret)
+            .collect();
+        let commitment = BbsCommitment {
+            c,
+            indices,
+            challenge: ch,
+            responses,
+        };
+        Ok((commitment, BbsBlinding { s }))
+    }
+
+    /// Input the blind signature from the issuer, consumes self and creates
+    /// the signature on all of the messages. This does not verify the
+    /// signature.
+    ///
+    /// # Mathematics
+    ///
+    /// * s = s' + s''
+    pub fn unblind(self, blind_sig: &BbsBlindSignature) -> BbsSignature {
+        BbsSignature {
+            a: blind_sig.a,
+            e: blind_sig.e,
+            s: self.s + blind_sig.s,
+        }
+    }
+}
+
+/// The blind signature (A, e, s'') returned by the issuer
+#[derive(Copy, Clone, Debug)]
+pub struct BbsBlindSignature {
+    a: G1Affine,
+    e: Scalar,
+    s: Scalar,
+}
+
+/// A BBS+ signature (A, e, s) on a vector of messages
+#[derive(Copy, Clone, Debug)]
+pub struct BbsSignature {
+    a: G1Affine,
+    e: Scalar,
+    s: Scalar,
+}
+
+impl BbsSignature {
+    /// Authenticates the signature on the full vector of messages.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the signature is valid.
+    ///
+    /// * False to indicate that the signature is not valid.
+    ///
+    /// # Mathematics
+    ///
+    /// * e(A, W + e*g2) == e(b, g2)
+    pub fn verify(&self, pub_key: &BbsPublicKey, msgs: &[&[u8]]) -> bool {
+        let known: Vec<(usize, &[u8])> = msgs.iter().cloned().enumerate().collect();
+        let b = G1Projective::generator() + generator(0) * self.s + msgs_sum(&known);
+        let w_e = G2Affine::from(G2Projective::from(pub_key.w) + G2Affine::generator() * self.e);
+        pairing(&self.a, &w_e) == pairing(&G1Affine::from(b), &G2Affine::generator())
+    }
+
+    /// Proves possession of the signature on the messages, disclosing only
+    /// the messages at the disclosed indices. Each proof is freshly
+    /// randomized, so proofs are unlinkable to each other and to the
+    /// signature.
+    ///
+    /// # Arguments
+    ///
+    /// * 'nonce' - The nonce from the verifier, which prevents the proof from
+    /// being replayed to other verifiers.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BbsProof) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates a disclosed index was out
+    /// of range or repeated, or the failure to initiate the RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * A' = r1*A, Ā = r1*(b - e*A), d = r1*b - r2*h0
+    /// * Ā - d == -e*A' + r2*h0
+    /// * g1 + Σ disclosed mi*hi == r3*d - s'*h0 - Σ hidden mi*hi
+    /// * r3 = 1/r1, s' = s - r2*r3
+    pub fn prove(
+        &self,
+        msgs: &[&[u8]],
+        disclosed: &[usize],
+        nonce: &[u8],
+    ) -> ::Result<BbsProof> {
+        check_indices(disclosed)?;
+        if disclosed.iter().any(|&index| index >= msgs.len()) {
+            return Err(BbsIndexInvalid);
+        }
+        let mut rng = rng::try_new()?;
+        let r1 = nonzero_scalar(&mut rng);
+        let r2 = random_scalar(&mut rng);
+        let r3 = r1.invert().unwrap();
+        let scalars: Vec<Scalar> = msgs.iter().map(|msg| msg_scalar(msg)).collect();
+        let b = G1Projective::generator()
+            + generator(0) * self.s
+            + scalars
+                .iter()
+                .enumerate()
+                .fold(G1Projective::identity(), |acc, (i, m)| acc + generator(i + 1) * m);
+        let a_prime = G1Projective::from(self.a) * r1;
+        let a_bar = a_prime * (-self.e) + b * r1;
+        let d = b * r1 - generator(0) * r2;
+        let s_prime = self.s - r2 * r3;
+
+        let hidden: Vec<usize> = (0..msgs.len()).filter(|i| !disclosed.contains(i)).collect();
+        let (e_t, r2_t, r3_t, s_t) = (
+            random_scalar(&mut rng),
+            random_scalar(&mut rng),
+            random_scalar(&mut rng),
+            random_scalar(&mut rng),
+        );
+        let m_t: Vec<Scalar> = hidden.iter().map(|_| random_scalar(&mut rng)).collect();
+        let t1 = a_prime * (-e_t) + generator(0) * r2_t;
+        let t2 = hidden
+            .iter()
+            .zip(&m_t)
+            .fold(d * r3_t - generator(0) * s_t, |acc, (&i, m)| acc - generator(i + 1) * m);
+
+        let disclosed_msgs: Vec<(usize, Scalar)> =
+            disclosed.iter().map(|&i| (i, scalars[i])).collect();
+        let points = [a_prime, a_bar, d];
+        let c = proof_challenge(&points, &t1, &t2, msgs.len(), &disclosed_msgs, nonce);
+        let proof = BbsProof {
+            a_prime: G1Affine::from(a_prime),
+            a_bar: G1Affine::from(a_bar),
+            d: G1Affine::from(d),
+            c,
+            e_hat: e_t + c * self.e,
+            r2_hat: r2_t + c * r2,
+            r3_hat: r3_t + c * r3,
+            s_hat: s_t + c * s_prime,
+            m_hat: hidden
+                .iter()
+                .zip(&m_t)
+                .map(|(&i, m)| m + c * scalars[i])
+                .collect(),
+        };
+        Ok(proof)
+    }
+}
+
+/// The BbsSignature in wired form, A || e || s
+pub struct WiredBbsSignature(pub [u8; 112]);
+
+impl From<BbsSignature> for WiredBbsSignature {
+    fn from(sig: BbsSignature) -> Self {
+        let mut arr = [0; 112];
+        arr[0..48].copy_from_slice(&sig.a.to_compressed());
+        arr[48..80].copy_from_slice(&sig.e.to_bytes());
+        arr[80..112].copy_from_slice(&sig.s.to_bytes());
+        WiredBbsSignature(arr)
+    }
+}
+
+impl WiredBbsSignature {
+    /// Converts the wired form into the internal BbsSignature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BbsSignature) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the point or either scalar
+    /// was malformed.
+    pub fn to_internal_format(&self) -> ::Result<BbsSignature> {
+        let mut a = [0; 48];
+        a.copy_from_slice(&self.0[0..48]);
+        let a: Option<G1Affine> = G1Affine::from_compressed(&a).into();
+        match a {
+            Some(a) if !bool::from(a.is_identity()) => Ok(BbsSignature {
+                a,
+                e: scalar(&self.0[48..80])?,
+                s: scalar(&self.0[80..112])?,
+            }),
+            _ => Err(BlsPointMalformed),
+        }
+    }
+}
+
+impl_wired_bytes!(WiredBbsSignature, 112);
+impl_wired_envelope!(WiredBbsSignature, PayloadType::BbsSignature);
+
+/// A zero knowledge proof of possession of a BBS+ signature, disclosing a
+/// subset of the signed messages.
+#[derive(Clone, Debug)]
+pub struct BbsProof {
+    a_prime: G1Affine,
+    a_bar: G1Affine,
+    d: G1Affine,
+    c: Scalar,
+    e_hat: Scalar,
+    r2_hat: Scalar,
+    r3_hat: Scalar,
+    s_hat: Scalar,
+    m_hat: Vec<Scalar>,
+}
+
+impl BbsProof {
+    /// Verifies the proof for the disclosed messages with their indices,
+    /// out of a signed vector of the provided number of messages.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the prover holds a valid signature on a vector
+    /// of messages including the disclosed messages.
+    ///
+    /// * False to indicate that the proof is not valid.
+    ///
+    /// # Mathematics
+    ///
+    /// * e(A', W) == e(Ā, g2)
+    pub fn verify(
+        &self,
+        pub_key: &BbsPublicKey,
+        msg_count: usize,
+        disclosed: &[(usize, &[u8])],
+        nonce: &[u8],
+    ) -> bool {
+        let indices: Vec<usize> = disclosed.iter().map(|&(index, _)| index).collect();
+        if check_indices(&indices).is_err() || indices.iter().any(|&i| i >= msg_count) {
+            return false;
+        }
+        let hidden: Vec<usize> = (0..msg_count).filter(|i| !indices.contains(i)).collect();
+        if hidden.len() != self.m_hat.len() || bool::from(self.a_prime.is_identity()) {
+            return false;
+        }
+        if pairing(&self.a_prime, &pub_key.w) != pairing(&self.a_bar, &G2Affine::generator()) {
+            return false;
+        }
+
+        let a_prime = G1Projective::from(self.a_prime);
+        let a_bar = G1Projective::from(self.a_bar);
+        let d = G1Projective::from(self.d);
+        let disclosed_msgs: Vec<(usize, Scalar)> = disclosed
+            .iter()
+            .map(|&(i, msg)| (i, msg_scalar(msg)))
+            .collect();
+        let revealed = disclosed_msgs
+            .iter()
+            .fold(G1Projective::generator(), |acc, &(i, m)| acc + generator(i + 1) * m);
+        let t1 = a_prime * (-self.e_hat) + generator(0) * self.r2_hat - (a_bar - d) * self.c;
+        let t2 = hidden
+            .iter()
+            .zip(&self.m_hat)
+            .fold(d * self.r3_hat - generator(0) * self.s_hat, |acc, (&i, m)| {
+                acc - generator(i + 1) * m
+            })
+            - revealed * self.c;
+        let points = [a_prime, a_bar, d];
+        self.c == proof_challenge(&points, &t1, &t2, msg_count, &disclosed_msgs, nonce)
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Derives the generator hi, with h0 being the generator for s
+fn generator(index: usize) -> G1Projective {
+    let input = (index as u32).to_be_bytes();
+    <G1Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(&input, GENERATOR_DST)
+}
+
+/// Hashes a message to a scalar
+fn msg_scalar(msg: &[u8]) -> Scalar {
+    hash_to_scalar(&[MESSAGE_DOMAIN, msg])
+}
+
+/// Computes Σ mi*h(i+1) over the messages with their 0 based indices
+fn msgs_sum(msgs: &[(usize, &[u8])]) -> G1Projective {
+    msgs.iter()
+        .fold(G1Projective::identity(), |acc, &(index, msg)| {
+            acc + generator(index + 1) * msg_scalar(msg)
+        })
+}
+
+/// Hashes the length prefixed inputs to a scalar
+fn hash_to_scalar(inputs: &[&[u8]]) -> Scalar {
+    let mut hasher = Sha512::new();
+    for input in inputs {
+        hasher.update(&(input.len() as u32).to_be_bytes());
+        hasher.update(input);
+    }
+    let mut wide = [0; 64];
+    wide.copy_from_slice(&hasher.finalize());
+    Scalar::from_bytes_wide(&wide)
+}
+
+/// Computes a challenge over the inputs
+fn challenge(inputs: &[&[u8]]) -> Scalar {
+    let mut all = vec![CHALLENGE_DOMAIN];
+    all.extend_from_slice(inputs);
+    hash_to_scalar(&all)
+}
+
+/// Computes the challenge of a proof of possession
+fn proof_challenge(
+    points: &[G1Projective; 3],
+    t1: &G1Projective,
+    t2: &G1Projective,
+    msg_count: usize,
+    disclosed: &[(usize, Scalar)],
+    nonce: &[u8],
+) -> Scalar {
+    let mut data = Vec::new();
+    for point in points.iter().chain(&[*t1, *t2]) {
+        data.extend_from_slice(&G1Affine::from(point).to_compressed());
+    }
+    data.extend_from_slice(&(msg_count as u32).to_be_bytes());
+    for &(index, m) in disclosed {
+        data.extend_from_slice(&(index as u32).to_be_bytes());
+        data.extend_from_slice(&m.to_bytes());
+    }
+    challenge(&[&data, nonce])
+}
+
+/// Encodes message indices as 4 byte big endian integers
+fn index_bytes(indices: &[usize]) -> Vec<u8> {
+    indices
+        .iter()
+        .flat_map(|&index| (index as u32).to_be_bytes().to_vec())
+        .collect()
+}
+
+/// Checks that the message indices are distinct
+fn check_indices(indices: &[usize]) -> ::Result<()> {
+    for (i, index) in indices.iter().enumerate() {
+        if indices[..i].contains(index) {
+            return Err(BbsIndexInvalid);
+        }
+    }
+    Ok(())
+}
+
+/// Generates a uniformly random scalar
+fn random_scalar(rng: &mut SecureRng) -> Scalar {
+    let mut bytes = [0; 64];
+    rng.fill_bytes(&mut bytes);
+    Scalar::from_bytes_wide(&bytes)
+}
+
+/// Generates a uniformly random nonzero scalar
+fn nonzero_scalar(rng: &mut SecureRng) -> Scalar {
+    loop {
+        let r = random_scalar(rng);
+        if r != Scalar::zero() {
+            return r;
+        }
+    }
+}
+
+/// Loads a 32 byte slice as a canonical scalar
+fn scalar(bytes: &[u8]) -> ::Result<Scalar> {
+    let mut arr = [0; 32];
+    arr.copy_from_slice(bytes);
+    let scalar: Option<Scalar> = Scalar::from_bytes(&arr).into();
+    scalar.ok_or(WiredScalarMalformed)
+}
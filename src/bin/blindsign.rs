@@ -0,0 +1,169 @@
+//! A command line tool for exercising the blind signature protocol by hand,
+//! with every value read from and written to files as hex. The secret state
+//! of sessions and requests between steps is sealed with a state key, as for
+//! BlindSession::seal and BlindRequest::seal.
+//!
+//! cargo run --features cli -- <subcommand> <args>
+extern crate blindsign;
+extern crate rand;
+extern crate sha3;
+
+use blindsign::{
+    keypair::{BlindKeypair, VerifyingKey, WiredPrivateKey, WiredPublicKey},
+    request::{BlindRequest, WiredEPrime},
+    session::{BlindSession, SessionId, WiredRPrime, WiredSPrime},
+    signature::WiredUnblindedSigData,
+    testvectors,
+};
+use rand::{OsRng, RngCore};
+use sha3::Sha3_512;
+use std::{env, fs, process};
+
+const USAGE: &str = "usage: blindsign <subcommand> <args>
+
+subcommands:
+    keygen <private-out> <public-out>
+    state-key <state-key-out>
+    session-init <state-key> <session-out> <rp-out>
+    blind <state-key> <rp> <msg> <request-out> <ep-out>
+    sign <state-key> <private> <public> <session> <ep> <sp-out>
+    unblind <state-key> <request> <sp> <sig-out>
+    verify <public> <msg> <sig>
+    test-vectors <msg> <count> <json-out>
+
+msg files are read as raw bytes, json files are the format of the
+testvectors module, and every other file is hex.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => (),
+        Err(e) => {
+            eprintln!("blindsign: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Runs the subcommand, returning a description of any failure
+fn run(args: &[String]) -> Result<(), String> {
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    match args.as_slice() {
+        ["keygen", private_out, public_out] => {
+            let keypair = BlindKeypair::generate().map_err(describe)?;
+            write_hex(private_out, keypair.private_wired().as_bytes())?;
+            write_hex(public_out, &keypair.verifying_key().wired())
+        }
+        ["state-key", out] => {
+            let mut key = [0; 32];
+            OsRng::new()
+                .map_err(|e| e.to_string())?
+                .fill_bytes(&mut key);
+            write_hex(out, &key)
+        }
+        ["session-init", state_key, session_out, rp_out] => {
+            let state_key = read32(state_key)?;
+            let (rp, session) = BlindSession::new().map_err(describe)?;
+            let sealed = session
+                .seal(&state_key, &SessionId([0; 16]))
+                .map_err(describe)?;
+            write_hex(session_out, &sealed)?;
+            write_hex(rp_out, rp.as_bytes())
+        }
+        ["blind", state_key, rp, msg, request_out, ep_out] => {
+            let state_key = read32(state_key)?;
+            let msg = fs::read(msg).map_err(|e| format!("{}: {}", msg, e))?;
+            let rp = WiredRPrime::from_bytes(&read32(rp)?).map_err(describe)?;
+            let (ep, request) =
+                BlindRequest::new_specific_msg::<Sha3_512, _>(&rp, msg).map_err(describe)?;
+            write_hex(request_out, &request.seal(&state_key).map_err(describe)?)?;
+            write_hex(ep_out, ep.as_bytes())
+        }
+        ["sign", state_key, private, public, session, ep, sp_out] => {
+            let state_key = read32(state_key)?;
+            let private = WiredPrivateKey::from_bytes(&read32(private)?).map_err(describe)?;
+            let public = WiredPublicKey::from_bytes(&read32(public)?).map_err(describe)?;
+            let keypair = BlindKeypair::from_wired(private, public).map_err(describe)?;
+            let (_, session) =
+                BlindSession::unseal(&state_key, &read_hex(session)?).map_err(describe)?;
+            let ep = WiredEPrime::from_bytes(&read32(ep)?).map_err(describe)?;
+            let sp = session
+                .sign_ep(&ep, &keypair.signing_key())
+                .map_err(describe)?;
+            write_hex(sp_out, sp.as_bytes())
+        }
+        ["unblind", state_key, request, sp, sig_out] => {
+            let state_key = read32(state_key)?;
+            let request = BlindRequest::restore(&state_key, &read_hex(request)?).map_err(describe)?;
+            let sp = WiredSPrime::from_bytes(&read32(sp)?).map_err(describe)?;
+            let sig = request.gen_signed_msg(&sp);
+            write_hex(sig_out, &WiredUnblindedSigData::from(sig).0)
+        }
+        ["verify", public, msg, sig] => {
+            let public = WiredPublicKey::from_bytes(&read32(public)?).map_err(describe)?;
+            let pub_key = VerifyingKey::from_wired(public).map_err(describe)?;
+            let msg = fs::read(msg).map_err(|e| format!("{}: {}", msg, e))?;
+            let sig = WiredUnblindedSigData::from_bytes(&read_hex(sig)?)
+                .and_then(|sig| sig.to_internal_format())
+                .map_err(describe)?;
+            if sig.msg_authenticate::<Sha3_512, _>(&pub_key, msg) {
+                println!("valid");
+                Ok(())
+            } else {
+                Err("signature is not valid".to_string())
+            }
+        }
+        ["test-vectors", msg, count, json_out] => {
+            let msg = fs::read(msg).map_err(|e| format!("{}: {}", msg, e))?;
+            let count: usize = count.parse().map_err(|_| USAGE.to_string())?;
+            let vectors = (0..count)
+                .map(|_| testvectors::generate::<Sha3_512, _>(&msg))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(describe)?;
+            fs::write(json_out, testvectors::to_json(&vectors))
+                .map_err(|e| format!("{}: {}", json_out, e))
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+/// Describes a library error
+fn describe(e: blindsign::Error) -> String {
+    e.to_string()
+}
+
+/// Reads a hex file, ignoring surrounding whitespace
+fn read_hex(path: &str) -> Result<Vec<u8>, String> {
+    let hex = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let hex = hex.trim().as_bytes();
+    if hex.len() % 2 != 0 {
+        return Err(format!("{}: not a valid hex string", path));
+    }
+    hex.chunks(2)
+        .map(|pair| {
+            let digit = |c: u8| (c as char).to_digit(16);
+            match (digit(pair[0]), digit(pair[1])) {
+                (Some(hi), Some(lo)) => Ok((hi << 4 | lo) as u8),
+                _ => Err(format!("{}: not a valid hex string", path)),
+            }
+        })
+        .collect()
+}
+
+/// Reads a hex file of exactly 32 bytes
+fn read32(path: &str) -> Result<[u8; 32], String> {
+    let bytes = read_hex(path)?;
+    if bytes.len() != 32 {
+        return Err(format!("{}: expected 32 bytes, found {}", path, bytes.len()));
+    }
+    let mut arr = [0; 32];
+    arr.copy_from_slice(&bytes);
+    Ok(arr)
+}
+
+/// Writes the bytes to a file as lowercase hex, with a trailing newline
+fn write_hex(path: &str, bytes: &[u8]) -> Result<(), String> {
+    let mut hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    hex.push('\n');
+    fs::write(path, hex).map_err(|e| format!("{}: {}", path, e))
+}
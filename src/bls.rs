@@ -0,0 +1,295 @@
+//! Blind BLS signatures over BLS12-381
+//!
+//! Signatures are in G1 and public keys in G2, with messages hashed to G1 under
+//! the BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_NUL_ ciphersuite, so unblinded
+//! signatures are ordinary (minimal signature size) BLS signatures. BLS
+//! signatures are deterministic, so a message has exactly one signature under
+//! a key, and any number of them can be aggregated into one.
+//!
+//! 1. The requester hashes its message to a point and blinds it with a random
+//!    scalar r, sending the blinded message r*H(m).
+//! 2. The signer signs the blinded message, returning x*r*H(m).
+//! 3. The requester unblinds the blind signature into x*H(m).
+//!
+//! # Note
+//!
+//! As with the ECC protocol, this **does not** include **any** networking
+//! code.
+use bls12_381::hash_to_curve::{ExpandMsgXmd, HashToCurve};
+use bls12_381::{pairing, G1Affine, G1Projective, G2Affine, Scalar};
+use envelope::PayloadType;
+use rand::RngCore;
+use rng::{self, SecureRng};
+use sha2::Sha256;
+use Error::BlsPointMalformed;
+
+/// The domain separation tag of the ciphersuite used for hashing messages,
+/// which is the ciphersuite ID of the IETF BLS signature draft
+pub const DST: &[u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_NUL_";
+
+/// A BLS secret key, for the signer
+pub struct BlsSecretKey {
+    x: Scalar,
+}
+
+impl BlsSecretKey {
+    /// Generates a new random BLS secret key.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlsSecretKey) on success.
+    ///
+    /// * Err(::Error) on failure, which is only due to the failure to initiate
+    /// the internal random number generator.
+    pub fn generate() -> ::Result<Self> {
+        Ok(BlsSecretKey {
+            x: random_scalar(&mut rng::try_new()?),
+        })
+    }
+
+    /// Returns the public key associated with the secret key
+    pub fn public_key(&self) -> BlsPublicKey {
+        BlsPublicKey {
+            point: G2Affine::from(G2Affine::generator() * self.x),
+        }
+    }
+
+    /// Signs the blinded message received from the requester.
+    ///
+    /// # Returns
+    ///
+    /// * Ok([u8; 48]) on success, representing the blind signature x*r*H(m).
+    ///
+    /// * Err(::Error) on failure, which indicates that the blinded message was
+    /// not a valid G1 point, or was the identity.
+    pub fn blind_sign(&self, blinded: &[u8; 48]) -> ::Result<[u8; 48]> {
+        let blinded = g1_point(blinded)?;
+        Ok(G1Affine::from(blinded * self.x).to_compressed())
+    }
+}
+
+/// A BLS public key, for authenticating signatures
+#[derive(Copy, Clone, Debug)]
+pub struct BlsPublicKey {
+    point: G2Affine,
+}
+
+impl BlsPublicKey {
+    /// Loads a public key from its compressed form, rejecting points that are
+    /// not in the G2 subgroup as well as the identity.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlsPublicKey) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the point was malformed.
+    pub fn from_wired(wired: WiredBlsPublicKey) -> ::Result<Self> {
+        let point: Option<G2Affine> = G2Affine::from_compressed(&wired.0).into();
+        match point {
+            Some(point) if !bool::from(point.is_identity()) => Ok(BlsPublicKey { point }),
+            _ => Err(BlsPointMalformed),
+        }
+    }
+
+    /// Returns the public key in wired form
+    pub fn wired(&self) -> WiredBlsPublicKey {
+        WiredBlsPublicKey(self.point.to_compressed())
+    }
+}
+
+/// For managing the requester side of a blind BLS signature.
+pub struct BlsBlindRequest {
+    r_inv: Scalar,
+}
+
+impl BlsBlindRequest {
+    /// Hashes the message to a point and blinds it.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 48], BlsBlindRequest)) on success. The [u8; 48] represents
+    /// the blinded message, which is sent to the signer.
+    ///
+    /// * Err(::Error) on failure, which is only due to the failure to initiate
+    /// the internal random number generator.
+    ///
+    /// # Mathematics
+    ///
+    /// * B = r*H(m)
+    /// * r = A randomly generated nonzero scalar by the requester
+    pub fn new<M>(m: M) -> ::Result<([u8; 48], Self)>
+    where
+        M: AsRef<[u8]>,
+    {
+        let mut rng = rng::try_new()?;
+        let r = loop {
+            let r = random_scalar(&mut rng);
+            if r != Scalar::zero() {
+                break r;
+            }
+        };
+        let r_inv = r.invert().unwrap();
+        let blinded = G1Affine::from(hash_to_g1(m.as_ref()) * r).to_compressed();
+        Ok((blinded, BlsBlindRequest { r_inv }))
+    }
+
+    /// Input the blind signature from the signer, consumes self and creates
+    /// the unblinded signature. This does not verify the signature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlsSignature) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the blind signature was
+    /// not a valid G1 point, or was the identity.
+    ///
+    /// # Mathematics
+    ///
+    /// * S = r^-1 * S'
+    pub fn gen_signed_msg(self, blind_sig: &[u8; 48]) -> ::Result<BlsSignature> {
+        let blind_sig = g1_point(blind_sig)?;
+        Ok(BlsSignature {
+            point: G1Affine::from(blind_sig * self.r_inv),
+        })
+    }
+}
+
+/// A BLS signature, which is a standard BLS signature in G1
+#[derive(Copy, Clone, Debug)]
+pub struct BlsSignature {
+    point: G1Affine,
+}
+
+impl BlsSignature {
+    /// Authenticates the signature on the message with the provided public key.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the signature is valid.
+    ///
+    /// * False to indicate that the signature is not valid.
+    ///
+    /// # Mathematics
+    ///
+    /// * e(S, g2) == e(H(m), Q)
+    pub fn authenticate<M>(&self, pub_key: &BlsPublicKey, msg: M) -> bool
+    where
+        M: AsRef<[u8]>,
+    {
+        let h = G1Affine::from(hash_to_g1(msg.as_ref()));
+        pairing(&self.point, &G2Affine::generator()) == pairing(&h, &pub_key.point)
+    }
+
+    /// Aggregates signatures into a single signature, which authenticates all
+    /// of their messages together with authenticate_aggregate.
+    pub fn aggregate(sigs: &[BlsSignature]) -> BlsSignature {
+        let sum = sigs
+            .iter()
+            .fold(G1Projective::identity(), |sum, sig| sum + sig.point);
+        BlsSignature {
+            point: G1Affine::from(sum),
+        }
+    }
+
+    /// Authenticates an aggregate signature on the provided public key and
+    /// message pairs. The messages must be distinct, otherwise aggregate
+    /// signatures can be forged from the signatures of other users.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the aggregate signature is valid on every pair
+    /// and that the messages are distinct.
+    ///
+    /// * False otherwise.
+    ///
+    /// # Mathematics
+    ///
+    /// * e(S, g2) == e(H(m1), Q1) + ... + e(H(mn), Qn)
+    pub fn authenticate_aggregate(&self, pairs: &[(&BlsPublicKey, &[u8])]) -> bool {
+        for (i, &(_, msg)) in pairs.iter().enumerate() {
+            if pairs[..i].iter().any(|&(_, other)| other == msg) {
+                return false;
+            }
+        }
+        let expected = pairs.iter().fold(None, |acc, &(pub_key, msg)| {
+            let h = G1Affine::from(hash_to_g1(msg));
+            let term = pairing(&h, &pub_key.point);
+            Some(match acc {
+                Some(acc) => acc + term,
+                None => term,
+            })
+        });
+        match expected {
+            Some(expected) => pairing(&self.point, &G2Affine::generator()) == expected,
+            None => false,
+        }
+    }
+}
+
+impl From<BlsSignature> for WiredBlsSignature {
+    fn from(sig: BlsSignature) -> Self {
+        WiredBlsSignature(sig.point.to_compressed())
+    }
+}
+
+/// The blinded message in wired form, a compressed G1 point
+pub struct WiredBlsBlindedMsg(pub [u8; 48]);
+
+impl_wired_bytes!(WiredBlsBlindedMsg, 48);
+impl_wired_envelope!(WiredBlsBlindedMsg, PayloadType::BlsBlindedMsg);
+
+/// The blind signature in wired form, a compressed G1 point
+pub struct WiredBlsBlindSig(pub [u8; 48]);
+
+impl_wired_bytes!(WiredBlsBlindSig, 48);
+impl_wired_envelope!(WiredBlsBlindSig, PayloadType::BlsBlindSig);
+
+/// The BlsSignature in wired form, a compressed G1 point
+pub struct WiredBlsSignature(pub [u8; 48]);
+
+impl WiredBlsSignature {
+    /// Converts the wired form into the internal BlsSignature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlsSignature) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the point was malformed.
+    pub fn to_internal_format(&self) -> ::Result<BlsSignature> {
+        Ok(BlsSignature {
+            point: G1Affine::from(g1_point(&self.0)?),
+        })
+    }
+}
+
+impl_wired_bytes!(WiredBlsSignature, 48);
+impl_wired_envelope!(WiredBlsSignature, PayloadType::BlsSignature);
+
+/// The BlsPublicKey in wired form, a compressed G2 point
+pub struct WiredBlsPublicKey(pub [u8; 96]);
+
+impl_wired_bytes!(WiredBlsPublicKey, 96);
+impl_wired_envelope!(WiredBlsPublicKey, PayloadType::BlsPublicKey);
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Hashes the message to G1 under the ciphersuite DST
+fn hash_to_g1(msg: &[u8]) -> G1Projective {
+    <G1Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(msg, DST)
+}
+
+/// Generates a uniformly random scalar
+fn random_scalar(rng: &mut SecureRng) -> Scalar {
+    let mut bytes = [0; 64];
+    rng.fill_bytes(&mut bytes);
+    Scalar::from_bytes_wide(&bytes)
+}
+
+/// Loads a compressed G1 point, rejecting the identity
+fn g1_point(bytes: &[u8; 48]) -> ::Result<G1Projective> {
+    let point: Option<G1Affine> = G1Affine::from_compressed(bytes).into();
+    match point {
+        Some(point) if !bool::from(point.is_identity()) => Ok(G1Projective::from(point)),
+        _ => Err(BlsPointMalformed),
+    }
+}
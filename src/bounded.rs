@@ -0,0 +1,299 @@
+//! Blind issuance of bounded amounts, with Bulletproofs range proofs
+//!
+//! The requester commits to a hidden amount with a Pedersen commitment, and
+//! proves with a Bulletproofs range proof that the amount is at most a limit
+//! set by the signer. The signer verifies the proof before signing, binding
+//! the commitment into the signature as the public info of a partially blind
+//! signature, so that the amount stays hidden while the signer enforces the
+//! limit. Binding the commitment is what ties the proof to the signature, as
+//! e' on its own is the hash of a message the signer cannot see.
+//!
+//! 1. The requester commits to the amount with AmountCommitment::new, and
+//!    proves it is at most the limit with AmountCommitment::prove_at_most,
+//!    sending the commitment and the proof to the signer.
+//! 2. The signer starts a BoundedSession for them, which verifies the proof,
+//!    sending the session commitment to the requester.
+//! 3. The requester blinds the session commitment with BoundedRequest::new.
+//! 4. The signer signs the challenge with BoundedSession::sign_e.
+//! 5. The requester unblinds the response into a BoundedToken.
+//!
+//! # Security
+//!
+//! The amount commitment is public info, so it is seen both at issuance and
+//! wherever the token is verified, linking the two. Only the amount, which
+//! the requester may open to the verifier or prove further statements about,
+//! is hidden from the signer.
+//!
+//! # Note
+//!
+//! The commitments and proofs are over the Ristretto group of the
+//! bulletproofs crate, with its default Pedersen generators. As with the ECC
+//! protocol, this **does not** include **any** networking code.
+use bulletproofs::{BulletproofGens, PedersenGens, RangeProof};
+use curve25519_dalek_bp::{ristretto::CompressedRistretto, scalar::Scalar};
+use digest::Digest;
+use keypair::{SigningKey, VerifyingKey};
+use merlin::Transcript;
+use partially_blind::{PartialRequest, PartialSession, PartialSignature};
+use rand::RngCore;
+use rng;
+use typenum::U64;
+use Error::{AmountExceedsLimit, BoundedTokenInvalid, RangeProofInvalid};
+
+/// The domain separation prefix of the commitment info
+const AMOUNT_DOMAIN: &[u8] = b"blindsign bounded amount";
+
+/// The label of the range proof transcript
+const TRANSCRIPT_LABEL: &[u8] = b"blindsign bounded amount proof";
+
+/// The bit size of the ranges, so that amounts are 64 bit integers
+const RANGE_BITS: usize = 64;
+
+/// A Pedersen commitment to an amount, along with its opening
+pub struct AmountCommitment {
+    value: u64,
+    blinding: Scalar,
+    commitment: [u8; 32],
+}
+
+impl AmountCommitment {
+    /// Commits to the amount with a fresh blinding factor.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(AmountCommitment) on success.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    pub fn new(value: u64) -> ::Result<Self> {
+        let mut bytes = [0; 64];
+        rng::try_new()?.fill_bytes(&mut bytes);
+        let blinding = Scalar::from_bytes_mod_order_wide(&bytes);
+        let commitment = PedersenGens::default()
+            .commit(Scalar::from(value), blinding)
+            .compress()
+            .to_bytes();
+        Ok(AmountCommitment {
+            value,
+            blinding,
+            commitment,
+        })
+    }
+
+    /// Returns the committed amount
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// Returns the blinding factor, for opening the commitment
+    pub fn blinding(&self) -> [u8; 32] {
+        self.blinding.to_bytes()
+    }
+
+    /// Returns the commitment, for sending to the signer
+    pub fn commitment(&self) -> [u8; 32] {
+        self.commitment
+    }
+
+    /// Proves that the committed amount is at most the limit, by proving that
+    /// both the amount and the limit minus the amount are 64 bit integers.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Vec<u8>) on success, the range proof for sending to the signer.
+    ///
+    /// * Err(::Error) on failure, which indicates that the amount is greater
+    /// than the limit.
+    pub fn prove_at_most(&self, limit: u64) -> ::Result<Vec<u8>> {
+        if self.value > limit {
+            return Err(AmountExceedsLimit);
+        }
+        let (proof, _) = RangeProof::prove_multiple(
+            &BulletproofGens::new(RANGE_BITS, 2),
+            &PedersenGens::default(),
+            &mut transcript(limit),
+            &[self.value, limit - self.value],
+            &[self.blinding, -self.blinding],
+            RANGE_BITS,
+        )
+        .map_err(|_| RangeProofInvalid)?;
+        Ok(proof.to_bytes())
+    }
+}
+
+/// Verifies a range proof that the amount in the commitment is at most the
+/// limit.
+///
+/// # Returns
+///
+/// * Ok(()) on success.
+///
+/// * Err(::Error) on failure, which indicates that the commitment or the
+/// proof was malformed, or that the proof was not valid for the limit.
+pub fn verify_at_most(commitment: &[u8; 32], limit: u64, proof: &[u8]) -> ::Result<()> {
+    let pc = PedersenGens::default();
+    let amount = CompressedRistretto(*commitment)
+        .decompress()
+        .ok_or(RangeProofInvalid)?;
+    let rest = pc.commit(Scalar::from(limit), Scalar::zero()) - amount;
+    RangeProof::from_bytes(proof)
+        .map_err(|_| RangeProofInvalid)?
+        .verify_multiple(
+            &BulletproofGens::new(RANGE_BITS, 2),
+            &pc,
+            &mut transcript(limit),
+            &[CompressedRistretto(*commitment), rest.compress()],
+            RANGE_BITS,
+        )
+        .map_err(|_| RangeProofInvalid)
+}
+
+/// For managing the signer side of issuing a bounded token.
+pub struct BoundedSession {
+    session: PartialSession,
+}
+
+impl BoundedSession {
+    /// Verifies the range proof that the amount in the commitment is at most
+    /// the limit, and initiates a new signer side session for it.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for hashing the commitment to a point
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 64], BoundedSession)) on success, with the [u8; 64] being
+    /// the session commitment for sending to the requester.
+    ///
+    /// * Err(::Error) on failure, which indicates that the range proof was
+    /// not valid, or the failure to initiate the internal RNG.
+    pub fn new<H>(commitment: &[u8; 32], limit: u64, proof: &[u8]) -> ::Result<([u8; 64], Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        verify_at_most(commitment, limit, proof)?;
+        let (session_commitment, session) = PartialSession::new::<H>(&amount_info(commitment))?;
+        Ok((session_commitment, BoundedSession { session }))
+    }
+
+    /// Signs the challenge e from the requester, consuming the session.
+    ///
+    /// # Returns
+    ///
+    /// * Ok([u8; 128]) on success, with the response being sent to the
+    /// requester.
+    ///
+    /// * Err(::Error) on failure, which indicates e was malformed.
+    pub fn sign_e(self, e: &[u8; 32], xs: &SigningKey) -> ::Result<[u8; 128]> {
+        self.session.sign_e(e, xs)
+    }
+}
+
+/// For managing the requester side of issuing a bounded token.
+pub struct BoundedRequest {
+    commitment: [u8; 32],
+    request: PartialRequest,
+}
+
+impl BoundedRequest {
+    /// Blinds the session commitment received from the signer for the amount
+    /// commitment, and the message to be signed.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], BoundedRequest)) on success, with the [u8; 32] being
+    /// the challenge e for sending to the signer.
+    ///
+    /// * Err(::Error) on failure, which could be caused by the failure to
+    /// initiate the RNG, or otherwise being input a malformed commitment.
+    pub fn new<H, M>(
+        session_commitment: &[u8; 64],
+        pub_key: &VerifyingKey,
+        amount: &AmountCommitment,
+        m: M,
+    ) -> ::Result<([u8; 32], Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        let info = amount_info(&amount.commitment);
+        let (e, request) = PartialRequest::new::<H, M>(session_commitment, pub_key, &info, m)?;
+        Ok((
+            e,
+            BoundedRequest {
+                commitment: amount.commitment,
+                request,
+            },
+        ))
+    }
+
+    /// Input the response from the signer, consumes self and creates the
+    /// bounded token. This does not verify the signature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BoundedToken) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the response contained
+    /// a malformed scalar.
+    pub fn gen_signed_msg(self, response: &[u8; 128]) -> ::Result<BoundedToken> {
+        Ok(BoundedToken {
+            commitment: self.commitment,
+            sig: self.request.gen_signed_msg(response)?,
+        })
+    }
+}
+
+/// A partially blind signature on a message, along with the commitment to
+/// the amount bound into it.
+#[derive(Copy, Clone, Debug)]
+pub struct BoundedToken {
+    commitment: [u8; 32],
+    sig: PartialSignature,
+}
+
+impl BoundedToken {
+    /// Returns the commitment to the amount
+    pub fn commitment(&self) -> [u8; 32] {
+        self.commitment
+    }
+
+    /// Verifies the signature on the message and the amount commitment.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(()) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the signature was not
+    /// valid on the message and commitment.
+    pub fn verify<H, M>(&self, pub_key: &VerifyingKey, msg: M) -> ::Result<()>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        if !self
+            .sig
+            .authenticate::<H, M>(pub_key, &amount_info(&self.commitment), msg)
+        {
+            return Err(BoundedTokenInvalid);
+        }
+        Ok(())
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Encodes the commitment as the public info of the partially blind signature
+fn amount_info(commitment: &[u8; 32]) -> Vec<u8> {
+    let mut info = AMOUNT_DOMAIN.to_vec();
+    info.extend_from_slice(commitment);
+    info
+}
+
+/// Creates the range proof transcript, bound to the limit
+fn transcript(limit: u64) -> Transcript {
+    let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+    transcript.append_message(b"limit", &limit.to_be_bytes());
+    transcript
+}
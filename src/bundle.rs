@@ -0,0 +1,290 @@
+//! A container for many compact signatures under one issuer key
+//!
+//! Wallets syncing or backing up many tokens from one issuer store them as a
+//! SignatureBundle, rather than framing the signatures themselves. The key
+//! fingerprint is stored once for the whole bundle, and each signature in its
+//! 64 byte compact form, with the messages held alongside by the wallet.
+//!
+//! The wired form is count || key fingerprint || signatures, with the count
+//! being a 4 byte big endian integer. As the count precedes the signatures,
+//! BundleWriter and BundleReader stream a bundle of any size through
+//! std::io, one signature at a time, without holding the whole bundle.
+use keypair::KeyFingerprint;
+use signature::CompactSignature;
+use std::io::{Read, Write};
+use Error::{BundleCountMismatch, BundleIoFailed, WiredLengthInvalid};
+
+/// The length of the header, the count and key fingerprint
+const HEADER_LEN: usize = 20;
+
+/// The length of each compact signature
+const SIG_LEN: usize = 64;
+
+/// Compact signatures which were all issued under the same key.
+#[derive(PartialEq, Eq)]
+pub struct SignatureBundle {
+    key_id: KeyFingerprint,
+    sigs: Vec<CompactSignature>,
+}
+
+impl SignatureBundle {
+    /// Creates an empty bundle for signatures under the key with the provided
+    /// fingerprint.
+    pub fn new(key_id: KeyFingerprint) -> Self {
+        SignatureBundle {
+            key_id,
+            sigs: Vec::new(),
+        }
+    }
+
+    /// Returns the fingerprint of the key the signatures were issued under
+    pub fn key_id(&self) -> KeyFingerprint {
+        self.key_id
+    }
+
+    /// Returns the signatures, in the order they were added
+    pub fn signatures(&self) -> &[CompactSignature] {
+        &self.sigs
+    }
+
+    /// Adds a signature to the end of the bundle
+    pub fn push(&mut self, sig: CompactSignature) {
+        self.sigs.push(sig);
+    }
+
+    /// Encodes the bundle as count || key fingerprint || signatures.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + SIG_LEN * self.sigs.len());
+        self.encode_to(&mut bytes)
+            .expect("writing to a Vec does not fail");
+        bytes
+    }
+
+    /// Decodes a bundle from count || key fingerprint || signatures.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(SignatureBundle) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the length did not
+    /// match the count.
+    pub fn from_bytes(bytes: &[u8]) -> ::Result<Self> {
+        if bytes.len() < HEADER_LEN {
+            return Err(WiredLengthInvalid {
+                expected: HEADER_LEN,
+                actual: bytes.len(),
+            });
+        }
+        let (count, key_id) = parse_header(&bytes[..HEADER_LEN]);
+        let expected = HEADER_LEN + SIG_LEN * count as usize;
+        if bytes.len() != expected {
+            return Err(WiredLengthInvalid {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        let sigs = bytes[HEADER_LEN..]
+            .chunks(SIG_LEN)
+            .map(CompactSignature::from_bytes)
+            .collect::<::Result<Vec<_>>>()?;
+        Ok(SignatureBundle { key_id, sigs })
+    }
+
+    /// Writes the encoding of the bundle to the writer.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(()) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the writer failed, or
+    /// that the bundle held more signatures than the count can hold.
+    pub fn encode_to<W>(&self, writer: W) -> ::Result<()>
+    where
+        W: Write,
+    {
+        if self.sigs.len() > u32::max_value() as usize {
+            return Err(BundleCountMismatch {
+                expected: u32::max_value() as usize,
+                actual: self.sigs.len(),
+            });
+        }
+        let mut bundle = BundleWriter::new(writer, self.key_id, self.sigs.len() as u32)?;
+        for sig in &self.sigs {
+            bundle.write(sig)?;
+        }
+        bundle.finish().map(|_| ())
+    }
+
+    /// Reads a whole bundle from the reader, as with BundleReader.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(SignatureBundle) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the reader failed or
+    /// ended before the last signature.
+    pub fn decode_from<R>(reader: R) -> ::Result<Self>
+    where
+        R: Read,
+    {
+        let bundle = BundleReader::new(reader)?;
+        let key_id = bundle.key_id();
+        let sigs = bundle.collect::<::Result<Vec<_>>>()?;
+        Ok(SignatureBundle { key_id, sigs })
+    }
+}
+
+/// Streams the encoding of a bundle to a writer, one signature at a time.
+pub struct BundleWriter<W> {
+    writer: W,
+    count: u32,
+    written: u32,
+}
+
+impl<W> BundleWriter<W>
+where
+    W: Write,
+{
+    /// Writes the header of a bundle of count signatures under the key with
+    /// the provided fingerprint.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BundleWriter) on success, to which the count signatures are then
+    /// written.
+    ///
+    /// * Err(::Error) on failure, which indicates that the writer failed.
+    pub fn new(mut writer: W, key_id: KeyFingerprint, count: u32) -> ::Result<Self> {
+        let mut header = [0; HEADER_LEN];
+        header[0..4].copy_from_slice(&count.to_be_bytes());
+        header[4..20].copy_from_slice(key_id.as_bytes());
+        writer.write_all(&header).map_err(|_| BundleIoFailed)?;
+        Ok(BundleWriter {
+            writer,
+            count,
+            written: 0,
+        })
+    }
+
+    /// Writes the next signature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(()) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the count signatures
+    /// were already written, or that the writer failed.
+    pub fn write(&mut self, sig: &CompactSignature) -> ::Result<()> {
+        if self.written == self.count {
+            return Err(BundleCountMismatch {
+                expected: self.count as usize,
+                actual: self.written as usize + 1,
+            });
+        }
+        self.writer
+            .write_all(sig.as_bytes())
+            .map_err(|_| BundleIoFailed)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Flushes the writer, after the last signature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(W) on success, returning the writer.
+    ///
+    /// * Err(::Error) on failure, which indicates that fewer than the count
+    /// signatures were written, or that the writer failed.
+    pub fn finish(mut self) -> ::Result<W> {
+        if self.written != self.count {
+            return Err(BundleCountMismatch {
+                expected: self.count as usize,
+                actual: self.written as usize,
+            });
+        }
+        self.writer.flush().map_err(|_| BundleIoFailed)?;
+        Ok(self.writer)
+    }
+}
+
+/// Streams the signatures of a bundle from a reader, as an iterator which
+/// reads each signature as it is reached.
+pub struct BundleReader<R> {
+    reader: R,
+    key_id: KeyFingerprint,
+    remaining: u32,
+}
+
+impl<R> BundleReader<R>
+where
+    R: Read,
+{
+    /// Reads the header of a bundle.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BundleReader) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the reader failed or
+    /// ended within the header.
+    pub fn new(mut reader: R) -> ::Result<Self> {
+        let mut header = [0; HEADER_LEN];
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| BundleIoFailed)?;
+        let (remaining, key_id) = parse_header(&header);
+        Ok(BundleReader {
+            reader,
+            key_id,
+            remaining,
+        })
+    }
+
+    /// Returns the fingerprint of the key the signatures were issued under
+    pub fn key_id(&self) -> KeyFingerprint {
+        self.key_id
+    }
+
+    /// Returns the number of signatures not yet read
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+}
+
+impl<R> Iterator for BundleReader<R>
+where
+    R: Read,
+{
+    type Item = ::Result<CompactSignature>;
+
+    /// Reads the next signature, or None once the count signatures are read.
+    /// A failure of the reader, including ending early, ends the iteration.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let mut sig = [0; SIG_LEN];
+        if self.reader.read_exact(&mut sig).is_err() {
+            self.remaining = 0;
+            return Some(Err(BundleIoFailed));
+        }
+        self.remaining -= 1;
+        Some(CompactSignature::from_bytes(&sig))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Splits a header into the count and key fingerprint
+fn parse_header(header: &[u8]) -> (u32, KeyFingerprint) {
+    let mut count = [0; 4];
+    count.copy_from_slice(&header[0..4]);
+    let mut key_id = [0; 16];
+    key_id.copy_from_slice(&header[4..20]);
+    (u32::from_be_bytes(count), KeyFingerprint(key_id))
+}
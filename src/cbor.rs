@@ -0,0 +1,214 @@
+//! Deterministic CBOR encoding of the wired types
+//!
+//! Each wired type is encoded as a CBOR map from text field names to byte
+//! strings, following the canonical CBOR rules of RFC 7049 section 3.9: map
+//! keys are sorted shortest first and then bytewise, and all lengths are
+//! encoded in their shortest form with no indefinite length items. Decoding is
+//! strict, so any input which is not the canonical encoding is rejected, which
+//! means every value has exactly one valid encoding.
+use keypair::{WiredPrivateKey, WiredPublicKey};
+use request::WiredEPrime;
+use session::{WiredRPrime, WiredSPrime};
+use signature::{CompactSignature, WiredUnblindedSigData};
+use Error::CborMalformed;
+
+/// CBOR major type for byte strings
+const MAJOR_BYTES: u8 = 2;
+/// CBOR major type for text strings
+const MAJOR_TEXT: u8 = 3;
+/// CBOR major type for maps
+const MAJOR_MAP: u8 = 5;
+
+/// Implements the CBOR conversions for a wired type with a single field
+macro_rules! impl_cbor_single {
+    ($name:ident, $field:expr) => {
+        impl $name {
+            /// Encodes the wired type as a canonical CBOR map
+            pub fn to_cbor(&self) -> Vec<u8> {
+                encode_map(&[($field, &self.as_bytes()[..])])
+            }
+
+            /// Decodes the wired type from a canonical CBOR map.
+            ///
+            /// # Returns
+            ///
+            /// * Ok(Self) on success.
+            ///
+            /// * Err(::Error) on failure, which indicates the input was not
+            /// the canonical CBOR encoding of the wired type.
+            pub fn from_cbor(bytes: &[u8]) -> ::Result<Self> {
+                let fields = decode_map(bytes, &[$field])?;
+                $name::from_bytes(fields[0])
+            }
+        }
+    };
+}
+
+impl_cbor_single!(WiredPublicKey, "public");
+impl_cbor_single!(WiredPrivateKey, "private");
+impl_cbor_single!(WiredRPrime, "rp");
+impl_cbor_single!(WiredEPrime, "ep");
+impl_cbor_single!(WiredSPrime, "sp");
+
+impl WiredUnblindedSigData {
+    /// Encodes the wired signature as a canonical CBOR map with the fields e,
+    /// r, and s.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        encode_map(&[
+            ("e", &self.0[0..32]),
+            ("r", &self.0[64..96]),
+            ("s", &self.0[32..64]),
+        ])
+    }
+
+    /// Decodes the wired signature from a canonical CBOR map with the fields
+    /// e, r, and s.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(WiredUnblindedSigData) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input was not the
+    /// canonical CBOR encoding of a wired signature.
+    pub fn from_cbor(bytes: &[u8]) -> ::Result<Self> {
+        let fields = decode_map(bytes, &["e", "r", "s"])?;
+        let mut arr = [0; 96];
+        arr[0..32].copy_from_slice(field_32(fields[0])?);
+        arr[32..64].copy_from_slice(field_32(fields[2])?);
+        arr[64..96].copy_from_slice(field_32(fields[1])?);
+        Ok(WiredUnblindedSigData(arr))
+    }
+}
+
+impl CompactSignature {
+    /// Encodes the compact signature as a canonical CBOR map with the fields e
+    /// and s.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        encode_map(&[("e", &self.0[0..32]), ("s", &self.0[32..64])])
+    }
+
+    /// Decodes the compact signature from a canonical CBOR map with the fields
+    /// e and s.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(CompactSignature) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input was not the
+    /// canonical CBOR encoding of a compact signature.
+    pub fn from_cbor(bytes: &[u8]) -> ::Result<Self> {
+        let fields = decode_map(bytes, &["e", "s"])?;
+        let mut arr = [0; 64];
+        arr[0..32].copy_from_slice(field_32(fields[0])?);
+        arr[32..64].copy_from_slice(field_32(fields[1])?);
+        Ok(CompactSignature(arr))
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Encodes a map of text keys to byte strings. The fields must already be in
+/// canonical order.
+fn encode_map(fields: &[(&str, &[u8])]) -> Vec<u8> {
+    debug_assert!(fields.windows(2).all(|w| canonical_order(w[0].0, w[1].0)));
+    let mut out = Vec::new();
+    encode_head(MAJOR_MAP, fields.len(), &mut out);
+    for &(key, value) in fields {
+        encode_head(MAJOR_TEXT, key.len(), &mut out);
+        out.extend_from_slice(key.as_bytes());
+        encode_head(MAJOR_BYTES, value.len(), &mut out);
+        out.extend_from_slice(value);
+    }
+    out
+}
+
+/// Decodes a map of text keys to byte strings, which must have exactly the
+/// provided keys in canonical order, returning the byte string values.
+fn decode_map<'a>(bytes: &'a [u8], keys: &[&str]) -> ::Result<Vec<&'a [u8]>> {
+    let mut pos = 0;
+    if decode_head(bytes, &mut pos, MAJOR_MAP)? != keys.len() {
+        return Err(CborMalformed);
+    }
+    let mut values = Vec::with_capacity(keys.len());
+    for key in keys {
+        let key_len = decode_head(bytes, &mut pos, MAJOR_TEXT)?;
+        if take(bytes, &mut pos, key_len)? != key.as_bytes() {
+            return Err(CborMalformed);
+        }
+        let value_len = decode_head(bytes, &mut pos, MAJOR_BYTES)?;
+        values.push(take(bytes, &mut pos, value_len)?);
+    }
+    if pos != bytes.len() {
+        return Err(CborMalformed);
+    }
+    Ok(values)
+}
+
+/// Encodes the head of a CBOR data item with the shortest length encoding
+fn encode_head(major: u8, len: usize, out: &mut Vec<u8>) {
+    let major = major << 5;
+    if len < 24 {
+        out.push(major | len as u8);
+    } else if len <= 0xff {
+        out.push(major | 24);
+        out.push(len as u8);
+    } else if len <= 0xffff {
+        out.push(major | 25);
+        out.push((len >> 8) as u8);
+        out.push(len as u8);
+    } else {
+        out.push(major | 26);
+        out.push((len >> 24) as u8);
+        out.push((len >> 16) as u8);
+        out.push((len >> 8) as u8);
+        out.push(len as u8);
+    }
+}
+
+/// Decodes the head of a CBOR data item of the expected major type, returning
+/// the length argument. Non-shortest and indefinite lengths are rejected.
+fn decode_head(bytes: &[u8], pos: &mut usize, major: u8) -> ::Result<usize> {
+    let initial = take(bytes, pos, 1)?[0];
+    if initial >> 5 != major {
+        return Err(CborMalformed);
+    }
+    let (len, min) = match initial & 0x1f {
+        info @ 0..=23 => (info as usize, 0),
+        24 => (be_len(take(bytes, pos, 1)?), 24),
+        25 => (be_len(take(bytes, pos, 2)?), 0x100),
+        26 => (be_len(take(bytes, pos, 4)?), 0x10000),
+        _ => return Err(CborMalformed),
+    };
+    if len < min {
+        return Err(CborMalformed);
+    }
+    Ok(len)
+}
+
+/// Takes the next len bytes of the input, advancing the position
+fn take<'a>(bytes: &'a [u8], pos: &mut usize, len: usize) -> ::Result<&'a [u8]> {
+    if bytes.len() - *pos < len {
+        return Err(CborMalformed);
+    }
+    let taken = &bytes[*pos..*pos + len];
+    *pos += len;
+    Ok(taken)
+}
+
+/// Interprets big endian bytes as a length
+fn be_len(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |len, &b| (len << 8) | b as usize)
+}
+
+/// Checks that a decoded field is 32 bytes long
+fn field_32(field: &[u8]) -> ::Result<&[u8]> {
+    if field.len() != 32 {
+        return Err(CborMalformed);
+    }
+    Ok(field)
+}
+
+/// Returns true if key a sorts before key b in canonical CBOR order
+fn canonical_order(a: &str, b: &str) -> bool {
+    (a.len(), a.as_bytes()) < (b.len(), b.as_bytes())
+}
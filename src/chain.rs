@@ -0,0 +1,138 @@
+//! Sequential multi-signer blind signatures
+//!
+//! A message is blindly signed by several independent signers in sequence,
+//! each signer's signature chaining over the message and every signature
+//! before it. Each link is an ordinary blind signature session, with the
+//! requester blinding ChainedSignature::link_msg as the specific message, so
+//! no signer needs to be aware of the chain. The chain verifies against the
+//! ordered list of public keys, so that reordering, dropping or replacing an
+//! endorsement invalidates it.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use digest::Digest;
+use keypair::VerifyingKey;
+use signature::{UnblindedSigData, WiredUnblindedSigData};
+use typenum::U64;
+use Error::WiredLengthInvalid;
+
+/// The domain separation prefix of chained link messages
+const CHAIN_DOMAIN: &[u8] = b"blindsign chain link";
+
+/// The length of each wired signature in the chain
+const LINK_LEN: usize = 96;
+
+/// A chain of blind signatures on one message, in signing order
+#[derive(Clone, Debug, Default)]
+pub struct ChainedSignature {
+    sigs: Vec<UnblindedSigData>,
+}
+
+impl ChainedSignature {
+    /// Creates an empty chain, to which signatures are pushed in order
+    pub fn new() -> Self {
+        ChainedSignature { sigs: Vec::new() }
+    }
+
+    /// Returns the number of signatures in the chain
+    pub fn len(&self) -> usize {
+        self.sigs.len()
+    }
+
+    /// Returns true if the chain has no signatures
+    pub fn is_empty(&self) -> bool {
+        self.sigs.is_empty()
+    }
+
+    /// Returns the message the next signer in the chain must blindly sign,
+    /// which covers msg and every signature already in the chain. The
+    /// requester passes it to BlindRequest::new_specific_msg.
+    ///
+    /// # Mathematics
+    ///
+    /// * mi = domain || i || msg || S0 || ... || S(i-1)
+    pub fn link_msg<M>(&self, msg: M) -> Vec<u8>
+    where
+        M: AsRef<[u8]>,
+    {
+        link_msg(msg.as_ref(), &self.sigs)
+    }
+
+    /// Appends the next signature to the chain. The signature is not
+    /// verified.
+    pub fn push(&mut self, sig: UnblindedSigData) {
+        self.sigs.push(sig);
+    }
+
+    /// Authenticates every signature of the chain on msg, with the signers'
+    /// public keys in signing order.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm that was used for generating each e
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the chain is valid, having exactly one
+    /// signature per key, each valid on its link message.
+    ///
+    /// * False to indicate that the chain is not valid.
+    pub fn msg_authenticate<H, M>(&self, keys: &[VerifyingKey], msg: M) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        if self.sigs.is_empty() || self.sigs.len() != keys.len() {
+            return false;
+        }
+        self.sigs.iter().zip(keys).enumerate().all(|(i, (sig, key))| {
+            sig.msg_authenticate::<H, Vec<u8>>(key, link_msg(msg.as_ref(), &self.sigs[..i]))
+        })
+    }
+
+    /// Returns the chain in wired form, the concatenated wired signatures
+    pub fn wired(&self) -> Vec<u8> {
+        self.sigs
+            .iter()
+            .flat_map(|&sig| WiredUnblindedSigData::from(sig).0.to_vec())
+            .collect()
+    }
+
+    /// Loads the chain from its wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(ChainedSignature) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input was not a multiple
+    /// of 96 bytes, or that a signature was malformed.
+    pub fn from_wired(wired: &[u8]) -> ::Result<Self> {
+        if wired.len() % LINK_LEN != 0 {
+            return Err(WiredLengthInvalid {
+                expected: (wired.len() / LINK_LEN + 1) * LINK_LEN,
+                actual: wired.len(),
+            });
+        }
+        let sigs = wired
+            .chunks(LINK_LEN)
+            .map(|chunk| WiredUnblindedSigData::from_bytes(chunk)?.to_internal_format())
+            .collect::<::Result<Vec<_>>>()?;
+        Ok(ChainedSignature { sigs })
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Computes the link message covering msg and the previous signatures
+fn link_msg(msg: &[u8], previous: &[UnblindedSigData]) -> Vec<u8> {
+    let mut link = CHAIN_DOMAIN.to_vec();
+    link.extend_from_slice(&(previous.len() as u32).to_be_bytes());
+    link.extend_from_slice(&(msg.len() as u32).to_be_bytes());
+    link.extend_from_slice(msg);
+    for &sig in previous {
+        link.extend_from_slice(&WiredUnblindedSigData::from(sig).0);
+    }
+    link
+}
@@ -0,0 +1,256 @@
+//! The clause blind Schnorr variant of the protocol
+//!
+//! The plain protocol implemented by the session and request modules can be
+//! forged against when many sessions are run concurrently under one key (see
+//! session::DEFAULT_MAX_OPEN_SESSIONS). The clause blind Schnorr signature of
+//! Fuchsbauer, Plouviez and Seurin resists these attacks without capping
+//! concurrency:
+//!
+//! 1. The signer generates two R' candidates, R'0 and R'1.
+//! 2. The requester blinds both of them and returns the blinded challenges
+//!    e'0 and e'1, which commit it to both clauses.
+//! 3. The signer picks one of the clauses at random and finishes only it,
+//!    returning the clause index b along with S'b.
+//! 4. The requester unblinds S'b into a signature on its message.
+//!
+//! The resulting signature is an ordinary UnblindedSigData, and so it is
+//! authenticated exactly as those from the plain protocol are.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+};
+use digest::Digest;
+use envelope::PayloadType;
+use keypair::{SigningKey, VerifyingKey};
+use rand::RngCore;
+use request;
+use rng;
+use signature::UnblindedSigData;
+use typenum::U64;
+use wipe;
+use Error::{ClauseIndexInvalid, WiredRistrettoPointMalformed, WiredScalarMalformed};
+
+/// For managing the signer side of a clause session. How the actual requests
+/// come in is orthogonal to this crate.
+///
+/// Both nonces are wiped from memory when the session is dropped, as either
+/// reveals the private key through S'b = kb + Xs*e'b.
+pub struct ClauseSession {
+    k: [Scalar; 2],
+}
+
+impl Drop for ClauseSession {
+    fn drop(&mut self) {
+        wipe::wipe_scalar(&mut self.k[0]);
+        wipe::wipe_scalar(&mut self.k[1]);
+    }
+}
+
+impl ClauseSession {
+    /// Initiate a new signer side clause session.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([[u8; 32]; 2], ClauseSession)) on success, with the [[u8; 32]; 2]
+    /// being the two R' candidates for sending to the requester.
+    ///
+    /// * Err(::Error) variant on failure, which is only due to the failure to
+    /// initiate the internal random number generator.
+    ///
+    /// # Mathematics
+    ///
+    /// * R'i = ki*P, for i in {0, 1}
+    /// * ki = A randomly generated scalar by the signer
+    /// * P = An ECC Generator Point
+    pub fn new() -> ::Result<([[u8; 32]; 2], Self)> {
+        let mut rng = rng::try_new()?;
+        let k = [Scalar::random(&mut rng), Scalar::random(&mut rng)];
+        let rps = [
+            (k[0] * RISTRETTO_BASEPOINT_POINT).compress().to_bytes(),
+            (k[1] * RISTRETTO_BASEPOINT_POINT).compress().to_bytes(),
+        ];
+        Ok((rps, Self { k }))
+    }
+
+    /// Consumes the session, picking one of the two clauses at random and
+    /// returning the blind signature on it.
+    ///
+    /// # Arguments
+    ///
+    /// * 'eps' - The blinded challenges e'0 and e'1, received from the
+    /// requester in some manner.
+    ///
+    /// * 'xs' - The signing key to sign with.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((usize, [u8; 32])) on success, with the usize being the index b of
+    /// the clause that was finished, and the [u8; 32] being the completed blind
+    /// signature value S'b. Both are sent to the requester.
+    ///
+    /// * Err(::Error) variant on error, which indicates that either e' value
+    /// was malformed, or the failure to initiate the internal random number
+    /// generator.
+    ///
+    /// # Mathematics
+    ///
+    /// * S'b = kb + Xs*e'b
+    /// * b = A randomly chosen clause index by the signer
+    pub fn sign_eps(mut self, eps: &[[u8; 32]; 2], xs: &SigningKey) -> ::Result<(usize, [u8; 32])> {
+        // Both e' values must be well formed before either clause is chosen.
+        let ep0 = Scalar::from_canonical_bytes(eps[0]).ok_or(WiredScalarMalformed)?;
+        let ep1 = Scalar::from_canonical_bytes(eps[1]).ok_or(WiredScalarMalformed)?;
+        let b = (rng::try_new()?.next_u32() & 1) as usize;
+        let ep = if b == 0 { ep0 } else { ep1 };
+        let sp = self.k[b] + xs.scalar() * ep;
+        // Neither k may be used again, so both are wiped before S'b is returned
+        wipe::wipe_scalar(&mut self.k[0]);
+        wipe::wipe_scalar(&mut self.k[1]);
+        Ok((b, sp.to_bytes()))
+    }
+}
+
+/// For managing the requester side of a clause session. Actually initiating
+/// the protocol is orthogonal to this crate.
+pub struct ClauseRequest {
+    alpha: [Scalar; 2],
+    r: [RistrettoPoint; 2],
+    e: [Scalar; 2],
+}
+
+impl ClauseRequest {
+    /// Blinds both R' candidates received from the signer, generating a random
+    /// 32 byte scalar to use as the message.
+    ///
+    /// # Arguments
+    ///
+    /// * 'rps' - The two R' candidates received from the signer.
+    ///
+    /// * 'pub_key' - The verifying key of the signer, which is required for
+    /// blinding in the clause variant.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm that will be used for generating e
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([[u8; 32]; 2], ClauseRequest)) on success. The [[u8; 32]; 2] are
+    /// the values e'0 and e'1, which are both sent to the signer.
+    ///
+    /// * Err(::Error) variant on error, which could be caused by the failure to
+    /// initiate the RNG, or otherwise being input a malformed R' value.
+    ///
+    /// # Mathematics
+    ///
+    /// * Ri = R'i + ai*P + bi*Q, for i in {0, 1}
+    /// * ai, bi = Randomly Generated Scalars by requester
+    /// * Q = The public key of the signer
+    /// _____
+    /// * ei = H(Ri||m)
+    /// * e'i = ei + bi
+    pub fn new<H>(rps: &[[u8; 32]; 2], pub_key: &VerifyingKey) -> ::Result<([[u8; 32]; 2], Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let m = Scalar::random(&mut rng::try_new()?);
+        initiate::<H, &[u8; 32]>(rps, pub_key, m.as_bytes())
+    }
+
+    /// The same as new, but allows for passing in a specific message value 'm'
+    /// to be blindly signed.
+    pub fn new_specific_msg<H, M>(
+        rps: &[[u8; 32]; 2],
+        pub_key: &VerifyingKey,
+        m: M,
+    ) -> ::Result<([[u8; 32]; 2], Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        initiate::<H, M>(rps, pub_key, m)
+    }
+
+    /// Input the clause index b and blinded signature S'b from the signer,
+    /// consumes self and creates the complete blindly signed message
+    /// structure. As with the plain protocol, this does not verify the
+    /// signature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(UnblindedSigData) on success.
+    ///
+    /// * Err(::Error) on error, which indicates that the clause index was
+    /// neither 0 nor 1, or that sp was not a valid scalar.
+    ///
+    /// # Mathematics
+    ///
+    /// * S = S'b + ab
+    pub fn gen_signed_msg(self, b: usize, sp: &[u8; 32]) -> ::Result<UnblindedSigData> {
+        if b > 1 {
+            return Err(ClauseIndexInvalid);
+        }
+        let sp = Scalar::from_canonical_bytes(*sp).ok_or(WiredScalarMalformed)?;
+        Ok(UnblindedSigData::new(self.e[b], sp + self.alpha[b], self.r[b]))
+    }
+}
+
+/// The two R' candidates in wired form, R'0 || R'1
+pub struct WiredClauseRPrimes(pub [u8; 64]);
+
+impl_wired_bytes!(WiredClauseRPrimes, 64);
+impl_wired_envelope!(WiredClauseRPrimes, PayloadType::ClauseRPrimes);
+
+/// The two blinded challenges in wired form, e'0 || e'1
+pub struct WiredClauseEPrimes(pub [u8; 64]);
+
+impl_wired_bytes!(WiredClauseEPrimes, 64);
+impl_wired_envelope!(WiredClauseEPrimes, PayloadType::ClauseEPrimes);
+
+/// The finished clause in wired form, b || S'b
+pub struct WiredClauseSPrime(pub [u8; 33]);
+
+impl_wired_bytes!(WiredClauseSPrime, 33);
+impl_wired_envelope!(WiredClauseSPrime, PayloadType::ClauseSPrime);
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Internal code for all new variants (ie: with random or specific msg)
+fn initiate<H, M>(
+    rps: &[[u8; 32]; 2],
+    pub_key: &VerifyingKey,
+    m: M,
+) -> ::Result<([[u8; 32]; 2], ClauseRequest)>
+where
+    H: Digest<OutputSize = U64> + Default,
+    M: AsRef<[u8]>,
+{
+    let mut rng = rng::try_new()?;
+    let mut alpha = [Scalar::zero(); 2];
+    let mut r = [RistrettoPoint::default(); 2];
+    let mut e = [Scalar::zero(); 2];
+    let mut eps = [[0; 32]; 2];
+    for (i, rp) in rps.iter().enumerate() {
+        let rp = CompressedRistretto(*rp)
+            .decompress()
+            .ok_or(WiredRistrettoPointMalformed)?;
+        let beta = Scalar::random(&mut rng);
+        alpha[i] = Scalar::random(&mut rng);
+
+        // Ri = R'i + ai*P + bi*Q
+        r[i] = rp + alpha[i] * RISTRETTO_BASEPOINT_POINT + beta * pub_key.point();
+
+        // ei = H(Ri||m)
+        e[i] = request::generate_e::<H>(r[i], m.as_ref());
+
+        // e'i = ei + bi
+        eps[i] = (e[i] + beta).to_bytes();
+    }
+    Ok((eps, ClauseRequest { alpha, r, e }))
+}
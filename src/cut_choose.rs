@@ -0,0 +1,345 @@
+//! Cut-and-choose issuance, for convincing the signer that a hidden message
+//! has a particular structure
+//!
+//! The requester blinds several candidate messages under the same R', and the
+//! signer picks one of them at random to keep. The requester opens every
+//! other candidate, revealing its message and blinding factors, so that the
+//! signer can check that each was blinded correctly and that its message is
+//! acceptable, before signing the one that stays hidden. A requester that
+//! blinds a single unacceptable candidate among n is caught with probability
+//! (n - 1) / n.
+//!
+//! 1. The signer starts a CutChooseSession, sending R' to the requester.
+//! 2. The requester blinds its candidates with CutChooseRequest::new, sending
+//!    every e' to the signer.
+//! 3. The signer picks the candidate to keep with CutChooseSession::choose,
+//!    sending its index to the requester.
+//! 4. The requester opens every other candidate with CutChooseRequest::open.
+//! 5. The signer checks the openings and signs the kept e' with
+//!    CutChooseChallenge::sign_kept, sending S' to the requester.
+//! 6. The requester unblinds S' with CutChooseKept::gen_signed_msg.
+//!
+//! # Security
+//!
+//! Only the kept candidate is ever signed, so k is used for one signature as
+//! in the plain protocol. The opened candidates reveal nothing about the kept
+//! one, but their messages should still be chosen independently of it.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+};
+use digest::Digest;
+use keypair::SigningKey;
+use rand::Rng;
+use request;
+use rng;
+use signature::UnblindedSigData;
+use typenum::U64;
+use wipe;
+use Error::{
+    CutChooseCandidatesInvalid, CutChooseMessageRejected, CutChooseOpeningInvalid,
+    WiredLengthInvalid, WiredRistrettoPointMalformed, WiredScalarMalformed,
+};
+
+/// For managing the signer side of a cut-and-choose issuance.
+///
+/// The nonce k is wiped from memory when the session is dropped, as it
+/// reveals the private key through S' = Xs*e' + k.
+pub struct CutChooseSession {
+    k: Scalar,
+    rp: RistrettoPoint,
+}
+
+impl Drop for CutChooseSession {
+    fn drop(&mut self) {
+        wipe::wipe_scalar(&mut self.k);
+    }
+}
+
+impl CutChooseSession {
+    /// Initiate a new signer side session, as with BlindSession::new.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], CutChooseSession)) on success, with the [u8; 32] being
+    /// the value R' for sending to the requester.
+    ///
+    /// * Err(::Error) on failure, which is only due to the failure to
+    /// initiate the internal random number generator.
+    pub fn new() -> ::Result<([u8; 32], Self)> {
+        let k = Scalar::random(&mut rng::try_new()?);
+        let rp = k * RISTRETTO_BASEPOINT_POINT;
+        Ok((rp.compress().to_bytes(), CutChooseSession { k, rp }))
+    }
+
+    /// Input every e' from the requester, consumes self and picks the
+    /// candidate to keep uniformly at random.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((usize, CutChooseChallenge)) on success, with the usize being the
+    /// index of the kept candidate for sending to the requester.
+    ///
+    /// * Err(::Error) on failure, which indicates that there were fewer than
+    /// two candidates, that an e' was malformed, or the failure to initiate
+    /// the internal RNG.
+    pub fn choose(self, eps: &[[u8; 32]]) -> ::Result<(usize, CutChooseChallenge)> {
+        if eps.len() < 2 {
+            return Err(CutChooseCandidatesInvalid);
+        }
+        let eps = eps
+            .iter()
+            .map(|ep| Scalar::from_canonical_bytes(*ep).ok_or(WiredScalarMalformed))
+            .collect::<::Result<Vec<_>>>()?;
+        let kept = rng::try_new()?.gen_range(0, eps.len());
+        Ok((
+            kept,
+            CutChooseChallenge {
+                k: self.k,
+                rp: self.rp,
+                eps,
+                kept,
+            },
+        ))
+    }
+}
+
+/// The signer side of a cut-and-choose issuance once the kept candidate has
+/// been picked.
+///
+/// The nonce k is wiped from memory when the challenge is dropped, whether it
+/// signed or not.
+pub struct CutChooseChallenge {
+    k: Scalar,
+    rp: RistrettoPoint,
+    eps: Vec<Scalar>,
+    kept: usize,
+}
+
+impl Drop for CutChooseChallenge {
+    fn drop(&mut self) {
+        wipe::wipe_scalar(&mut self.k);
+    }
+}
+
+impl CutChooseChallenge {
+    /// Returns the index of the kept candidate
+    pub fn kept(&self) -> usize {
+        self.kept
+    }
+
+    /// Checks the openings of every candidate other than the kept one, in
+    /// order of index, consumes self and signs the kept e' if each opening
+    /// matches its e' and accept returns true for its message.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for generating e
+    ///
+    /// # Returns
+    ///
+    /// * Ok([u8; 32]) on success, with the [u8; 32] being S' on the kept e'.
+    ///
+    /// * Err(::Error) on failure, which indicates that the number of openings
+    /// was wrong, that an opening did not match its e', or that the message of
+    /// the opened candidate with the returned index was rejected.
+    pub fn sign_kept<H, F>(
+        mut self,
+        openings: &[CandidateOpening],
+        accept: F,
+        xs: &SigningKey,
+    ) -> ::Result<[u8; 32]>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        F: Fn(&[u8]) -> bool,
+    {
+        if openings.len() + 1 != self.eps.len() {
+            return Err(CutChooseOpeningInvalid);
+        }
+        let opened = (0..self.eps.len()).filter(|&i| i != self.kept);
+        for (i, opening) in opened.zip(openings) {
+            let r = opening.u * self.rp + opening.v * RISTRETTO_BASEPOINT_POINT;
+            let e = request::generate_e::<H>(r, &opening.msg);
+            if opening.u == Scalar::zero() || opening.u.invert() * e != self.eps[i] {
+                return Err(CutChooseOpeningInvalid);
+            }
+            if !accept(&opening.msg) {
+                return Err(CutChooseMessageRejected(i));
+            }
+        }
+        let sp = xs.scalar() * self.eps[self.kept] + self.k;
+        // k must never be used twice, so is wiped before S' is even returned
+        wipe::wipe_scalar(&mut self.k);
+        Ok(sp.to_bytes())
+    }
+}
+
+/// The opening of a candidate, revealing its blinding factors and message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CandidateOpening {
+    u: Scalar,
+    v: Scalar,
+    msg: Vec<u8>,
+}
+
+impl CandidateOpening {
+    /// Returns the message of the candidate
+    pub fn msg(&self) -> &[u8] {
+        &self.msg
+    }
+
+    /// Encodes the opening as u || v || m
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(64 + self.msg.len());
+        bytes.extend_from_slice(self.u.as_bytes());
+        bytes.extend_from_slice(self.v.as_bytes());
+        bytes.extend_from_slice(&self.msg);
+        bytes
+    }
+
+    /// Decodes an opening.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(CandidateOpening) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input was shorter than
+    /// two scalars, or a malformed scalar.
+    pub fn from_bytes(bytes: &[u8]) -> ::Result<Self> {
+        if bytes.len() < 64 {
+            return Err(WiredLengthInvalid {
+                expected: 64,
+                actual: bytes.len(),
+            });
+        }
+        Ok(CandidateOpening {
+            u: scalar(&bytes[0..32])?,
+            v: scalar(&bytes[32..64])?,
+            msg: bytes[64..].to_vec(),
+        })
+    }
+}
+
+/// For managing the requester side of a cut-and-choose issuance.
+pub struct CutChooseRequest {
+    candidates: Vec<Candidate>,
+}
+
+impl CutChooseRequest {
+    /// Blinds each candidate message under R', as with
+    /// BlindRequest::new_specific_msg.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((Vec<[u8; 32]>, CutChooseRequest)) on success, with the vector
+    /// being the e' of each candidate for sending to the signer.
+    ///
+    /// * Err(::Error) on failure, which indicates that there were fewer than
+    /// two candidates, that R' was malformed, or the failure to initiate the
+    /// RNG.
+    pub fn new<H, M>(rp: &[u8; 32], msgs: &[M]) -> ::Result<(Vec<[u8; 32]>, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        if msgs.len() < 2 {
+            return Err(CutChooseCandidatesInvalid);
+        }
+        let mut rng = rng::try_new()?;
+        let rp = CompressedRistretto(*rp)
+            .decompress()
+            .ok_or(WiredRistrettoPointMalformed)?;
+        let candidates = msgs
+            .iter()
+            .map(|m| {
+                let u = Scalar::random(&mut rng);
+                let v = Scalar::random(&mut rng);
+                let r = u * rp + v * RISTRETTO_BASEPOINT_POINT;
+                let e = request::generate_e::<H>(r, m.as_ref());
+                Candidate {
+                    u,
+                    v,
+                    r,
+                    e,
+                    msg: m.as_ref().to_vec(),
+                }
+            })
+            .collect::<Vec<_>>();
+        let eps = candidates
+            .iter()
+            .map(|c| (c.u.invert() * c.e).to_bytes())
+            .collect();
+        Ok((eps, CutChooseRequest { candidates }))
+    }
+
+    /// Input the index of the kept candidate from the signer, consumes self
+    /// and opens every other candidate, in order of index.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((Vec<CandidateOpening>, CutChooseKept)) on success, with the
+    /// openings being sent to the signer.
+    ///
+    /// * Err(::Error) on failure, which indicates the index was out of range.
+    pub fn open(mut self, kept: usize) -> ::Result<(Vec<CandidateOpening>, CutChooseKept)> {
+        if kept >= self.candidates.len() {
+            return Err(CutChooseCandidatesInvalid);
+        }
+        let candidate = self.candidates.remove(kept);
+        let openings = self
+            .candidates
+            .into_iter()
+            .map(|c| CandidateOpening {
+                u: c.u,
+                v: c.v,
+                msg: c.msg,
+            })
+            .collect();
+        Ok((openings, CutChooseKept { candidate }))
+    }
+}
+
+/// The requester side of a cut-and-choose issuance once the other candidates
+/// have been opened.
+pub struct CutChooseKept {
+    candidate: Candidate,
+}
+
+impl CutChooseKept {
+    /// Returns the message of the kept candidate
+    pub fn msg(&self) -> &[u8] {
+        &self.candidate.msg
+    }
+
+    /// Input S' from the signer, consumes self and creates the signature on
+    /// the kept message, as with BlindRequest::gen_signed_msg.
+    pub fn gen_signed_msg(self, sp: &[u8; 32]) -> ::Result<UnblindedSigData> {
+        let sp = Scalar::from_canonical_bytes(*sp).ok_or(WiredScalarMalformed)?;
+        let c = self.candidate;
+        Ok(UnblindedSigData::new(c.e, sp * c.u + c.v, c.r))
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// The blinding of a candidate message
+struct Candidate {
+    u: Scalar,
+    v: Scalar,
+    r: RistrettoPoint,
+    e: Scalar,
+    msg: Vec<u8>,
+}
+
+/// Loads a canonical scalar from a 32 byte slice
+fn scalar(bytes: &[u8]) -> ::Result<Scalar> {
+    let mut arr = [0; 32];
+    arr.copy_from_slice(bytes);
+    Scalar::from_canonical_bytes(arr).ok_or(WiredScalarMalformed)
+}
@@ -0,0 +1,205 @@
+//! Denomination registry mapping issuer keys to values
+//!
+//! A Chaumian mint signs each denomination with its own key, as the blind
+//! signature itself cannot carry a value the signer enforces. The Registry
+//! associates each issuer verifying key with its value and validity window,
+//! and is distributed to clients in its wired form so that they can select
+//! the key to withdraw under, and to verifiers so that they can tell the value
+//! of a redeemed token from the key it verifies under.
+//!
+//! # Note
+//!
+//! Validity windows are expressed in whatever unit of time the caller uses
+//! for `now`, as with KeySet.
+use digest::Digest;
+use keypair::{VerifyingKey, WiredPublicKey};
+use signature::UnblindedSigData;
+use std::collections::BTreeMap;
+use typenum::U64;
+use Error::{DenominationDuplicate, WiredLengthInvalid};
+
+/// The length of each wired entry of the registry
+const ENTRY_LEN: usize = 56;
+
+/// An issuer verifying key along with its value and validity window
+#[derive(Copy, Clone, Debug)]
+pub struct Denomination {
+    key: VerifyingKey,
+    value: u64,
+    not_before: u64,
+    not_after: u64,
+}
+
+impl Denomination {
+    /// Creates a new Denomination, with the key being valid from not_before
+    /// up to and including not_after.
+    pub fn new(key: VerifyingKey, value: u64, not_before: u64, not_after: u64) -> Self {
+        Denomination {
+            key,
+            value,
+            not_before,
+            not_after,
+        }
+    }
+
+    /// Returns the verifying key of the denomination
+    pub fn key(&self) -> &VerifyingKey {
+        &self.key
+    }
+
+    /// Returns the value of the denomination
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// Returns true if the key is valid at the provided time
+    pub fn is_valid_at(&self, now: u64) -> bool {
+        self.not_before <= now && now <= self.not_after
+    }
+}
+
+/// A registry of denominations, indexed by their wired verifying keys
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    denominations: BTreeMap<[u8; 32], Denomination>,
+}
+
+impl Registry {
+    /// Creates a new empty Registry
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// Inserts a denomination into the registry.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(()) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that a denomination with
+    /// the same key is already in the registry.
+    pub fn insert(&mut self, denomination: Denomination) -> ::Result<()> {
+        let public = denomination.key.wired();
+        if self.denominations.contains_key(&public) {
+            return Err(DenominationDuplicate);
+        }
+        self.denominations.insert(public, denomination);
+        Ok(())
+    }
+
+    /// Removes the denomination with the provided key, returning it
+    pub fn remove(&mut self, key: &VerifyingKey) -> Option<Denomination> {
+        self.denominations.remove(&key.wired())
+    }
+
+    /// Returns the denomination with the provided key
+    pub fn get(&self, key: &VerifyingKey) -> Option<&Denomination> {
+        self.denominations.get(&key.wired())
+    }
+
+    /// Returns the number of denominations in the registry
+    pub fn len(&self) -> usize {
+        self.denominations.len()
+    }
+
+    /// Returns true if the registry has no denominations
+    pub fn is_empty(&self) -> bool {
+        self.denominations.is_empty()
+    }
+
+    /// Returns the denominations valid at the provided time, which are those
+    /// a client may withdraw under.
+    pub fn valid_at(&self, now: u64) -> Vec<&Denomination> {
+        self.denominations
+            .values()
+            .filter(|denomination| denomination.is_valid_at(now))
+            .collect()
+    }
+
+    /// Returns the valid denomination with the provided value at the provided
+    /// time.
+    pub fn find_value(&self, value: u64, now: u64) -> Option<&Denomination> {
+        self.denominations
+            .values()
+            .find(|denomination| denomination.value == value && denomination.is_valid_at(now))
+    }
+
+    /// Authenticates the signature on msg against every denomination valid at
+    /// the provided time, returning the denomination it verifies under.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm that was used for generating e
+    ///
+    /// # Returns
+    ///
+    /// * Some(&Denomination) if the signature is valid under the key of a
+    /// denomination valid at the provided time.
+    ///
+    /// * None otherwise.
+    pub fn denomination_of<H, M>(
+        &self,
+        sig: &UnblindedSigData,
+        msg: M,
+        now: u64,
+    ) -> Option<&Denomination>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        self.denominations.values().find(|denomination| {
+            denomination.is_valid_at(now)
+                && sig.msg_authenticate::<H, &[u8]>(&denomination.key, msg.as_ref())
+        })
+    }
+
+    /// Returns the registry in wired form, the concatenated entries of
+    /// public key || value || not_before || not_after, with the integers
+    /// being 8 byte big endian.
+    pub fn wired(&self) -> Vec<u8> {
+        let mut wired = Vec::with_capacity(self.denominations.len() * ENTRY_LEN);
+        for (public, denomination) in &self.denominations {
+            wired.extend_from_slice(public);
+            wired.extend_from_slice(&denomination.value.to_be_bytes());
+            wired.extend_from_slice(&denomination.not_before.to_be_bytes());
+            wired.extend_from_slice(&denomination.not_after.to_be_bytes());
+        }
+        wired
+    }
+
+    /// Loads the registry from its wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Registry) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input was not a multiple
+    /// of 56 bytes, that a key was malformed, or that a key was repeated.
+    pub fn from_wired(wired: &[u8]) -> ::Result<Self> {
+        if wired.len() % ENTRY_LEN != 0 {
+            return Err(WiredLengthInvalid {
+                expected: (wired.len() / ENTRY_LEN + 1) * ENTRY_LEN,
+                actual: wired.len(),
+            });
+        }
+        let mut registry = Registry::new();
+        for entry in wired.chunks(ENTRY_LEN) {
+            registry.insert(Denomination {
+                key: VerifyingKey::from_wired(WiredPublicKey::from_bytes(&entry[0..32])?)?,
+                value: be_u64(&entry[32..40]),
+                not_before: be_u64(&entry[40..48]),
+                not_after: be_u64(&entry[48..56]),
+            })?;
+        }
+        Ok(registry)
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Reads an 8 byte big endian integer
+fn be_u64(bytes: &[u8]) -> u64 {
+    let mut arr = [0; 8];
+    arr.copy_from_slice(bytes);
+    u64::from_be_bytes(arr)
+}
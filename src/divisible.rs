@@ -0,0 +1,532 @@
+//! Divisible e-cash with binary tree coins
+//!
+//! A coin of value 2^n is a binary tree of depth n, whose nodes each stand
+//! for a portion of its value: the root for all of it, and each node at level
+//! l for 2^(n - l). Every node has a secret derived from its parent's, and the
+//! mint blindly signs a Merkle style commitment to the whole tree at
+//! withdrawal. The wallet then spends any set of nodes none of which is an
+//! ancestor of another, each spend revealing the secret of the node, which
+//! lets the verifier recompute the commitment of its subtree, and the path up
+//! to the root commitment, without contacting the mint for each portion.
+//!
+//! Revealing a node secret reveals those of its descendants, which are part
+//! of the same spend, but nothing about the secrets of any other node, so no
+//! one observing a spend can spend the rest of the coin.
+//!
+//! 1. The wallet generates a DivisibleCoin and blinds its root commitment
+//!    with DivisibleRequest::new, for an ordinary BlindSession.
+//! 2. The wallet unblinds S' into a SignedCoin.
+//! 3. The wallet spends a node with SignedCoin::spend, and the verifier
+//!    redeems the DivisibleSpend with redeem, which detects overlapping
+//!    spends through a DivisibleStore.
+//!
+//! # Security
+//!
+//! Every spend reveals the root commitment of its coin, so spends of the same
+//! coin are linkable to each other, though as with plain tokens not to the
+//! withdrawal. Mints with several coin sizes should use one key per depth.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use digest::Digest;
+use keypair::{Redacted, VerifyingKey};
+use rand::RngCore;
+use request::{BlindRequest, WiredEPrime};
+use rng;
+use session::{WiredRPrime, WiredSPrime};
+use signature::{UnblindedSigData, WiredUnblindedSigData};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use typenum::U64;
+use Error::{AlreadySpent, DivisibleNodeInvalid, DivisibleSpendInvalid, WiredLengthInvalid};
+
+/// The maximum depth of a coin, so that the largest coin has value 2^16
+pub const MAX_DEPTH: u8 = 16;
+
+/// The domain separation prefix of the messages signed for coins
+const COIN_DOMAIN: &[u8] = b"blindsign divisible coin";
+
+/// The domain separation prefix for deriving child secrets
+const SECRET_DOMAIN: &[u8] = b"blindsign divisible secret";
+
+/// The domain separation prefix for hashing node secrets
+const PUBLIC_DOMAIN: &[u8] = b"blindsign divisible public";
+
+/// The domain separation prefix for node commitments
+const NODE_DOMAIN: &[u8] = b"blindsign divisible node";
+
+/// A node of a coin tree, at a level of the tree and an index within it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    level: u8,
+    index: u32,
+}
+
+impl Node {
+    /// Creates the node at index within the level.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Node) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the level was greater than
+    /// MAX_DEPTH, or the index was not less than 2^level.
+    pub fn new(level: u8, index: u32) -> ::Result<Self> {
+        if level > MAX_DEPTH || u64::from(index) >> level != 0 {
+            return Err(DivisibleNodeInvalid);
+        }
+        Ok(Node { level, index })
+    }
+
+    /// Returns the level of the node, 0 being the root
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Returns the index of the node within its level
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns true if the node is this node or one of its descendants
+    pub fn covers(&self, node: &Node) -> bool {
+        if self.level > node.level {
+            return false;
+        }
+        // Both levels are at most MAX_DEPTH, so the shift is less than 32
+        node.index >> (node.level - self.level) == self.index
+    }
+
+    /// Returns true if either node covers the other, so that they cannot
+    /// both be spent
+    pub fn overlaps(&self, node: &Node) -> bool {
+        self.covers(node) || node.covers(self)
+    }
+
+    /// Checks that the node is in a tree of the provided depth
+    fn check(&self, depth: u8) -> ::Result<()> {
+        if self.level > depth {
+            return Err(DivisibleNodeInvalid);
+        }
+        Ok(())
+    }
+}
+
+/// A coin tree, which the wallet keeps secret
+pub struct DivisibleCoin {
+    seed: [u8; 32],
+    depth: u8,
+}
+
+impl DivisibleCoin {
+    /// Generates a new coin of value 2^depth.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(DivisibleCoin) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the depth was greater than
+    /// MAX_DEPTH, or the failure to initiate the internal RNG.
+    pub fn generate(depth: u8) -> ::Result<Self> {
+        if depth > MAX_DEPTH {
+            return Err(DivisibleNodeInvalid);
+        }
+        let mut seed = [0; 32];
+        rng::try_new()?.fill_bytes(&mut seed);
+        Ok(DivisibleCoin { seed, depth })
+    }
+
+    /// Returns the value of the coin, 2^depth
+    pub fn value(&self) -> u64 {
+        1 << self.depth
+    }
+
+    /// Computes the root commitment of the coin tree.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for deriving the tree
+    pub fn root<H>(&self) -> [u8; 32]
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        subtree::<H>(&self.seed, self.depth)
+    }
+}
+
+/// For managing the wallet side of withdrawing a divisible coin.
+pub struct DivisibleRequest {
+    coin: DivisibleCoin,
+    request: BlindRequest,
+}
+
+impl DivisibleRequest {
+    /// Blinds the root commitment of the coin for the session with the
+    /// provided R' from the mint.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((WiredEPrime, DivisibleRequest)) on success, with the WiredEPrime
+    /// being e' for sending to the mint.
+    ///
+    /// * Err(::Error) on failure, which indicates the failure to initiate the
+    /// internal RNG.
+    pub fn new<H>(rp: &WiredRPrime, coin: DivisibleCoin) -> ::Result<(WiredEPrime, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let msg = coin_msg(coin.depth, &coin.root::<H>());
+        let (ep, request) = BlindRequest::new_specific_msg::<H, Vec<u8>>(rp, msg)?;
+        Ok((ep, DivisibleRequest { coin, request }))
+    }
+
+    /// Input S' from the mint, consumes self and creates the signed coin.
+    /// This does not verify the signature.
+    pub fn gen_signed_coin(self, sp: &WiredSPrime) -> SignedCoin {
+        SignedCoin {
+            coin: self.coin,
+            sig: self.request.gen_signed_msg(sp),
+        }
+    }
+}
+
+/// A coin along with the blind signature of the mint on its root commitment
+pub struct SignedCoin {
+    coin: DivisibleCoin,
+    sig: UnblindedSigData,
+}
+
+impl SignedCoin {
+    /// Returns the value of the coin
+    pub fn value(&self) -> u64 {
+        self.coin.value()
+    }
+
+    /// Creates the spend of the provided node, of value 2^(depth - level).
+    /// The wallet must track which nodes it has spent, as spending
+    /// overlapping nodes is detected as double spending.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(DivisibleSpend) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the node is not in the
+    /// coin tree.
+    pub fn spend<H>(&self, node: Node) -> ::Result<DivisibleSpend>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        node.check(self.coin.depth)?;
+        let mut secret = self.coin.seed;
+        let mut path = Vec::with_capacity(node.level as usize);
+        for l in 0..node.level {
+            let bit = path_bit(&node, l);
+            let sibling = child_secret::<H>(&secret, 1 - bit);
+            path.push((
+                public_hash::<H>(&secret),
+                subtree::<H>(&sibling, self.coin.depth - l - 1),
+            ));
+            secret = child_secret::<H>(&secret, bit);
+        }
+        path.reverse();
+        Ok(DivisibleSpend {
+            depth: self.coin.depth,
+            node,
+            secret,
+            path,
+            sig: self.sig,
+        })
+    }
+}
+
+/// The spend of a node of a coin, which is sent from the wallet to the
+/// verifier
+#[derive(Clone)]
+pub struct DivisibleSpend {
+    depth: u8,
+    node: Node,
+    secret: [u8; 32],
+    path: Vec<([u8; 32], [u8; 32])>,
+    sig: UnblindedSigData,
+}
+
+impl fmt::Debug for DivisibleSpend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DivisibleSpend")
+            .field("depth", &self.depth)
+            .field("node", &self.node)
+            .field("secret", &Redacted)
+            .field("sig", &self.sig)
+            .finish()
+    }
+}
+
+impl DivisibleSpend {
+    /// Returns the spent node
+    pub fn node(&self) -> Node {
+        self.node
+    }
+
+    /// Returns the value of the spend, 2^(depth - level)
+    pub fn value(&self) -> u64 {
+        1 << (self.depth - self.node.level)
+    }
+
+    /// Computes the root commitment of the coin the spend is from, which
+    /// identifies the coin for double spend detection
+    pub fn root<H>(&self) -> [u8; 32]
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut c = subtree::<H>(&self.secret, self.depth - self.node.level);
+        for (i, &(ref h, ref sibling)) in self.path.iter().enumerate() {
+            let l = self.node.level - 1 - i as u8;
+            c = if path_bit(&self.node, l) == 0 {
+                node_commitment::<H>(h, Some((&c, sibling)))
+            } else {
+                node_commitment::<H>(h, Some((sibling, &c)))
+            };
+        }
+        c
+    }
+
+    /// Verifies that the spend is of a node of a coin signed by the mint.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the spend is valid.
+    ///
+    /// * False to indicate that the spend is not valid.
+    pub fn verify<H>(&self, mint: &VerifyingKey) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        self.depth <= MAX_DEPTH
+            && self.node.check(self.depth).is_ok()
+            && self.path.len() == self.node.level as usize
+            && self
+                .sig
+                .msg_authenticate::<H, Vec<u8>>(mint, coin_msg(self.depth, &self.root::<H>()))
+    }
+
+    /// Encodes the spend as depth || level || index || secret || path || e ||
+    /// S || R, with the index being a 4 byte big endian integer and the path
+    /// being the public hash and sibling commitment of each ancestor, from
+    /// the parent up.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.depth, self.node.level];
+        bytes.extend_from_slice(&self.node.index.to_be_bytes());
+        bytes.extend_from_slice(&self.secret);
+        for &(ref h, ref sibling) in &self.path {
+            bytes.extend_from_slice(h);
+            bytes.extend_from_slice(sibling);
+        }
+        bytes.extend_from_slice(&WiredUnblindedSigData::from(self.sig).0);
+        bytes
+    }
+
+    /// Decodes the spend.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(DivisibleSpend) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input had the wrong
+    /// length for its level, the node was not in the tree, or the signature
+    /// was malformed.
+    pub fn from_bytes(bytes: &[u8]) -> ::Result<Self> {
+        if bytes.len() < 2 {
+            return Err(WiredLengthInvalid {
+                expected: 134,
+                actual: bytes.len(),
+            });
+        }
+        let (depth, level) = (bytes[0], bytes[1]);
+        let expected = 134 + 64 * level as usize;
+        if bytes.len() != expected {
+            return Err(WiredLengthInvalid {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        if depth > MAX_DEPTH {
+            return Err(DivisibleNodeInvalid);
+        }
+        let mut index = [0; 4];
+        index.copy_from_slice(&bytes[2..6]);
+        let node = Node::new(level, u32::from_be_bytes(index))?;
+        node.check(depth)?;
+        let path = bytes[38..expected - 96]
+            .chunks(64)
+            .map(|chunk| (array32(&chunk[0..32]), array32(&chunk[32..64])))
+            .collect();
+        Ok(DivisibleSpend {
+            depth,
+            node,
+            secret: array32(&bytes[6..38]),
+            path,
+            sig: WiredUnblindedSigData::from_bytes(&bytes[expected - 96..])?
+                .to_internal_format()?,
+        })
+    }
+}
+
+/// A record of the spent nodes of every coin
+pub trait DivisibleStore {
+    /// Records the node of the coin with the provided root commitment as
+    /// spent, if no overlapping node of the coin is already recorded. This
+    /// must be atomic, so that concurrent overlapping spends cannot both
+    /// succeed.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(true) if the node was recorded by this call.
+    ///
+    /// * Ok(false) if an overlapping node was already recorded.
+    ///
+    /// * Err(::Error) if the store failed, in which case the spend must not
+    /// be accepted.
+    fn spend_if_disjoint(&self, root: &[u8; 32], node: &Node) -> ::Result<bool>;
+}
+
+/// A DivisibleStore kept in memory, which may be shared between threads.
+#[derive(Debug, Default)]
+pub struct MemoryDivisibleStore {
+    spent: Mutex<HashMap<[u8; 32], Vec<Node>>>,
+}
+
+impl MemoryDivisibleStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        MemoryDivisibleStore {
+            spent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the spent nodes of the coin with the provided root commitment
+    pub fn spent_nodes(&self, root: &[u8; 32]) -> Vec<Node> {
+        self.spent().get(root).cloned().unwrap_or_default()
+    }
+
+    /// Locks the spent nodes. The map is never left in an inconsistent
+    /// state, so it remains usable even if another thread panicked while
+    /// holding the lock.
+    fn spent(&self) -> MutexGuard<HashMap<[u8; 32], Vec<Node>>> {
+        self.spent.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl DivisibleStore for MemoryDivisibleStore {
+    fn spend_if_disjoint(&self, root: &[u8; 32], node: &Node) -> ::Result<bool> {
+        let mut spent = self.spent();
+        let nodes = spent.entry(*root).or_insert_with(Vec::new);
+        if nodes.iter().any(|spent| spent.overlaps(node)) {
+            return Ok(false);
+        }
+        nodes.push(*node);
+        Ok(true)
+    }
+}
+
+/// Redeems the spend of a node, verifying it with the mint key and then
+/// recording the node as spent in the store.
+///
+/// # Returns
+///
+/// * Ok(u64) on success, with the u64 being the value of the spend.
+///
+/// * Err(::Error) on failure, which indicates that the spend was not valid,
+/// that it overlapped a node already spent, or that the store failed.
+pub fn redeem<H, S>(store: &S, mint: &VerifyingKey, spend: &DivisibleSpend) -> ::Result<u64>
+where
+    H: Digest<OutputSize = U64> + Default,
+    S: DivisibleStore + ?Sized,
+{
+    if !spend.verify::<H>(mint) {
+        return Err(DivisibleSpendInvalid);
+    }
+    if !store.spend_if_disjoint(&spend.root::<H>(), &spend.node)? {
+        return Err(AlreadySpent);
+    }
+    Ok(spend.value())
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Encodes the message signed for the coin, domain || depth || root
+fn coin_msg(depth: u8, root: &[u8; 32]) -> Vec<u8> {
+    let mut msg = COIN_DOMAIN.to_vec();
+    msg.push(depth);
+    msg.extend_from_slice(root);
+    msg
+}
+
+/// Returns the bit selecting the child at level + 1 on the path to the node
+fn path_bit(node: &Node, level: u8) -> u8 {
+    ((node.index >> (node.level - level - 1)) & 1) as u8
+}
+
+/// Hashes the inputs to 32 bytes
+fn hash32<H>(inputs: &[&[u8]]) -> [u8; 32]
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut hasher = H::default();
+    for input in inputs {
+        hasher.input(input);
+    }
+    array32(&hasher.result()[..32])
+}
+
+/// Derives the secret of the child selected by bit
+fn child_secret<H>(secret: &[u8; 32], bit: u8) -> [u8; 32]
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    hash32::<H>(&[SECRET_DOMAIN, secret, &[bit]])
+}
+
+/// Hashes the secret of a node into its public hash
+fn public_hash<H>(secret: &[u8; 32]) -> [u8; 32]
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    hash32::<H>(&[PUBLIC_DOMAIN, secret])
+}
+
+/// Computes the commitment of a node from its public hash and the
+/// commitments of its children, if it is not a leaf
+fn node_commitment<H>(h: &[u8; 32], children: Option<(&[u8; 32], &[u8; 32])>) -> [u8; 32]
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    match children {
+        Some((left, right)) => hash32::<H>(&[NODE_DOMAIN, h, left, right]),
+        None => hash32::<H>(&[NODE_DOMAIN, h]),
+    }
+}
+
+/// Computes the commitment of the subtree of the provided height under the
+/// node with the provided secret
+fn subtree<H>(secret: &[u8; 32], height: u8) -> [u8; 32]
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let h = public_hash::<H>(secret);
+    if height == 0 {
+        return node_commitment::<H>(&h, None);
+    }
+    let left = subtree::<H>(&child_secret::<H>(secret, 0), height - 1);
+    let right = subtree::<H>(&child_secret::<H>(secret, 1), height - 1);
+    node_commitment::<H>(&h, Some((&left, &right)))
+}
+
+/// Copies a 32 byte slice into an array
+fn array32(bytes: &[u8]) -> [u8; 32] {
+    let mut arr = [0; 32];
+    arr.copy_from_slice(bytes);
+    arr
+}
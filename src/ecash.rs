@@ -0,0 +1,464 @@
+//! Chaumian e-cash built on the blind signature protocol
+//!
+//! The glue that nearly every user of this crate writes: a Mint signing
+//! withdrawals through a SessionManager, a Wallet blinding random serial
+//! numbers and storing the unblinded tokens, and a SpendProof that carries a
+//! token from the wallet to the mint for redemption. Every token has the same
+//! value; mints with several denominations use one keypair per denomination.
+//!
+//! 1. The mint starts a withdrawal with Mint::start_withdrawal, sending the
+//!    SessionId and R' to the wallet.
+//! 2. The wallet blinds a fresh serial number with Wallet::withdraw, sending
+//!    e' to the mint.
+//! 3. The mint signs e' with Mint::finish_withdrawal, sending S' to the
+//!    wallet.
+//! 4. The wallet unblinds and stores the token with Wallet::receive.
+//! 5. The wallet spends a token with Wallet::spend, and the mint redeems the
+//!    SpendProof with Mint::redeem.
+//!
+//! A token that was spent without being redeemed, such as a token the wallet
+//! no longer trusts to be unseen, can be exchanged for a fresh one with
+//! Mint::start_reissue and Mint::finish_reissue, which redeem it only once
+//! the fresh token is signed.
+//!
+//! # Security
+//!
+//! A SpendProof is a bearer token. Mint::verify alone does not detect double
+//! spending, while Mint::redeem records each redeemed token in a SpentStore.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use digest::Digest;
+use envelope::PayloadType;
+use keypair::{BlindKeypair, Redacted, VerifyingKey};
+use rand::RngCore;
+use request::{BlindRequest, WiredEPrime};
+use rng;
+use session::{SessionId, SessionManager, WiredRPrime, WiredSPrime};
+use signature::{UnblindedSigData, WiredUnblindedSigData};
+use spent::{self, Nullifier, SpentStore};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use typenum::U64;
+use Error::{AlreadySpent, EcashTokenInvalid, ReissuePending, SessionUnknown};
+
+/// The domain separation prefix of the messages signed for tokens
+const TOKEN_DOMAIN: &[u8] = b"blindsign ecash token";
+
+/// The mint, which signs withdrawals and verifies spent tokens
+pub struct Mint {
+    keypair: BlindKeypair,
+    sessions: SessionManager,
+    reissues: SessionManager,
+    // The nullifier of the spent token of each open reissue, along with the
+    // time after which it is released, as for the sessions
+    pending: Mutex<HashMap<SessionId, (Nullifier, u64)>>,
+}
+
+impl Mint {
+    /// Creates a mint signing with the provided keypair, allowing
+    /// DEFAULT_MAX_OPEN_SESSIONS concurrent withdrawals and as many
+    /// concurrent reissues, each open for DEFAULT_SESSION_TTL.
+    pub fn new(keypair: BlindKeypair) -> Self {
+        Mint {
+            sessions: SessionManager::new(keypair.signing_key()),
+            reissues: SessionManager::new(keypair.signing_key()),
+            pending: Mutex::new(HashMap::new()),
+            keypair,
+        }
+    }
+
+    /// The same as new, but with a custom cap on concurrent withdrawals and
+    /// on concurrent reissues, as for SessionManager::with_max_open.
+    pub fn with_max_open(keypair: BlindKeypair, max_open: usize) -> Self {
+        Mint {
+            sessions: SessionManager::with_max_open(keypair.signing_key(), max_open),
+            reissues: SessionManager::with_max_open(keypair.signing_key(), max_open),
+            pending: Mutex::new(HashMap::new()),
+            keypair,
+        }
+    }
+
+    /// Returns the verifying key of the mint, which wallets need to receive
+    /// tokens
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.keypair.verifying_key()
+    }
+
+    /// Starts a withdrawal of one token at the provided time, as for
+    /// SessionManager::start.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((SessionId, WiredRPrime)) on success, with the SessionId and R'
+    /// being sent to the wallet.
+    ///
+    /// * Err(::Error) on failure, which indicates either that the cap on open
+    /// withdrawals has been reached, or the failure to initiate the RNG.
+    pub fn start_withdrawal(&self, now: u64) -> ::Result<(SessionId, WiredRPrime)> {
+        self.sessions.start(now)
+    }
+
+    /// Finishes the withdrawal with the provided id, signing e' from the
+    /// wallet. This is the point at which the wallet should be charged.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(WiredSPrime) on success, with S' being sent to the wallet.
+    ///
+    /// * Err(::Error) on failure, which indicates either that there is no
+    /// pending withdrawal with the provided id, or the failure to initiate
+    /// the internal RNG.
+    pub fn finish_withdrawal(&self, id: &SessionId, ep: &WiredEPrime) -> ::Result<WiredSPrime> {
+        self.sessions.finish(id, ep)
+    }
+
+    /// Aborts the withdrawal with the provided id, returning true if there
+    /// was such a pending withdrawal.
+    pub fn abort_withdrawal(&self, id: &SessionId) -> bool {
+        self.sessions.abort(id)
+    }
+
+    /// Verifies a spent token, without checking whether it was spent before.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm that was used by the wallet
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the token was issued by this mint.
+    ///
+    /// * False to indicate that the token is not valid.
+    pub fn verify<H>(&self, proof: &SpendProof) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        proof.verify::<H>(&self.keypair.verifying_key())
+    }
+
+    /// Redeems a spent token, verifying it and then recording its nullifier
+    /// in the store, as for spent::redeem.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Nullifier) on success, the nullifier that was recorded.
+    ///
+    /// * Err(::Error) on failure, which indicates that the token was not
+    /// issued by this mint, that it was already spent, or that the store
+    /// failed.
+    pub fn redeem<H, S>(&self, store: &S, proof: &SpendProof) -> ::Result<Nullifier>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        S: SpentStore + ?Sized,
+    {
+        spent::redeem::<H, Vec<u8>, S>(
+            store,
+            &self.keypair.verifying_key(),
+            &proof.token.sig,
+            token_msg(&proof.token.serial),
+        )
+    }
+
+    /// Starts the exchange of a spent token for a fresh one at the provided
+    /// time, verifying the spent token and holding it as pending against the
+    /// returned SessionId. The exchange is finished with finish_reissue,
+    /// without the wallet being charged, which is the point at which the
+    /// spent token is redeemed.
+    ///
+    /// The spent token is released if the reissue is aborted with
+    /// abort_reissue, or once its ttl passes, so that it can be reissued
+    /// again.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((SessionId, WiredRPrime)) on success, with the SessionId and R'
+    /// being sent to the wallet.
+    ///
+    /// * Err(::Error) on failure, which indicates that the token was not
+    /// issued by this mint, that it is pending in another reissue, that the
+    /// cap on open reissues has been reached, or the failure to initiate the
+    /// RNG.
+    ///
+    /// # Note
+    ///
+    /// `now` is expressed in whatever unit of time the caller uses, as for
+    /// start_withdrawal.
+    pub fn start_reissue<H>(
+        &self,
+        proof: &SpendProof,
+        now: u64,
+    ) -> ::Result<(SessionId, WiredRPrime)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        if !self.verify::<H>(proof) {
+            return Err(EcashTokenInvalid);
+        }
+        let nullifier = Nullifier::from_msg::<H, Vec<u8>>(token_msg(&proof.token.serial));
+        let mut pending = self.pending();
+        pending.retain(|_, &mut (_, deadline)| now <= deadline);
+        if pending.values().any(|&(other, _)| other == nullifier) {
+            return Err(ReissuePending);
+        }
+        let (id, rp) = self.reissues.start(now)?;
+        pending.insert(id, (nullifier, now.saturating_add(self.reissues.ttl())));
+        Ok((id, rp))
+    }
+
+    /// Finishes the reissue with the provided id, recording the nullifier of
+    /// its spent token in the store and only then signing e' from the wallet,
+    /// so that the spent token is redeemed exactly when the fresh one is
+    /// signed.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(WiredSPrime) on success, with S' being sent to the wallet.
+    ///
+    /// * Err(::Error) on failure, which indicates either that there is no
+    /// pending reissue with the provided id, that the spent token was already
+    /// spent, or that the store failed, in which case nothing is signed and
+    /// the reissue is consumed.
+    pub fn finish_reissue<S>(
+        &self,
+        store: &S,
+        id: &SessionId,
+        ep: &WiredEPrime,
+    ) -> ::Result<WiredSPrime>
+    where
+        S: SpentStore + ?Sized,
+    {
+        let (nullifier, _) = self.pending().remove(id).ok_or(SessionUnknown)?;
+        self.reissues.finish_committed(id, ep, || {
+            if !store.insert_if_absent(&nullifier)? {
+                return Err(AlreadySpent);
+            }
+            Ok(())
+        })
+    }
+
+    /// Aborts the reissue with the provided id, releasing its spent token and
+    /// returning true if there was such a pending reissue.
+    pub fn abort_reissue(&self, id: &SessionId) -> bool {
+        self.pending().remove(id);
+        self.reissues.abort(id)
+    }
+
+    /// Locks the pending reissues. The map is never left in an inconsistent
+    /// state, so it remains usable even if another thread panicked while
+    /// holding the lock.
+    fn pending(&self) -> MutexGuard<HashMap<SessionId, (Nullifier, u64)>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A token held by a wallet, a signature on a random serial number
+#[derive(Copy, Clone)]
+pub struct Token {
+    serial: [u8; 32],
+    sig: UnblindedSigData,
+}
+
+impl fmt::Debug for Token {
+    // The serial is redacted as, once spent, it links the token to its spend
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Token")
+            .field("serial", &Redacted)
+            .field("sig", &self.sig)
+            .finish()
+    }
+}
+
+impl Token {
+    /// Returns the serial number of the token
+    pub fn serial(&self) -> [u8; 32] {
+        self.serial
+    }
+}
+
+/// The wallet, which withdraws tokens from one mint and stores them
+pub struct Wallet {
+    mint: VerifyingKey,
+    pending: HashMap<SessionId, ([u8; 32], BlindRequest)>,
+    tokens: Vec<Token>,
+}
+
+impl Wallet {
+    /// Creates an empty wallet for the mint with the provided verifying key
+    pub fn new(mint: VerifyingKey) -> Self {
+        Wallet {
+            mint,
+            pending: HashMap::new(),
+            tokens: Vec::new(),
+        }
+    }
+
+    /// Creates a wallet holding previously stored tokens. Tokens that are not
+    /// valid under the mint key are dropped.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm that was used for withdrawing the tokens
+    pub fn with_tokens<H>(mint: VerifyingKey, tokens: &[SpendProof]) -> Self
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut wallet = Wallet::new(mint);
+        wallet.tokens = tokens
+            .iter()
+            .filter(|proof| proof.verify::<H>(&mint))
+            .map(|proof| proof.token)
+            .collect();
+        wallet
+    }
+
+    /// Returns the number of stored tokens
+    pub fn balance(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Returns the stored tokens
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Blinds a fresh serial number for the withdrawal with the provided id
+    /// and R' from the mint.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for generating e
+    ///
+    /// # Returns
+    ///
+    /// * Ok(WiredEPrime) on success, with e' being sent to the mint.
+    ///
+    /// * Err(::Error) on failure, which indicates the failure to initiate the
+    /// internal RNG.
+    pub fn withdraw<H>(&mut self, id: SessionId, rp: &WiredRPrime) -> ::Result<WiredEPrime>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut serial = [0; 32];
+        rng::try_new()?.fill_bytes(&mut serial);
+        let (ep, req) = BlindRequest::new_specific_msg::<H, Vec<u8>>(rp, token_msg(&serial))?;
+        self.pending.insert(id, (serial, req));
+        Ok(ep)
+    }
+
+    /// Unblinds S' from the mint for the withdrawal with the provided id,
+    /// storing the token after verifying it.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Token) on success, with the Token also being stored.
+    ///
+    /// * Err(::Error) on failure, which indicates either that there is no
+    /// pending withdrawal with the provided id, or that the token did not
+    /// verify under the mint key. The withdrawal is removed either way.
+    pub fn receive<H>(&mut self, id: &SessionId, sp: &WiredSPrime) -> ::Result<Token>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let (serial, req) = self.pending.remove(id).ok_or(SessionUnknown)?;
+        let token = Token {
+            serial,
+            sig: req.gen_signed_msg(sp),
+        };
+        if !token
+            .sig
+            .msg_authenticate::<H, Vec<u8>>(&self.mint, token_msg(&serial))
+        {
+            return Err(EcashTokenInvalid);
+        }
+        self.tokens.push(token);
+        Ok(token)
+    }
+
+    /// Removes a token from the wallet for spending, returning None if the
+    /// wallet is empty.
+    pub fn spend(&mut self) -> Option<SpendProof> {
+        self.tokens.pop().map(|token| SpendProof { token })
+    }
+}
+
+/// A token being spent, which is sent from the wallet to the mint
+#[derive(Copy, Clone, Debug)]
+pub struct SpendProof {
+    token: Token,
+}
+
+impl SpendProof {
+    /// Returns the serial number of the token being spent, which the mint
+    /// records to detect double spending
+    pub fn serial(&self) -> [u8; 32] {
+        self.token.serial
+    }
+
+    /// Returns the signature of the token being spent
+    pub fn signature(&self) -> UnblindedSigData {
+        self.token.sig
+    }
+
+    /// Verifies that the token was issued under the provided mint key.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the token is valid.
+    ///
+    /// * False to indicate that the token is not valid.
+    pub fn verify<H>(&self, mint: &VerifyingKey) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        self.token
+            .sig
+            .msg_authenticate::<H, Vec<u8>>(mint, token_msg(&self.token.serial))
+    }
+}
+
+/// The SpendProof in wired form, serial || e || S || R
+pub struct WiredSpendProof(pub [u8; 128]);
+
+impl From<SpendProof> for WiredSpendProof {
+    fn from(proof: SpendProof) -> Self {
+        let mut arr = [0; 128];
+        arr[0..32].copy_from_slice(&proof.token.serial);
+        arr[32..128].copy_from_slice(&WiredUnblindedSigData::from(proof.token.sig).0);
+        WiredSpendProof(arr)
+    }
+}
+
+impl WiredSpendProof {
+    /// Converts the wired form into the internal SpendProof.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(SpendProof) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the signature was
+    /// malformed.
+    pub fn to_internal_format(&self) -> ::Result<SpendProof> {
+        let mut serial = [0; 32];
+        serial.copy_from_slice(&self.0[0..32]);
+        let sig = WiredUnblindedSigData::from_bytes(&self.0[32..128])?.to_internal_format()?;
+        Ok(SpendProof {
+            token: Token { serial, sig },
+        })
+    }
+}
+
+impl_wired_bytes!(WiredSpendProof, 128);
+impl_wired_envelope!(WiredSpendProof, PayloadType::SpendProof);
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Encodes the message signed for the token with the provided serial number
+fn token_msg(serial: &[u8; 32]) -> Vec<u8> {
+    let mut msg = TOKEN_DOMAIN.to_vec();
+    msg.extend_from_slice(serial);
+    msg
+}
@@ -0,0 +1,237 @@
+//! Crate internal text encodings of byte strings
+use Error::{Base64Malformed, Bech32HrpMismatch, Bech32Malformed, HexMalformed};
+
+/// The lowercase hex alphabet
+const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+
+/// The standard base64 alphabet
+const BASE64_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The bech32 alphabet
+const BECH32_CHARS: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// The generator coefficients of the bech32 checksum
+const BECH32_GENERATORS: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+
+/// The number of characters in the bech32 checksum
+const BECH32_CHECKSUM_LEN: usize = 6;
+
+/// Encodes the bytes as a lowercase hex string
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        hex.push(HEX_CHARS[(b >> 4) as usize] as char);
+        hex.push(HEX_CHARS[(b & 0x0f) as usize] as char);
+    }
+    hex
+}
+
+/// Decodes a hex string of either case into bytes, erroring if the string has
+/// an odd length or contains non hex characters.
+pub(crate) fn from_hex(hex: &str) -> ::Result<Vec<u8>> {
+    let hex = hex.as_bytes();
+    if hex.len() % 2 != 0 {
+        return Err(HexMalformed);
+    }
+    hex.chunks(2)
+        .map(|pair| Ok(hex_value(pair[0])? << 4 | hex_value(pair[1])?))
+        .collect()
+}
+
+/// Encodes the bytes as a padded standard base64 string
+#[cfg_attr(not(feature = "pkcs8"), allow(dead_code))]
+pub(crate) fn to_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_CHARS[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decodes a padded standard base64 string into bytes, erroring if the string
+/// is not canonical base64.
+#[cfg_attr(not(feature = "pkcs8"), allow(dead_code))]
+pub(crate) fn from_base64(encoded: &str) -> ::Result<Vec<u8>> {
+    let encoded = encoded.as_bytes();
+    if encoded.len() % 4 != 0 {
+        return Err(Base64Malformed);
+    }
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3);
+    for (i, chunk) in encoded.chunks(4).enumerate() {
+        let last = i == encoded.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return Err(Base64Malformed);
+        }
+        let mut n: u32 = 0;
+        for &c in &chunk[..4 - padding] {
+            let value = BASE64_CHARS
+                .iter()
+                .position(|&b| b == c)
+                .ok_or(Base64Malformed)?;
+            n = n << 6 | value as u32;
+        }
+        n <<= 6 * padding as u32;
+        let decoded = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+        // Canonical encodings have zeroed bits beneath the padding.
+        if decoded[3 - padding..].iter().any(|&b| b != 0) {
+            return Err(Base64Malformed);
+        }
+        bytes.extend_from_slice(&decoded[..3 - padding]);
+    }
+    Ok(bytes)
+}
+
+/// Encodes the bytes as a bech32 string with the provided human readable part,
+/// as described in BIP 173. The 90 character length limit of BIP 173 is not
+/// applied, as the wired types are longer than addresses.
+pub(crate) fn to_bech32(hrp: &str, bytes: &[u8]) -> ::Result<String> {
+    if !valid_hrp(hrp) || hrp.bytes().any(|c| c.is_ascii_uppercase()) {
+        return Err(Bech32Malformed);
+    }
+    let mut data = convert_bits(bytes, 8, 5, true)?;
+    let checksum = bech32_checksum(hrp.as_bytes(), &data);
+    data.extend_from_slice(&checksum);
+    let mut encoded = String::with_capacity(hrp.len() + 1 + data.len());
+    encoded.push_str(hrp);
+    encoded.push('1');
+    for d in data {
+        encoded.push(BECH32_CHARS[d as usize] as char);
+    }
+    Ok(encoded)
+}
+
+/// Decodes a bech32 string of either (but not mixed) case into bytes, erroring
+/// if the checksum is invalid or the human readable part is not the expected
+/// one.
+pub(crate) fn from_bech32(expected_hrp: &str, encoded: &str) -> ::Result<Vec<u8>> {
+    let has_lower = encoded.bytes().any(|c| c.is_ascii_lowercase());
+    let has_upper = encoded.bytes().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err(Bech32Malformed);
+    }
+    let encoded = encoded.to_ascii_lowercase();
+    let separator = encoded.rfind('1').ok_or(Bech32Malformed)?;
+    let (hrp, data) = (&encoded[..separator], &encoded[separator + 1..]);
+    if !valid_hrp(hrp) || data.len() < BECH32_CHECKSUM_LEN {
+        return Err(Bech32Malformed);
+    }
+    if hrp != expected_hrp.to_ascii_lowercase() {
+        return Err(Bech32HrpMismatch);
+    }
+    let data = data
+        .bytes()
+        .map(|c| {
+            BECH32_CHARS
+                .iter()
+                .position(|&b| b == c)
+                .map(|p| p as u8)
+                .ok_or(Bech32Malformed)
+        })
+        .collect::<::Result<Vec<u8>>>()?;
+    let mut values = hrp_expand(hrp.as_bytes());
+    values.extend_from_slice(&data);
+    if bech32_polymod(&values) != 1 {
+        return Err(Bech32Malformed);
+    }
+    convert_bits(&data[..data.len() - BECH32_CHECKSUM_LEN], 5, 8, false)
+}
+
+/// Returns true if the human readable part consists of 1 to 83 printable
+/// US-ASCII characters.
+fn valid_hrp(hrp: &str) -> bool {
+    !hrp.is_empty() && hrp.len() <= 83 && hrp.bytes().all(|c| c >= 33 && c <= 126)
+}
+
+/// Computes the bech32 checksum of the human readable part and data
+fn bech32_checksum(hrp: &[u8], data: &[u8]) -> [u8; BECH32_CHECKSUM_LEN] {
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0; BECH32_CHECKSUM_LEN]);
+    let polymod = bech32_polymod(&values) ^ 1;
+    let mut checksum = [0; BECH32_CHECKSUM_LEN];
+    for (i, c) in checksum.iter_mut().enumerate() {
+        *c = ((polymod >> (5 * (5 - i))) & 0x1f) as u8;
+    }
+    checksum
+}
+
+/// Expands the human readable part for use in the checksum computation
+fn hrp_expand(hrp: &[u8]) -> Vec<u8> {
+    let mut expanded: Vec<u8> = hrp.iter().map(|c| c >> 5).collect();
+    expanded.push(0);
+    expanded.extend(hrp.iter().map(|c| c & 0x1f));
+    expanded
+}
+
+/// The bech32 checksum polynomial, modulo the bech32 generator
+fn bech32_polymod(values: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+    for &v in values {
+        let top = chk >> 25;
+        chk = (chk & 0x01ff_ffff) << 5 ^ u32::from(v);
+        for (i, g) in BECH32_GENERATORS.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+/// Regroups the bits of the input from groups of `from` bits into groups of
+/// `to` bits. When not padding, leftover bits must be fewer than `from` and
+/// all zero.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> ::Result<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let max = (1 << to) - 1;
+    let max_acc = (1 << (from + to - 1)) - 1;
+    let mut converted = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        acc = ((acc << from) | u32::from(value)) & max_acc;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            converted.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            converted.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return Err(Bech32Malformed);
+    }
+    Ok(converted)
+}
+
+/// Returns the value of a single hex character
+#[cfg_attr(not(feature = "serde"), allow(dead_code))]
+fn hex_value(c: u8) -> ::Result<u8> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(HexMalformed),
+    }
+}
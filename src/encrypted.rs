@@ -0,0 +1,177 @@
+//! Password encrypted export and import of the ECC keys
+//!
+//! The private key is encrypted with ChaCha20-Poly1305 under a key derived
+//! from the password with Argon2id. The exported blob is self describing, so
+//! that it can be imported without knowing the parameters it was exported
+//! with:
+//!
+//! version (1) || kdf (1) || mem_cost (4) || time_cost (4) || lanes (4) ||
+//! salt (16) || nonce (12) || ciphertext (64) || tag (16)
+//!
+//! The integers are big endian, and the header preceding the ciphertext is
+//! authenticated as associated data so the parameters cannot be altered.
+use argon2::{self, Config, Variant};
+use keypair::{BlindKeypair, WiredPrivateKey, WiredPublicKey};
+use rand::RngCore;
+use rng;
+use seal;
+use Error::{EncryptedKeyMalformed, KeyDerivationFailed};
+
+/// The current version of the encrypted key format
+const FORMAT_VERSION: u8 = 1;
+
+/// The identifier of the Argon2id key derivation function
+const KDF_ARGON2ID: u8 = 1;
+
+/// The length of the Argon2id salt
+const SALT_LEN: usize = 16;
+
+/// The length of the header, which precedes the ciphertext
+const HEADER_LEN: usize = 2 + 12 + SALT_LEN + seal::NONCE_LEN;
+
+/// The length of the encrypted private || public key
+const SEALED_LEN: usize = 64 + seal::TAG_LEN;
+
+/// The largest memory cost accepted on import, in KiB, which guards against
+/// crafted blobs requesting unbounded memory.
+const MAX_MEM_COST: u32 = 4 * 1024 * 1024;
+
+/// The largest time cost accepted on import
+const MAX_TIME_COST: u32 = 64;
+
+/// The largest degree of parallelism accepted on import
+const MAX_LANES: u32 = 64;
+
+/// The Argon2id parameters used for deriving the encryption key
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// The memory cost in KiB
+    pub mem_cost: u32,
+    /// The number of passes over the memory
+    pub time_cost: u32,
+    /// The degree of parallelism
+    pub lanes: u32,
+}
+
+impl Default for KdfParams {
+    /// 64 MiB of memory over 3 passes with a single lane
+    fn default() -> Self {
+        KdfParams {
+            mem_cost: 64 * 1024,
+            time_cost: 3,
+            lanes: 1,
+        }
+    }
+}
+
+impl BlindKeypair {
+    /// Exports the keypair encrypted under the provided password, using the
+    /// default key derivation parameters.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Vec<u8>) on success, which is the self describing encrypted blob.
+    ///
+    /// * Err(::Error) on failure, which indicates the internal RNG failed to
+    /// initiate or that key derivation failed.
+    pub fn export_encrypted(&self, password: &[u8]) -> ::Result<Vec<u8>> {
+        self.export_encrypted_with_params(password, KdfParams::default())
+    }
+
+    /// The same as export_encrypted, but with the provided key derivation
+    /// parameters.
+    pub fn export_encrypted_with_params(
+        &self,
+        password: &[u8],
+        params: KdfParams,
+    ) -> ::Result<Vec<u8>> {
+        let mut salt = [0; SALT_LEN];
+        rng::try_new()?.fill_bytes(&mut salt);
+        let nonce = seal::random_nonce()?;
+
+        let mut blob = Vec::with_capacity(HEADER_LEN + SEALED_LEN);
+        blob.push(FORMAT_VERSION);
+        blob.push(KDF_ARGON2ID);
+        blob.extend_from_slice(&u32_to_be(params.mem_cost));
+        blob.extend_from_slice(&u32_to_be(params.time_cost));
+        blob.extend_from_slice(&u32_to_be(params.lanes));
+        blob.extend_from_slice(&salt);
+        blob.extend_from_slice(&nonce);
+
+        let key = derive_key(password, &salt, params)?;
+        let mut plaintext = [0; 64];
+        plaintext[0..32].copy_from_slice(self.private_wired().as_bytes());
+        plaintext[32..64].copy_from_slice(&self.verifying_key().wired());
+        let sealed = seal::seal(&key, &nonce, &blob, &plaintext);
+        blob.extend_from_slice(&sealed);
+        Ok(blob)
+    }
+
+    /// Imports a keypair from a blob produced by export_encrypted.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlindKeypair) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the blob was malformed
+    /// or requested excessive key derivation parameters, that the password was
+    /// wrong, that the blob was tampered with, or that its public key did not
+    /// match its private key.
+    pub fn import_encrypted(password: &[u8], blob: &[u8]) -> ::Result<Self> {
+        if blob.len() != HEADER_LEN + SEALED_LEN
+            || blob[0] != FORMAT_VERSION
+            || blob[1] != KDF_ARGON2ID
+        {
+            return Err(EncryptedKeyMalformed);
+        }
+        let params = KdfParams {
+            mem_cost: u32_from_be(&blob[2..6]),
+            time_cost: u32_from_be(&blob[6..10]),
+            lanes: u32_from_be(&blob[10..14]),
+        };
+        if params.mem_cost > MAX_MEM_COST
+            || params.time_cost > MAX_TIME_COST
+            || params.lanes > MAX_LANES
+        {
+            return Err(EncryptedKeyMalformed);
+        }
+        let (header, sealed) = blob.split_at(HEADER_LEN);
+        let salt = &header[14..14 + SALT_LEN];
+        let mut nonce = [0; seal::NONCE_LEN];
+        nonce.copy_from_slice(&header[14 + SALT_LEN..]);
+
+        let key = derive_key(password, salt, params)?;
+        let plaintext = seal::open(&key, &nonce, header, sealed)?;
+        let private = WiredPrivateKey::from_bytes(&plaintext[0..32])?;
+        let public = WiredPublicKey::from_bytes(&plaintext[32..64])?;
+        BlindKeypair::from_wired(private, public)
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Derives the 32 byte encryption key from the password with Argon2id
+fn derive_key(password: &[u8], salt: &[u8], params: KdfParams) -> ::Result<[u8; 32]> {
+    let config = Config {
+        variant: Variant::Argon2id,
+        mem_cost: params.mem_cost,
+        time_cost: params.time_cost,
+        lanes: params.lanes,
+        hash_length: 32,
+        ..Config::default()
+    };
+    let hash = argon2::hash_raw(password, salt, &config).map_err(|_| KeyDerivationFailed)?;
+    let mut key = [0; 32];
+    key.copy_from_slice(&hash);
+    Ok(key)
+}
+
+/// Encodes a u32 as big endian bytes
+fn u32_to_be(n: u32) -> [u8; 4] {
+    [(n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8, n as u8]
+}
+
+/// Decodes a u32 from big endian bytes
+fn u32_from_be(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |n, &b| (n << 8) | u32::from(b))
+}
@@ -0,0 +1,209 @@
+//! Versioned envelope encoding for the wired types
+//!
+//! The envelope consists of version || type || payload, where the version is a
+//! single byte identifying the envelope format, the type is a single byte
+//! identifying which wired type is carried, and the payload is the wired type
+//! itself. Parsers reject envelopes with unknown versions or types rather than
+//! guessing at their layout, so new formats can be introduced under a new
+//! version or type without being misinterpreted by older parsers.
+use Error::{
+    EnvelopePayloadTypeMismatch, EnvelopePayloadTypeUnknown, EnvelopeTruncated,
+    EnvelopeVersionUnsupported, WiredLengthInvalid,
+};
+
+/// The current envelope format version
+pub const VERSION: u8 = 1;
+
+/// The length of the envelope header, which is the version and type bytes
+pub const HEADER_LEN: usize = 2;
+
+/// The types of wired payload that can be carried in an envelope
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PayloadType {
+    /// WiredUnblindedSigData, e || S || R
+    UnblindedSigData = 1,
+    /// CompactSignature, e || S
+    CompactSignature = 2,
+    /// WiredPublicKey
+    PublicKey = 3,
+    /// WiredRPrime
+    RPrime = 4,
+    /// WiredEPrime
+    EPrime = 5,
+    /// WiredSPrime
+    SPrime = 6,
+    /// WiredEpochSigData, epoch || e || S || R
+    EpochUnblindedSigData = 7,
+    /// WiredClauseRPrimes, R'0 || R'1
+    ClauseRPrimes = 8,
+    /// WiredClauseEPrimes, e'0 || e'1
+    ClauseEPrimes = 9,
+    /// WiredClauseSPrime, b || S'b
+    ClauseSPrime = 10,
+    /// WiredPartialCommitment, A || B
+    PartialCommitment = 11,
+    /// WiredPartialResponse, r || c || s || d
+    PartialResponse = 12,
+    /// WiredPartialSignature, ρ || ω || σ || δ
+    PartialSignature = 13,
+    /// WiredBlsBlindedMsg
+    BlsBlindedMsg = 14,
+    /// WiredBlsBlindSig
+    BlsBlindSig = 15,
+    /// WiredBlsSignature
+    BlsSignature = 16,
+    /// WiredBlsPublicKey
+    BlsPublicKey = 17,
+    /// WiredOkamotoSPrime, S1 || S2
+    OkamotoSPrime = 18,
+    /// WiredOkamotoSignature, e || S1 || S2
+    OkamotoSignature = 19,
+    /// WiredFairEscrow, A1 || B1 || A2 || B2 || c || zu || zv || z1 || z2
+    FairEscrow = 20,
+    /// WiredRecoverableSignature, e || S
+    RecoverableSignature = 21,
+    /// WiredIbsSignature, U || V
+    IbsSignature = 22,
+    /// WiredBbsSignature, A || e || s
+    BbsSignature = 23,
+    /// WiredSpendProof, serial || e || S || R
+    SpendProof = 24,
+    /// WiredExpiringToken, expiry || ρ || ω || σ || δ
+    ExpiringToken = 25,
+    /// WiredSignedBallot, election || choice || serial || e || S || R
+    SignedBallot = 26,
+    /// WiredVoucher, campaign || value || id || ρ || ω || σ || δ
+    Voucher = 27,
+}
+
+impl PayloadType {
+    /// Returns the PayloadType associated with the type byte of an envelope.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(PayloadType) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the type byte is unknown.
+    pub fn from_u8(tag: u8) -> ::Result<Self> {
+        match tag {
+            1 => Ok(PayloadType::UnblindedSigData),
+            2 => Ok(PayloadType::CompactSignature),
+            3 => Ok(PayloadType::PublicKey),
+            4 => Ok(PayloadType::RPrime),
+            5 => Ok(PayloadType::EPrime),
+            6 => Ok(PayloadType::SPrime),
+            7 => Ok(PayloadType::EpochUnblindedSigData),
+            8 => Ok(PayloadType::ClauseRPrimes),
+            9 => Ok(PayloadType::ClauseEPrimes),
+            10 => Ok(PayloadType::ClauseSPrime),
+            11 => Ok(PayloadType::PartialCommitment),
+            12 => Ok(PayloadType::PartialResponse),
+            13 => Ok(PayloadType::PartialSignature),
+            14 => Ok(PayloadType::BlsBlindedMsg),
+            15 => Ok(PayloadType::BlsBlindSig),
+            16 => Ok(PayloadType::BlsSignature),
+            17 => Ok(PayloadType::BlsPublicKey),
+            18 => Ok(PayloadType::OkamotoSPrime),
+            19 => Ok(PayloadType::OkamotoSignature),
+            20 => Ok(PayloadType::FairEscrow),
+            21 => Ok(PayloadType::RecoverableSignature),
+            22 => Ok(PayloadType::IbsSignature),
+            23 => Ok(PayloadType::BbsSignature),
+            24 => Ok(PayloadType::SpendProof),
+            25 => Ok(PayloadType::ExpiringToken),
+            26 => Ok(PayloadType::SignedBallot),
+            27 => Ok(PayloadType::Voucher),
+            _ => Err(EnvelopePayloadTypeUnknown(tag)),
+        }
+    }
+
+    /// Returns the type byte of the PayloadType
+    pub fn to_u8(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Returns the length of the payload carried by this PayloadType
+    pub fn payload_len(&self) -> usize {
+        match *self {
+            PayloadType::FairEscrow => 288,
+            PayloadType::Voucher => 172,
+            PayloadType::SignedBallot => 140,
+            PayloadType::ExpiringToken => 136,
+            PayloadType::BbsSignature => 112,
+            PayloadType::PartialResponse
+            | PayloadType::PartialSignature
+            | PayloadType::SpendProof => 128,
+            PayloadType::EpochUnblindedSigData => 100,
+            PayloadType::UnblindedSigData
+            | PayloadType::BlsPublicKey
+            | PayloadType::OkamotoSignature
+            | PayloadType::IbsSignature => 96,
+            PayloadType::CompactSignature
+            | PayloadType::ClauseRPrimes
+            | PayloadType::ClauseEPrimes
+            | PayloadType::PartialCommitment
+            | PayloadType::OkamotoSPrime
+            | PayloadType::RecoverableSignature => 64,
+            PayloadType::BlsBlindedMsg
+            | PayloadType::BlsBlindSig
+            | PayloadType::BlsSignature => 48,
+            PayloadType::ClauseSPrime => 33,
+            PayloadType::PublicKey
+            | PayloadType::RPrime
+            | PayloadType::EPrime
+            | PayloadType::SPrime => 32,
+        }
+    }
+}
+
+/// Decodes an envelope, returning the type of the payload along with a
+/// reference to the payload itself.
+///
+/// # Returns
+///
+/// * Ok((PayloadType, &[u8])) on success.
+///
+/// * Err(::Error) on failure, which indicates that the envelope was truncated,
+/// had an unsupported version, had an unknown payload type, or had a payload
+/// of the wrong length for its type.
+pub fn decode(bytes: &[u8]) -> ::Result<(PayloadType, &[u8])> {
+    if bytes.len() < HEADER_LEN {
+        return Err(EnvelopeTruncated);
+    }
+    if bytes[0] != VERSION {
+        return Err(EnvelopeVersionUnsupported(bytes[0]));
+    }
+    let payload_type = PayloadType::from_u8(bytes[1])?;
+    let payload = &bytes[HEADER_LEN..];
+    if payload.len() != payload_type.payload_len() {
+        return Err(WiredLengthInvalid {
+            expected: payload_type.payload_len(),
+            actual: payload.len(),
+        });
+    }
+    Ok((payload_type, payload))
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Wraps the payload in an envelope of the current version.
+///
+/// pub(crate) as used by the wired types
+pub(crate) fn encode(payload_type: PayloadType, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.push(VERSION);
+    bytes.push(payload_type.to_u8());
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+/// Decodes an envelope, erroring if the payload is not of the expected type.
+///
+/// pub(crate) as used by the wired types
+pub(crate) fn decode_expecting(expected: PayloadType, bytes: &[u8]) -> ::Result<&[u8]> {
+    let (payload_type, payload) = decode(bytes)?;
+    if payload_type != expected {
+        return Err(EnvelopePayloadTypeMismatch);
+    }
+    Ok(payload)
+}
@@ -0,0 +1,224 @@
+//! Expiring tokens, with the expiry bound into the signature by the signer
+//!
+//! The signer chooses the expiry of each token it issues and binds it into
+//! the signature as the public info of a partially blind signature, so that
+//! the issuer bounds its liability window while the message stays blind. The
+//! requester learns the expiry from the signer alongside the commitment and
+//! only needs to check that it is acceptable.
+//!
+//! 1. The signer starts an ExpiringSession for the expiry, sending the
+//!    commitment and the expiry to the requester.
+//! 2. The requester blinds the commitment with ExpiringRequest::new.
+//! 3. The signer signs the challenge with ExpiringSession::sign_e.
+//! 4. The requester unblinds the response into an ExpiringToken, which is
+//!    checked on redemption with ExpiringToken::verify_not_expired.
+//!
+//! # Security
+//!
+//! Every distinct expiry partitions the set of tokens a redemption could have
+//! come from, so signers should issue coarse expiries, such as the end of the
+//! current epoch as returned by epoch_end, rather than per token ones.
+//!
+//! # Note
+//!
+//! Expiries are expressed in whatever unit of time the caller uses for `now`,
+//! as with KeySet. As with the plain protocol, this **does not** include
+//! **any** networking code.
+use digest::Digest;
+use envelope::PayloadType;
+use keypair::{SigningKey, VerifyingKey};
+use partially_blind::{PartialRequest, PartialSession, PartialSignature, WiredPartialSignature};
+use typenum::U64;
+use Error::{ExpiringTokenInvalid, TokenExpired};
+
+/// The domain separation prefix of the expiry info
+const EXPIRY_DOMAIN: &[u8] = b"blindsign expiry";
+
+/// Returns the last moment of the epoch of the provided length containing
+/// now, which is a coarse expiry for tokens issued at now.
+pub fn epoch_end(now: u64, epoch_len: u64) -> u64 {
+    (now / epoch_len)
+        .saturating_add(1)
+        .saturating_mul(epoch_len)
+        .saturating_sub(1)
+}
+
+/// For managing the signer side of issuing an expiring token.
+pub struct ExpiringSession {
+    session: PartialSession,
+}
+
+impl ExpiringSession {
+    /// Initiate a new signer side session for a token expiring at the
+    /// provided time.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for hashing the expiry to a point
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 64], ExpiringSession)) on success, with the [u8; 64] being
+    /// the commitment for sending to the requester along with the expiry.
+    ///
+    /// * Err(::Error) on failure, which is only due to the failure to
+    /// initiate the internal random number generator.
+    pub fn new<H>(expiry: u64) -> ::Result<([u8; 64], Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let (commitment, session) = PartialSession::new::<H>(&expiry_info(expiry))?;
+        Ok((commitment, ExpiringSession { session }))
+    }
+
+    /// Signs the challenge e from the requester, consuming the session.
+    ///
+    /// # Returns
+    ///
+    /// * Ok([u8; 128]) on success, with the response being sent to the
+    /// requester.
+    ///
+    /// * Err(::Error) on failure, which indicates e was malformed.
+    pub fn sign_e(self, e: &[u8; 32], xs: &SigningKey) -> ::Result<[u8; 128]> {
+        self.session.sign_e(e, xs)
+    }
+}
+
+/// For managing the requester side of issuing an expiring token.
+pub struct ExpiringRequest {
+    expiry: u64,
+    request: PartialRequest,
+}
+
+impl ExpiringRequest {
+    /// Blinds the commitment received from the signer for the expiry it sent
+    /// along with it, and the message to be signed.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], ExpiringRequest)) on success, with the [u8; 32] being
+    /// the challenge e for sending to the signer.
+    ///
+    /// * Err(::Error) on failure, which could be caused by the failure to
+    /// initiate the RNG, or otherwise being input a malformed commitment.
+    pub fn new<H, M>(
+        commitment: &[u8; 64],
+        pub_key: &VerifyingKey,
+        expiry: u64,
+        m: M,
+    ) -> ::Result<([u8; 32], Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        let (e, request) =
+            PartialRequest::new::<H, M>(commitment, pub_key, &expiry_info(expiry), m)?;
+        Ok((e, ExpiringRequest { expiry, request }))
+    }
+
+    /// Input the response from the signer, consumes self and creates the
+    /// expiring token. This does not verify the signature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(ExpiringToken) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the response contained
+    /// a malformed scalar.
+    pub fn gen_signed_msg(self, response: &[u8; 128]) -> ::Result<ExpiringToken> {
+        Ok(ExpiringToken {
+            expiry: self.expiry,
+            sig: self.request.gen_signed_msg(response)?,
+        })
+    }
+}
+
+/// A partially blind signature on a message, along with the expiry bound
+/// into it.
+#[derive(Copy, Clone, Debug)]
+pub struct ExpiringToken {
+    expiry: u64,
+    sig: PartialSignature,
+}
+
+impl ExpiringToken {
+    /// Returns the expiry of the token
+    pub fn expiry(&self) -> u64 {
+        self.expiry
+    }
+
+    /// Verifies the signature on the message and its expiry, and that the
+    /// token has not expired at the provided time. Tokens are valid up to and
+    /// including their expiry.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(()) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the signature was not
+    /// valid on the message and expiry, or that the token has expired.
+    pub fn verify_not_expired<H, M>(
+        &self,
+        pub_key: &VerifyingKey,
+        msg: M,
+        now: u64,
+    ) -> ::Result<()>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        if !self
+            .sig
+            .authenticate::<H, M>(pub_key, &expiry_info(self.expiry), msg)
+        {
+            return Err(ExpiringTokenInvalid);
+        }
+        if now > self.expiry {
+            return Err(TokenExpired(self.expiry));
+        }
+        Ok(())
+    }
+}
+
+/// The ExpiringToken in wired form, expiry || ρ || ω || σ || δ, with the
+/// expiry being an 8 byte big endian integer.
+pub struct WiredExpiringToken(pub [u8; 136]);
+
+impl From<ExpiringToken> for WiredExpiringToken {
+    fn from(token: ExpiringToken) -> Self {
+        let mut arr = [0; 136];
+        arr[0..8].copy_from_slice(&token.expiry.to_be_bytes());
+        arr[8..136].copy_from_slice(&WiredPartialSignature::from(token.sig).0);
+        WiredExpiringToken(arr)
+    }
+}
+
+impl WiredExpiringToken {
+    /// Converts the wired form into the internal ExpiringToken.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(ExpiringToken) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates a malformed scalar.
+    pub fn to_internal_format(&self) -> ::Result<ExpiringToken> {
+        let mut expiry = [0; 8];
+        expiry.copy_from_slice(&self.0[0..8]);
+        Ok(ExpiringToken {
+            expiry: u64::from_be_bytes(expiry),
+            sig: WiredPartialSignature::from_bytes(&self.0[8..136])?.to_internal_format()?,
+        })
+    }
+}
+
+impl_wired_bytes!(WiredExpiringToken, 136);
+impl_wired_envelope!(WiredExpiringToken, PayloadType::ExpiringToken);
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Encodes the expiry as the public info of the partially blind signature
+fn expiry_info(expiry: u64) -> Vec<u8> {
+    let mut info = EXPIRY_DOMAIN.to_vec();
+    info.extend_from_slice(&expiry.to_be_bytes());
+    info
+}
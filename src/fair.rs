@@ -0,0 +1,458 @@
+//! Fair blind signatures with a revocation authority
+//!
+//! A designated trustee can link a signature back to the session it was issued
+//! in, or a session forward to the signature issued in it, for example given a
+//! court order, while the signer alone still cannot. The signatures themselves
+//! are ordinary UnblindedSigData values.
+//!
+//! Alongside e', the requester sends an escrow, which is an ElGamal encryption
+//! under the trustee key of both R and e*P, along with a zero knowledge proof
+//! that they were derived from this session's R' and e' with the same blinding
+//! factors as the signature will be. The signer refuses to sign unless the
+//! proof verifies, and stores the escrow as its record of the session. Only
+//! the trustee can decrypt an escrow, recovering the R and e of the signature.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::Identity,
+};
+use digest::Digest;
+use encoding;
+use envelope::PayloadType;
+use keypair::{Redacted, SigningKey};
+use request;
+use rng::{self, SecureRng};
+use signature::UnblindedSigData;
+use std::fmt;
+use typenum::U64;
+use wipe;
+use Error::{
+    FairEscrowInvalid, PublicKeyInvalid, WiredRistrettoPointMalformed, WiredScalarMalformed,
+};
+
+/// The domain separation prefix for escrow proof challenges
+const ESCROW_DOMAIN: &[u8] = b"blindsign fair escrow";
+
+/// The keypair of the trustee, which is the only party able to trace
+/// signatures and sessions.
+#[derive(Copy, Clone)]
+pub struct TrusteeKeypair {
+    private: Scalar,
+    public: RistrettoPoint,
+}
+
+impl fmt::Debug for TrusteeKeypair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TrusteeKeypair")
+            .field("private", &Redacted)
+            .field("public", &encoding::to_hex(self.public.compress().as_bytes()))
+            .finish()
+    }
+}
+
+impl TrusteeKeypair {
+    /// Generates a new random trustee keypair.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(TrusteeKeypair) on success.
+    ///
+    /// * Err(::Error) on error, which can only be the failure to initiate the
+    /// internal RNG.
+    pub fn generate() -> ::Result<Self> {
+        let private = Scalar::random(&mut rng::try_new()?);
+        Ok(TrusteeKeypair {
+            private,
+            public: private * RISTRETTO_BASEPOINT_POINT,
+        })
+    }
+
+    /// Creates the trustee keypair from its private key in wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(TrusteeKeypair) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the private key was not a
+    /// canonical scalar.
+    pub fn from_wired(private: [u8; 32]) -> ::Result<Self> {
+        let private = Scalar::from_canonical_bytes(private).ok_or(WiredScalarMalformed)?;
+        Ok(TrusteeKeypair {
+            private,
+            public: private * RISTRETTO_BASEPOINT_POINT,
+        })
+    }
+
+    /// Returns the private key in wired form
+    pub fn private_wired(&self) -> [u8; 32] {
+        self.private.to_bytes()
+    }
+
+    /// Returns the public trustee key, which is published to signers and
+    /// requesters.
+    pub fn trustee_key(&self) -> TrusteeKey {
+        TrusteeKey {
+            public: self.public,
+        }
+    }
+
+    /// Traces a session forward to the signature issued in it, by decrypting
+    /// the escrow stored by the signer.
+    ///
+    /// # Returns
+    ///
+    /// * Ok([u8; 32]) on success, which is the R component of the signature
+    /// issued in the session, and which is unique to that signature.
+    ///
+    /// * Err(::Error) on failure, which indicates the escrow was malformed.
+    pub fn trace_session(&self, escrow: &WiredFairEscrow) -> ::Result<[u8; 32]> {
+        let (r, _) = self.open(escrow)?;
+        Ok(r.compress().to_bytes())
+    }
+
+    /// Traces a signature back to the session it was issued in, by checking
+    /// it against each of the escrows stored by the signer.
+    ///
+    /// # Returns
+    ///
+    /// * Some(usize) with the index of the escrow of the session that the
+    /// signature was issued in.
+    ///
+    /// * None if the signature was not issued in any of the sessions.
+    pub fn trace_signature(
+        &self,
+        sig: &UnblindedSigData,
+        escrows: &[WiredFairEscrow],
+    ) -> Option<usize> {
+        let e = sig.e() * RISTRETTO_BASEPOINT_POINT;
+        escrows.iter().position(|escrow| match self.open(escrow) {
+            Ok(opened) => opened == (sig.r(), e),
+            Err(_) => false,
+        })
+    }
+
+    /// Decrypts an escrow into the R and e*P values of the signature
+    fn open(&self, escrow: &WiredFairEscrow) -> ::Result<(RistrettoPoint, RistrettoPoint)> {
+        let a1 = point(&escrow.0[0..32])?;
+        let b1 = point(&escrow.0[32..64])?;
+        let a2 = point(&escrow.0[64..96])?;
+        let b2 = point(&escrow.0[96..128])?;
+        Ok((b1 - self.private * a1, b2 - self.private * a2))
+    }
+}
+
+/// The public key of the trustee
+#[derive(Copy, Clone, Debug)]
+pub struct TrusteeKey {
+    public: RistrettoPoint,
+}
+
+impl TrusteeKey {
+    /// Creates the trustee key from its wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(TrusteeKey) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the key was not a valid
+    /// compressed Ristretto point, or was the identity.
+    pub fn from_wired(public: [u8; 32]) -> ::Result<Self> {
+        let public = point(&public)?;
+        if public == RistrettoPoint::identity() {
+            return Err(PublicKeyInvalid);
+        }
+        Ok(TrusteeKey { public })
+    }
+
+    /// Returns the trustee key in wired form
+    pub fn wired(&self) -> [u8; 32] {
+        self.public.compress().to_bytes()
+    }
+}
+
+/// For managing the signer side of a fair blind signature session.
+///
+/// The nonce k is wiped from memory when the session is dropped, as it
+/// reveals the private key through S' = Xs*e' + k.
+pub struct FairSession {
+    k: Scalar,
+}
+
+impl Drop for FairSession {
+    fn drop(&mut self) {
+        wipe::wipe_scalar(&mut self.k);
+    }
+}
+
+impl FairSession {
+    /// Initiate a new signer side session, as with BlindSession::new.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], FairSession)) on success, with the [u8; 32] being the
+    /// value R' for sending to the requester.
+    ///
+    /// * Err(::Error) variant on failure, which is only due to the failure to
+    /// initiate the internal random number generator.
+    pub fn new() -> ::Result<([u8; 32], Self)> {
+        let k = Scalar::random(&mut rng::try_new()?);
+        let rp = (k * RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
+        Ok((rp, FairSession { k }))
+    }
+
+    /// Consumes the session and returns the blind signature on e', provided
+    /// that the escrow sent alongside e' is valid for the trustee key. The
+    /// escrow should be stored as the record of the session, as it is what the
+    /// trustee traces.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for the escrow proof
+    ///
+    /// # Returns
+    ///
+    /// * Ok([u8; 32]) on success, representing the completed blind signature
+    /// value S'.
+    ///
+    /// * Err(::Error) variant on error, which indicates that ep was malformed,
+    /// or that the escrow was malformed or its proof did not verify.
+    ///
+    /// # Mathematics
+    ///
+    /// * S' = Xs*e' + k
+    pub fn sign_ep<H>(
+        mut self,
+        ep: &[u8; 32],
+        escrow: &WiredFairEscrow,
+        trustee: &TrusteeKey,
+        xs: &SigningKey,
+    ) -> ::Result<[u8; 32]>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let ep = Scalar::from_canonical_bytes(*ep).ok_or(WiredScalarMalformed)?;
+        let statement = Statement {
+            trustee: trustee.public,
+            rp: self.k * RISTRETTO_BASEPOINT_POINT,
+            ep: ep * RISTRETTO_BASEPOINT_POINT,
+        };
+        if !statement.verify::<H>(escrow)? {
+            return Err(FairEscrowInvalid);
+        }
+        let sp = xs.scalar() * ep + self.k;
+        // k must never be used twice, so is wiped before S' is even returned
+        wipe::wipe_scalar(&mut self.k);
+        Ok(sp.to_bytes())
+    }
+}
+
+/// For managing the requester side of a fair blind signature session.
+pub struct FairRequest {
+    u: Scalar,
+    v: Scalar,
+    r: RistrettoPoint,
+    e: Scalar,
+}
+
+impl FairRequest {
+    /// Blinds R' for the message m as with BlindRequest, additionally creating
+    /// the escrow for the trustee.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for generating e and for the escrow proof
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], WiredFairEscrow, FairRequest)) on success. The [u8; 32]
+    /// represents the value e', which is sent to the signer along with the
+    /// escrow.
+    ///
+    /// * Err(::Error) variant on error, which could be caused by the failure to
+    /// initiate the RNG, or otherwise being input a malformed R' value.
+    ///
+    /// # Mathematics
+    ///
+    /// * R = u*R' + v*P, e = H(R||m), e' = e / u
+    /// * A1 = k1*P, B1 = R + k1*T
+    /// * A2 = k2*P, B2 = e*P + k2*T
+    /// * T = The trustee key
+    pub fn new<H, M>(
+        rp: &[u8; 32],
+        trustee: &TrusteeKey,
+        m: M,
+    ) -> ::Result<([u8; 32], WiredFairEscrow, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        let mut rng = rng::try_new()?;
+        let rp = point(rp)?;
+        let u = Scalar::random(&mut rng);
+        let v = Scalar::random(&mut rng);
+        let r = u * rp + v * RISTRETTO_BASEPOINT_POINT;
+        let e = request::generate_e::<H>(r, m.as_ref());
+        let ep = u.invert() * e;
+
+        let statement = Statement {
+            trustee: trustee.public,
+            rp,
+            ep: ep * RISTRETTO_BASEPOINT_POINT,
+        };
+        let escrow = statement.prove::<H>(
+            &Witness {
+                u,
+                v,
+                k1: Scalar::random(&mut rng),
+                k2: Scalar::random(&mut rng),
+            },
+            &mut rng,
+        );
+        Ok((ep.to_bytes(), escrow, FairRequest { u, v, r, e }))
+    }
+
+    /// Input the blinded signature S' from the signer, consumes self and
+    /// creates the complete blindly signed message structure, as with
+    /// BlindRequest::gen_signed_msg.
+    pub fn gen_signed_msg(self, sp: &[u8; 32]) -> ::Result<UnblindedSigData> {
+        let sp = Scalar::from_canonical_bytes(*sp).ok_or(WiredScalarMalformed)?;
+        Ok(UnblindedSigData::new(self.e, sp * self.u + self.v, self.r))
+    }
+}
+
+/// The escrow in wired form, A1 || B1 || A2 || B2 || c || zu || zv || z1 || z2
+pub struct WiredFairEscrow(pub [u8; 288]);
+
+impl_wired_bytes!(WiredFairEscrow, 288);
+impl_wired_envelope!(WiredFairEscrow, PayloadType::FairEscrow);
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// The public values that the escrow proof is about
+struct Statement {
+    trustee: RistrettoPoint,
+    rp: RistrettoPoint,
+    ep: RistrettoPoint,
+}
+
+/// The secret values that the escrow proof is about
+struct Witness {
+    u: Scalar,
+    v: Scalar,
+    k1: Scalar,
+    k2: Scalar,
+}
+
+impl Statement {
+    /// Creates the escrow, proving knowledge of u, v, k1 and k2 such that
+    ///
+    /// * A1 = k1*P
+    /// * B1 = u*R' + v*P + k1*T
+    /// * A2 = k2*P
+    /// * B2 = u*(e'*P) + k2*T
+    fn prove<H>(&self, w: &Witness, rng: &mut SecureRng) -> WiredFairEscrow
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let p = RISTRETTO_BASEPOINT_POINT;
+        let ciphertexts = [
+            w.k1 * p,
+            w.u * self.rp + w.v * p + w.k1 * self.trustee,
+            w.k2 * p,
+            w.u * self.ep + w.k2 * self.trustee,
+        ];
+        let (wu, wv, w1, w2) = (
+            Scalar::random(rng),
+            Scalar::random(rng),
+            Scalar::random(rng),
+            Scalar::random(rng),
+        );
+        let commitments = [
+            w1 * p,
+            wu * self.rp + wv * p + w1 * self.trustee,
+            w2 * p,
+            wu * self.ep + w2 * self.trustee,
+        ];
+        let c = self.challenge::<H>(&ciphertexts, &commitments);
+
+        let mut escrow = [0; 288];
+        for (i, ciphertext) in ciphertexts.iter().enumerate() {
+            escrow[32 * i..32 * (i + 1)].copy_from_slice(ciphertext.compress().as_bytes());
+        }
+        let responses = [c, wu + c * w.u, wv + c * w.v, w1 + c * w.k1, w2 + c * w.k2];
+        for (i, response) in responses.iter().enumerate() {
+            escrow[128 + 32 * i..160 + 32 * i].copy_from_slice(response.as_bytes());
+        }
+        WiredFairEscrow(escrow)
+    }
+
+    /// Verifies the proof of an escrow, recomputing the commitments from the
+    /// responses and checking the challenge.
+    fn verify<H>(&self, escrow: &WiredFairEscrow) -> ::Result<bool>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let p = RISTRETTO_BASEPOINT_POINT;
+        let bytes = &escrow.0;
+        let ciphertexts = [
+            point(&bytes[0..32]).map_err(|_| FairEscrowInvalid)?,
+            point(&bytes[32..64]).map_err(|_| FairEscrowInvalid)?,
+            point(&bytes[64..96]).map_err(|_| FairEscrowInvalid)?,
+            point(&bytes[96..128]).map_err(|_| FairEscrowInvalid)?,
+        ];
+        let c = scalar(&bytes[128..160])?;
+        let zu = scalar(&bytes[160..192])?;
+        let zv = scalar(&bytes[192..224])?;
+        let z1 = scalar(&bytes[224..256])?;
+        let z2 = scalar(&bytes[256..288])?;
+        let commitments = [
+            z1 * p - c * ciphertexts[0],
+            zu * self.rp + zv * p + z1 * self.trustee - c * ciphertexts[1],
+            z2 * p - c * ciphertexts[2],
+            zu * self.ep + z2 * self.trustee - c * ciphertexts[3],
+        ];
+        Ok(self.challenge::<H>(&ciphertexts, &commitments) == c)
+    }
+
+    /// Computes the challenge c = H(domain || T || R' || e'P || ciphertexts ||
+    /// commitments)
+    fn challenge<H>(
+        &self,
+        ciphertexts: &[RistrettoPoint],
+        commitments: &[RistrettoPoint],
+    ) -> Scalar
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut hasher = H::default();
+        hasher.input(ESCROW_DOMAIN);
+        for value in [self.trustee, self.rp, self.ep]
+            .iter()
+            .chain(ciphertexts)
+            .chain(commitments)
+        {
+            hasher.input(value.compress().as_bytes());
+        }
+        Scalar::from_hash(hasher)
+    }
+}
+
+/// Loads a 32 byte slice as a canonical scalar
+fn scalar(bytes: &[u8]) -> ::Result<Scalar> {
+    let mut arr = [0; 32];
+    arr.copy_from_slice(bytes);
+    Scalar::from_canonical_bytes(arr).ok_or(FairEscrowInvalid)
+}
+
+/// Loads a 32 byte slice as a compressed ristretto point
+fn point(bytes: &[u8]) -> ::Result<RistrettoPoint> {
+    CompressedRistretto::from_slice(bytes)
+        .decompress()
+        .ok_or(WiredRistrettoPointMalformed)
+}
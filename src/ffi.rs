@@ -0,0 +1,303 @@
+//! A C ABI over the plain protocol, for consumption from other languages
+//!
+//! Keypairs, sessions and requests are passed across the boundary as opaque
+//! handles, created by the `_new` and `_generate` functions and released by
+//! the matching `_free` functions, while every wired value is passed as a
+//! pointer to a buffer of its fixed length: 32 bytes for keys, R', e' and S',
+//! and 96 bytes for unblinded signatures. Functions return BLINDSIGN_OK on
+//! success and one of the negative error codes on failure, and functions
+//! creating handles return null on failure. The hash algorithm is always
+//! SHA3-512. The declarations for C are in include/blindsign.h.
+//!
+//! The crate is built as a C library with, for example,
+//! `cargo rustc --release --features ffi --crate-type cdylib`.
+//!
+//! # Safety
+//!
+//! Every pointer must be null or valid for its documented length, and every
+//! handle must have been created by this library and not yet consumed or
+//! freed. Handles must not be shared between threads without
+//! synchronization.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use keypair::{BlindKeypair, VerifyingKey, WiredPrivateKey, WiredPublicKey};
+use request::{BlindRequest, WiredEPrime};
+use session::{BlindSession, WiredRPrime, WiredSPrime};
+use sha3::Sha3_512;
+use signature::WiredUnblindedSigData;
+use std::{ptr, slice};
+use Error;
+
+/// Returned on success
+pub const BLINDSIGN_OK: i32 = 0;
+
+/// Returned if a required pointer was null
+pub const BLINDSIGN_ERR_NULL: i32 = -1;
+
+/// Returned if the internal RNG could not be initiated
+pub const BLINDSIGN_ERR_RNG: i32 = -2;
+
+/// Returned if a wired input was malformed
+pub const BLINDSIGN_ERR_MALFORMED: i32 = -3;
+
+/// Returned on any other failure
+pub const BLINDSIGN_ERR_FAILED: i32 = -4;
+
+/// Generates a new keypair.
+///
+/// # Returns
+///
+/// * The handle of the keypair on success.
+///
+/// * Null on failure, which indicates the failure to initiate the RNG.
+#[no_mangle]
+pub extern "C" fn blindsign_keypair_generate() -> *mut BlindKeypair {
+    match BlindKeypair::generate() {
+        Ok(keypair) => Box::into_raw(Box::new(keypair)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Loads a keypair from its 32 byte wired private and public keys.
+///
+/// # Returns
+///
+/// * The handle of the keypair on success.
+///
+/// * Null on failure, which indicates that a pointer was null or the keys
+/// were malformed or did not match, that is the public key was not the
+/// private key times the generator point.
+#[no_mangle]
+pub unsafe extern "C" fn blindsign_keypair_from_wired(
+    private: *const u8,
+    public: *const u8,
+) -> *mut BlindKeypair {
+    let (private, public) = match (bytes(private, 32), bytes(public, 32)) {
+        (Some(private), Some(public)) => (private, public),
+        _ => return ptr::null_mut(),
+    };
+    let keypair = match (WiredPrivateKey::from_bytes(private), WiredPublicKey::from_bytes(public)) {
+        (Ok(private), Ok(public)) => BlindKeypair::from_wired(private, public),
+        (Err(e), _) | (_, Err(e)) => Err(e),
+    };
+    match keypair {
+        Ok(keypair) => Box::into_raw(Box::new(keypair)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Writes the 32 byte wired public key of the keypair to out.
+#[no_mangle]
+pub unsafe extern "C" fn blindsign_keypair_public(
+    keypair: *const BlindKeypair,
+    out: *mut u8,
+) -> i32 {
+    match keypair.as_ref() {
+        Some(keypair) => write(out, &keypair.verifying_key().wired()),
+        None => BLINDSIGN_ERR_NULL,
+    }
+}
+
+/// Writes the 32 byte wired private key of the keypair to out, for storing.
+#[no_mangle]
+pub unsafe extern "C" fn blindsign_keypair_private(
+    keypair: *const BlindKeypair,
+    out: *mut u8,
+) -> i32 {
+    match keypair.as_ref() {
+        Some(keypair) => write(out, keypair.private_wired().as_bytes()),
+        None => BLINDSIGN_ERR_NULL,
+    }
+}
+
+/// Releases the keypair. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn blindsign_keypair_free(keypair: *mut BlindKeypair) {
+    if !keypair.is_null() {
+        drop(Box::from_raw(keypair));
+    }
+}
+
+/// Starts a new signer side session, writing the 32 byte R' to rp_out.
+///
+/// # Returns
+///
+/// * The handle of the session on success.
+///
+/// * Null on failure, which indicates that rp_out was null, or the failure
+/// to initiate the RNG.
+#[no_mangle]
+pub unsafe extern "C" fn blindsign_session_new(rp_out: *mut u8) -> *mut BlindSession {
+    if rp_out.is_null() {
+        return ptr::null_mut();
+    }
+    match BlindSession::new() {
+        Ok((rp, session)) => {
+            write(rp_out, rp.as_bytes());
+            Box::into_raw(Box::new(session))
+        }
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Signs the 32 byte e' with the keypair, writing the 32 byte S' to sp_out.
+/// The session is consumed, and must not be used or freed afterwards, even
+/// on failure, unless the failure was BLINDSIGN_ERR_NULL.
+#[no_mangle]
+pub unsafe extern "C" fn blindsign_sign_ep(
+    session: *mut BlindSession,
+    keypair: *const BlindKeypair,
+    ep: *const u8,
+    sp_out: *mut u8,
+) -> i32 {
+    let (keypair, ep) = match (keypair.as_ref(), bytes(ep, 32)) {
+        (Some(keypair), Some(ep)) if !session.is_null() && !sp_out.is_null() => (keypair, ep),
+        _ => return BLINDSIGN_ERR_NULL,
+    };
+    let session = Box::from_raw(session);
+    let xs = keypair.signing_key();
+    let signed = WiredEPrime::from_bytes(ep).and_then(|ep| session.sign_ep(&ep, &xs));
+    match signed {
+        Ok(sp) => write(sp_out, sp.as_bytes()),
+        Err(e) => code(&e),
+    }
+}
+
+/// Releases the session without signing. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn blindsign_session_free(session: *mut BlindSession) {
+    if !session.is_null() {
+        drop(Box::from_raw(session));
+    }
+}
+
+/// Blinds the message of msg_len bytes with the 32 byte R' from the signer,
+/// writing the 32 byte e' to ep_out.
+///
+/// # Returns
+///
+/// * The handle of the request on success.
+///
+/// * Null on failure, which indicates that a pointer was null, that R' was
+/// malformed, or the failure to initiate the RNG.
+#[no_mangle]
+pub unsafe extern "C" fn blindsign_request_new(
+    rp: *const u8,
+    msg: *const u8,
+    msg_len: usize,
+    ep_out: *mut u8,
+) -> *mut BlindRequest {
+    let (rp, msg) = match (bytes(rp, 32), bytes(msg, msg_len)) {
+        (Some(rp), Some(msg)) if !ep_out.is_null() => (rp, msg),
+        _ => return ptr::null_mut(),
+    };
+    let blinded = WiredRPrime::from_bytes(rp)
+        .and_then(|rp| BlindRequest::new_specific_msg::<Sha3_512, &[u8]>(&rp, msg));
+    match blinded {
+        Ok((ep, request)) => {
+            write(ep_out, ep.as_bytes());
+            Box::into_raw(Box::new(request))
+        }
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Unblinds the 32 byte S' from the signer, writing the 96 byte wired
+/// signature to sig_out. The request is consumed, and must not be used or
+/// freed afterwards, even on failure, unless the failure was
+/// BLINDSIGN_ERR_NULL. As with BlindRequest::gen_signed_msg, the signature is
+/// not verified.
+#[no_mangle]
+pub unsafe extern "C" fn blindsign_request_unblind(
+    request: *mut BlindRequest,
+    sp: *const u8,
+    sig_out: *mut u8,
+) -> i32 {
+    let sp = match bytes(sp, 32) {
+        Some(sp) if !request.is_null() && !sig_out.is_null() => sp,
+        _ => return BLINDSIGN_ERR_NULL,
+    };
+    let request = Box::from_raw(request);
+    match WiredSPrime::from_bytes(sp) {
+        Ok(sp) => write(sig_out, &WiredUnblindedSigData::from(request.gen_signed_msg(&sp)).0),
+        Err(e) => code(&e),
+    }
+}
+
+/// Releases the request without unblinding. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn blindsign_request_free(request: *mut BlindRequest) {
+    if !request.is_null() {
+        drop(Box::from_raw(request));
+    }
+}
+
+/// Authenticates the 96 byte wired signature on the message of msg_len bytes
+/// under the 32 byte wired public key.
+///
+/// # Returns
+///
+/// * 1 if the signature is valid.
+///
+/// * 0 if the signature is not valid.
+///
+/// * A negative error code if a pointer was null or an input was malformed.
+#[no_mangle]
+pub unsafe extern "C" fn blindsign_authenticate(
+    public: *const u8,
+    sig: *const u8,
+    msg: *const u8,
+    msg_len: usize,
+) -> i32 {
+    let (public, sig, msg) = match (bytes(public, 32), bytes(sig, 96), bytes(msg, msg_len)) {
+        (Some(public), Some(sig), Some(msg)) => (public, sig, msg),
+        _ => return BLINDSIGN_ERR_NULL,
+    };
+    let pub_key = match WiredPublicKey::from_bytes(public).and_then(VerifyingKey::from_wired) {
+        Ok(pub_key) => pub_key,
+        Err(e) => return code(&e),
+    };
+    let sig = match WiredUnblindedSigData::from_bytes(sig).and_then(|w| w.to_internal_format()) {
+        Ok(sig) => sig,
+        Err(e) => return code(&e),
+    };
+    sig.msg_authenticate::<Sha3_512, &[u8]>(&pub_key, msg) as i32
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Maps an error to its error code
+fn code(e: &Error) -> i32 {
+    match *e {
+        Error::RngInitFailed => BLINDSIGN_ERR_RNG,
+        Error::WiredScalarMalformed
+        | Error::WiredRistrettoPointMalformed
+        | Error::WiredFieldMalformed { .. }
+        | Error::WiredLengthInvalid { .. } => BLINDSIGN_ERR_MALFORMED,
+        _ => BLINDSIGN_ERR_FAILED,
+    }
+}
+
+/// Borrows len bytes at ptr, or None if ptr is null. A zero length message
+/// may be passed with a null pointer.
+unsafe fn bytes<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    if len == 0 {
+        Some(&[])
+    } else if ptr.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(ptr, len))
+    }
+}
+
+/// Copies the bytes to out, which must be valid for their length
+unsafe fn write(out: *mut u8, bytes: &[u8]) -> i32 {
+    if out.is_null() {
+        return BLINDSIGN_ERR_NULL;
+    }
+    ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len());
+    BLINDSIGN_OK
+}
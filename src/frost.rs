@@ -0,0 +1,307 @@
+//! FROST style two round nonce generation for threshold sessions
+//!
+//! With a plain ThresholdSession each node commits to a single nonce, and the
+//! last node to reveal its commitment can bias R'. Here each node instead
+//! commits to a pair of nonces (Di, Ei), and R' is derived from the whole
+//! commitment list with per node binding factors, so that no node (nor the
+//! party relaying the commitments) can choose R', and no trusted coordinator
+//! is needed to pick k. Anyone holding the commitment list and context can
+//! compute R' and aggregate the partial signatures.
+//!
+//! 1. Each participating node starts a FrostSession, publishing its
+//!    NonceCommitment.
+//! 2. R' is computed from the commitment list with group_commitment, and sent
+//!    to the requester, who responds with e'.
+//! 3. Each node signs e' given the commitment list, producing a
+//!    PartialBlindSig.
+//! 4. The partial signatures are verified and summed into S' with aggregate.
+//!
+//! The context must be unique to the session, for example a session id chosen
+//! by the requester, and the same for every party.
+//!
+//! # Mathematics
+//!
+//! * ρi = H(i || context || B), B = The encoded commitment list
+//! * R' = (D1 + ρ1*E1) + ... + (Dt + ρt*Et)
+//! * S'i = di + ρi*ei + λi*Xi*e'
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::Identity,
+};
+use digest::Digest;
+use request::WiredEPrime;
+use rng;
+use session::{WiredRPrime, WiredSPrime};
+use threshold::{self, KeyShare, PartialBlindSig, ShareCommitments};
+use typenum::U64;
+use wipe;
+use Error::{ThresholdPartialInvalid, ThresholdSignersInvalid, WiredRistrettoPointMalformed};
+
+/// The domain separation prefix for binding factors
+const BINDING_DOMAIN: &[u8] = b"blindsign frost binding";
+
+/// The commitment (Di, Ei) of one node to its pair of nonces
+#[derive(Copy, Clone, Debug)]
+pub struct NonceCommitment {
+    index: u32,
+    d: RistrettoPoint,
+    e: RistrettoPoint,
+}
+
+impl NonceCommitment {
+    /// Returns the index of the node that created the commitment
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the commitment in wired form, index || Di || Ei
+    pub fn wired(&self) -> [u8; 68] {
+        let mut arr = [0; 68];
+        arr[0..4].copy_from_slice(&self.index.to_be_bytes());
+        arr[4..36].copy_from_slice(self.d.compress().as_bytes());
+        arr[36..68].copy_from_slice(self.e.compress().as_bytes());
+        arr
+    }
+
+    /// Loads the commitment from its wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(NonceCommitment) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the index was 0 or either
+    /// point was malformed.
+    pub fn from_wired(wired: [u8; 68]) -> ::Result<Self> {
+        let mut index = [0; 4];
+        index.copy_from_slice(&wired[0..4]);
+        let index = u32::from_be_bytes(index);
+        if index == 0 {
+            return Err(ThresholdSignersInvalid);
+        }
+        Ok(NonceCommitment {
+            index,
+            d: point(&wired[4..36])?,
+            e: point(&wired[36..68])?,
+        })
+    }
+}
+
+/// For managing the side of one signer node in a FROST style session.
+///
+/// The nonces d and e are wiped from memory when the session is dropped, as
+/// they reveal the share through S'i = di + ρi*ei + λi*Xi*e'.
+pub struct FrostSession {
+    index: u32,
+    d: Scalar,
+    e: Scalar,
+}
+
+impl Drop for FrostSession {
+    fn drop(&mut self) {
+        wipe::wipe_scalar(&mut self.d);
+        wipe::wipe_scalar(&mut self.e);
+    }
+}
+
+impl FrostSession {
+    /// Initiate a new session on the signer node holding the share.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((NonceCommitment, FrostSession)) on success, with the
+    /// NonceCommitment being published to the other parties.
+    ///
+    /// * Err(::Error) on failure, which is only due to the failure to initiate
+    /// the internal random number generator.
+    pub fn new(share: &KeyShare) -> ::Result<(NonceCommitment, Self)> {
+        let mut rng = rng::try_new()?;
+        let d = Scalar::random(&mut rng);
+        let e = Scalar::random(&mut rng);
+        let commitment = NonceCommitment {
+            index: share.index(),
+            d: d * RISTRETTO_BASEPOINT_POINT,
+            e: e * RISTRETTO_BASEPOINT_POINT,
+        };
+        let session = FrostSession {
+            index: share.index(),
+            d,
+            e,
+        };
+        Ok((commitment, session))
+    }
+
+    /// Consumes the session and returns the partial blind signature on e'.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for binding factors
+    ///
+    /// # Returns
+    ///
+    /// * Ok(PartialBlindSig) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the share is not that
+    /// of this session, or that the commitment list does not contain the
+    /// commitment of this session or has repeated indices.
+    ///
+    /// # Mathematics
+    ///
+    /// * S'i = di + ρi*ei + λi*Xi*e'
+    pub fn sign_ep<H>(
+        mut self,
+        ep: &WiredEPrime,
+        commitments: &[NonceCommitment],
+        context: &[u8],
+        share: &KeyShare,
+    ) -> ::Result<PartialBlindSig>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let ep = ep.scalar();
+        let own = commitments
+            .iter()
+            .find(|commitment| commitment.index == self.index)
+            .ok_or(ThresholdSignersInvalid)?;
+        if share.index() != self.index
+            || own.d != self.d * RISTRETTO_BASEPOINT_POINT
+            || own.e != self.e * RISTRETTO_BASEPOINT_POINT
+        {
+            return Err(ThresholdSignersInvalid);
+        }
+        let rho = binding_factor::<H>(self.index, commitments, context);
+        let lambda = threshold::lagrange(self.index, &indices(commitments))?;
+        let sp = self.d + rho * self.e + lambda * share.private() * ep;
+        // The nonces must never be used twice, so are wiped before S'i is even
+        // returned
+        wipe::wipe_scalar(&mut self.d);
+        wipe::wipe_scalar(&mut self.e);
+        Ok(PartialBlindSig::new(self.index, sp))
+    }
+}
+
+/// Computes R' from the commitment list, for sending to the requester.
+///
+/// # Returns
+///
+/// * Ok(WiredRPrime) on success, representing R'.
+///
+/// * Err(::Error) on failure, which indicates the commitment list was empty or
+/// had repeated indices, or that R' was the identity.
+///
+/// # Mathematics
+///
+/// * R' = (D1 + ρ1*E1) + ... + (Dt + ρt*Et)
+pub fn group_commitment<H>(
+    commitments: &[NonceCommitment],
+    context: &[u8],
+) -> ::Result<WiredRPrime>
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    if commitments.is_empty() {
+        return Err(ThresholdSignersInvalid);
+    }
+    threshold::check_signers(&indices(commitments))?;
+    let rp = commitments
+        .iter()
+        .fold(RistrettoPoint::identity(), |acc, commitment| {
+            acc + node_commitment::<H>(commitment, commitments, context)
+        });
+    WiredRPrime::from_bytes(rp.compress().as_bytes())
+}
+
+/// Verifies each partial signature, and sums them into the blind signature S'
+/// for the requester.
+///
+/// # Returns
+///
+/// * Ok(WiredSPrime) on success, representing the blind signature S'.
+///
+/// * Err(::Error) on failure, which indicates that fewer than t nodes
+/// participated, or that a partial signature was missing or invalid,
+/// identifying the node.
+///
+/// # Mathematics
+///
+/// * S'i*P == Di + ρi*Ei + λi*e'*(Xi*P)
+pub fn aggregate<H>(
+    shares: &ShareCommitments,
+    commitments: &[NonceCommitment],
+    context: &[u8],
+    ep: &WiredEPrime,
+    partials: &[PartialBlindSig],
+) -> ::Result<WiredSPrime>
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let ep = ep.scalar();
+    let signers = indices(commitments);
+    if signers.len() < shares.threshold() {
+        return Err(ThresholdSignersInvalid);
+    }
+    let mut sp = Scalar::zero();
+    for commitment in commitments {
+        let index = commitment.index;
+        let partial = partials
+            .iter()
+            .find(|partial| partial.index() == index)
+            .ok_or(ThresholdPartialInvalid(index))?;
+        let lambda = threshold::lagrange(index, &signers)?;
+        let expected = node_commitment::<H>(commitment, commitments, context)
+            + lambda * ep * shares.share_public(index);
+        if partial.sp() * RISTRETTO_BASEPOINT_POINT != expected {
+            return Err(ThresholdPartialInvalid(index));
+        }
+        sp += partial.sp();
+    }
+    WiredSPrime::from_bytes(sp.as_bytes())
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Returns the node indices of the commitment list
+fn indices(commitments: &[NonceCommitment]) -> Vec<u32> {
+    commitments.iter().map(|commitment| commitment.index).collect()
+}
+
+/// Computes the binding factor ρi = H(i || context || B) of node i
+fn binding_factor<H>(index: u32, commitments: &[NonceCommitment], context: &[u8]) -> Scalar
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut hasher = H::default();
+    hasher.input(BINDING_DOMAIN);
+    hasher.input(&index.to_be_bytes());
+    hasher.input(&(context.len() as u32).to_be_bytes());
+    hasher.input(context);
+    for commitment in commitments {
+        hasher.input(&commitment.wired());
+    }
+    Scalar::from_hash(hasher)
+}
+
+/// Computes the commitment Di + ρi*Ei of one node
+fn node_commitment<H>(
+    commitment: &NonceCommitment,
+    commitments: &[NonceCommitment],
+    context: &[u8],
+) -> RistrettoPoint
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    commitment.d + binding_factor::<H>(commitment.index, commitments, context) * commitment.e
+}
+
+/// Loads a 32 byte slice as a compressed ristretto point
+fn point(bytes: &[u8]) -> ::Result<RistrettoPoint> {
+    CompressedRistretto::from_slice(bytes)
+        .decompress()
+        .ok_or(WiredRistrettoPointMalformed)
+}
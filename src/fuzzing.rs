@@ -0,0 +1,112 @@
+//! Proptest strategies for property testing and fuzzing of downstream code
+//!
+//! The strategies generate wired values that parse, complete signatures that
+//! authenticate, and corrupted blobs derived from any of them, so that
+//! parsers and protocol handlers built on this crate can be tested against
+//! both. With the fuzzing feature, every wired type also implements
+//! arbitrary::Arbitrary, for use with cargo fuzz.
+//!
+//! # Note
+//!
+//! Generated keys and signatures are derived from values chosen by proptest,
+//! and must never be used outside of tests.
+use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, scalar::Scalar};
+use digest::Digest;
+use proptest::{collection::vec, prelude::*};
+use request::generate_e;
+use signature::{UnblindedSigData, WiredUnblindedSigData};
+use typenum::U64;
+
+/// A blob corrupted from a valid one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Corruption {
+    /// A single bit was flipped at the byte offset
+    BitFlip(usize),
+    /// The blob was truncated to the length
+    Truncated(usize),
+    /// Bytes were appended, giving the length
+    Extended(usize),
+}
+
+/// Generates canonical wired scalars, such as S' or e'
+pub fn scalar_bytes() -> impl Strategy<Value = [u8; 32]> {
+    any::<[u8; 32]>().prop_map(|bytes| Scalar::from_bytes_mod_order(bytes).to_bytes())
+}
+
+/// Generates valid wired Ristretto points, such as R' or public keys
+pub fn point_bytes() -> impl Strategy<Value = [u8; 32]> {
+    scalar_bytes().prop_map(|bytes| {
+        let scalar = Scalar::from_canonical_bytes(bytes).expect("scalar is canonical");
+        (scalar * RISTRETTO_BASEPOINT_POINT).compress().to_bytes()
+    })
+}
+
+/// Generates wired signatures, in the form of WiredUnblindedSigData, which
+/// parse but do not authenticate under any particular key
+pub fn sig_data_bytes() -> impl Strategy<Value = [u8; 96]> {
+    (scalar_bytes(), scalar_bytes(), point_bytes()).prop_map(|(e, s, r)| {
+        let mut arr = [0; 96];
+        arr[0..32].copy_from_slice(&e);
+        arr[32..64].copy_from_slice(&s);
+        arr[64..96].copy_from_slice(&r);
+        arr
+    })
+}
+
+/// Generates complete wired signatures on messages of up to 64 bytes, which
+/// authenticate under the generated wired public key.
+///
+/// # Type Parameters
+///
+/// H is the hash algorithm used for generating e
+///
+/// # Mathematics
+///
+/// * R = kP, for a generated nonce k
+/// * e = H(R||m)
+/// * S = ex + k, for the generated private key x
+pub fn valid_signature<H>() -> impl Strategy<Value = ([u8; 32], Vec<u8>, [u8; 96])>
+where
+    H: Digest<OutputSize = U64> + Default + 'static,
+{
+    (scalar_bytes(), scalar_bytes(), vec(any::<u8>(), 0..64)).prop_map(|(x, k, msg)| {
+        let x = Scalar::from_canonical_bytes(x).expect("scalar is canonical");
+        let k = Scalar::from_canonical_bytes(k).expect("scalar is canonical");
+        let r = k * RISTRETTO_BASEPOINT_POINT;
+        let e = generate_e::<H>(r, &msg);
+        let sig = UnblindedSigData::new(e, e * x + k, r);
+        let public = (x * RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
+        (public, msg, WiredUnblindedSigData::from(sig).0)
+    })
+}
+
+/// Corrupts blobs generated by the provided strategy, returning the
+/// corrupted blob along with how it was corrupted.
+pub fn corrupted<S>(valid: S) -> impl Strategy<Value = (Vec<u8>, Corruption)>
+where
+    S: Strategy,
+    S::Value: AsRef<[u8]>,
+{
+    (valid, any::<usize>(), 0u8..8, vec(any::<u8>(), 1..16), 0u8..3).prop_map(
+        |(blob, offset, bit, extra, kind)| {
+            let mut blob = blob.as_ref().to_vec();
+            match kind {
+                0 if !blob.is_empty() => {
+                    let offset = offset % blob.len();
+                    blob[offset] ^= 1 << bit;
+                    (blob, Corruption::BitFlip(offset))
+                }
+                1 if !blob.is_empty() => {
+                    blob.truncate(offset % blob.len());
+                    let len = blob.len();
+                    (blob, Corruption::Truncated(len))
+                }
+                _ => {
+                    blob.extend_from_slice(&extra);
+                    let len = blob.len();
+                    (blob, Corruption::Extended(len))
+                }
+            }
+        },
+    )
+}
@@ -0,0 +1,350 @@
+//! Identity-based blind signatures over BLS12-381
+//!
+//! This implements the blind variant of the Cha-Cheon identity-based
+//! signature. A private key generator (PKG) holds the master key s and
+//! publishes the master public key s*g2. Verification keys are the identity
+//! strings themselves, hashed to G1, so verifiers only need the master public
+//! key rather than a key per issuer. Each issuer is given its identity key
+//! s*H(ID) by the PKG.
+//!
+//! 1. The signer commits to U = r*H(ID), sending it to the requester.
+//! 2. The requester blinds U and the challenge, sending the blinded challenge.
+//! 3. The signer signs the blinded challenge.
+//! 4. The requester unblinds the blind signature into the signature (U', V').
+//!
+//! # Note
+//!
+//! As with the ECC protocol, this **does not** include **any** networking
+//! code.
+use bls12_381::hash_to_curve::{ExpandMsgXmd, HashToCurve};
+use bls12_381::{pairing, G1Affine, G1Projective, G2Affine, Scalar};
+use envelope::PayloadType;
+use rand::RngCore;
+use rng::{self, SecureRng};
+use sha2::{Digest, Sha256, Sha512};
+use wipe;
+use Error::{BlsPointMalformed, WiredScalarMalformed};
+
+/// The domain separation tag used for hashing identities to G1
+const ID_DST: &[u8] = b"BLINDSIGN-IBS-V01-CS01-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+
+/// The domain separation prefix for hashing to the challenge scalar
+const CHALLENGE_DOMAIN: &[u8] = b"blindsign ibs challenge";
+
+/// The master key of the private key generator
+pub struct MasterKey {
+    s: Scalar,
+}
+
+impl MasterKey {
+    /// Generates a new random master key.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(MasterKey) on success.
+    ///
+    /// * Err(::Error) on failure, which is only due to the failure to initiate
+    /// the internal random number generator.
+    pub fn generate() -> ::Result<Self> {
+        Ok(MasterKey {
+            s: nonzero_scalar(&mut rng::try_new()?),
+        })
+    }
+
+    /// Returns the master public key, which is published to verifiers
+    pub fn public_key(&self) -> MasterPublicKey {
+        MasterPublicKey {
+            point: G2Affine::from(G2Affine::generator() * self.s),
+        }
+    }
+
+    /// Extracts the identity key for the provided identity, which is given to
+    /// the issuer holding that identity.
+    ///
+    /// # Mathematics
+    ///
+    /// * D = s*H(ID)
+    pub fn extract<I>(&self, identity: I) -> IdentityKey
+    where
+        I: AsRef<[u8]>,
+    {
+        IdentityKey {
+            q: hash_to_g1(identity.as_ref()),
+            d: hash_to_g1(identity.as_ref()) * self.s,
+        }
+    }
+}
+
+/// The master public key of the private key generator
+#[derive(Copy, Clone, Debug)]
+pub struct MasterPublicKey {
+    point: G2Affine,
+}
+
+impl MasterPublicKey {
+    /// Loads the master public key from its compressed form, rejecting points
+    /// that are not in the G2 subgroup as well as the identity.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(MasterPublicKey) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the point was malformed.
+    pub fn from_wired(wired: [u8; 96]) -> ::Result<Self> {
+        let point: Option<G2Affine> = G2Affine::from_compressed(&wired).into();
+        match point {
+            Some(point) if !bool::from(point.is_identity()) => Ok(MasterPublicKey { point }),
+            _ => Err(BlsPointMalformed),
+        }
+    }
+
+    /// Returns the master public key in wired form
+    pub fn wired(&self) -> [u8; 96] {
+        self.point.to_compressed()
+    }
+}
+
+/// The identity key of an issuer, extracted by the private key generator
+pub struct IdentityKey {
+    q: G1Projective,
+    d: G1Projective,
+}
+
+impl IdentityKey {
+    /// Validates that the identity key was extracted for the identity under
+    /// the master public key, which the issuer should do upon receipt.
+    ///
+    /// # Mathematics
+    ///
+    /// * e(D, g2) == e(H(ID), Ppub)
+    pub fn validate(&self, master: &MasterPublicKey) -> bool {
+        pairing(&G1Affine::from(self.d), &G2Affine::generator())
+            == pairing(&G1Affine::from(self.q), &master.point)
+    }
+}
+
+/// For managing the signer side of an identity-based blind signature session.
+///
+/// The nonce r is wiped from memory when the session is dropped, as it
+/// reveals the identity key D through V = (r + h)*D.
+pub struct IbsSession {
+    r: Scalar,
+}
+
+impl Drop for IbsSession {
+    fn drop(&mut self) {
+        wipe::wipe_bls_scalar(&mut self.r);
+    }
+}
+
+impl IbsSession {
+    /// Initiate a new signer side session.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 48], IbsSession)) on success, with the [u8; 48] being the
+    /// commitment U for sending to the requester.
+    ///
+    /// * Err(::Error) on failure, which is only due to the failure to initiate
+    /// the internal random number generator.
+    ///
+    /// # Mathematics
+    ///
+    /// * U = r*H(ID)
+    /// * r = A randomly generated nonzero scalar by the signer
+    pub fn new(key: &IdentityKey) -> ::Result<([u8; 48], Self)> {
+        let r = nonzero_scalar(&mut rng::try_new()?);
+        Ok((G1Affine::from(key.q * r).to_compressed(), IbsSession { r }))
+    }
+
+    /// Consumes the session and returns the blind signature on the blinded
+    /// challenge h.
+    ///
+    /// # Returns
+    ///
+    /// * Ok([u8; 48]) on success, representing the blind signature V.
+    ///
+    /// * Err(::Error) on failure, which indicates that h was malformed.
+    ///
+    /// # Mathematics
+    ///
+    /// * V = (r + h)*D
+    pub fn sign_h(mut self, h: &[u8; 32], key: &IdentityKey) -> ::Result<[u8; 48]> {
+        let h: Option<Scalar> = Scalar::from_bytes(h).into();
+        let h = h.ok_or(WiredScalarMalformed)?;
+        let v = G1Affine::from(key.d * (self.r + h)).to_compressed();
+        // r must never be used twice, so is wiped before V is even returned
+        wipe::wipe_bls_scalar(&mut self.r);
+        Ok(v)
+    }
+}
+
+/// For managing the requester side of an identity-based blind signature.
+pub struct IbsRequest {
+    alpha: Scalar,
+    u: G1Projective,
+}
+
+impl IbsRequest {
+    /// Blinds the commitment U received from the signer holding the identity
+    /// for the message m.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], IbsRequest)) on success. The [u8; 32] represents the
+    /// blinded challenge h, which is sent to the signer.
+    ///
+    /// * Err(::Error) on failure, which indicates the commitment was malformed
+    /// or the failure to initiate the RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * U' = α*U + α*β*H(ID)
+    /// * h' = H(U' || m)
+    /// * h = h'/α + β
+    /// * α, β = Randomly generated scalars by the requester
+    pub fn new<I, M>(u: &[u8; 48], identity: I, m: M) -> ::Result<([u8; 32], Self)>
+    where
+        I: AsRef<[u8]>,
+        M: AsRef<[u8]>,
+    {
+        let mut rng = rng::try_new()?;
+        let u = g1_point(u)?;
+        let alpha = nonzero_scalar(&mut rng);
+        let beta = random_scalar(&mut rng);
+        let u_prime = u * alpha + hash_to_g1(identity.as_ref()) * (alpha * beta);
+        let h_prime = challenge(&u_prime, m.as_ref());
+        let h = h_prime * alpha.invert().unwrap() + beta;
+        Ok((h.to_bytes(), IbsRequest { alpha, u: u_prime }))
+    }
+
+    /// Input the blind signature V from the signer, consumes self and creates
+    /// the signature. This does not verify the signature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(IbsSignature) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that V was not a valid G1
+    /// point, or was the identity.
+    ///
+    /// # Mathematics
+    ///
+    /// * V' = α*V
+    pub fn gen_signed_msg(self, v: &[u8; 48]) -> ::Result<IbsSignature> {
+        Ok(IbsSignature {
+            u: G1Affine::from(self.u),
+            v: G1Affine::from(g1_point(v)? * self.alpha),
+        })
+    }
+}
+
+/// An identity-based signature, U' || V'
+#[derive(Copy, Clone, Debug)]
+pub struct IbsSignature {
+    u: G1Affine,
+    v: G1Affine,
+}
+
+impl IbsSignature {
+    /// Authenticates the signature on the message by the holder of the
+    /// identity, under the master public key.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the signature is valid.
+    ///
+    /// * False to indicate that the signature is not valid.
+    ///
+    /// # Mathematics
+    ///
+    /// * e(V', g2) == e(U' + H(U' || m)*H(ID), Ppub)
+    pub fn authenticate<I, M>(&self, master: &MasterPublicKey, identity: I, msg: M) -> bool
+    where
+        I: AsRef<[u8]>,
+        M: AsRef<[u8]>,
+    {
+        let h = challenge(&G1Projective::from(self.u), msg.as_ref());
+        let rhs = G1Affine::from(G1Projective::from(self.u) + hash_to_g1(identity.as_ref()) * h);
+        pairing(&self.v, &G2Affine::generator()) == pairing(&rhs, &master.point)
+    }
+}
+
+/// The IbsSignature in wired form, U' || V', as compressed G1 points
+pub struct WiredIbsSignature(pub [u8; 96]);
+
+impl From<IbsSignature> for WiredIbsSignature {
+    fn from(sig: IbsSignature) -> Self {
+        let mut arr = [0; 96];
+        arr[0..48].copy_from_slice(&sig.u.to_compressed());
+        arr[48..96].copy_from_slice(&sig.v.to_compressed());
+        WiredIbsSignature(arr)
+    }
+}
+
+impl WiredIbsSignature {
+    /// Converts the wired form into the internal IbsSignature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(IbsSignature) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates either point was malformed.
+    pub fn to_internal_format(&self) -> ::Result<IbsSignature> {
+        let mut u = [0; 48];
+        let mut v = [0; 48];
+        u.copy_from_slice(&self.0[0..48]);
+        v.copy_from_slice(&self.0[48..96]);
+        Ok(IbsSignature {
+            u: G1Affine::from(g1_point(&u)?),
+            v: G1Affine::from(g1_point(&v)?),
+        })
+    }
+}
+
+impl_wired_bytes!(WiredIbsSignature, 96);
+impl_wired_envelope!(WiredIbsSignature, PayloadType::IbsSignature);
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Hashes the identity to G1 under the identity DST
+fn hash_to_g1(identity: &[u8]) -> G1Projective {
+    <G1Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(identity, ID_DST)
+}
+
+/// Hashes the commitment and message to the challenge scalar H(U' || m)
+fn challenge(u: &G1Projective, msg: &[u8]) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(CHALLENGE_DOMAIN);
+    hasher.update(&G1Affine::from(u).to_compressed()[..]);
+    hasher.update(msg);
+    let mut wide = [0; 64];
+    wide.copy_from_slice(&hasher.finalize());
+    Scalar::from_bytes_wide(&wide)
+}
+
+/// Generates a uniformly random scalar
+fn random_scalar(rng: &mut SecureRng) -> Scalar {
+    let mut bytes = [0; 64];
+    rng.fill_bytes(&mut bytes);
+    Scalar::from_bytes_wide(&bytes)
+}
+
+/// Generates a uniformly random nonzero scalar
+fn nonzero_scalar(rng: &mut SecureRng) -> Scalar {
+    loop {
+        let r = random_scalar(rng);
+        if r != Scalar::zero() {
+            return r;
+        }
+    }
+}
+
+/// Loads a compressed G1 point, rejecting the identity
+fn g1_point(bytes: &[u8; 48]) -> ::Result<G1Projective> {
+    let point: Option<G1Affine> = G1Affine::from_compressed(bytes).into();
+    match point {
+        Some(point) if !bool::from(point.is_identity()) => Ok(G1Projective::from(point)),
+        _ => Err(BlsPointMalformed),
+    }
+}
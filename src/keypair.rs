@@ -3,20 +3,71 @@ use curve25519_dalek::{
     constants::RISTRETTO_BASEPOINT_POINT,
     ristretto::{CompressedRistretto, RistrettoPoint},
     scalar::Scalar,
+    traits::Identity,
 };
-use rand::OsRng;
-use Error::{WiredRistrettoPointMalformed, WiredScalarMalformed};
+use digest::Digest;
+use encoding;
+use envelope::PayloadType;
+use rand::{CryptoRng, RngCore};
+use rng;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use subtle::{Choice, ConstantTimeEq};
+#[cfg(feature = "async")]
+use std::future::{self, Future};
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use typenum::U64;
+use Error::{KeypairMismatch, PublicKeyInvalid, WiredFieldMalformed, WiredScalarZero};
+use WiredField;
+
+/// The domain separation prefix for deriving private keys from seeds
+const SEED_DOMAIN: &[u8] = b"blindsign keypair seed";
+
+/// The domain separation prefix for deriving child private keys
+const CHILD_DOMAIN: &[u8] = b"blindsign child key";
+
+/// The domain separation prefix for computing key fingerprints
+const FINGERPRINT_DOMAIN: &[u8] = b"blindsign key fingerprint";
 
 /// An elliptic curve cryptography keypair. The private key (Xs) is used by the
 /// signer for creating the blind signature on the blinded hash(msg||R), and the
 /// public key (Qs) is usable by anyone for verifying the authenticity of the
 /// unblinded signature on the unblinded hash(msg||R).
-#[derive(Copy, Clone, Debug)]
+///
+/// Debug output shows the public key, with the private key redacted.
+#[derive(Copy, Clone)]
 pub struct BlindKeypair {
     private: Scalar,
     public: RistrettoPoint,
 }
 
+impl ConstantTimeEq for BlindKeypair {
+    /// Compares the private keys, which determine the public keys
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.private.ct_eq(&other.private)
+    }
+}
+
+/// Constant time, as for ConstantTimeEq
+impl PartialEq for BlindKeypair {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).unwrap_u8() == 1
+    }
+}
+
+impl Eq for BlindKeypair {}
+
+impl fmt::Debug for BlindKeypair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlindKeypair")
+            .field("private", &Redacted)
+            .field("public", &encoding::to_hex(&self.verifying_key().wired()))
+            .finish()
+    }
+}
+
 impl BlindKeypair {
     /// Generates an ECC keypair for use with the blind signature protocol.
     /// The private key is a random scalar, and the public key is an elliptic
@@ -37,28 +88,148 @@ impl BlindKeypair {
     /// * Qs = Xs * P
     /// * P = The ECC generator point
     pub fn generate() -> ::Result<Self> {
-        let mut rng = OsRng::new()?;
-        let private = Scalar::random(&mut rng);
+        Ok(Self::generate_with_rng(&mut rng::try_new()?))
+    }
+
+    /// The same as generate, but drawing the private key from the provided
+    /// RNG rather than the internal one, such as the hardware TRNG of a target
+    /// without an operating system entropy source.
+    ///
+    /// # Security
+    ///
+    /// The RNG must be cryptographically secure, as its CryptoRng
+    /// implementation asserts.
+    pub fn generate_with_rng<R>(rng: &mut R) -> Self
+    where
+        R: RngCore + CryptoRng,
+    {
+        let private = Scalar::random(rng);
         let public = private * RISTRETTO_BASEPOINT_POINT;
-        Ok(BlindKeypair { private, public })
+        BlindKeypair { private, public }
+    }
+
+    /// Deterministically derives an ECC keypair from a 32 byte seed, so that
+    /// the keypair can be regenerated from a master seed rather than the
+    /// private key itself being persisted. The same seed and hash algorithm
+    /// always produce the same keypair.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for deriving the private key from the seed
+    ///
+    /// # Mathematics
+    ///
+    /// * Xs = H("blindsign keypair seed" || seed)
+    /// * Qs = Xs * P
+    /// * P = The ECC generator point
+    pub fn from_seed<H>(seed: &[u8; 32]) -> Self
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut hasher = H::default();
+        hasher.input(SEED_DOMAIN);
+        hasher.input(seed);
+        BlindKeypair::from_private(Scalar::from_hash(hasher))
+    }
+
+    /// Deterministically derives a hardened child keypair at the provided
+    /// index, so that one master keypair can yield independent issuance
+    /// keypairs (for example per denomination or per epoch). Derivation
+    /// requires the private key, and neither the child public keys nor the
+    /// master public key can be linked to one another without it.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for deriving the child private key
+    ///
+    /// # Mathematics
+    ///
+    /// * Xc = H("blindsign child key" || Xs || index)
+    /// * Qc = Xc * P
+    /// * Xs = The private key of the master keypair
+    /// * index = The big endian bytes of the child index
+    /// * P = The ECC generator point
+    pub fn derive_child<H>(&self, index: u32) -> Self
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let index = [
+            (index >> 24) as u8,
+            (index >> 16) as u8,
+            (index >> 8) as u8,
+            index as u8,
+        ];
+        let mut hasher = H::default();
+        hasher.input(CHILD_DOMAIN);
+        hasher.input(self.private.as_bytes());
+        hasher.input(&index);
+        BlindKeypair::from_private(Scalar::from_hash(hasher))
+    }
+
+    /// Derives the keypair of an existing ed25519 secret key from its 32 byte
+    /// seed, with the standard ed25519 expansion of the seed to a clamped
+    /// scalar, so that blind signing can be backed by keys already held as
+    /// ed25519 keys. The public key is the same curve point as the ed25519
+    /// public key, though in its ristretto encoding.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for expanding the seed, which must be
+    /// SHA-512 for the private key to match that of the ed25519 key
+    ///
+    /// # Mathematics
+    ///
+    /// * h = H(seed)
+    /// * Xs = The first 32 bytes of h, with bits 0 to 2 and 255 cleared and
+    /// bit 254 set, reduced mod the group order
+    /// * Qs = Xs * P
+    /// * P = The ECC generator point
+    pub fn from_ed25519_seed<H>(seed: &[u8; 32]) -> Self
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut hasher = H::default();
+        hasher.input(seed);
+        let mut bits = [0; 32];
+        bits.copy_from_slice(&hasher.result()[..32]);
+        bits[0] &= 0b1111_1000;
+        bits[31] &= 0b0111_1111;
+        bits[31] |= 0b0100_0000;
+        BlindKeypair::from_private(Scalar::from_bytes_mod_order(bits))
     }
 
     /// Creates a new BlindKeypair object from the provided private and public
-    /// key components (in wired form).
+    /// key components (in wired form), which were validated on construction,
+    /// checking that they belong together.
     ///
     /// # Returns
     ///
     /// * Ok(BlindKeypair) on success.
     ///
-    /// * Err(::Error) on failure, which can indicate either that the private
-    /// or public key inputs were malformed.
-    pub fn from_wired(private: [u8; 32], public: [u8; 32]) -> ::Result<Self> {
-        Ok(BlindKeypair {
-            private: Scalar::from_canonical_bytes(private).ok_or(WiredScalarMalformed)?,
-            public: CompressedRistretto(public)
-                .decompress()
-                .ok_or(WiredRistrettoPointMalformed)?,
-        })
+    /// * Err(::Error) on failure, which indicates the public key failed
+    /// VerifyingKey::from_wired, or was not the public key of the private
+    /// key. Signing with such a pair would produce signatures that never
+    /// verify under the public key.
+    ///
+    /// # Mathematics
+    ///
+    /// * Qs == Xs * P
+    pub fn from_wired(private: WiredPrivateKey, public: WiredPublicKey) -> ::Result<Self> {
+        let private = SigningKey::from_wired(private).private;
+        let public = VerifyingKey::from_wired(public)?.public;
+        if private * RISTRETTO_BASEPOINT_POINT != public {
+            return Err(KeypairMismatch);
+        }
+        Ok(BlindKeypair { private, public })
+    }
+
+    /// Creates the keypair for the provided private key, computing the public
+    /// key from it.
+    ///
+    /// pub(crate) as used in mnemonic.rs, pkcs8.rs, proxy.rs and testvectors.rs
+    pub(crate) fn from_private(private: Scalar) -> Self {
+        let public = private * RISTRETTO_BASEPOINT_POINT;
+        BlindKeypair { private, public }
     }
 
     /// Returns the private key in Scalar form
@@ -71,13 +242,427 @@ impl BlindKeypair {
         self.public
     }
 
-    /// Returns the public key in wired form
-    pub fn public_wired(&self) -> [u8; 32] {
-        self.public.compress().to_bytes()
+    /// Returns the public key in wired form, validated as for
+    /// WiredPublicKey::from_bytes.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(WiredPublicKey) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the public key is the
+    /// identity, as for a private key of zero.
+    pub fn public_wired(&self) -> ::Result<WiredPublicKey> {
+        WiredPublicKey::from_bytes(&self.verifying_key().wired())
     }
 
     /// Returns the private key in wired form
-    pub fn private_wired(&self) -> [u8; 32] {
+    pub fn private_wired(&self) -> WiredPrivateKey {
+        WiredPrivateKey(self.private.to_bytes())
+    }
+
+    /// Returns the signing key and verifying key halves of the keypair
+    pub fn keypair(&self) -> (SigningKey, VerifyingKey) {
+        (self.signing_key(), self.verifying_key())
+    }
+
+    /// Returns the signing key half of the keypair, which holds the private key
+    pub fn signing_key(&self) -> SigningKey {
+        SigningKey {
+            private: self.private,
+        }
+    }
+
+    /// Returns the verifying key half of the keypair, which holds the public
+    /// key
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey {
+            public: self.public,
+        }
+    }
+}
+
+/// The private key (Xs) half of a BlindKeypair, which is used by the signer
+/// for creating blind signatures and never needs to be held by verifiers.
+///
+/// Debug output shows the associated public key, with the private key
+/// redacted.
+#[derive(Copy, Clone)]
+pub struct SigningKey {
+    private: Scalar,
+}
+
+impl ConstantTimeEq for SigningKey {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.private.ct_eq(&other.private)
+    }
+}
+
+/// Constant time, as for ConstantTimeEq, so that applications comparing
+/// stored keys do not introduce a timing oracle on the private key.
+impl PartialEq for SigningKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).unwrap_u8() == 1
+    }
+}
+
+impl Eq for SigningKey {}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("private", &Redacted)
+            .field("public", &encoding::to_hex(&self.verifying_key().wired()))
+            .finish()
+    }
+}
+
+impl SigningKey {
+    /// Creates a new SigningKey object from the provided private key (in wired
+    /// form), which was validated on construction.
+    pub fn from_wired(private: WiredPrivateKey) -> Self {
+        SigningKey {
+            private: private_scalar(private.0).expect("WiredPrivateKey is validated"),
+        }
+    }
+
+    /// Returns the verifying key associated with the signing key
+    ///
+    /// # Mathematics
+    ///
+    /// * Qs = Xs * P
+    /// * P = The ECC generator point
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey {
+            public: self.private * RISTRETTO_BASEPOINT_POINT,
+        }
+    }
+
+    /// Returns the private key in Scalar form
+    pub fn scalar(&self) -> Scalar {
+        self.private
+    }
+
+    /// Returns the private key in wired form
+    pub fn wired(&self) -> [u8; 32] {
         self.private.to_bytes()
     }
 }
+
+/// A key that can produce blind signatures on e' values, so that the private
+/// key can be held by an HSM, a KMS or a separate process rather than in
+/// memory as a SigningKey. Implementations only need to compute S' for the
+/// provided e' and session nonce k, and never expose the private key.
+pub trait BlindSignerKey {
+    /// Computes S' = Xs*e' + k.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Scalar) on success, representing S'.
+    ///
+    /// * Err(::Error) on failure, which indicates that the key backend failed.
+    ///
+    /// # Security
+    ///
+    /// Implementations must compute S' in constant time with respect to Xs
+    /// and k, as the attacker chooses e' and observes S'.
+    fn sign_blinded(&self, ep: &Scalar, k: &Scalar) -> ::Result<Scalar>;
+
+    /// Returns the verifying key associated with the private key
+    fn verifying_key(&self) -> VerifyingKey;
+}
+
+impl BlindSignerKey for SigningKey {
+    fn sign_blinded(&self, ep: &Scalar, k: &Scalar) -> ::Result<Scalar> {
+        Ok(self.private * ep + k)
+    }
+
+    fn verifying_key(&self) -> VerifyingKey {
+        SigningKey::verifying_key(self)
+    }
+}
+
+/// The future of S' returned by an AsyncBlindSigner
+#[cfg(feature = "async")]
+pub type SignFuture<'a> = Pin<Box<dyn Future<Output = ::Result<Scalar>> + Send + 'a>>;
+
+/// The same as BlindSignerKey, but computing S' asynchronously, for keys held
+/// by a network key service. Every BlindSignerKey that is Sync is also an
+/// AsyncBlindSigner, whose futures are ready immediately.
+#[cfg(feature = "async")]
+pub trait AsyncBlindSigner {
+    /// Computes S' = Xs*e' + k, as for BlindSignerKey::sign_blinded.
+    fn sign_blinded<'a>(&'a self, ep: Scalar, k: Scalar) -> SignFuture<'a>;
+
+    /// Returns the verifying key associated with the private key
+    fn verifying_key(&self) -> VerifyingKey;
+}
+
+#[cfg(feature = "async")]
+impl<K> AsyncBlindSigner for K
+where
+    K: BlindSignerKey + Sync,
+{
+    fn sign_blinded<'a>(&'a self, ep: Scalar, k: Scalar) -> SignFuture<'a> {
+        Box::pin(future::ready(BlindSignerKey::sign_blinded(self, &ep, &k)))
+    }
+
+    fn verifying_key(&self) -> VerifyingKey {
+        BlindSignerKey::verifying_key(self)
+    }
+}
+
+/// The public key (Qs) half of a BlindKeypair, which is all that is required
+/// for authenticating unblinded signatures.
+///
+/// Verifying keys compare, hash and order by their wired form.
+#[derive(Copy, Clone, Debug)]
+pub struct VerifyingKey {
+    public: RistrettoPoint,
+}
+
+impl PartialEq for VerifyingKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.public == other.public
+    }
+}
+
+impl Eq for VerifyingKey {}
+
+impl Hash for VerifyingKey {
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.wired().hash(state)
+    }
+}
+
+impl PartialOrd for VerifyingKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for VerifyingKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.wired().cmp(&other.wired())
+    }
+}
+
+impl VerifyingKey {
+    /// Creates a new VerifyingKey object from the provided public key (in
+    /// wired form), which was validated on construction.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(VerifyingKey) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the public key did not load
+    /// as a point passing VerifyingKey::validate.
+    pub fn from_wired(public: WiredPublicKey) -> ::Result<Self> {
+        Ok(VerifyingKey {
+            public: public_point(public.0)?,
+        })
+    }
+
+    /// Creates the verifying key for the provided public key point, without
+    /// validating it.
+    ///
+    /// pub(crate) as used in okamoto.rs and proxy.rs
+    pub(crate) fn from_point(public: RistrettoPoint) -> Self {
+        VerifyingKey { public }
+    }
+
+    /// Validates that the public key is usable for authenticating signatures.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(()) if the public key is valid.
+    ///
+    /// * Err(::Error) if the public key is the identity point. Every signature
+    /// with S*P == R would authenticate under the identity, regardless of the
+    /// message, so such a key must never be trusted.
+    pub fn validate(&self) -> ::Result<()> {
+        if self.public == RistrettoPoint::identity() {
+            return Err(PublicKeyInvalid);
+        }
+        Ok(())
+    }
+
+    /// Computes a short stable identifier of the public key, for referencing
+    /// which issuer key signed a token in databases and logs.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for computing the fingerprint, which must
+    /// produce outputs of at least 16 bytes. The same key has a different
+    /// fingerprint under each hash algorithm, so one should be used throughout.
+    ///
+    /// # Mathematics
+    ///
+    /// * fingerprint = The first 16 bytes of H("blindsign key fingerprint" || Qs)
+    /// * Qs = The compressed public key
+    pub fn fingerprint<H>(&self) -> KeyFingerprint
+    where
+        H: Digest + Default,
+    {
+        let mut hasher = H::default();
+        hasher.input(FINGERPRINT_DOMAIN);
+        hasher.input(self.public.compress().as_bytes());
+        let mut arr = [0; 16];
+        arr.copy_from_slice(&hasher.result()[..16]);
+        KeyFingerprint(arr)
+    }
+
+    /// Returns the public key in RistrettoPoint form
+    pub fn point(&self) -> RistrettoPoint {
+        self.public
+    }
+
+    /// Returns the public key in wired form
+    pub fn wired(&self) -> [u8; 32] {
+        self.public.compress().to_bytes()
+    }
+}
+
+/// A short stable identifier of a verifying key, which displays as lowercase
+/// hex.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyFingerprint(pub [u8; 16]);
+
+impl_wired_bytes!(KeyFingerprint, 16);
+impl_wired_from_hex!(KeyFingerprint);
+
+impl fmt::Display for KeyFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&encoding::to_hex(&self.0))
+    }
+}
+
+/// The public key of a BlindKeypair in wired form, which is the 32 byte
+/// compressed Ristretto point.
+///
+/// Construction fails unless the bytes are the canonical encoding of a point
+/// which passes VerifyingKey::validate, so a WiredPublicKey always loads.
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WiredPublicKey([u8; 32]);
+
+impl WiredPublicKey {
+    /// Encodes the wired public key as a bech32 string with the provided
+    /// human readable prefix.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(String) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the hrp was not 1 to 83
+    /// lowercase printable ASCII characters.
+    pub fn to_bech32(&self, hrp: &str) -> ::Result<String> {
+        encoding::to_bech32(hrp, &self.0)
+    }
+
+    /// Decodes the wired public key from a bech32 string, which must have the
+    /// provided human readable prefix.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(WiredPublicKey) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the string was not valid
+    /// bech32, had a different prefix, or did not contain 32 bytes.
+    pub fn from_bech32(hrp: &str, encoded: &str) -> ::Result<Self> {
+        WiredPublicKey::from_bytes(&encoding::from_bech32(hrp, encoded)?)
+    }
+}
+
+impl_wired_bytes!(WiredPublicKey, 32, validate_public);
+impl_wired_hex!(WiredPublicKey);
+impl_wired_envelope!(WiredPublicKey, PayloadType::PublicKey);
+
+/// The private key of a BlindKeypair in wired form, which is the 32 byte
+/// canonical scalar.
+///
+/// Construction fails unless the bytes are a canonical nonzero scalar, so a
+/// WiredPrivateKey always loads.
+pub struct WiredPrivateKey([u8; 32]);
+
+impl_wired_bytes!(WiredPrivateKey, 32, validate_private);
+// Only parsed from hex, for reading config files, as a Display impl would make
+// logging the private key too easy.
+impl_wired_from_hex!(WiredPrivateKey);
+
+impl ConstantTimeEq for WiredPrivateKey {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0[..].ct_eq(&other.0[..])
+    }
+}
+
+/// Constant time, as for ConstantTimeEq
+impl PartialEq for WiredPrivateKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).unwrap_u8() == 1
+    }
+}
+
+impl Eq for WiredPrivateKey {}
+
+/// Compares wired secret material, such as private keys, seeds or sealing
+/// keys, in constant time with respect to the contents. Only the lengths,
+/// which are not secret, may be compared in variable time.
+///
+/// # Returns
+///
+/// * true if a and b have the same length and contents.
+pub fn ct_eq_wired(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.ct_eq(b).unwrap_u8() == 1
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Loads a wired private key
+fn private_scalar(private: [u8; 32]) -> ::Result<Scalar> {
+    Scalar::from_canonical_bytes(private).ok_or(WiredFieldMalformed {
+        field: WiredField::PrivateKey,
+        offset: 0,
+    })
+}
+
+/// Loads a wired public key, which must be the canonical encoding of a point
+/// passing VerifyingKey::validate
+fn public_point(public: [u8; 32]) -> ::Result<RistrettoPoint> {
+    let point = CompressedRistretto(public)
+        .decompress()
+        .ok_or(WiredFieldMalformed {
+            field: WiredField::PublicKey,
+            offset: 0,
+        })?;
+    // Ristretto decoding only accepts canonical encodings, but this is
+    // checked explicitly as public keys are often compared by their bytes.
+    if point.compress().to_bytes() != public {
+        return Err(PublicKeyInvalid);
+    }
+    VerifyingKey { public: point }.validate()?;
+    Ok(point)
+}
+
+/// Validates the bytes of a WiredPrivateKey on construction, rejecting zero,
+/// whose public key is the identity
+fn validate_private(private: &[u8; 32]) -> ::Result<()> {
+    if private_scalar(*private)? == Scalar::zero() {
+        return Err(WiredScalarZero);
+    }
+    Ok(())
+}
+
+/// Validates the bytes of a WiredPublicKey on construction
+fn validate_public(public: &[u8; 32]) -> ::Result<()> {
+    public_point(*public).map(|_| ())
+}
+
+/// Stands in for a secret field in Debug output
+///
+/// pub(crate) as used in session.rs and request.rs
+pub(crate) struct Redacted;
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
@@ -0,0 +1,175 @@
+//! Key epochs and rotation aware verification
+//!
+//! Issuers rotating their keys assign each verifying key an epoch id and a
+//! validity window. Signatures are then distributed alongside the id of the
+//! epoch they were issued under, in the EpochUnblindedSigData envelope, so
+//! that verifiers can select the right key from their KeySet.
+//!
+//! # Note
+//!
+//! Validity windows are expressed in whatever unit of time the caller uses
+//! for `now` (typically seconds since the unix epoch), as this crate performs
+//! no IO and so never reads the clock itself.
+use envelope::PayloadType;
+use keypair::VerifyingKey;
+use signature::{UnblindedSigData, WiredUnblindedSigData};
+use std::collections::BTreeMap;
+use Error::KeyEpochDuplicate;
+
+/// A verifying key along with its epoch id and validity window
+#[derive(Copy, Clone, Debug)]
+pub struct KeyEpoch {
+    id: u32,
+    key: VerifyingKey,
+    not_before: u64,
+    not_after: u64,
+}
+
+impl KeyEpoch {
+    /// Creates a new KeyEpoch, with the key being valid from not_before up to
+    /// and including not_after.
+    pub fn new(id: u32, key: VerifyingKey, not_before: u64, not_after: u64) -> Self {
+        KeyEpoch {
+            id,
+            key,
+            not_before,
+            not_after,
+        }
+    }
+
+    /// Returns the epoch id
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the verifying key of the epoch
+    pub fn key(&self) -> &VerifyingKey {
+        &self.key
+    }
+
+    /// Returns true if the key is valid at the provided time
+    pub fn is_valid_at(&self, now: u64) -> bool {
+        self.not_before <= now && now <= self.not_after
+    }
+}
+
+/// A set of key epochs, indexed by their epoch ids
+#[derive(Clone, Debug, Default)]
+pub struct KeySet {
+    epochs: BTreeMap<u32, KeyEpoch>,
+}
+
+impl KeySet {
+    /// Creates a new empty KeySet
+    pub fn new() -> Self {
+        KeySet::default()
+    }
+
+    /// Inserts a key epoch into the set.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(()) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that an epoch with the same
+    /// id is already in the set.
+    pub fn insert(&mut self, epoch: KeyEpoch) -> ::Result<()> {
+        if self.epochs.contains_key(&epoch.id) {
+            return Err(KeyEpochDuplicate(epoch.id));
+        }
+        self.epochs.insert(epoch.id, epoch);
+        Ok(())
+    }
+
+    /// Removes the key epoch with the provided id from the set, returning it
+    pub fn remove(&mut self, id: u32) -> Option<KeyEpoch> {
+        self.epochs.remove(&id)
+    }
+
+    /// Returns the key epoch with the provided id
+    pub fn get(&self, id: u32) -> Option<&KeyEpoch> {
+        self.epochs.get(&id)
+    }
+
+    /// Returns the valid key epoch with the highest id at the provided time,
+    /// which is the epoch an issuer should currently be signing under.
+    pub fn current(&self, now: u64) -> Option<&KeyEpoch> {
+        self.epochs.values().rev().find(|epoch| epoch.is_valid_at(now))
+    }
+}
+
+/// An unblinded signature along with the id of the key epoch it was issued
+/// under.
+#[derive(Copy, Clone, Debug)]
+pub struct EpochSigData {
+    epoch: u32,
+    sig: UnblindedSigData,
+}
+
+impl EpochSigData {
+    /// Creates a new EpochSigData from the epoch id and the signature
+    pub fn new(epoch: u32, sig: UnblindedSigData) -> Self {
+        EpochSigData { epoch, sig }
+    }
+
+    /// Returns the epoch id
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    /// Returns the unblinded signature
+    pub fn sig(&self) -> &UnblindedSigData {
+        &self.sig
+    }
+
+    /// Authenticates the signature with the key of its epoch from the
+    /// provided key set.
+    ///
+    /// # Returns
+    ///
+    /// * True if the key set contains the epoch, the epoch key is valid at the
+    /// provided time, and the signature authenticates with it.
+    ///
+    /// * False otherwise.
+    pub fn authenticate_with_keyset(&self, keyset: &KeySet, now: u64) -> bool {
+        match keyset.get(self.epoch) {
+            Some(epoch) if epoch.is_valid_at(now) => self.sig.authenticate(epoch.key()),
+            _ => false,
+        }
+    }
+}
+
+/// The EpochSigData in wired form, consisting of epoch || e || S || R, with the
+/// epoch being a 4 byte big endian integer.
+pub struct WiredEpochSigData(pub [u8; 100]);
+
+impl From<EpochSigData> for WiredEpochSigData {
+    fn from(esd: EpochSigData) -> Self {
+        let mut arr = [0; 100];
+        arr[0..4].copy_from_slice(&esd.epoch.to_be_bytes());
+        arr[4..100].copy_from_slice(WiredUnblindedSigData::from(esd.sig).as_bytes());
+        WiredEpochSigData(arr)
+    }
+}
+
+impl WiredEpochSigData {
+    /// Converts WiredEpochSigData into EpochSigData.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(EpochSigData) on success
+    ///
+    /// * Err(::Error) on failure, which indicates the signature component was
+    /// malformed.
+    pub fn to_internal_format(&self) -> ::Result<EpochSigData> {
+        let mut epoch = [0; 4];
+        epoch.copy_from_slice(&self.0[0..4]);
+        Ok(EpochSigData {
+            epoch: u32::from_be_bytes(epoch),
+            sig: WiredUnblindedSigData::from_bytes(&self.0[4..100])?.to_internal_format()?,
+        })
+    }
+}
+
+impl_wired_bytes!(WiredEpochSigData, 100);
+impl_wired_envelope!(WiredEpochSigData, PayloadType::EpochUnblindedSigData);
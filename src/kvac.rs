@@ -0,0 +1,834 @@
+//! Keyed-verification anonymous credentials, from the algebraic MACs of
+//! Chase, Meiklejohn and Zaverucha (CMZ'14)
+//!
+//! When the issuer and the verifier of a credential are the same party, the
+//! credential can be a MAC on its attributes rather than a signature, which
+//! needs no pairings and fits the ristretto group used by the rest of the
+//! crate. The issuer MACs a vector of attributes, some of which are hidden
+//! from it by the requester under ElGamal encryption, and proves that it used
+//! the keys committed to in its public IssuerParams, so that every credential
+//! is issued under the same keys. The holder presents the credential with a
+//! zero knowledge proof, disclosing any subset of the attributes. Each
+//! presentation is rerandomized, so presentations are unlinkable to the
+//! issuance and to each other.
+//!
+//! 1. The requester encrypts its hidden attributes with
+//!    CredentialRequester::new, sending the CredentialRequest to the issuer.
+//! 2. The issuer MACs the request along with the known attributes with
+//!    IssuerSecretKey::issue, sending back the BlindCredential.
+//! 3. The requester verifies and decrypts the BlindCredential with
+//!    CredentialRequester::finalize.
+//! 4. The holder presents the credential with Credential::present, which
+//!    the issuer checks with IssuerSecretKey::verify_presentation.
+//!
+//! The attributes are byte strings, each identified by its index in the
+//! vector, and hashed to scalars.
+//!
+//! # Mathematics
+//!
+//! * Cx0 = x0*G + x0'*H, Xi = xi*H
+//! * u = b*G, u' = (x0 + x1*m1 + ... + xn*mn)*u
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::Identity,
+};
+use digest::Digest;
+use rng::{self, SecureRng};
+use typenum::U64;
+use Error::{KvacAttributesInvalid, KvacProofInvalid, WiredRistrettoPointMalformed};
+
+/// The domain separation prefix for deriving the generator H
+const GENERATOR_DOMAIN: &[u8] = b"blindsign kvac generator";
+
+/// The domain separation prefix for hashing attributes to scalars
+const ATTRIBUTE_DOMAIN: &[u8] = b"blindsign kvac attribute";
+
+/// The domain separation prefix for proof challenges
+const PROOF_DOMAIN: &[u8] = b"blindsign kvac proof";
+
+/// The secret key of the issuer, which is also the verifier
+pub struct IssuerSecretKey {
+    x0: Scalar,
+    x0_blinding: Scalar,
+    xs: Vec<Scalar>,
+}
+
+impl IssuerSecretKey {
+    /// Generates a new random issuer key for credentials with the provided
+    /// number of attributes.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(IssuerSecretKey) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates there were no attributes, or
+    /// the failure to initiate the internal RNG.
+    pub fn generate(attribute_count: usize) -> ::Result<Self> {
+        if attribute_count == 0 {
+            return Err(KvacAttributesInvalid);
+        }
+        let mut rng = rng::try_new()?;
+        Ok(IssuerSecretKey {
+            x0: Scalar::random(&mut rng),
+            x0_blinding: Scalar::random(&mut rng),
+            xs: (0..attribute_count)
+                .map(|_| Scalar::random(&mut rng))
+                .collect(),
+        })
+    }
+
+    /// Returns the public IssuerParams committing to the key.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for deriving the generator H
+    pub fn params<H>(&self) -> IssuerParams
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let h = generator::<H>();
+        IssuerParams {
+            cx0: self.x0 * RISTRETTO_BASEPOINT_POINT + self.x0_blinding * h,
+            xs: self.xs.iter().map(|x| x * h).collect(),
+        }
+    }
+
+    /// MACs the requester's encrypted hidden attributes along with the
+    /// attributes known to the issuer, after verifying the request proof.
+    ///
+    /// # Arguments
+    ///
+    /// * 'known' - The attributes known to the issuer, with their indices,
+    /// which together with the hidden indices must cover every attribute
+    /// exactly once.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlindCredential) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the attribute indices were
+    /// invalid, that the request proof did not verify, or the failure to
+    /// initiate the RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * u = b*G
+    /// * E1 = r*G + Σ hidden b*xi*Ei1
+    /// * E2 = (x0 + Σ known xi*mi)*u + r*D + Σ hidden b*xi*Ei2
+    pub fn issue<H>(
+        &self,
+        request: &CredentialRequest,
+        known: &[(usize, &[u8])],
+    ) -> ::Result<BlindCredential>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let known: Vec<(usize, Scalar)> = known
+            .iter()
+            .map(|&(index, attribute)| (index, attribute_scalar::<H>(attribute)))
+            .collect();
+        check_partition(self.xs.len(), &request.indices, &known)?;
+        if request.ciphertexts.len() != request.indices.len() || !request.verify::<H>() {
+            return Err(KvacProofInvalid);
+        }
+        let mut rng = rng::try_new()?;
+        let b = Scalar::random(&mut rng);
+        let r = Scalar::random(&mut rng);
+        let u = b * RISTRETTO_BASEPOINT_POINT;
+        let ts: Vec<Scalar> = request.indices.iter().map(|&i| b * self.xs[i]).collect();
+        let e1 = request
+            .ciphertexts
+            .iter()
+            .zip(&ts)
+            .fold(r * RISTRETTO_BASEPOINT_POINT, |acc, (&(c1, _), t)| acc + t * c1);
+        let known_mac = known
+            .iter()
+            .fold(self.x0, |acc, &(i, m)| acc + self.xs[i] * m);
+        let e2 = request
+            .ciphertexts
+            .iter()
+            .zip(&ts)
+            .fold(known_mac * u + r * request.d, |acc, (&(_, c2), t)| acc + t * c2);
+
+        let params = self.params::<H>();
+        let statement = issuance_statement::<H>(&params, request, &known, &u, &(e1, e2));
+        let mut witnesses = vec![b, self.x0, self.x0_blinding];
+        witnesses.extend_from_slice(&self.xs);
+        witnesses.push(r);
+        witnesses.extend_from_slice(&ts);
+        Ok(BlindCredential {
+            u,
+            e: (e1, e2),
+            proof: statement.prove::<H>(&witnesses, &[], &mut rng),
+        })
+    }
+
+    /// Verifies a presentation of a credential issued under this key,
+    /// disclosing the provided attributes with their indices.
+    ///
+    /// # Arguments
+    ///
+    /// * 'nonce' - The nonce sent by the verifier to the holder, which
+    /// prevents the presentation from being replayed.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the holder has a valid credential with the
+    /// disclosed attributes.
+    ///
+    /// * False to indicate that the presentation is not valid.
+    ///
+    /// # Mathematics
+    ///
+    /// * V = x0*u + Σ hidden xi*Cmi + Σ disclosed xi*mi*u - Cu'
+    /// * V == Σ hidden zi*Xi - r*H
+    pub fn verify_presentation<H>(
+        &self,
+        presentation: &Presentation,
+        disclosed: &[(usize, &[u8])],
+        nonce: &[u8],
+    ) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let disclosed: Vec<(usize, Scalar)> = disclosed
+            .iter()
+            .map(|&(index, attribute)| (index, attribute_scalar::<H>(attribute)))
+            .collect();
+        let hidden = match hidden_indices(self.xs.len(), &disclosed) {
+            Ok(hidden) => hidden,
+            Err(_) => return false,
+        };
+        if hidden.len() != presentation.cms.len() || presentation.u == RistrettoPoint::identity()
+        {
+            return false;
+        }
+        let u = presentation.u;
+        let v = hidden
+            .iter()
+            .zip(&presentation.cms)
+            .fold(self.x0 * u, |acc, (&i, cm)| acc + self.xs[i] * cm)
+            + disclosed
+                .iter()
+                .fold(Scalar::zero(), |acc, &(i, m)| acc + self.xs[i] * m)
+                * u
+            - presentation.cu_prime;
+        let params = self.params::<H>();
+        let statement =
+            presentation_statement::<H>(&params, &u, &presentation.cms, &hidden, &v);
+        let context = presentation_context(&presentation.cu_prime, &disclosed, nonce);
+        statement.verify::<H>(&presentation.proof, &context)
+    }
+}
+
+/// The public parameters of an issuer, which commit to its secret key
+#[derive(Clone, Debug)]
+pub struct IssuerParams {
+    cx0: RistrettoPoint,
+    xs: Vec<RistrettoPoint>,
+}
+
+impl IssuerParams {
+    /// Returns the number of attributes of the credentials
+    pub fn attribute_count(&self) -> usize {
+        self.xs.len()
+    }
+
+    /// Returns the params in wired form, Cx0 || X1 || ... || Xn
+    pub fn wired(&self) -> Vec<u8> {
+        Some(&self.cx0)
+            .into_iter()
+            .chain(&self.xs)
+            .flat_map(|point| point.compress().to_bytes().to_vec())
+            .collect()
+    }
+
+    /// Loads the params from their wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(IssuerParams) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input was not a multiple
+    /// of 32 bytes with at least one attribute, or that a point was malformed.
+    pub fn from_wired(wired: &[u8]) -> ::Result<Self> {
+        if wired.len() % 32 != 0 || wired.len() < 64 {
+            return Err(KvacAttributesInvalid);
+        }
+        let mut points = wired
+            .chunks(32)
+            .map(|chunk| {
+                CompressedRistretto::from_slice(chunk)
+                    .decompress()
+                    .ok_or(WiredRistrettoPointMalformed)
+            })
+            .collect::<::Result<Vec<_>>>()?;
+        let cx0 = points.remove(0);
+        Ok(IssuerParams { cx0, xs: points })
+    }
+}
+
+/// The request for a credential, carrying the ElGamal encryptions of the
+/// hidden attributes along with a proof of knowledge of them.
+#[derive(Clone, Debug)]
+pub struct CredentialRequest {
+    d: RistrettoPoint,
+    indices: Vec<usize>,
+    ciphertexts: Vec<(RistrettoPoint, RistrettoPoint)>,
+    proof: Proof,
+}
+
+impl CredentialRequest {
+    /// Returns the indices of the hidden attributes
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Verifies the proof of knowledge of the encrypted attributes
+    fn verify<H>(&self) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        request_statement(&self.d, &self.ciphertexts)
+            .verify::<H>(&self.proof, &index_bytes(&self.indices))
+    }
+}
+
+/// For managing the requester side of an issuance.
+pub struct CredentialRequester {
+    d: Scalar,
+    request: CredentialRequest,
+    params: IssuerParams,
+}
+
+impl CredentialRequester {
+    /// Encrypts the hidden attributes with their indices under a fresh
+    /// ElGamal key, proving knowledge of them.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((CredentialRequest, CredentialRequester)) on success, with the
+    /// CredentialRequest being sent to the issuer.
+    ///
+    /// * Err(::Error) on failure, which indicates an index was repeated or
+    /// out of range, or the failure to initiate the internal RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * D = d*G
+    /// * Ei = (ri*G, mi*G + ri*D)
+    pub fn new<H>(
+        params: &IssuerParams,
+        hidden: &[(usize, &[u8])],
+    ) -> ::Result<(CredentialRequest, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let hidden: Vec<(usize, Scalar)> = hidden
+            .iter()
+            .map(|&(index, attribute)| (index, attribute_scalar::<H>(attribute)))
+            .collect();
+        hidden_indices(params.xs.len(), &hidden)?;
+        let mut rng = rng::try_new()?;
+        let d = Scalar::random(&mut rng);
+        let g = RISTRETTO_BASEPOINT_POINT;
+        let rs: Vec<Scalar> = hidden.iter().map(|_| Scalar::random(&mut rng)).collect();
+        let d_pub = d * g;
+        let indices: Vec<usize> = hidden.iter().map(|&(index, _)| index).collect();
+        let ciphertexts: Vec<(RistrettoPoint, RistrettoPoint)> = hidden
+            .iter()
+            .zip(&rs)
+            .map(|(&(_, m), r)| (r * g, m * g + r * d_pub))
+            .collect();
+        let mut witnesses = vec![d];
+        witnesses.extend(hidden.iter().map(|&(_, m)| m));
+        witnesses.extend_from_slice(&rs);
+        let proof = request_statement(&d_pub, &ciphertexts).prove::<H>(
+            &witnesses,
+            &index_bytes(&indices),
+            &mut rng,
+        );
+        let request = CredentialRequest {
+            d: d_pub,
+            indices,
+            ciphertexts,
+            proof,
+        };
+
+        let requester = CredentialRequester {
+            d,
+            request: request.clone(),
+            params: params.clone(),
+        };
+        Ok((request, requester))
+    }
+
+    /// Input the BlindCredential from the issuer along with the attributes
+    /// known to the issuer, consumes self and creates the credential after
+    /// verifying that the issuer used the keys of its IssuerParams.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Credential) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the issuance proof did
+    /// not verify.
+    ///
+    /// # Mathematics
+    ///
+    /// * u' = E2 - d*E1
+    pub fn finalize<H>(
+        self,
+        blind_cred: &BlindCredential,
+        known: &[(usize, &[u8])],
+    ) -> ::Result<Credential>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let known: Vec<(usize, Scalar)> = known
+            .iter()
+            .map(|&(index, attribute)| (index, attribute_scalar::<H>(attribute)))
+            .collect();
+        let statement = issuance_statement::<H>(
+            &self.params,
+            &self.request,
+            &known,
+            &blind_cred.u,
+            &blind_cred.e,
+        );
+        if blind_cred.u == RistrettoPoint::identity()
+            || !statement.verify::<H>(&blind_cred.proof, &[])
+        {
+            return Err(KvacProofInvalid);
+        }
+        Ok(Credential {
+            u: blind_cred.u,
+            u_prime: blind_cred.e.1 - self.d * blind_cred.e.0,
+            params: self.params,
+        })
+    }
+}
+
+/// The encrypted MAC returned by the issuer, along with its issuance proof
+#[derive(Clone, Debug)]
+pub struct BlindCredential {
+    u: RistrettoPoint,
+    e: (RistrettoPoint, RistrettoPoint),
+    proof: Proof,
+}
+
+/// A credential, the MAC (u, u') on its attributes
+#[derive(Clone, Debug)]
+pub struct Credential {
+    u: RistrettoPoint,
+    u_prime: RistrettoPoint,
+    params: IssuerParams,
+}
+
+impl Credential {
+    /// Presents the credential on the attributes, disclosing only the
+    /// attributes at the disclosed indices.
+    ///
+    /// # Arguments
+    ///
+    /// * 'nonce' - The nonce from the verifier, which prevents the
+    /// presentation from being replayed.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Presentation) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the wrong number of
+    /// attributes, an invalid disclosed index, or the failure to initiate the
+    /// internal RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * u = a*u, u' = a*u'
+    /// * Cmi = mi*u + zi*H, Cu' = u' + r*H
+    pub fn present<H>(
+        &self,
+        attributes: &[&[u8]],
+        disclosed: &[usize],
+        nonce: &[u8],
+    ) -> ::Result<Presentation>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        if attributes.len() != self.params.xs.len() {
+            return Err(KvacAttributesInvalid);
+        }
+        let scalars: Vec<Scalar> = attributes
+            .iter()
+            .map(|attribute| attribute_scalar::<H>(attribute))
+            .collect();
+        let disclosed: Vec<(usize, Scalar)> = disclosed
+            .iter()
+            .map(|&i| scalars.get(i).map(|&m| (i, m)).ok_or(KvacAttributesInvalid))
+            .collect::<::Result<Vec<_>>>()?;
+        let hidden = hidden_indices(self.params.xs.len(), &disclosed)?;
+
+        let mut rng = rng::try_new()?;
+        let a = nonzero_scalar(&mut rng);
+        let (u, u_prime) = (a * self.u, a * self.u_prime);
+        let h = generator::<H>();
+        let zs: Vec<Scalar> = hidden.iter().map(|_| Scalar::random(&mut rng)).collect();
+        let r = Scalar::random(&mut rng);
+        let cu_prime = u_prime + r * h;
+        let cms: Vec<RistrettoPoint> = hidden
+            .iter()
+            .zip(&zs)
+            .map(|(&i, z)| scalars[i] * u + z * h)
+            .collect();
+        let v = hidden
+            .iter()
+            .zip(&zs)
+            .fold(-r * h, |acc, (&i, z)| acc + z * self.params.xs[i]);
+
+        let statement = presentation_statement::<H>(&self.params, &u, &cms, &hidden, &v);
+        let mut witnesses: Vec<Scalar> = hidden.iter().map(|&i| scalars[i]).collect();
+        witnesses.extend_from_slice(&zs);
+        witnesses.push(r);
+        let context = presentation_context(&cu_prime, &disclosed, nonce);
+        Ok(Presentation {
+            u,
+            cu_prime,
+            cms,
+            proof: statement.prove::<H>(&witnesses, &context, &mut rng),
+        })
+    }
+}
+
+/// A presentation of a credential, disclosing a subset of its attributes
+#[derive(Clone, Debug)]
+pub struct Presentation {
+    u: RistrettoPoint,
+    cu_prime: RistrettoPoint,
+    cms: Vec<RistrettoPoint>,
+    proof: Proof,
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// A Fiat-Shamir proof of knowledge for a Statement
+///
+/// pub(crate) as used in linkable.rs
+#[derive(Clone, Debug)]
+pub(crate) struct Proof {
+    c: Scalar,
+    responses: Vec<Scalar>,
+}
+
+/// A linear relation lhs = Σ wi*Bi over the witnesses of a Statement
+///
+/// pub(crate) as used in linkable.rs
+pub(crate) struct Relation {
+    pub(crate) lhs: RistrettoPoint,
+    pub(crate) terms: Vec<(usize, RistrettoPoint)>,
+}
+
+/// A set of linear relations over a vector of witnesses, each relation
+/// referring to witnesses by their index
+///
+/// pub(crate) as used in linkable.rs
+pub(crate) struct Statement {
+    pub(crate) relations: Vec<Relation>,
+}
+
+impl Statement {
+    /// Proves knowledge of the witnesses satisfying every relation
+    pub(crate) fn prove<H>(
+        &self,
+        witnesses: &[Scalar],
+        context: &[u8],
+        rng: &mut SecureRng,
+    ) -> Proof
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let blindings: Vec<Scalar> = witnesses.iter().map(|_| Scalar::random(rng)).collect();
+        let commitments: Vec<RistrettoPoint> = self
+            .relations
+            .iter()
+            .map(|relation| relation.combine(&blindings))
+            .collect();
+        let c = self.challenge::<H>(&commitments, context);
+        Proof {
+            c,
+            responses: blindings
+                .iter()
+                .zip(witnesses)
+                .map(|(blinding, witness)| blinding + c * witness)
+                .collect(),
+        }
+    }
+
+    /// Verifies the proof of knowledge of the witnesses
+    pub(crate) fn verify<H>(&self, proof: &Proof, context: &[u8]) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let count = self
+            .relations
+            .iter()
+            .flat_map(|relation| relation.terms.iter().map(|&(w, _)| w + 1))
+            .max()
+            .unwrap_or(0);
+        if proof.responses.len() != count {
+            return false;
+        }
+        let commitments: Vec<RistrettoPoint> = self
+            .relations
+            .iter()
+            .map(|relation| relation.combine(&proof.responses) - proof.c * relation.lhs)
+            .collect();
+        proof.c == self.challenge::<H>(&commitments, context)
+    }
+
+    /// Computes the challenge over the relations and commitments
+    fn challenge<H>(&self, commitments: &[RistrettoPoint], context: &[u8]) -> Scalar
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut hasher = H::default();
+        hasher.input(PROOF_DOMAIN);
+        hasher.input(&(context.len() as u32).to_be_bytes());
+        hasher.input(context);
+        for relation in &self.relations {
+            hasher.input(relation.lhs.compress().as_bytes());
+            for &(w, base) in &relation.terms {
+                hasher.input(&(w as u32).to_be_bytes());
+                hasher.input(base.compress().as_bytes());
+            }
+        }
+        for commitment in commitments {
+            hasher.input(commitment.compress().as_bytes());
+        }
+        Scalar::from_hash(hasher)
+    }
+}
+
+impl Relation {
+    /// Computes Σ si*Bi over the scalars in place of the witnesses
+    fn combine(&self, scalars: &[Scalar]) -> RistrettoPoint {
+        self.terms
+            .iter()
+            .fold(RistrettoPoint::identity(), |acc, &(w, base)| {
+                acc + scalars[w] * base
+            })
+    }
+}
+
+/// The statement of a request, with witnesses d, the hidden mi and the ri
+///
+/// * D = d*G
+/// * Ei1 = ri*G
+/// * Ei2 = mi*G + ri*D
+fn request_statement(
+    d: &RistrettoPoint,
+    ciphertexts: &[(RistrettoPoint, RistrettoPoint)],
+) -> Statement {
+    let g = RISTRETTO_BASEPOINT_POINT;
+    let k = ciphertexts.len();
+    let mut relations = vec![Relation {
+        lhs: *d,
+        terms: vec![(0, g)],
+    }];
+    for (j, &(c1, c2)) in ciphertexts.iter().enumerate() {
+        relations.push(Relation {
+            lhs: c1,
+            terms: vec![(1 + k + j, g)],
+        });
+        relations.push(Relation {
+            lhs: c2,
+            terms: vec![(1 + j, g), (1 + k + j, *d)],
+        });
+    }
+    Statement { relations }
+}
+
+/// The statement of an issuance, with witnesses b, x0, x0', the xi, r and
+/// the ti = b*xi of the hidden attributes
+///
+/// * u = b*G
+/// * Cx0 = x0*G + x0'*H
+/// * Xi = xi*H
+/// * 0 = ti*H - b*Xi
+/// * E1 = r*G + Σ hidden ti*Ei1
+/// * E2 = x0*u + Σ known xi*(mi*u) + r*D + Σ hidden ti*Ei2
+fn issuance_statement<H>(
+    params: &IssuerParams,
+    request: &CredentialRequest,
+    known: &[(usize, Scalar)],
+    u: &RistrettoPoint,
+    e: &(RistrettoPoint, RistrettoPoint),
+) -> Statement
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let (g, h) = (RISTRETTO_BASEPOINT_POINT, generator::<H>());
+    let n = params.xs.len();
+    let (x, r, t) = (3, 3 + n, 4 + n);
+    let mut relations = vec![
+        Relation {
+            lhs: *u,
+            terms: vec![(0, g)],
+        },
+        Relation {
+            lhs: params.cx0,
+            terms: vec![(1, g), (2, h)],
+        },
+    ];
+    for (i, &xi) in params.xs.iter().enumerate() {
+        relations.push(Relation {
+            lhs: xi,
+            terms: vec![(x + i, h)],
+        });
+    }
+    for (j, &i) in request.indices.iter().enumerate() {
+        relations.push(Relation {
+            lhs: RistrettoPoint::identity(),
+            terms: vec![(t + j, h), (0, -params.xs[i])],
+        });
+    }
+    let mut e1_terms = vec![(r, g)];
+    let mut e2_terms = vec![(1, *u)];
+    e2_terms.extend(known.iter().map(|&(i, m)| (x + i, m * u)));
+    e2_terms.push((r, request.d));
+    for (j, &(c1, c2)) in request.ciphertexts.iter().enumerate() {
+        e1_terms.push((t + j, c1));
+        e2_terms.push((t + j, c2));
+    }
+    relations.push(Relation {
+        lhs: e.0,
+        terms: e1_terms,
+    });
+    relations.push(Relation {
+        lhs: e.1,
+        terms: e2_terms,
+    });
+    Statement { relations }
+}
+
+/// The statement of a presentation, with witnesses the hidden mi, the zi
+/// and r
+///
+/// * Cmi = mi*u + zi*H
+/// * V = Σ hidden zi*Xi - r*H
+fn presentation_statement<H>(
+    params: &IssuerParams,
+    u: &RistrettoPoint,
+    cms: &[RistrettoPoint],
+    hidden: &[usize],
+    v: &RistrettoPoint,
+) -> Statement
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let h = generator::<H>();
+    let k = hidden.len();
+    let mut relations: Vec<Relation> = cms
+        .iter()
+        .enumerate()
+        .map(|(j, &cm)| Relation {
+            lhs: cm,
+            terms: vec![(j, *u), (k + j, h)],
+        })
+        .collect();
+    let mut v_terms: Vec<(usize, RistrettoPoint)> = hidden
+        .iter()
+        .enumerate()
+        .map(|(j, &i)| (k + j, params.xs[i]))
+        .collect();
+    v_terms.push((2 * k, -h));
+    relations.push(Relation {
+        lhs: *v,
+        terms: v_terms,
+    });
+    Statement { relations }
+}
+
+/// Encodes the context of a presentation, binding its proof to Cu', the
+/// disclosed attributes and the nonce
+fn presentation_context(
+    cu_prime: &RistrettoPoint,
+    disclosed: &[(usize, Scalar)],
+    nonce: &[u8],
+) -> Vec<u8> {
+    let mut context = cu_prime.compress().to_bytes().to_vec();
+    for &(index, m) in disclosed {
+        context.extend_from_slice(&(index as u32).to_be_bytes());
+        context.extend_from_slice(m.as_bytes());
+    }
+    context.extend_from_slice(nonce);
+    context
+}
+
+/// Derives the generator H, whose discrete log relative to G is unknown
+fn generator<H>() -> RistrettoPoint
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    RistrettoPoint::hash_from_bytes::<H>(GENERATOR_DOMAIN)
+}
+
+/// Hashes an attribute to a scalar
+fn attribute_scalar<H>(attribute: &[u8]) -> Scalar
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut input = ATTRIBUTE_DOMAIN.to_vec();
+    input.extend_from_slice(attribute);
+    Scalar::hash_from_bytes::<H>(&input)
+}
+
+/// Returns the indices not in the provided attributes, checking that their
+/// indices are distinct and in range
+fn hidden_indices(count: usize, attributes: &[(usize, Scalar)]) -> ::Result<Vec<usize>> {
+    for (j, &(index, _)) in attributes.iter().enumerate() {
+        if index >= count || attributes[..j].iter().any(|&(other, _)| other == index) {
+            return Err(KvacAttributesInvalid);
+        }
+    }
+    Ok((0..count)
+        .filter(|i| !attributes.iter().any(|&(index, _)| index == *i))
+        .collect())
+}
+
+/// Checks that the hidden and known indices cover every attribute exactly
+/// once
+fn check_partition(count: usize, hidden: &[usize], known: &[(usize, Scalar)]) -> ::Result<()> {
+    let mut hidden = hidden.to_vec();
+    hidden.sort();
+    if hidden_indices(count, known)? != hidden {
+        return Err(KvacAttributesInvalid);
+    }
+    Ok(())
+}
+
+/// Encodes attribute indices as 4 byte big endian integers
+fn index_bytes(indices: &[usize]) -> Vec<u8> {
+    indices
+        .iter()
+        .flat_map(|&index| (index as u32).to_be_bytes().to_vec())
+        .collect()
+}
+
+/// Generates a uniformly random nonzero scalar
+///
+/// pub(crate) as used in linkable.rs
+pub(crate) fn nonzero_scalar(rng: &mut SecureRng) -> Scalar {
+    loop {
+        let a = Scalar::random(rng);
+        if a != Scalar::zero() {
+            return a;
+        }
+    }
+}
@@ -22,11 +22,170 @@ extern crate subtle;
 #[macro_use]
 extern crate failure_derive;
 
+// Optionally imported crates
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "encrypted-keys")]
+extern crate argon2;
+#[cfg(any(feature = "encrypted-keys", feature = "sealing"))]
+extern crate chacha20poly1305;
+#[cfg(feature = "mnemonic")]
+extern crate bip39;
+#[cfg(feature = "rsa-blind")]
+extern crate num_bigint_dig;
+#[cfg(feature = "rsa-blind")]
+extern crate rsa;
+#[cfg(feature = "rsa-blind")]
+extern crate rand_core_rsa;
+#[cfg(any(
+    feature = "rsa-blind",
+    feature = "bls",
+    feature = "ibs",
+    feature = "voprf",
+    feature = "bbs"
+))]
+extern crate sha2;
+#[cfg(any(feature = "bls", feature = "ibs", feature = "bbs"))]
+extern crate bls12_381;
+#[cfg(feature = "bulletproofs")]
+extern crate bulletproofs_crate as bulletproofs;
+#[cfg(feature = "bulletproofs")]
+extern crate curve25519_dalek_bp;
+#[cfg(feature = "bulletproofs")]
+extern crate merlin;
+#[cfg(feature = "pkcs11")]
+extern crate cryptoki;
+#[cfg(feature = "tokio-transport")]
+extern crate tokio;
+#[cfg(any(
+    feature = "ffi",
+    feature = "wasm",
+    feature = "mobile",
+    feature = "python"
+))]
+extern crate sha3;
+#[cfg(feature = "mobile")]
+extern crate uniffi;
+#[cfg(feature = "python")]
+extern crate pyo3;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "fuzzing")]
+extern crate arbitrary;
+#[cfg(feature = "fuzzing")]
+extern crate proptest;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(feature = "protobuf")]
+extern crate prost;
+#[cfg(feature = "protobuf")]
+#[macro_use]
+extern crate prost_derive;
+
+// Crate internal macros, which must be declared before the modules using them
+#[macro_use]
+mod macros;
+
+// Crate internal modules
+mod encoding;
+#[cfg(any(feature = "encrypted-keys", feature = "sealing"))]
+mod seal;
+#[cfg(feature = "serde")]
+mod serde_impls;
+mod wipe;
+
 // The public interface
+pub mod announce;
+pub mod audit;
+pub mod ballot;
+pub mod batch;
+pub mod bundle;
+pub mod chain;
+pub mod clause;
+pub mod cut_choose;
+pub mod denominations;
+pub mod divisible;
+pub mod ecash;
+pub mod envelope;
+pub mod expiry;
+pub mod fair;
+pub mod frost;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
 pub mod keypair;
+pub mod keyset;
+pub mod kvac;
+pub mod linkable;
+pub mod local;
+pub mod multi;
+pub mod okamoto;
+pub mod partially_blind;
+pub mod pedersen;
+pub mod policy;
+pub mod proof;
+pub mod protocol;
+pub mod proxy;
+pub mod ratelimit;
+pub mod recovery;
 pub mod request;
+pub mod rng;
 pub mod session;
 pub mod signature;
+pub mod spent;
+pub mod testvectors;
+pub mod threshold;
+#[cfg(feature = "timing-harness")]
+pub mod timing;
+pub mod transport;
+pub mod usage;
+pub mod verifier;
+pub mod voucher;
+
+// Optional encodings
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "protobuf")]
+pub mod proto;
+#[cfg(feature = "pkcs8")]
+pub mod pkcs8;
+#[cfg(feature = "encrypted-keys")]
+pub mod encrypted;
+#[cfg(feature = "mnemonic")]
+pub mod mnemonic;
+
+// Optional schemes
+#[cfg(feature = "rsa-blind")]
+pub mod rsa_blind;
+#[cfg(feature = "bls")]
+pub mod bls;
+#[cfg(feature = "ibs")]
+pub mod ibs;
+#[cfg(feature = "voprf")]
+pub mod voprf;
+#[cfg(feature = "privacy-pass")]
+pub mod privacy_pass;
+#[cfg(feature = "bbs")]
+pub mod bbs;
+#[cfg(feature = "bulletproofs")]
+pub mod bounded;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+
+// Optional bindings
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "mobile")]
+pub mod mobile;
+#[cfg(feature = "python")]
+pub mod python;
+
+// The UniFFI scaffolding, which must be included at the crate root
+#[cfg(feature = "mobile")]
+use mobile::{authenticate, deserialize_token, serialize_token, BlindRequest, BlindsignError};
+#[cfg(feature = "mobile")]
+uniffi::include_scaffolding!("blindsign");
 
 /// The Result type used
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -40,6 +199,188 @@ pub enum Error {
     WiredScalarMalformed,
     #[fail(display = "failed to convert wired ristretto point to ristretto point")]
     WiredRistrettoPointMalformed,
+    #[fail(display = "wired input had length {}, expected {}", actual, expected)]
+    WiredLengthInvalid { expected: usize, actual: usize },
+    #[fail(display = "envelope was too short to contain a header")]
+    EnvelopeTruncated,
+    #[fail(display = "envelope version {} is not supported", _0)]
+    EnvelopeVersionUnsupported(u8),
+    #[fail(display = "envelope payload type {} is unknown", _0)]
+    EnvelopePayloadTypeUnknown(u8),
+    #[fail(display = "envelope payload type did not match the expected type")]
+    EnvelopePayloadTypeMismatch,
+    #[fail(display = "input was not canonical CBOR of the expected type")]
+    CborMalformed,
+    #[fail(display = "input was not a valid hex string")]
+    HexMalformed,
+    #[fail(display = "input was not a valid bech32 string")]
+    Bech32Malformed,
+    #[fail(display = "bech32 human readable part did not match the expected one")]
+    Bech32HrpMismatch,
+    #[fail(display = "input was not a valid base64 string")]
+    Base64Malformed,
+    #[fail(display = "input was not a valid PKCS#8 blindsign private key")]
+    Pkcs8Malformed,
+    #[fail(display = "input was not a valid PEM document")]
+    PemMalformed,
+    #[fail(display = "failed to derive a key from the password")]
+    KeyDerivationFailed,
+    #[fail(display = "failed to decrypt, the key was wrong or the input was altered")]
+    DecryptionFailed,
+    #[fail(display = "input was not a valid encrypted key")]
+    EncryptedKeyMalformed,
+    #[fail(display = "input was not a valid BIP 39 mnemonic")]
+    MnemonicInvalid,
+    #[fail(display = "public key was the identity or non-canonically encoded")]
+    PublicKeyInvalid,
+    #[fail(display = "key epoch {} is already in the key set", _0)]
+    KeyEpochDuplicate(u32),
+    #[fail(display = "no pending session has the provided session id")]
+    SessionUnknown,
+    #[fail(display = "a session with the provided session id is already pending")]
+    SessionDuplicate,
+    #[fail(display = "the cap on concurrently open sessions has been reached")]
+    SessionLimitReached,
+    #[fail(display = "input was not valid sealed state of the expected kind")]
+    SealedStateMalformed,
+    #[fail(display = "clause index was neither 0 nor 1")]
+    ClauseIndexInvalid,
+    #[fail(display = "RSA input was malformed or out of range for the key")]
+    RsaInputInvalid,
+    #[fail(display = "RSA key was too small for the message encoding")]
+    RsaEncodingFailed,
+    #[fail(display = "RSA signature failed verification")]
+    RsaSignatureInvalid,
+    #[fail(display = "input was not a valid BLS12-381 group element")]
+    BlsPointMalformed,
+    #[fail(display = "fair blind signature escrow was malformed or its proof was invalid")]
+    FairEscrowInvalid,
+    #[fail(display = "message was too long to be recoverable from a signature")]
+    RecoveryMessageTooLong,
+    #[fail(display = "recovered message failed its redundancy check")]
+    RecoveryRedundancyInvalid,
+    #[fail(display = "delegation was not issued by the owner to the proxy")]
+    ProxyDelegationInvalid,
+    #[fail(display = "threshold was zero or greater than the number of shares")]
+    ThresholdParamsInvalid,
+    #[fail(display = "participating signers were too few, repeated or included index 0")]
+    ThresholdSignersInvalid,
+    #[fail(display = "partial signature of signer {} was missing or invalid", _0)]
+    ThresholdPartialInvalid(u32),
+    #[fail(display = "VOPRF input was malformed or the identity element")]
+    VoprfInputInvalid,
+    #[fail(display = "VOPRF proof failed verification")]
+    VoprfProofInvalid,
+    #[fail(display = "VOPRF key derivation did not find a nonzero key")]
+    VoprfKeyDerivationFailed,
+    #[fail(display = "Privacy Pass message had the wrong length or token type")]
+    PrivacyPassMalformed,
+    #[fail(display = "Privacy Pass request was for a different or unsupported issuer key")]
+    PrivacyPassKeyMismatch,
+    #[fail(display = "BBS+ message index was repeated or out of range")]
+    BbsIndexInvalid,
+    #[fail(display = "BBS+ commitment proof failed verification")]
+    BbsProofInvalid,
+    #[fail(display = "credential attribute indices were repeated, out of range or incomplete")]
+    KvacAttributesInvalid,
+    #[fail(display = "credential proof failed verification")]
+    KvacProofInvalid,
+    #[fail(display = "e-cash token was not valid under the mint key")]
+    EcashTokenInvalid,
+    #[fail(display = "signature being redeemed failed verification")]
+    RedemptionSignatureInvalid,
+    #[fail(display = "signature was already spent")]
+    AlreadySpent,
+    #[fail(display = "spent store failed to record the nullifier")]
+    SpentStoreFailed,
+    #[fail(display = "a denomination with the same key is already in the registry")]
+    DenominationDuplicate,
+    #[fail(display = "expiring token signature was not valid on its message and expiry")]
+    ExpiringTokenInvalid,
+    #[fail(display = "token expired at {}", _0)]
+    TokenExpired(u64),
+    #[fail(display = "ballot was for a different election")]
+    BallotElectionMismatch,
+    #[fail(display = "ballot was not signed by the election authority")]
+    BallotSignatureInvalid,
+    #[fail(display = "voucher was for a different campaign")]
+    VoucherCampaignMismatch,
+    #[fail(display = "voucher signature was not valid on its terms")]
+    VoucherInvalid,
+    #[fail(display = "client reached its issuance quota for the current window")]
+    IssuanceQuotaExceeded,
+    #[fail(display = "divisible coin depth or node was out of range")]
+    DivisibleNodeInvalid,
+    #[fail(display = "divisible spend was not of a coin signed by the mint")]
+    DivisibleSpendInvalid,
+    #[fail(display = "cut-and-choose needed at least two candidates and a kept index in range")]
+    CutChooseCandidatesInvalid,
+    #[fail(display = "cut-and-choose openings were missing or did not match their e' values")]
+    CutChooseOpeningInvalid,
+    #[fail(display = "opened cut-and-choose candidate {} was rejected", _0)]
+    CutChooseMessageRejected(usize),
+    #[fail(display = "amount was greater than the limit")]
+    AmountExceedsLimit,
+    #[fail(display = "amount range proof was malformed or failed verification")]
+    RangeProofInvalid,
+    #[fail(display = "bounded token signature was not valid on its message and commitment")]
+    BoundedTokenInvalid,
+    #[fail(display = "blind signature was not valid under the published key")]
+    BlindSignatureInvalid,
+    #[fail(display = "audit log entry {} had a wrong hash or was not chained", _0)]
+    AuditLogInvalid(usize),
+    #[fail(display = "signing was rejected by policy: {}", _0)]
+    PolicyRejected(policy::PolicyRejection),
+    #[fail(display = "PKCS#11 token failed to wrap or unwrap the key")]
+    Pkcs11Failed,
+    #[fail(display = "unwrapped PKCS#11 key did not match its public key")]
+    Pkcs11KeyMismatch,
+    #[fail(display = "protocol message was unexpected in the current state")]
+    ProtocolUnexpectedMessage,
+    #[fail(display = "protocol message was for a different session")]
+    ProtocolSessionMismatch,
+    #[fail(display = "protocol was aborted by the other side")]
+    ProtocolAborted,
+    #[fail(display = "protocol message frame was not yet complete")]
+    ProtocolMessageIncomplete,
+    #[fail(display = "protocol message type tag {} is unknown", _0)]
+    ProtocolMessageTagUnknown(u8),
+    #[fail(display = "transport failed to send or receive a message")]
+    TransportFailed,
+    #[fail(display = "test vector was not in the documented format")]
+    TestVectorMalformed,
+    #[fail(display = "test vector value {} did not match", _0)]
+    TestVectorMismatch(&'static str),
+    #[fail(display = "wired ristretto point was the identity")]
+    WiredRistrettoPointIdentity,
+    #[fail(display = "wired scalar was zero")]
+    WiredScalarZero,
+    #[fail(display = "wired input was not canonically encoded")]
+    WiredEncodingNonCanonical,
+    #[fail(display = "e' was already signed in another session")]
+    EPrimeReplayed,
+    #[fail(display = "entropy source self test found constant output")]
+    RngSelfTestFailed,
+    #[fail(display = "wired {} at byte offset {} was malformed", field, offset)]
+    WiredFieldMalformed { field: WiredField, offset: usize },
+    #[fail(display = "vector count {} was zero or greater than the maximum", _0)]
+    MultiCountInvalid(usize),
+    #[fail(display = "vector had {} values, expected {}", actual, expected)]
+    MultiCountMismatch { expected: usize, actual: usize },
+    #[fail(display = "signature bundle failed to read or write")]
+    BundleIoFailed,
+    #[fail(display = "signature bundle had {} signatures, expected {}", actual, expected)]
+    BundleCountMismatch { expected: usize, actual: usize },
+    #[fail(display = "signing key reached its cap on signatures")]
+    KeyUsageCapReached,
+    #[fail(display = "key announcement was not signed by the announced key")]
+    KeyAnnouncementInvalid,
+    #[fail(display = "tag key certificate was not issued for the key under the issuer params")]
+    TagCertificateInvalid,
+    #[fail(display = "spent token is already pending in another reissue")]
+    ReissuePending,
+    #[fail(display = "public key was not the private key times the generator point")]
+    KeypairMismatch,
 }
 
 impl From<rand::Error> for Error {
@@ -47,3 +388,31 @@ impl From<rand::Error> for Error {
         Error::RngInitFailed
     }
 }
+
+/// The field of a wired value which failed to parse, as reported by
+/// Error::WiredFieldMalformed along with its byte offset in the input.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WiredField {
+    /// The e scalar of a signature
+    E,
+    /// The S scalar of a signature
+    S,
+    /// The R point of a signature
+    R,
+    /// A public key point
+    PublicKey,
+    /// A private key scalar
+    PrivateKey,
+}
+
+impl ::std::fmt::Display for WiredField {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str(match *self {
+            WiredField::E => "e",
+            WiredField::S => "S",
+            WiredField::R => "R",
+            WiredField::PublicKey => "public key",
+            WiredField::PrivateKey => "private key",
+        })
+    }
+}
@@ -0,0 +1,581 @@
+//! Linkable tokens with deterministic per message tags
+//!
+//! Each token carries a tag T = z*H(m), which is a PRF of the message m under
+//! a tag key z held by the requester, and the blind signature covers T || m.
+//! Redeeming two tokens on the same message with the same tag key produces
+//! the same tag, so verifiers can detect duplicates by storing the tags they
+//! have seen, as with the key images of linkable ring signatures.
+//!
+//! The tag key is bound to the requester by the signer, which certifies the
+//! public tag key Z = z*P once per requester with a keyed-verification
+//! credential on z, as in kvac.rs. The credential is a MAC under a
+//! TagIssuerKey separate from the signing key, so no blind signing session
+//! can produce one. Each token carries a rerandomized presentation of the
+//! credential along with a proof that T was computed with the same z, so
+//! neither z nor Z is revealed on redemption, and the signer checks both
+//! with msg_authenticate.
+//!
+//! 1. The requester sends its public tag key from TagKey::public_key to the
+//!    signer, which authenticates the requester and, if it has not certified
+//!    a tag key for the requester before, certifies it with
+//!    TagIssuerKey::certify.
+//! 2. The requester computes the tagged message with TagKey::tagged_msg, and
+//!    blinds it with BlindRequest::new_specific_msg.
+//! 3. The signer signs e' with an ordinary BlindSession.
+//! 4. The requester pairs the signature with the tag and a presentation of
+//!    its certificate into a LinkableToken with TagKey::token.
+//!
+//! # Mathematics
+//!
+//! * Cx0 = x0*P + x0'*H, X1 = x1*H
+//! * u = b*P, u' = x0*u + x1*b*Z = (x0 + x1*z)*u
+//!
+//! # Security
+//!
+//! A requester can only obtain a second tag key, and so a second tag on the
+//! same message, if the signer certifies more than one tag key for it, so
+//! the signer must keep track of the requesters it has certified keys for.
+//!
+//! The certificate proves that it was issued under the published
+//! TagIssuerParams, which the requester checks in TagKey::token, so the
+//! signer cannot tell requesters apart by certifying them under different
+//! keys. Tokens on different messages are unlinkable to each other and to
+//! their issuance sessions.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::Identity,
+};
+use digest::Digest;
+use encoding;
+use keypair::{Redacted, VerifyingKey};
+use kvac::{self, Proof, Relation, Statement};
+use rng;
+use signature::UnblindedSigData;
+use std::fmt;
+use typenum::U64;
+use Error::{
+    TagCertificateInvalid, WiredRistrettoPointIdentity, WiredRistrettoPointMalformed,
+    WiredScalarMalformed,
+};
+
+/// The domain separation prefix for hashing messages to points
+const TAG_DOMAIN: &[u8] = b"blindsign linkable tag";
+
+/// The domain separation prefix for deriving the generator H
+const GENERATOR_DOMAIN: &[u8] = b"blindsign linkable generator";
+
+/// The domain separation context of the proof of a tag key certificate
+const CERTIFICATE_DOMAIN: &[u8] = b"blindsign tag key certificate";
+
+/// The domain separation prefix for the context of a token presentation
+const PRESENTATION_DOMAIN: &[u8] = b"blindsign linkable presentation";
+
+/// The tag key z of a requester
+#[derive(Copy, Clone)]
+pub struct TagKey {
+    z: Scalar,
+}
+
+impl fmt::Debug for TagKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TagKey")
+            .field("z", &Redacted)
+            .field("public", &encoding::to_hex(&self.public_key().0))
+            .finish()
+    }
+}
+
+impl TagKey {
+    /// Generates a new random tag key.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(TagKey) on success.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    pub fn generate() -> ::Result<Self> {
+        Ok(TagKey {
+            z: kvac::nonzero_scalar(&mut rng::try_new()?),
+        })
+    }
+
+    /// Loads the tag key from its wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(TagKey) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the key was not a canonical
+    /// scalar, or was zero.
+    pub fn from_wired(z: [u8; 32]) -> ::Result<Self> {
+        match Scalar::from_canonical_bytes(z) {
+            Some(z) if z != Scalar::zero() => Ok(TagKey { z }),
+            _ => Err(WiredScalarMalformed),
+        }
+    }
+
+    /// Returns the tag key in wired form
+    pub fn wired(&self) -> [u8; 32] {
+        self.z.to_bytes()
+    }
+
+    /// Returns the public tag key, which is sent to the signer for
+    /// certification.
+    ///
+    /// # Mathematics
+    ///
+    /// * Z = z*P
+    pub fn public_key(&self) -> TagPublicKey {
+        TagPublicKey((self.z * RISTRETTO_BASEPOINT_POINT).compress().to_bytes())
+    }
+
+    /// Computes the tag on the message.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for hashing the message to a point
+    ///
+    /// # Mathematics
+    ///
+    /// * T = z*H(m)
+    pub fn tag<H, M>(&self, m: M) -> LinkTag
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        LinkTag((self.z * msg_point::<H>(m.as_ref())).compress().to_bytes())
+    }
+
+    /// Computes the tagged message T || m, which is the message that is
+    /// blindly signed for a linkable token.
+    pub fn tagged_msg<H, M>(&self, m: M) -> Vec<u8>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        tagged_msg(&self.tag::<H, &[u8]>(m.as_ref()), m.as_ref())
+    }
+
+    /// Pairs the signature on the tagged message with the tag and a
+    /// rerandomized presentation of the certificate of this tag key, proving
+    /// that the tag was computed with the certified tag key.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for hashing the message to a point and
+    /// for the proofs
+    ///
+    /// # Returns
+    ///
+    /// * Ok(LinkableToken) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the certificate was not
+    /// issued for this tag key under the provided params, or the failure to
+    /// initiate the internal RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * U = a*u and U' = a*u', with a random
+    /// * Cz = z*U + r1*H and Cu' = U' + r*H, with r1 and r random
+    /// * Proof of z, r1 and r with Cz = z*U + r1*H, V = r1*X1 - r*H and
+    /// T = z*H(m)
+    pub fn token<H, M>(
+        &self,
+        params: &TagIssuerParams,
+        cert: &TagKeyCertificate,
+        m: M,
+        sig: UnblindedSigData,
+    ) -> ::Result<LinkableToken>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        if !cert.verify::<H>(params, &self.public_key()) {
+            return Err(TagCertificateInvalid);
+        }
+        let h = generator::<H>();
+        let hm = msg_point::<H>(m.as_ref());
+        let tag = self.z * hm;
+        let mut rng = rng::try_new()?;
+        let a = kvac::nonzero_scalar(&mut rng);
+        let (r1, r) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+        let u = a * cert.u;
+        let cz = self.z * u + r1 * h;
+        let cu_prime = a * cert.u_prime + r * h;
+        let v = r1 * params.x1 - r * h;
+        let proof = presentation_statement(&u, &cz, &v, &tag, &hm, &params.x1, &h)
+            .prove::<H>(&[self.z, r1, r], &presentation_context(m.as_ref()), &mut rng);
+        Ok(LinkableToken {
+            tag: LinkTag(tag.compress().to_bytes()),
+            u,
+            cz,
+            cu_prime,
+            proof,
+            sig,
+        })
+    }
+}
+
+/// The public tag key Z of a requester in wired form
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TagPublicKey(pub [u8; 32]);
+
+/// The certification key of the signer, which is also the verifier of the
+/// tokens. It is separate from the signing key, and must itself be kept
+/// secret.
+pub struct TagIssuerKey {
+    x0: Scalar,
+    x0_blinding: Scalar,
+    x1: Scalar,
+}
+
+impl fmt::Debug for TagIssuerKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TagIssuerKey")
+            .field("x0", &Redacted)
+            .field("x0_blinding", &Redacted)
+            .field("x1", &Redacted)
+            .finish()
+    }
+}
+
+impl TagIssuerKey {
+    /// Generates a new random certification key.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(TagIssuerKey) on success.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    pub fn generate() -> ::Result<Self> {
+        let mut rng = rng::try_new()?;
+        Ok(TagIssuerKey {
+            x0: Scalar::random(&mut rng),
+            x0_blinding: Scalar::random(&mut rng),
+            x1: Scalar::random(&mut rng),
+        })
+    }
+
+    /// Returns the public TagIssuerParams committing to the key, which the
+    /// signer publishes to requesters.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for deriving the generator H
+    pub fn params<H>(&self) -> TagIssuerParams
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let h = generator::<H>();
+        TagIssuerParams {
+            cx0: self.x0 * RISTRETTO_BASEPOINT_POINT + self.x0_blinding * h,
+            x1: self.x1 * h,
+        }
+    }
+
+    /// Certifies the public tag key of a requester, which the signer must
+    /// have authenticated, and must not have certified another tag key for.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for deriving the generator H and for the
+    /// proof
+    ///
+    /// # Returns
+    ///
+    /// * Ok(TagKeyCertificate) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the public tag key was
+    /// malformed or the identity, or the failure to initiate the internal
+    /// RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * u = b*P, with b random
+    /// * u' = x0*u + t*Z, with t = b*x1
+    /// * Proof of b, x0, x0', x1 and t with u = b*P, Cx0 = x0*P + x0'*H,
+    /// X1 = x1*H, 0 = t*H - b*X1 and u' = x0*u + t*Z
+    pub fn certify<H>(&self, key: &TagPublicKey) -> ::Result<TagKeyCertificate>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let z = CompressedRistretto(key.0)
+            .decompress()
+            .ok_or(WiredRistrettoPointMalformed)?;
+        if z == RistrettoPoint::identity() {
+            return Err(WiredRistrettoPointIdentity);
+        }
+        let params = self.params::<H>();
+        let mut rng = rng::try_new()?;
+        let b = kvac::nonzero_scalar(&mut rng);
+        let t = b * self.x1;
+        let u = b * RISTRETTO_BASEPOINT_POINT;
+        let u_prime = self.x0 * u + t * z;
+        let witnesses = [b, self.x0, self.x0_blinding, self.x1, t];
+        let proof = certificate_statement(&params, &z, &u, &u_prime, &generator::<H>())
+            .prove::<H>(&witnesses, CERTIFICATE_DOMAIN, &mut rng);
+        Ok(TagKeyCertificate { u, u_prime, proof })
+    }
+}
+
+/// The public parameters of the certification key, which commit to it
+#[derive(Copy, Clone, Debug)]
+pub struct TagIssuerParams {
+    cx0: RistrettoPoint,
+    x1: RistrettoPoint,
+}
+
+impl TagIssuerParams {
+    /// Returns the params in wired form, Cx0 || X1
+    pub fn wired(&self) -> [u8; 64] {
+        let mut wired = [0; 64];
+        wired[..32].copy_from_slice(self.cx0.compress().as_bytes());
+        wired[32..].copy_from_slice(self.x1.compress().as_bytes());
+        wired
+    }
+
+    /// Loads the params from their wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(TagIssuerParams) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates a point was malformed.
+    pub fn from_wired(wired: [u8; 64]) -> ::Result<Self> {
+        let point = |bytes: &[u8]| {
+            CompressedRistretto::from_slice(bytes)
+                .decompress()
+                .ok_or(WiredRistrettoPointMalformed)
+        };
+        Ok(TagIssuerParams {
+            cx0: point(&wired[..32])?,
+            x1: point(&wired[32..])?,
+        })
+    }
+}
+
+/// The certification of the public tag key Z of a requester by the signer,
+/// a MAC (u, u') on z along with a proof that it was issued under the
+/// published TagIssuerParams.
+#[derive(Clone, Debug)]
+pub struct TagKeyCertificate {
+    u: RistrettoPoint,
+    u_prime: RistrettoPoint,
+    proof: Proof,
+}
+
+impl TagKeyCertificate {
+    /// Verifies that the certificate was issued for the public tag key under
+    /// the provided params.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for deriving the generator H and for the
+    /// proof
+    pub fn verify<H>(&self, params: &TagIssuerParams, key: &TagPublicKey) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let z = match CompressedRistretto(key.0).decompress() {
+            Some(z) => z,
+            None => return false,
+        };
+        self.u != RistrettoPoint::identity()
+            && certificate_statement(params, &z, &self.u, &self.u_prime, &generator::<H>())
+                .verify::<H>(&self.proof, CERTIFICATE_DOMAIN)
+    }
+}
+
+/// The deterministic tag of a linkable token, which verifiers store for
+/// duplicate detection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LinkTag(pub [u8; 32]);
+
+/// A blind signature on a tagged message, along with its tag and a
+/// presentation of the certificate of the tag key proving the tag
+#[derive(Clone, Debug)]
+pub struct LinkableToken {
+    tag: LinkTag,
+    u: RistrettoPoint,
+    cz: RistrettoPoint,
+    cu_prime: RistrettoPoint,
+    proof: Proof,
+    sig: UnblindedSigData,
+}
+
+impl LinkableToken {
+    /// Returns the tag of the token, for duplicate detection
+    pub fn tag(&self) -> LinkTag {
+        self.tag
+    }
+
+    /// Returns the signature of the token
+    pub fn signature(&self) -> UnblindedSigData {
+        self.sig
+    }
+
+    /// Authenticates that the signature is valid on the tagged message T || m
+    /// with the provided public key, that the tag key was certified with the
+    /// provided certification key, and that the tag on m was computed with
+    /// the certified tag key.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the token is valid on the message.
+    ///
+    /// * False to indicate that the token is not valid.
+    ///
+    /// # Mathematics
+    ///
+    /// * V = x0*U + x1*Cz - Cu'
+    pub fn msg_authenticate<H, M>(
+        &self,
+        issuer: &TagIssuerKey,
+        pub_key: &VerifyingKey,
+        m: M,
+    ) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        self.presentation_valid::<H>(issuer, m.as_ref())
+            && self
+                .sig
+                .msg_authenticate::<H, Vec<u8>>(pub_key, tagged_msg(&self.tag, m.as_ref()))
+    }
+
+    /// Verifies the presentation of the certificate and the tag on m
+    fn presentation_valid<H>(&self, issuer: &TagIssuerKey, m: &[u8]) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let tag = match CompressedRistretto(self.tag.0).decompress() {
+            Some(tag) => tag,
+            None => return false,
+        };
+        if self.u == RistrettoPoint::identity() {
+            return false;
+        }
+        let h = generator::<H>();
+        let x1 = issuer.x1 * h;
+        let v = issuer.x0 * self.u + issuer.x1 * self.cz - self.cu_prime;
+        presentation_statement(&self.u, &self.cz, &v, &tag, &msg_point::<H>(m), &x1, &h)
+            .verify::<H>(&self.proof, &presentation_context(m))
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Hashes the message to a point, whose discrete log relative to P is unknown
+fn msg_point<H>(m: &[u8]) -> RistrettoPoint
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut input = TAG_DOMAIN.to_vec();
+    input.extend_from_slice(m);
+    RistrettoPoint::hash_from_bytes::<H>(&input)
+}
+
+/// Derives the generator H, whose discrete log relative to P is unknown
+fn generator<H>() -> RistrettoPoint
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    RistrettoPoint::hash_from_bytes::<H>(GENERATOR_DOMAIN)
+}
+
+/// The statement of a certificate, with witnesses b, x0, x0', x1 and t
+///
+/// * u = b*P
+/// * Cx0 = x0*P + x0'*H
+/// * X1 = x1*H
+/// * 0 = t*H - b*X1
+/// * u' = x0*u + t*Z
+fn certificate_statement(
+    params: &TagIssuerParams,
+    z: &RistrettoPoint,
+    u: &RistrettoPoint,
+    u_prime: &RistrettoPoint,
+    h: &RistrettoPoint,
+) -> Statement {
+    let p = RISTRETTO_BASEPOINT_POINT;
+    Statement {
+        relations: vec![
+            Relation {
+                lhs: *u,
+                terms: vec![(0, p)],
+            },
+            Relation {
+                lhs: params.cx0,
+                terms: vec![(1, p), (2, *h)],
+            },
+            Relation {
+                lhs: params.x1,
+                terms: vec![(3, *h)],
+            },
+            Relation {
+                lhs: RistrettoPoint::identity(),
+                terms: vec![(4, *h), (0, -params.x1)],
+            },
+            Relation {
+                lhs: *u_prime,
+                terms: vec![(1, *u), (4, *z)],
+            },
+        ],
+    }
+}
+
+/// The statement of a presentation, with witnesses z, r1 and r
+///
+/// * Cz = z*U + r1*H
+/// * V = r1*X1 - r*H
+/// * T = z*H(m)
+fn presentation_statement(
+    u: &RistrettoPoint,
+    cz: &RistrettoPoint,
+    v: &RistrettoPoint,
+    tag: &RistrettoPoint,
+    hm: &RistrettoPoint,
+    x1: &RistrettoPoint,
+    h: &RistrettoPoint,
+) -> Statement {
+    Statement {
+        relations: vec![
+            Relation {
+                lhs: *cz,
+                terms: vec![(0, *u), (1, *h)],
+            },
+            Relation {
+                lhs: *v,
+                terms: vec![(1, *x1), (2, -h)],
+            },
+            Relation {
+                lhs: *tag,
+                terms: vec![(0, *hm)],
+            },
+        ],
+    }
+}
+
+/// Returns the context of a presentation, the domain prefix || m
+fn presentation_context(m: &[u8]) -> Vec<u8> {
+    let mut context = PRESENTATION_DOMAIN.to_vec();
+    context.extend_from_slice(m);
+    context
+}
+
+/// Concatenates the tag and message into T || m
+fn tagged_msg(tag: &LinkTag, m: &[u8]) -> Vec<u8> {
+    let mut tagged = tag.0.to_vec();
+    tagged.extend_from_slice(m);
+    tagged
+}
@@ -0,0 +1,89 @@
+//! One-shot blind signing for single process use and testing
+//!
+//! The plain protocol takes four steps across the session, request and
+//! signature modules. Here, request drives the requester side against any
+//! signer, passed as a function from each ProtocolMessage to the reply, and
+//! signer provides that function for a key held in the same process.
+//! sign_blind combines the two for the common case of holding the keypair.
+//!
+//! # Note
+//!
+//! Blindness is only meaningful when the requester and signer are different
+//! parties. With both in one process, these are for testing, and for services
+//! which sign on behalf of a requester that later holds the signature.
+use digest::Digest;
+use keypair::{BlindKeypair, BlindSignerKey, VerifyingKey};
+use protocol::{ProtocolMessage, RequesterProtocol, SignerProtocol};
+use signature::UnblindedSigData;
+use typenum::U64;
+use Error::BlindSignatureInvalid;
+
+/// Blindly signs the message with the keypair, running the whole protocol
+/// in process.
+///
+/// # Type Parameters
+///
+/// H is the hash algorithm used for generating e
+///
+/// # Returns
+///
+/// * Ok(UnblindedSigData) on success, which authenticates on msg under the
+/// public key of the keypair.
+///
+/// * Err(::Error) on failure, which can only be the failure to initiate the
+/// internal RNG.
+pub fn sign_blind<H, M>(keypair: &BlindKeypair, msg: M) -> ::Result<UnblindedSigData>
+where
+    H: Digest<OutputSize = U64> + Default,
+    M: AsRef<[u8]>,
+{
+    let xs = keypair.signing_key();
+    request::<H, M, _>(&keypair.verifying_key(), msg, signer(&xs))
+}
+
+/// Runs the requester side of the protocol for a blind signature on the
+/// message, exchanging each message with the signer function, which returns
+/// the signer's reply. The signature is verified under the public key.
+///
+/// # Type Parameters
+///
+/// H is the hash algorithm used for generating e
+///
+/// # Returns
+///
+/// * Ok(UnblindedSigData) on success.
+///
+/// * Err(::Error) on failure, which indicates any failure of the signer
+/// function or of RequesterProtocol::handle, or that the signature was not
+/// valid under the public key.
+pub fn request<H, M, F>(
+    pub_key: &VerifyingKey,
+    msg: M,
+    mut signer: F,
+) -> ::Result<UnblindedSigData>
+where
+    H: Digest<OutputSize = U64> + Default,
+    M: AsRef<[u8]>,
+    F: FnMut(ProtocolMessage) -> ::Result<ProtocolMessage>,
+{
+    let mut requester = RequesterProtocol::new(msg);
+    let mut outgoing = requester.start()?;
+    while let Some(next) = requester.handle::<H>(signer(outgoing)?)? {
+        outgoing = next;
+    }
+    let sig = *requester.signature().expect("done requester has a signature");
+    if pub_key.validate().is_err() || !sig.authenticate(pub_key) {
+        return Err(BlindSignatureInvalid);
+    }
+    Ok(sig)
+}
+
+/// Returns the signer function for request, answering a single session with
+/// the provided key.
+pub fn signer<'a, K>(xs: &'a K) -> impl FnMut(ProtocolMessage) -> ::Result<ProtocolMessage> + 'a
+where
+    K: BlindSignerKey + ?Sized,
+{
+    let mut signer = SignerProtocol::new(xs);
+    move |msg| signer.handle(msg)
+}
@@ -0,0 +1,166 @@
+//! Crate internal macros
+
+/// Implements the byte conversions shared by all of the wired types, each of
+/// which is a tuple struct around a fixed size byte array. Types whose bytes
+/// must be validated on construction also pass a validating function from the
+/// byte array to ::Result<()>.
+macro_rules! impl_wired_bytes {
+    ($name:ident, $len:expr $(, $validate:path)?) => {
+        impl $name {
+            /// Creates the wired type from a byte slice.
+            ///
+            /// # Returns
+            ///
+            /// * Ok(Self) on success.
+            ///
+            /// * Err(::Error) on failure, which indicates that the length of the
+            /// byte slice did not match the length of the wired type, or that
+            /// the bytes failed the wired type's validation.
+            pub fn from_bytes(bytes: &[u8]) -> ::Result<Self> {
+                if bytes.len() != $len {
+                    return Err(::Error::WiredLengthInvalid {
+                        expected: $len,
+                        actual: bytes.len(),
+                    });
+                }
+                let mut arr = [0; $len];
+                arr.copy_from_slice(bytes);
+                $($validate(&arr)?;)?
+                Ok($name(arr))
+            }
+
+            /// Returns a reference to the internal byte array
+            pub fn as_bytes(&self) -> &[u8; $len] {
+                &self.0
+            }
+
+            /// Returns a copy of the internal byte array
+            pub fn to_bytes(&self) -> [u8; $len] {
+                self.0
+            }
+        }
+
+        impl<'a> ::std::convert::TryFrom<&'a [u8]> for $name {
+            type Error = ::Error;
+
+            fn try_from(bytes: &'a [u8]) -> ::Result<Self> {
+                $name::from_bytes(bytes)
+            }
+        }
+
+        #[cfg(feature = "fuzzing")]
+        impl<'a> ::arbitrary::Arbitrary<'a> for $name {
+            fn arbitrary(u: &mut ::arbitrary::Unstructured<'a>) -> ::arbitrary::Result<Self> {
+                let mut arr = [0; $len];
+                u.fill_buffer(&mut arr)?;
+                $name::from_bytes(&arr).map_err(|_| ::arbitrary::Error::IncorrectFormat)
+            }
+
+            fn size_hint(_: usize) -> (usize, Option<usize>) {
+                ($len, Some($len))
+            }
+        }
+    };
+}
+
+/// Implements the canonical textual form of a wired type, which is lowercase
+/// hex, as Display and FromStr. The wired byte conversions must already be
+/// implemented.
+macro_rules! impl_wired_hex {
+    ($name:ident) => {
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                f.write_str(&::encoding::to_hex(&self.0))
+            }
+        }
+
+        impl_wired_from_hex!($name);
+    };
+}
+
+/// Implements only the FromStr half of impl_wired_hex, accepting hex of either
+/// case, for types which either already implement Display or must not.
+macro_rules! impl_wired_from_hex {
+    ($name:ident) => {
+        impl ::std::str::FromStr for $name {
+            type Err = ::Error;
+
+            fn from_str(hex: &str) -> ::Result<Self> {
+                $name::from_bytes(&::encoding::from_hex(hex)?)
+            }
+        }
+    };
+}
+
+/// Implements the versioned envelope conversions for a wired type, which must
+/// already implement the wired byte conversions.
+macro_rules! impl_wired_envelope {
+    ($name:ident, $payload_type:expr) => {
+        impl $name {
+            /// Encodes the wired type in the versioned envelope format
+            pub fn to_envelope(&self) -> Vec<u8> {
+                ::envelope::encode($payload_type, &self.0)
+            }
+
+            /// Decodes the wired type from the versioned envelope format.
+            ///
+            /// # Returns
+            ///
+            /// * Ok(Self) on success.
+            ///
+            /// * Err(::Error) on failure, which indicates that the envelope
+            /// was malformed, had an unsupported version, or carried a
+            /// different type of payload.
+            pub fn from_envelope(bytes: &[u8]) -> ::Result<Self> {
+                $name::from_bytes(::envelope::decode_expecting($payload_type, bytes)?)
+            }
+        }
+    };
+}
+
+/// Defines a borrowed view over the bytes of a wired type, which must already
+/// implement the wired byte conversions, so that wired values inside larger
+/// buffers can be parsed without copying them first.
+macro_rules! impl_wired_ref {
+    ($(#[$attr:meta])* $ref_name:ident, $name:ident, $len:expr) => {
+        $(#[$attr])*
+        #[derive(Copy, Clone)]
+        pub struct $ref_name<'a>(pub &'a [u8; $len]);
+
+        impl<'a> $ref_name<'a> {
+            /// Creates the view from a byte slice, without copying.
+            ///
+            /// # Returns
+            ///
+            /// * Ok(Self) on success.
+            ///
+            /// * Err(::Error) on failure, which indicates that the length of the
+            /// byte slice did not match the length of the wired type.
+            pub fn from_bytes(bytes: &'a [u8]) -> ::Result<Self> {
+                <&[u8; $len] as ::std::convert::TryFrom<&[u8]>>::try_from(bytes)
+                    .map($ref_name)
+                    .map_err(|_| ::Error::WiredLengthInvalid {
+                        expected: $len,
+                        actual: bytes.len(),
+                    })
+            }
+
+            /// Returns a reference to the viewed byte array
+            pub fn as_bytes(&self) -> &'a [u8; $len] {
+                self.0
+            }
+
+            /// Copies the viewed bytes into the owned wired type
+            pub fn to_wired(&self) -> $name {
+                $name(*self.0)
+            }
+        }
+
+        impl $name {
+            /// Returns a borrowed view of the wired type
+            pub fn view(&self) -> $ref_name {
+                $ref_name(&self.0)
+            }
+        }
+    };
+}
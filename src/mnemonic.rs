@@ -0,0 +1,85 @@
+//! BIP 39 mnemonic generation and recovery of the ECC keys
+//!
+//! The mnemonic phrase and passphrase are expanded into the 64 byte BIP 39
+//! seed, from which the private key is derived with a domain separated hash.
+//! This gives small issuers a paper backup of their signing key, as the same
+//! phrase, passphrase, and hash algorithm always recover the same keypair.
+use bip39::{Language, Mnemonic, Seed};
+use curve25519_dalek::scalar::Scalar;
+use digest::Digest;
+use keypair::BlindKeypair;
+use rand::RngCore;
+use rng;
+use typenum::U64;
+use wipe;
+use Error::MnemonicInvalid;
+
+/// The domain separation prefix for deriving private keys from BIP 39 seeds
+const MNEMONIC_DOMAIN: &[u8] = b"blindsign mnemonic seed";
+
+impl BlindKeypair {
+    /// Generates a fresh 24 word English mnemonic along with the keypair it
+    /// recovers to under the provided passphrase. The 32 bytes of entropy
+    /// encoded by the mnemonic are drawn from the internal RNG, as with every
+    /// other key.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for deriving the private key from the seed
+    ///
+    /// # Returns
+    ///
+    /// * (String, BlindKeypair), with the String being the mnemonic phrase.
+    pub fn generate_mnemonic<H>(passphrase: &str) -> (String, Self)
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut entropy = [0; 32];
+        rng::new().fill_bytes(&mut entropy);
+        let mnemonic = Mnemonic::from_entropy(&entropy, Language::English)
+            .expect("32 bytes is a valid length of BIP 39 entropy");
+        wipe::wipe_bytes(&mut entropy);
+        let keypair = from_bip39_seed::<H>(&Seed::new(&mnemonic, passphrase));
+        (mnemonic.phrase().to_owned(), keypair)
+    }
+
+    /// Recovers a keypair from an English mnemonic phrase and passphrase.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for deriving the private key from the seed
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlindKeypair) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the phrase was not a
+    /// valid BIP 39 mnemonic (unknown words or a bad checksum).
+    ///
+    /// # Mathematics
+    ///
+    /// * Xs = H("blindsign mnemonic seed" || BIP39Seed(phrase, passphrase))
+    /// * Qs = Xs * P
+    /// * P = The ECC generator point
+    pub fn from_mnemonic<H>(phrase: &str, passphrase: &str) -> ::Result<Self>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mnemonic =
+            Mnemonic::from_phrase(phrase, Language::English).map_err(|_| MnemonicInvalid)?;
+        Ok(from_bip39_seed::<H>(&Seed::new(&mnemonic, passphrase)))
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Derives the keypair from the 64 byte BIP 39 seed
+fn from_bip39_seed<H>(seed: &Seed) -> BlindKeypair
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut hasher = H::default();
+    hasher.input(MNEMONIC_DOMAIN);
+    hasher.input(seed.as_bytes());
+    BlindKeypair::from_private(Scalar::from_hash(hasher))
+}
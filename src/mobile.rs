@@ -0,0 +1,127 @@
+//! UniFFI bindings of the requester side, for mobile wallets
+//!
+//! The interface is defined by uniffi/blindsign.udl, from which UniFFI
+//! generates the Swift and Kotlin bindings, for example with
+//! `uniffi-bindgen generate uniffi/blindsign.udl --language swift`. Every
+//! wired value crosses the boundary as bytes, signatures are serialized as
+//! bech32 tokens, and the hash algorithm is always SHA3-512. The crate is
+//! built as a library for the app with, for example,
+//! `cargo rustc --release --features mobile --crate-type cdylib`.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use keypair::{VerifyingKey, WiredPublicKey};
+use request::{self, WiredEPrime};
+use session::{WiredRPrime, WiredSPrime};
+use sha3::Sha3_512;
+use signature::WiredUnblindedSigData;
+use std::{
+    error, fmt,
+    sync::{Mutex, MutexGuard},
+};
+use Error;
+
+/// The errors raised to Swift and Kotlin
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlindsignError {
+    /// The internal RNG could not be initiated
+    Rng,
+    /// A wired input or token was malformed
+    Malformed,
+    /// The request was already unblinded
+    AlreadyUnblinded,
+    /// Any other failure
+    Failed,
+}
+
+impl fmt::Display for BlindsignError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BlindsignError::Rng => write!(f, "failed to initialize the RNG"),
+            BlindsignError::Malformed => write!(f, "input was malformed"),
+            BlindsignError::AlreadyUnblinded => write!(f, "request was already unblinded"),
+            BlindsignError::Failed => write!(f, "operation failed"),
+        }
+    }
+}
+
+impl error::Error for BlindsignError {}
+
+impl From<Error> for BlindsignError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::RngInitFailed => BlindsignError::Rng,
+            Error::WiredScalarMalformed
+            | Error::WiredRistrettoPointMalformed
+            | Error::WiredFieldMalformed { .. }
+            | Error::WiredLengthInvalid { .. }
+            | Error::Bech32Malformed
+            | Error::Bech32HrpMismatch => BlindsignError::Malformed,
+            _ => BlindsignError::Failed,
+        }
+    }
+}
+
+/// The requester side of a blind signature on a message. UniFFI shares
+/// objects by reference, so the request is taken out once unblinded.
+pub struct BlindRequest {
+    inner: Mutex<Option<request::BlindRequest>>,
+    ep: WiredEPrime,
+}
+
+impl BlindRequest {
+    /// Blinds the message with the 32 byte R' from the signer
+    pub fn new(rp: Vec<u8>, msg: Vec<u8>) -> Result<Self, BlindsignError> {
+        let rp = WiredRPrime::from_bytes(&rp)?;
+        let (ep, inner) = request::BlindRequest::new_specific_msg::<Sha3_512, _>(&rp, msg)?;
+        Ok(BlindRequest {
+            inner: Mutex::new(Some(inner)),
+            ep,
+        })
+    }
+
+    /// Returns the 32 byte e', for sending to the signer
+    pub fn ep(&self) -> Vec<u8> {
+        self.ep.as_bytes().to_vec()
+    }
+
+    /// Unblinds the 32 byte S' from the signer, returning the 96 byte wired
+    /// signature, which is not verified. A request can only be unblinded
+    /// once.
+    pub fn unblind(&self, sp: Vec<u8>) -> Result<Vec<u8>, BlindsignError> {
+        let sp = WiredSPrime::from_bytes(&sp)?;
+        let inner = self.inner().take().ok_or(BlindsignError::AlreadyUnblinded)?;
+        Ok(WiredUnblindedSigData::from(inner.gen_signed_msg(&sp)).0.to_vec())
+    }
+
+    /// Locks the request. The request is only ever taken whole, so it
+    /// remains usable even if another thread panicked while holding the lock.
+    fn inner(&self) -> MutexGuard<Option<request::BlindRequest>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Authenticates the 96 byte wired signature on the message under the 32
+/// byte wired public key
+pub fn authenticate(
+    public_key: Vec<u8>,
+    sig: Vec<u8>,
+    msg: Vec<u8>,
+) -> Result<bool, BlindsignError> {
+    let pub_key = VerifyingKey::from_wired(WiredPublicKey::from_bytes(&public_key)?)?;
+    let sig = WiredUnblindedSigData::from_bytes(&sig)?.to_internal_format()?;
+    Ok(sig.msg_authenticate::<Sha3_512, _>(&pub_key, msg))
+}
+
+/// Encodes the 96 byte wired signature as a bech32 token with the prefix
+pub fn serialize_token(hrp: String, sig: Vec<u8>) -> Result<String, BlindsignError> {
+    Ok(WiredUnblindedSigData::from_bytes(&sig)?.to_bech32(&hrp)?)
+}
+
+/// Decodes a bech32 token with the prefix to the 96 byte wired signature
+pub fn deserialize_token(hrp: String, token: String) -> Result<Vec<u8>, BlindsignError> {
+    Ok(WiredUnblindedSigData::from_bech32(&hrp, &token)?.0.to_vec())
+}
+
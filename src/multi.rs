@@ -0,0 +1,264 @@
+//! Blind signing of a vector of messages in one round trip
+//!
+//! Withdrawing N tokens with the plain protocol takes N exchanges of R' and
+//! e'. Here the signer starts all N sessions at once, sending every R' as one
+//! WiredRPrimes, and the requester blinds all N messages in reply, sending
+//! every e' as one WiredEPrimes, so that the whole vector is signed in a
+//! single exchange.
+//!
+//! 1. The requester asks the signer for N sessions, in some manner.
+//! 2. The signer calls MultiSession::new, sending the WiredRPrimes.
+//! 3. The requester calls MultiRequest::new, sending the WiredEPrimes.
+//! 4. The signer calls MultiSession::sign_eps, sending the WiredSPrimes.
+//! 5. The requester calls MultiRequest::gen_signed_msgs for the signatures.
+//!
+//! Each combined wired encoding is a 2 byte big endian count, followed by
+//! that many 32 byte values in session order.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use batch;
+use digest::Digest;
+use keypair::BlindSignerKey;
+use request::{BlindRequest, WiredEPrime};
+use session::{BlindSession, WiredRPrime, WiredSPrime};
+use signature::UnblindedSigData;
+use typenum::U64;
+use Error::{MultiCountInvalid, MultiCountMismatch, WiredLengthInvalid};
+
+/// The maximum number of sessions in a vector, the largest count the 2 byte
+/// prefix of the combined wired encodings can hold.
+pub const MAX_COUNT: usize = 0xffff;
+
+/// The length of the count prefix of the combined wired encodings
+const PREFIX_LEN: usize = 2;
+
+/// Implements the combined wired encoding of a vector of 32 byte wired values
+macro_rules! impl_wired_vector {
+    ($name:ident, $item:ident) => {
+        impl $name {
+            /// Creates the vector from its values.
+            ///
+            /// # Returns
+            ///
+            /// * Ok(Self) on success.
+            ///
+            /// * Err(::Error) on failure, which indicates that there were no
+            /// values, or more than MAX_COUNT.
+            pub fn new(values: Vec<$item>) -> ::Result<Self> {
+                check_count(values.len())?;
+                Ok($name(values))
+            }
+
+            /// Returns the values, in session order
+            pub fn values(&self) -> &[$item] {
+                &self.0
+            }
+
+            /// Returns the number of values, which is never 0
+            pub fn count(&self) -> usize {
+                self.0.len()
+            }
+
+            /// Encodes the vector as count || values.
+            pub fn to_bytes(&self) -> Vec<u8> {
+                let mut bytes = Vec::with_capacity(PREFIX_LEN + 32 * self.0.len());
+                bytes.extend_from_slice(&(self.0.len() as u16).to_be_bytes());
+                for value in &self.0 {
+                    bytes.extend_from_slice(value.as_bytes());
+                }
+                bytes
+            }
+
+            /// Decodes the vector from count || values.
+            ///
+            /// # Returns
+            ///
+            /// * Ok(Self) on success.
+            ///
+            /// * Err(::Error) on failure, which indicates that the count was
+            /// 0, that the length did not match the count, or that a value
+            /// failed its own validation.
+            pub fn from_bytes(bytes: &[u8]) -> ::Result<Self> {
+                let values = decode(bytes)?
+                    .chunks(32)
+                    .map($item::from_bytes)
+                    .collect::<::Result<Vec<_>>>()?;
+                Ok($name(values))
+            }
+        }
+    };
+}
+
+/// Every R' of a MultiSession in wired form, sent from the signer to the
+/// requester.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WiredRPrimes(Vec<WiredRPrime>);
+
+impl_wired_vector!(WiredRPrimes, WiredRPrime);
+
+/// Every e' of a MultiRequest in wired form, sent from the requester to the
+/// signer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WiredEPrimes(Vec<WiredEPrime>);
+
+impl_wired_vector!(WiredEPrimes, WiredEPrime);
+
+/// Every S' of a MultiSession in wired form, the blind signatures sent from
+/// the signer to the requester.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WiredSPrimes(Vec<WiredSPrime>);
+
+impl_wired_vector!(WiredSPrimes, WiredSPrime);
+
+/// For managing the signer side of a vector of sessions.
+pub struct MultiSession {
+    sessions: Vec<BlindSession>,
+}
+
+impl MultiSession {
+    /// Initiate count new sessions, as with BlindSession::new.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((WiredRPrimes, MultiSession)) on success, with the WiredRPrimes
+    /// being every R' for sending to the requester.
+    ///
+    /// * Err(::Error) on failure, which indicates that count was 0 or greater
+    /// than MAX_COUNT, or the failure to initiate the internal RNG.
+    pub fn new(count: usize) -> ::Result<(WiredRPrimes, Self)> {
+        check_count(count)?;
+        let (rps, sessions): (Vec<_>, Vec<_>) = (0..count)
+            .map(|_| BlindSession::new())
+            .collect::<::Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+        Ok((WiredRPrimes(rps), MultiSession { sessions }))
+    }
+
+    /// Returns the number of sessions
+    pub fn count(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Consumes the sessions, signing each e' with its session as for
+    /// BlindSession::sign_ep. Every session is consumed even if signing
+    /// fails.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(WiredSPrimes) on success, representing every completed blind
+    /// signature value S', in the same order.
+    ///
+    /// * Err(::Error) on failure, which indicates that the number of e'
+    /// values did not match the number of sessions, or that the key backend
+    /// failed.
+    pub fn sign_eps<K>(self, eps: &WiredEPrimes, xs: &K) -> ::Result<WiredSPrimes>
+    where
+        K: BlindSignerKey + Sync + ?Sized,
+    {
+        check_match(self.sessions.len(), eps.count())?;
+        let pairs = self.sessions.into_iter().zip(eps.0.iter().cloned()).collect();
+        let sps = batch::sign_all(pairs, xs)
+            .into_iter()
+            .collect::<::Result<Vec<_>>>()?;
+        Ok(WiredSPrimes(sps))
+    }
+}
+
+/// For managing the requester side of a vector of sessions.
+pub struct MultiRequest {
+    requests: Vec<BlindRequest>,
+}
+
+impl MultiRequest {
+    /// Blinds each message for the session with the R' at the same position,
+    /// as with BlindRequest::new_specific_msg.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for generating e
+    ///
+    /// # Returns
+    ///
+    /// * Ok((WiredEPrimes, MultiRequest)) on success, with the WiredEPrimes
+    /// being every e' for sending to the signer.
+    ///
+    /// * Err(::Error) on failure, which indicates that the number of messages
+    /// did not match the number of R' values, or the failure to initiate the
+    /// internal RNG.
+    pub fn new<H, M>(rps: &WiredRPrimes, msgs: &[M]) -> ::Result<(WiredEPrimes, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        check_match(rps.count(), msgs.len())?;
+        let pairs: Vec<_> = rps.0.iter().cloned().zip(msgs).collect();
+        let (eps, requests): (Vec<_>, Vec<_>) = BlindRequest::new_batch::<H, &M>(&pairs)?
+            .into_iter()
+            .unzip();
+        Ok((WiredEPrimes(eps), MultiRequest { requests }))
+    }
+
+    /// Returns the number of requests
+    pub fn count(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Input every S' from the signer, consumes self and creates the
+    /// signatures, in the same order as the messages. As with
+    /// BlindRequest::gen_signed_msg, this does not verify the signatures.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Vec<UnblindedSigData>) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the number of S'
+    /// values did not match the number of requests.
+    pub fn gen_signed_msgs(self, sps: &WiredSPrimes) -> ::Result<Vec<UnblindedSigData>> {
+        check_match(self.requests.len(), sps.count())?;
+        let pairs = self.requests.into_iter().zip(sps.0.iter().cloned()).collect();
+        Ok(batch::unblind_all(pairs))
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Errors unless count is between 1 and MAX_COUNT inclusive
+fn check_count(count: usize) -> ::Result<()> {
+    if count == 0 || count > MAX_COUNT {
+        return Err(MultiCountInvalid(count));
+    }
+    Ok(())
+}
+
+/// Errors unless the number of values matches the number of sessions
+fn check_match(expected: usize, actual: usize) -> ::Result<()> {
+    if expected != actual {
+        return Err(MultiCountMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// Checks the count prefix of a combined wired encoding against its length,
+/// returning the values
+fn decode(bytes: &[u8]) -> ::Result<&[u8]> {
+    if bytes.len() < PREFIX_LEN {
+        return Err(WiredLengthInvalid {
+            expected: PREFIX_LEN,
+            actual: bytes.len(),
+        });
+    }
+    let count = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+    check_count(count)?;
+    let expected = PREFIX_LEN + 32 * count;
+    if bytes.len() != expected {
+        return Err(WiredLengthInvalid {
+            expected,
+            actual: bytes.len(),
+        });
+    }
+    Ok(&bytes[PREFIX_LEN..])
+}
@@ -0,0 +1,339 @@
+//! Okamoto-Schnorr blind signatures
+//!
+//! The Okamoto-Schnorr variant uses a second generator H alongside P, with a
+//! private key of two scalars (X1, X2) and the public key Y = X1*P + X2*H.
+//! Many private keys share each public key, so signing is witness
+//! indistinguishable, which gives the scheme a security proof against
+//! sequential sessions that the paper's construction lacks.
+//!
+//! Public keys are ordinary VerifyingKeys, and the commitment A and challenge
+//! e are carried as 32 byte values, as R' and e' are in the plain protocol.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+};
+use digest::Digest;
+use encoding;
+use envelope::PayloadType;
+use keypair::{Redacted, VerifyingKey};
+use request;
+use rng;
+use std::fmt;
+use typenum::U64;
+use wipe;
+use Error::{WiredRistrettoPointMalformed, WiredScalarMalformed};
+
+/// The label hashed to the second generator H
+const GENERATOR_LABEL: &[u8] = b"blindsign okamoto generator";
+
+/// An Okamoto-Schnorr keypair, with the private key (X1, X2) and the public
+/// key Y = X1*P + X2*H.
+#[derive(Copy, Clone)]
+pub struct OkamotoKeypair {
+    x1: Scalar,
+    x2: Scalar,
+    public: RistrettoPoint,
+}
+
+impl fmt::Debug for OkamotoKeypair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OkamotoKeypair")
+            .field("x1", &Redacted)
+            .field("x2", &Redacted)
+            .field("public", &encoding::to_hex(self.public.compress().as_bytes()))
+            .finish()
+    }
+}
+
+impl OkamotoKeypair {
+    /// Generates a new random Okamoto-Schnorr keypair.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for deriving the second generator, which
+    /// must be the same for every party using the key.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(OkamotoKeypair) on success.
+    ///
+    /// * Err(::Error) on error, which can only be the failure to initiate the
+    /// internal RNG.
+    pub fn generate<H>() -> ::Result<Self>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut rng = rng::try_new()?;
+        let x1 = Scalar::random(&mut rng);
+        let x2 = Scalar::random(&mut rng);
+        let public = x1 * RISTRETTO_BASEPOINT_POINT + x2 * generator::<H>();
+        Ok(OkamotoKeypair { x1, x2, public })
+    }
+
+    /// Creates the keypair from the private key in wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(OkamotoKeypair) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that either scalar of the
+    /// private key was malformed.
+    pub fn from_wired<H>(private: &WiredOkamotoPrivateKey) -> ::Result<Self>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let x1 = scalar(&private.0[0..32])?;
+        let x2 = scalar(&private.0[32..64])?;
+        let public = x1 * RISTRETTO_BASEPOINT_POINT + x2 * generator::<H>();
+        Ok(OkamotoKeypair { x1, x2, public })
+    }
+
+    /// Returns the private key in wired form
+    pub fn private_wired(&self) -> WiredOkamotoPrivateKey {
+        let mut arr = [0; 64];
+        arr[0..32].copy_from_slice(self.x1.as_bytes());
+        arr[32..64].copy_from_slice(self.x2.as_bytes());
+        WiredOkamotoPrivateKey(arr)
+    }
+
+    /// Returns the public key Y as a VerifyingKey
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey::from_point(self.public)
+    }
+}
+
+/// For managing the signer side of an Okamoto-Schnorr session.
+///
+/// The nonces t1 and t2 are wiped from memory when the session is dropped, as
+/// they reveal the private key through S1 = t1 + e*X1 and S2 = t2 + e*X2.
+pub struct OkamotoSession {
+    t1: Scalar,
+    t2: Scalar,
+}
+
+impl Drop for OkamotoSession {
+    fn drop(&mut self) {
+        wipe::wipe_scalar(&mut self.t1);
+        wipe::wipe_scalar(&mut self.t2);
+    }
+}
+
+impl OkamotoSession {
+    /// Initiate a new signer side session.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], OkamotoSession)) on success, with the [u8; 32] being the
+    /// commitment A for sending to the requester.
+    ///
+    /// * Err(::Error) variant on failure, which is only due to the failure to
+    /// initiate the internal random number generator.
+    ///
+    /// # Mathematics
+    ///
+    /// * A = t1*P + t2*H
+    /// * t1, t2 = Randomly generated scalars by the signer
+    pub fn new<H>() -> ::Result<([u8; 32], Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut rng = rng::try_new()?;
+        let t1 = Scalar::random(&mut rng);
+        let t2 = Scalar::random(&mut rng);
+        let a = t1 * RISTRETTO_BASEPOINT_POINT + t2 * generator::<H>();
+        Ok((a.compress().to_bytes(), OkamotoSession { t1, t2 }))
+    }
+
+    /// Consumes the session and returns the blind signature on e.
+    ///
+    /// # Returns
+    ///
+    /// * Ok([u8; 64]) on success, representing the blind signature S1 || S2.
+    ///
+    /// * Err(::Error) variant on error. Only errors if the requester provided
+    /// a malformed scalar value e.
+    ///
+    /// # Mathematics
+    ///
+    /// * S1 = t1 + e*X1
+    /// * S2 = t2 + e*X2
+    pub fn sign_e(mut self, e: &[u8; 32], keypair: &OkamotoKeypair) -> ::Result<[u8; 64]> {
+        let e = Scalar::from_canonical_bytes(*e).ok_or(WiredScalarMalformed)?;
+        let mut sp = [0; 64];
+        sp[0..32].copy_from_slice((self.t1 + e * keypair.x1).as_bytes());
+        sp[32..64].copy_from_slice((self.t2 + e * keypair.x2).as_bytes());
+        // The nonces must never be used twice, so are wiped before S1 || S2 is
+        // even returned
+        wipe::wipe_scalar(&mut self.t1);
+        wipe::wipe_scalar(&mut self.t2);
+        Ok(sp)
+    }
+}
+
+/// For managing the requester side of an Okamoto-Schnorr session.
+pub struct OkamotoRequest {
+    alpha: Scalar,
+    beta: Scalar,
+    e: Scalar,
+}
+
+impl OkamotoRequest {
+    /// Blinds the commitment A received from the signer for the message m.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], OkamotoRequest)) on success. The [u8; 32] represents
+    /// the challenge e, which is sent to the signer.
+    ///
+    /// * Err(::Error) variant on error, which could be caused by the failure to
+    /// initiate the RNG, or otherwise being input a malformed commitment.
+    ///
+    /// # Mathematics
+    ///
+    /// * A* = A + α*P + β*H + γ*Y
+    /// * α, β, γ = Randomly generated scalars by the requester
+    /// _____
+    /// * e* = H(A*||m)
+    /// * e = e* + γ
+    pub fn new<H, M>(a: &[u8; 32], pub_key: &VerifyingKey, m: M) -> ::Result<([u8; 32], Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        let mut rng = rng::try_new()?;
+        let a = CompressedRistretto(*a)
+            .decompress()
+            .ok_or(WiredRistrettoPointMalformed)?;
+        let alpha = Scalar::random(&mut rng);
+        let beta = Scalar::random(&mut rng);
+        let gamma = Scalar::random(&mut rng);
+        let a_star = a
+            + alpha * RISTRETTO_BASEPOINT_POINT
+            + beta * generator::<H>()
+            + gamma * pub_key.point();
+        let e = request::generate_e::<H>(a_star, m.as_ref());
+        Ok(((e + gamma).to_bytes(), OkamotoRequest { alpha, beta, e }))
+    }
+
+    /// Input the blind signature S1 || S2 from the signer, consumes self and
+    /// creates the signature. This does not verify the signature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(OkamotoSignature) on success.
+    ///
+    /// * Err(::Error) on error, which indicates the blind signature contained
+    /// a malformed scalar.
+    ///
+    /// # Mathematics
+    ///
+    /// * S1* = S1 + α
+    /// * S2* = S2 + β
+    pub fn gen_signed_msg(self, sp: &[u8; 64]) -> ::Result<OkamotoSignature> {
+        Ok(OkamotoSignature {
+            e: self.e,
+            s1: scalar(&sp[0..32])? + self.alpha,
+            s2: scalar(&sp[32..64])? + self.beta,
+        })
+    }
+}
+
+/// An Okamoto-Schnorr signature, e* || S1* || S2*
+#[derive(Copy, Clone, Debug)]
+pub struct OkamotoSignature {
+    e: Scalar,
+    s1: Scalar,
+    s2: Scalar,
+}
+
+impl OkamotoSignature {
+    /// Authenticates the signature on the message with the provided public
+    /// key.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the signature is valid on the message.
+    ///
+    /// * False to indicate that the signature is not valid.
+    ///
+    /// # Mathematics
+    ///
+    /// * A* = S1*P + S2*H - e*Y
+    /// * e == H(A*||m)
+    pub fn msg_authenticate<H, M>(&self, pub_key: &VerifyingKey, msg: M) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        let a_star = self.s1 * RISTRETTO_BASEPOINT_POINT + self.s2 * generator::<H>()
+            - self.e * pub_key.point();
+        self.e == request::generate_e::<H>(a_star, msg.as_ref())
+    }
+}
+
+/// The private key (X1, X2) of an OkamotoKeypair in wired form
+pub struct WiredOkamotoPrivateKey(pub [u8; 64]);
+
+impl_wired_bytes!(WiredOkamotoPrivateKey, 64);
+
+/// The blind signature S1 || S2 in wired form
+pub struct WiredOkamotoSPrime(pub [u8; 64]);
+
+impl_wired_bytes!(WiredOkamotoSPrime, 64);
+impl_wired_envelope!(WiredOkamotoSPrime, PayloadType::OkamotoSPrime);
+
+/// The OkamotoSignature in wired form, e* || S1* || S2*
+pub struct WiredOkamotoSignature(pub [u8; 96]);
+
+impl From<OkamotoSignature> for WiredOkamotoSignature {
+    fn from(sig: OkamotoSignature) -> Self {
+        let mut arr = [0; 96];
+        arr[0..32].copy_from_slice(sig.e.as_bytes());
+        arr[32..64].copy_from_slice(sig.s1.as_bytes());
+        arr[64..96].copy_from_slice(sig.s2.as_bytes());
+        WiredOkamotoSignature(arr)
+    }
+}
+
+impl WiredOkamotoSignature {
+    /// Converts the wired form into the internal OkamotoSignature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(OkamotoSignature) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates a malformed scalar.
+    pub fn to_internal_format(&self) -> ::Result<OkamotoSignature> {
+        Ok(OkamotoSignature {
+            e: scalar(&self.0[0..32])?,
+            s1: scalar(&self.0[32..64])?,
+            s2: scalar(&self.0[64..96])?,
+        })
+    }
+}
+
+impl_wired_bytes!(WiredOkamotoSignature, 96);
+impl_wired_envelope!(WiredOkamotoSignature, PayloadType::OkamotoSignature);
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Derives the second generator H, whose discrete log relative to P is unknown
+fn generator<H>() -> RistrettoPoint
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    RistrettoPoint::hash_from_bytes::<H>(GENERATOR_LABEL)
+}
+
+/// Loads a 32 byte slice as a canonical scalar
+fn scalar(bytes: &[u8]) -> ::Result<Scalar> {
+    let mut arr = [0; 32];
+    arr.copy_from_slice(bytes);
+    Scalar::from_canonical_bytes(arr).ok_or(WiredScalarMalformed)
+}
@@ -0,0 +1,326 @@
+//! Partially blind signatures with public info, in the style of Abe and Okamoto
+//!
+//! Both parties agree in advance on a public info string, such as an expiry
+//! date or a denomination, which the signer can see and which is bound into the
+//! signature, while the message itself stays blind. A signature only
+//! authenticates against the info it was issued with.
+//!
+//! 1. The signer sends the commitment A || B, for the agreed info.
+//! 2. The requester blinds the commitment and returns the challenge e.
+//! 3. The signer returns the response r || c || s || d.
+//! 4. The requester unblinds the response into a PartialSignature.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code, nor any means of agreeing on the info.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+};
+use digest::Digest;
+use envelope::PayloadType;
+use keypair::{SigningKey, VerifyingKey};
+use rng;
+use typenum::U64;
+use wipe;
+use Error::{WiredRistrettoPointMalformed, WiredScalarMalformed};
+
+/// The domain separator for hashing the info to a point
+const INFO_DOMAIN: &[u8] = b"blindsign partially blind info";
+
+/// For managing the signer side of a partially blind session.
+///
+/// The nonces u, s and d are wiped from memory when the session is dropped,
+/// as u reveals the private key through r = u - c*Xs.
+pub struct PartialSession {
+    u: Scalar,
+    s: Scalar,
+    d: Scalar,
+}
+
+impl Drop for PartialSession {
+    fn drop(&mut self) {
+        wipe::wipe_scalar(&mut self.u);
+        wipe::wipe_scalar(&mut self.s);
+        wipe::wipe_scalar(&mut self.d);
+    }
+}
+
+impl PartialSession {
+    /// Initiate a new signer side session for the provided info.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for hashing the info to a point
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 64], PartialSession)) on success, with the [u8; 64] being the
+    /// commitment A || B for sending to the requester.
+    ///
+    /// * Err(::Error) variant on failure, which is only due to the failure to
+    /// initiate the internal random number generator.
+    ///
+    /// # Mathematics
+    ///
+    /// * A = uP
+    /// * B = sP + dZ
+    /// * u, s, d = Randomly generated scalars by the signer
+    /// * Z = The info hashed to a point
+    pub fn new<H>(info: &[u8]) -> ::Result<([u8; 64], Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut rng = rng::try_new()?;
+        let u = Scalar::random(&mut rng);
+        let s = Scalar::random(&mut rng);
+        let d = Scalar::random(&mut rng);
+        let a = u * RISTRETTO_BASEPOINT_POINT;
+        let b = s * RISTRETTO_BASEPOINT_POINT + d * info_point::<H>(info);
+        let mut commitment = [0; 64];
+        commitment[0..32].copy_from_slice(a.compress().as_bytes());
+        commitment[32..64].copy_from_slice(b.compress().as_bytes());
+        Ok((commitment, Self { u, s, d }))
+    }
+
+    /// Consumes the session and returns the response to the challenge e.
+    ///
+    /// # Returns
+    ///
+    /// * Ok([u8; 128]) on success, representing the response r || c || s || d.
+    ///
+    /// * Err(::Error) variant on error. Only errors if the requester provided
+    /// a malformed scalar value e.
+    ///
+    /// # Mathematics
+    ///
+    /// * c = e - d
+    /// * r = u - c*Xs
+    pub fn sign_e(mut self, e: &[u8; 32], xs: &SigningKey) -> ::Result<[u8; 128]> {
+        let e = Scalar::from_canonical_bytes(*e).ok_or(WiredScalarMalformed)?;
+        let c = e - self.d;
+        let r = self.u - c * xs.scalar();
+        let mut response = [0; 128];
+        response[0..32].copy_from_slice(r.as_bytes());
+        response[32..64].copy_from_slice(c.as_bytes());
+        response[64..96].copy_from_slice(self.s.as_bytes());
+        response[96..128].copy_from_slice(self.d.as_bytes());
+        // The nonces must never be used twice, so are wiped before the
+        // response is even returned
+        wipe::wipe_scalar(&mut self.u);
+        wipe::wipe_scalar(&mut self.s);
+        wipe::wipe_scalar(&mut self.d);
+        Ok(response)
+    }
+}
+
+/// For managing the requester side of a partially blind session.
+pub struct PartialRequest {
+    t: [Scalar; 4],
+}
+
+impl PartialRequest {
+    /// Blinds the commitment received from the signer for the agreed info and
+    /// the message to be signed.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for hashing the info and the challenge
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], PartialRequest)) on success. The [u8; 32] represents the
+    /// challenge e, which is sent to the signer.
+    ///
+    /// * Err(::Error) variant on error, which could be caused by the failure to
+    /// initiate the RNG, or otherwise being input a malformed commitment.
+    ///
+    /// # Mathematics
+    ///
+    /// * α = A + t1*P + t2*Q
+    /// * β = B + t3*P + t4*Z
+    /// * t1, t2, t3, t4 = Randomly generated scalars by the requester
+    /// _____
+    /// * ε = H(α||β||Z||m)
+    /// * e = ε - t2 - t4
+    pub fn new<H, M>(
+        commitment: &[u8; 64],
+        pub_key: &VerifyingKey,
+        info: &[u8],
+        m: M,
+    ) -> ::Result<([u8; 32], Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        let mut rng = rng::try_new()?;
+        let a = decompress(&commitment[0..32])?;
+        let b = decompress(&commitment[32..64])?;
+        let z = info_point::<H>(info);
+        let t = [
+            Scalar::random(&mut rng),
+            Scalar::random(&mut rng),
+            Scalar::random(&mut rng),
+            Scalar::random(&mut rng),
+        ];
+        let alpha = a + t[0] * RISTRETTO_BASEPOINT_POINT + t[1] * pub_key.point();
+        let beta = b + t[2] * RISTRETTO_BASEPOINT_POINT + t[3] * z;
+        let epsilon = challenge::<H>(alpha, beta, z, m.as_ref());
+        Ok(((epsilon - t[1] - t[3]).to_bytes(), Self { t }))
+    }
+
+    /// Input the response from the signer, consumes self and creates the
+    /// partially blind signature. This does not verify the signature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(PartialSignature) on success.
+    ///
+    /// * Err(::Error) on error, which indicates that the response contained a
+    /// malformed scalar.
+    ///
+    /// # Mathematics
+    ///
+    /// * ρ = r + t1
+    /// * ω = c + t2
+    /// * σ = s + t3
+    /// * δ = d + t4
+    pub fn gen_signed_msg(self, response: &[u8; 128]) -> ::Result<PartialSignature> {
+        Ok(PartialSignature {
+            rho: scalar(&response[0..32])? + self.t[0],
+            omega: scalar(&response[32..64])? + self.t[1],
+            sigma: scalar(&response[64..96])? + self.t[2],
+            delta: scalar(&response[96..128])? + self.t[3],
+        })
+    }
+}
+
+/// A partially blind signature, which authenticates a message along with the
+/// info it was issued for.
+#[derive(Copy, Clone, Debug)]
+pub struct PartialSignature {
+    rho: Scalar,
+    omega: Scalar,
+    sigma: Scalar,
+    delta: Scalar,
+}
+
+impl PartialSignature {
+    /// Authenticates the signature on the provided info and message.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the signature is valid on both the info and the
+    /// message for the provided pub_key.
+    ///
+    /// * False to indicate that the signature is not valid, which includes it
+    /// having been issued for different info.
+    ///
+    /// # Mathematics
+    ///
+    /// * ω + δ == H(ρP + ωQ || σP + δZ || Z || m)
+    pub fn authenticate<H, M>(&self, pub_key: &VerifyingKey, info: &[u8], msg: M) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        let z = info_point::<H>(info);
+        let alpha = self.rho * RISTRETTO_BASEPOINT_POINT + self.omega * pub_key.point();
+        let beta = self.sigma * RISTRETTO_BASEPOINT_POINT + self.delta * z;
+        self.omega + self.delta == challenge::<H>(alpha, beta, z, msg.as_ref())
+    }
+}
+
+/// The commitment A || B in wired form
+pub struct WiredPartialCommitment(pub [u8; 64]);
+
+impl_wired_bytes!(WiredPartialCommitment, 64);
+impl_wired_envelope!(WiredPartialCommitment, PayloadType::PartialCommitment);
+
+/// The response r || c || s || d in wired form
+pub struct WiredPartialResponse(pub [u8; 128]);
+
+impl_wired_bytes!(WiredPartialResponse, 128);
+impl_wired_envelope!(WiredPartialResponse, PayloadType::PartialResponse);
+
+/// The PartialSignature in wired form, ρ || ω || σ || δ
+pub struct WiredPartialSignature(pub [u8; 128]);
+
+impl From<PartialSignature> for WiredPartialSignature {
+    fn from(sig: PartialSignature) -> Self {
+        let mut arr = [0; 128];
+        arr[0..32].copy_from_slice(sig.rho.as_bytes());
+        arr[32..64].copy_from_slice(sig.omega.as_bytes());
+        arr[64..96].copy_from_slice(sig.sigma.as_bytes());
+        arr[96..128].copy_from_slice(sig.delta.as_bytes());
+        WiredPartialSignature(arr)
+    }
+}
+
+impl WiredPartialSignature {
+    /// Converts the wired form into the internal PartialSignature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(PartialSignature) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates a malformed scalar.
+    pub fn to_internal_format(&self) -> ::Result<PartialSignature> {
+        Ok(PartialSignature {
+            rho: scalar(&self.0[0..32])?,
+            omega: scalar(&self.0[32..64])?,
+            sigma: scalar(&self.0[64..96])?,
+            delta: scalar(&self.0[96..128])?,
+        })
+    }
+}
+
+impl_wired_bytes!(WiredPartialSignature, 128);
+impl_wired_envelope!(WiredPartialSignature, PayloadType::PartialSignature);
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Hashes the info to the point Z = H(domain || info)
+fn info_point<H>(info: &[u8]) -> RistrettoPoint
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut bytes = Vec::with_capacity(INFO_DOMAIN.len() + info.len());
+    bytes.extend_from_slice(INFO_DOMAIN);
+    bytes.extend_from_slice(info);
+    RistrettoPoint::hash_from_bytes::<H>(&bytes)
+}
+
+/// Generates the challenge ε = H(α||β||Z||m)
+fn challenge<H>(
+    alpha: RistrettoPoint,
+    beta: RistrettoPoint,
+    z: RistrettoPoint,
+    m: &[u8],
+) -> Scalar
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut hasher = H::default();
+    hasher.input(alpha.compress().as_bytes());
+    hasher.input(beta.compress().as_bytes());
+    hasher.input(z.compress().as_bytes());
+    hasher.input(m);
+    Scalar::from_hash(hasher)
+}
+
+/// Loads a 32 byte slice as a canonical scalar
+fn scalar(bytes: &[u8]) -> ::Result<Scalar> {
+    let mut arr = [0; 32];
+    arr.copy_from_slice(bytes);
+    Scalar::from_canonical_bytes(arr).ok_or(WiredScalarMalformed)
+}
+
+/// Loads a 32 byte slice as a compressed ristretto point
+fn decompress(bytes: &[u8]) -> ::Result<RistrettoPoint> {
+    CompressedRistretto::from_slice(bytes)
+        .decompress()
+        .ok_or(WiredRistrettoPointMalformed)
+}
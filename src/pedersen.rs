@@ -0,0 +1,427 @@
+//! Blind signatures over Pedersen commitments to the message
+//!
+//! Rather than a message itself, the requester has a Pedersen commitment to
+//! it blindly signed, so that e = H(R || "blindsign pedersen message" || C).
+//! The signed commitment can later be shown along with zero knowledge proofs
+//! about the committed value, such as an OpeningProof of knowing it, or an
+//! EqualityProof that another commitment holds the same value, without the
+//! value ever being revealed.
+//!
+//! 1. The requester commits to the value with MessageCommitment::new, and
+//!    blinds the commitment with CommittedRequest::new for an ordinary
+//!    BlindSession.
+//! 2. The requester unblinds S' into a CommittedSignature.
+//! 3. The holder shows the CommittedSignature along with proofs about the
+//!    value made with its MessageCommitment.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+};
+use digest::Digest;
+use encoding;
+use keypair::{Redacted, VerifyingKey};
+use request::{BlindRequest, WiredEPrime};
+use rng;
+use session::{WiredRPrime, WiredSPrime};
+use signature::UnblindedSigData;
+use std::fmt;
+use typenum::U64;
+use Error::{WiredRistrettoPointMalformed, WiredScalarMalformed};
+
+/// The domain separation prefix of the messages signed for commitments
+const MESSAGE_DOMAIN: &[u8] = b"blindsign pedersen message";
+
+/// The input hashed to the second generator
+const GENERATOR_DOMAIN: &[u8] = b"blindsign pedersen generator";
+
+/// The domain separation prefix for opening proof challenges
+const OPENING_DOMAIN: &[u8] = b"blindsign pedersen opening";
+
+/// The domain separation prefix for equality proof challenges
+const EQUALITY_DOMAIN: &[u8] = b"blindsign pedersen equality";
+
+/// Returns the second generator Q of the commitments, which is a hash to the
+/// curve, so that no one knows its discrete log relative to P.
+///
+/// # Type Parameters
+///
+/// H is the hash algorithm used for hashing to the curve
+pub fn generator<H>() -> RistrettoPoint
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    RistrettoPoint::hash_from_bytes::<H>(GENERATOR_DOMAIN)
+}
+
+/// A Pedersen commitment to a value, along with its opening
+#[derive(Copy, Clone)]
+pub struct MessageCommitment {
+    m: Scalar,
+    r: Scalar,
+    c: RistrettoPoint,
+}
+
+impl fmt::Debug for MessageCommitment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MessageCommitment")
+            .field("m", &Redacted)
+            .field("r", &Redacted)
+            .field("c", &encoding::to_hex(&self.wired()))
+            .finish()
+    }
+}
+
+impl MessageCommitment {
+    /// Commits to the value with a fresh blinding factor.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for deriving the second generator
+    ///
+    /// # Returns
+    ///
+    /// * Ok(MessageCommitment) on success.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * C = m*P + r*Q
+    /// * m = The committed value
+    /// * r = A randomly generated scalar
+    /// * Q = The second generator
+    pub fn new<H>(m: Scalar) -> ::Result<Self>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let r = Scalar::random(&mut rng::try_new()?);
+        Ok(MessageCommitment {
+            m,
+            r,
+            c: m * RISTRETTO_BASEPOINT_POINT + r * generator::<H>(),
+        })
+    }
+
+    /// The same as new, but commits to a 64 bit integer value.
+    pub fn from_u64<H>(value: u64) -> ::Result<Self>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        Self::new::<H>(value_scalar(value))
+    }
+
+    /// Returns the committed value
+    pub fn value(&self) -> Scalar {
+        self.m
+    }
+
+    /// Returns the commitment in wired form
+    pub fn wired(&self) -> [u8; 32] {
+        self.c.compress().to_bytes()
+    }
+
+    /// Proves knowledge of the opening of the commitment, bound to the
+    /// provided context.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(OpeningProof) on success.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * T = a*P + b*Q
+    /// * a, b = Randomly generated scalars
+    /// * c = H("blindsign pedersen opening" || C || T || context)
+    /// * s1 = a + c*m, s2 = b + c*r
+    pub fn prove_opening<H>(&self, context: &[u8]) -> ::Result<OpeningProof>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut rng = rng::try_new()?;
+        let a = Scalar::random(&mut rng);
+        let b = Scalar::random(&mut rng);
+        let t = a * RISTRETTO_BASEPOINT_POINT + b * generator::<H>();
+        let c = challenge::<H>(OPENING_DOMAIN, &[self.c, t], context);
+        Ok(OpeningProof {
+            c,
+            s1: a + c * self.m,
+            s2: b + c * self.r,
+        })
+    }
+
+    /// Proves that the other commitment is to the same value as this one,
+    /// bound to the provided context.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(EqualityProof) on success.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * D = C - C2 = (r - r2)*Q
+    /// * T = b*Q
+    /// * b = A randomly generated scalar
+    /// * c = H("blindsign pedersen equality" || C || C2 || T || context)
+    /// * s = b + c*(r - r2)
+    pub fn prove_equal<H>(
+        &self,
+        other: &MessageCommitment,
+        context: &[u8],
+    ) -> ::Result<EqualityProof>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let b = Scalar::random(&mut rng::try_new()?);
+        let t = b * generator::<H>();
+        let c = challenge::<H>(EQUALITY_DOMAIN, &[self.c, other.c, t], context);
+        Ok(EqualityProof {
+            c,
+            s: b + c * (self.r - other.r),
+        })
+    }
+}
+
+/// For managing the requester side of having a commitment blindly signed.
+pub struct CommittedRequest {
+    commitment: [u8; 32],
+    request: BlindRequest,
+}
+
+impl CommittedRequest {
+    /// Blinds the commitment for the session with the provided R' from the
+    /// signer.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((WiredEPrime, CommittedRequest)) on success, with the WiredEPrime
+    /// being e' for sending to the signer.
+    ///
+    /// * Err(::Error) on failure, which indicates the failure to initiate the
+    /// internal RNG.
+    pub fn new<H>(
+        rp: &WiredRPrime,
+        commitment: &MessageCommitment,
+    ) -> ::Result<(WiredEPrime, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let commitment = commitment.wired();
+        let (ep, request) = BlindRequest::new_specific_msg::<H, Vec<u8>>(rp, msg(&commitment))?;
+        Ok((ep, CommittedRequest { commitment, request }))
+    }
+
+    /// Input S' from the signer, consumes self and creates the signed
+    /// commitment. This does not verify the signature.
+    pub fn gen_signed_msg(self, sp: &WiredSPrime) -> CommittedSignature {
+        CommittedSignature {
+            commitment: self.commitment,
+            sig: self.request.gen_signed_msg(sp),
+        }
+    }
+}
+
+/// A commitment along with the blind signature of the signer on it
+#[derive(Copy, Clone, Debug)]
+pub struct CommittedSignature {
+    commitment: [u8; 32],
+    sig: UnblindedSigData,
+}
+
+impl CommittedSignature {
+    /// Returns the commitment in wired form
+    pub fn commitment(&self) -> [u8; 32] {
+        self.commitment
+    }
+
+    /// Returns the signature on the commitment
+    pub fn signature(&self) -> &UnblindedSigData {
+        &self.sig
+    }
+
+    /// Authenticates the signature of the signer on the commitment.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the commitment was signed by the signer.
+    ///
+    /// * False to indicate that the signature is not valid.
+    pub fn verify<H>(&self, pub_key: &VerifyingKey) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        self.sig
+            .msg_authenticate::<H, Vec<u8>>(pub_key, msg(&self.commitment))
+    }
+}
+
+/// A proof of knowledge of the opening of a commitment
+#[derive(Copy, Clone, Debug)]
+pub struct OpeningProof {
+    c: Scalar,
+    s1: Scalar,
+    s2: Scalar,
+}
+
+impl OpeningProof {
+    /// Verifies that the proof demonstrates knowledge of the opening of the
+    /// commitment, for the provided context.
+    ///
+    /// # Returns
+    ///
+    /// * True if the proof is valid.
+    ///
+    /// * False otherwise, including if the commitment was malformed.
+    ///
+    /// # Mathematics
+    ///
+    /// * T = s1*P + s2*Q - c*C
+    /// * c == H("blindsign pedersen opening" || C || T || context)
+    pub fn verify<H>(&self, commitment: &[u8; 32], context: &[u8]) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let c = match point(commitment) {
+            Ok(c) => c,
+            Err(_) => return false,
+        };
+        let t = self.s1 * RISTRETTO_BASEPOINT_POINT + self.s2 * generator::<H>() - self.c * c;
+        challenge::<H>(OPENING_DOMAIN, &[c, t], context) == self.c
+    }
+
+    /// Returns the proof in wired form, c || s1 || s2
+    pub fn wired(&self) -> [u8; 96] {
+        let mut arr = [0; 96];
+        arr[0..32].copy_from_slice(self.c.as_bytes());
+        arr[32..64].copy_from_slice(self.s1.as_bytes());
+        arr[64..96].copy_from_slice(self.s2.as_bytes());
+        arr
+    }
+
+    /// Loads the proof from its wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(OpeningProof) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates a malformed scalar.
+    pub fn from_wired(wired: &[u8; 96]) -> ::Result<Self> {
+        Ok(OpeningProof {
+            c: scalar(&wired[0..32])?,
+            s1: scalar(&wired[32..64])?,
+            s2: scalar(&wired[64..96])?,
+        })
+    }
+}
+
+/// A proof that two commitments are to the same value
+#[derive(Copy, Clone, Debug)]
+pub struct EqualityProof {
+    c: Scalar,
+    s: Scalar,
+}
+
+impl EqualityProof {
+    /// Verifies that the proof demonstrates that the commitments are to the
+    /// same value, for the provided context.
+    ///
+    /// # Returns
+    ///
+    /// * True if the proof is valid.
+    ///
+    /// * False otherwise, including if either commitment was malformed.
+    ///
+    /// # Mathematics
+    ///
+    /// * T = s*Q - c*(C - C2)
+    /// * c == H("blindsign pedersen equality" || C || C2 || T || context)
+    pub fn verify<H>(&self, commitment: &[u8; 32], other: &[u8; 32], context: &[u8]) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let (c1, c2) = match (point(commitment), point(other)) {
+            (Ok(c1), Ok(c2)) => (c1, c2),
+            _ => return false,
+        };
+        let t = self.s * generator::<H>() - self.c * (c1 - c2);
+        challenge::<H>(EQUALITY_DOMAIN, &[c1, c2, t], context) == self.c
+    }
+
+    /// Returns the proof in wired form, c || s
+    pub fn wired(&self) -> [u8; 64] {
+        let mut arr = [0; 64];
+        arr[0..32].copy_from_slice(self.c.as_bytes());
+        arr[32..64].copy_from_slice(self.s.as_bytes());
+        arr
+    }
+
+    /// Loads the proof from its wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(EqualityProof) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates a malformed scalar.
+    pub fn from_wired(wired: &[u8; 64]) -> ::Result<Self> {
+        Ok(EqualityProof {
+            c: scalar(&wired[0..32])?,
+            s: scalar(&wired[32..64])?,
+        })
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Encodes the message signed for the commitment
+fn msg(commitment: &[u8; 32]) -> Vec<u8> {
+    let mut msg = MESSAGE_DOMAIN.to_vec();
+    msg.extend_from_slice(commitment);
+    msg
+}
+
+/// Computes a proof challenge over the points and context
+fn challenge<H>(domain: &[u8], points: &[RistrettoPoint], context: &[u8]) -> Scalar
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut hasher = H::default();
+    hasher.input(domain);
+    for p in points {
+        hasher.input(p.compress().as_bytes());
+    }
+    hasher.input(context);
+    Scalar::from_hash(hasher)
+}
+
+/// Loads a wired point
+fn point(bytes: &[u8; 32]) -> ::Result<RistrettoPoint> {
+    CompressedRistretto(*bytes)
+        .decompress()
+        .ok_or(WiredRistrettoPointMalformed)
+}
+
+/// Converts a 64 bit integer value into a scalar
+fn value_scalar(value: u64) -> Scalar {
+    let mut bytes = [0; 32];
+    bytes[0..8].copy_from_slice(&value.to_le_bytes());
+    Scalar::from_canonical_bytes(bytes).expect("64 bit integers are canonical scalars")
+}
+
+/// Loads a canonical scalar from a 32 byte slice
+fn scalar(bytes: &[u8]) -> ::Result<Scalar> {
+    let mut arr = [0; 32];
+    arr.copy_from_slice(bytes);
+    Scalar::from_canonical_bytes(arr).ok_or(WiredScalarMalformed)
+}
@@ -0,0 +1,129 @@
+//! Signer keys held wrapped by a PKCS#11 token
+//!
+//! PKCS#11 tokens do not implement Ristretto scalar arithmetic, so the
+//! private scalar is instead kept wrapped, that is encrypted with AES key
+//! wrap under a key that never leaves the token. For each signature the
+//! wrapped scalar is unwrapped by the token, used for computing S' and
+//! immediately overwritten, so it is never stored in plaintext. The wrapped
+//! form is safe to store alongside the public key in a database or
+//! configuration file.
+//!
+//! # Note
+//!
+//! The session must be logged in with the permission to encrypt and decrypt
+//! with the wrapping key, which must be an AES key created with CKA_ENCRYPT
+//! and CKA_DECRYPT.
+use cryptoki::{mechanism::Mechanism, object::ObjectHandle, session::Session};
+use curve25519_dalek::scalar::Scalar;
+use keypair::{BlindKeypair, BlindSignerKey, SigningKey, VerifyingKey, WiredPrivateKey};
+use std::sync::{Mutex, MutexGuard};
+use Error::{Pkcs11Failed, Pkcs11KeyMismatch};
+
+/// A BlindSignerKey whose private scalar is wrapped by a PKCS#11 token
+pub struct Pkcs11Key {
+    session: Mutex<Session>,
+    wrapping_key: ObjectHandle,
+    wrapped: Vec<u8>,
+    public: VerifyingKey,
+}
+
+impl Pkcs11Key {
+    /// Generates a new keypair, wrapping its private key with the provided
+    /// key of the token.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Pkcs11Key) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the failure to initiate the
+    /// internal RNG, or that the token failed to wrap the key.
+    pub fn generate(session: Session, wrapping_key: ObjectHandle) -> ::Result<Self> {
+        Self::wrap(session, wrapping_key, &BlindKeypair::generate()?.signing_key())
+    }
+
+    /// Wraps an existing signing key with the provided key of the token, for
+    /// example to migrate a key to the token.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Pkcs11Key) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the token failed to
+    /// wrap the key.
+    pub fn wrap(
+        session: Session,
+        wrapping_key: ObjectHandle,
+        key: &SigningKey,
+    ) -> ::Result<Self> {
+        let wrapped = session
+            .encrypt(&Mechanism::AesKeyWrap, wrapping_key, &key.wired())
+            .map_err(|_| Pkcs11Failed)?;
+        Ok(Pkcs11Key {
+            session: Mutex::new(session),
+            wrapping_key,
+            wrapped,
+            public: key.verifying_key(),
+        })
+    }
+
+    /// Loads a key from its wrapped form and public key, as previously
+    /// returned by wrapped and verifying_key. The wrapped form is checked
+    /// against the public key on every signature.
+    pub fn from_wrapped(
+        session: Session,
+        wrapping_key: ObjectHandle,
+        wrapped: Vec<u8>,
+        public: VerifyingKey,
+    ) -> Self {
+        Pkcs11Key {
+            session: Mutex::new(session),
+            wrapping_key,
+            wrapped,
+            public,
+        }
+    }
+
+    /// Returns the wrapped private key, for storing
+    pub fn wrapped(&self) -> &[u8] {
+        &self.wrapped
+    }
+
+    /// Locks the session. The session holds no state of this key, so it
+    /// remains usable even if another thread panicked while holding the lock.
+    fn session(&self) -> MutexGuard<Session> {
+        self.session.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl BlindSignerKey for Pkcs11Key {
+    fn sign_blinded(&self, ep: &Scalar, k: &Scalar) -> ::Result<Scalar> {
+        let mut unwrapped = self
+            .session()
+            .decrypt(&Mechanism::AesKeyWrap, self.wrapping_key, &self.wrapped)
+            .map_err(|_| Pkcs11Failed)?;
+        let mut wired = [0; 32];
+        let len = unwrapped.len();
+        if len == 32 {
+            wired.copy_from_slice(&unwrapped);
+        }
+        for byte in unwrapped.iter_mut() {
+            *byte = 0;
+        }
+        if len != 32 {
+            return Err(Pkcs11Failed);
+        }
+        let key = WiredPrivateKey::from_bytes(&wired).map(SigningKey::from_wired);
+        for byte in wired.iter_mut() {
+            *byte = 0;
+        }
+        let key = key?;
+        if key.verifying_key().wired() != self.public.wired() {
+            return Err(Pkcs11KeyMismatch);
+        }
+        key.sign_blinded(ep, k)
+    }
+
+    fn verifying_key(&self) -> VerifyingKey {
+        self.public
+    }
+}
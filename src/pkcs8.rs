@@ -0,0 +1,209 @@
+//! PKCS#8 import and export of the ECC keys
+//!
+//! Keypairs are encoded as the RFC 5958 OneAsymmetricKey structure, with the
+//! private key wrapped in an OCTET STRING as in RFC 8410 and the public key
+//! included as the optional publicKey field. There is no registered object
+//! identifier for ristretto255 keys, so the algorithm is identified by the
+//! UUID based OID 2.25.121033627253834078037909702935127721157, which does not
+//! require registration.
+//!
+//! # Note
+//!
+//! The encodings contain the private key in the clear, and should be stored
+//! with the same care as any other service credential.
+use encoding;
+use keypair::{BlindKeypair, SigningKey, WiredPrivateKey, WiredPublicKey};
+use Error::{PemMalformed, Pkcs8Malformed};
+
+/// The DER encoded contents of the algorithm object identifier
+pub const ALGORITHM_OID: &[u8] = &[
+    0x69, 0x81, 0xb6, 0x8e, 0x9d, 0xa3, 0x83, 0xf4, 0xea, 0xb0, 0xcf, 0x9a, 0xb0, 0xcf, 0xc5,
+    0xd7, 0xdc, 0xfc, 0x89, 0x45,
+];
+
+/// The label of PEM encoded private keys
+const PEM_LABEL: &str = "PRIVATE KEY";
+
+// DER tags used by the OneAsymmetricKey structure
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_ATTRIBUTES: u8 = 0xa0;
+const TAG_PUBLIC_KEY: u8 = 0x81;
+
+impl BlindKeypair {
+    /// Encodes the keypair as a PKCS#8 (RFC 5958 version 2) DER document,
+    /// including both the private and public keys.
+    pub fn to_pkcs8_der(&self) -> Vec<u8> {
+        let mut algorithm = Vec::new();
+        write_tlv(TAG_OID, ALGORITHM_OID, &mut algorithm);
+
+        let mut private = Vec::new();
+        write_tlv(TAG_OCTET_STRING, self.private_wired().as_bytes(), &mut private);
+
+        let mut public = vec![0];
+        public.extend_from_slice(&self.verifying_key().wired());
+
+        let mut body = Vec::new();
+        write_tlv(TAG_INTEGER, &[1], &mut body);
+        write_tlv(TAG_SEQUENCE, &algorithm, &mut body);
+        write_tlv(TAG_OCTET_STRING, &private, &mut body);
+        write_tlv(TAG_PUBLIC_KEY, &public, &mut body);
+
+        let mut der = Vec::new();
+        write_tlv(TAG_SEQUENCE, &body, &mut der);
+        der
+    }
+
+    /// Decodes a keypair from a PKCS#8 DER document. Both version 1 documents,
+    /// which only contain the private key, and version 2 documents, which also
+    /// contain the public key, are accepted.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlindKeypair) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the document was not a
+    /// blindsign PKCS#8 document, that either key was malformed, or that the
+    /// included public key did not correspond to the private key.
+    pub fn from_pkcs8_der(der: &[u8]) -> ::Result<Self> {
+        let mut pos = 0;
+        let body = read_tlv(der, &mut pos, TAG_SEQUENCE)?;
+        if pos != der.len() {
+            return Err(Pkcs8Malformed);
+        }
+
+        let mut pos = 0;
+        let version = match read_tlv(body, &mut pos, TAG_INTEGER)? {
+            [0] => 1,
+            [1] => 2,
+            _ => return Err(Pkcs8Malformed),
+        };
+
+        let algorithm = read_tlv(body, &mut pos, TAG_SEQUENCE)?;
+        let mut alg_pos = 0;
+        if read_tlv(algorithm, &mut alg_pos, TAG_OID)? != ALGORITHM_OID
+            || alg_pos != algorithm.len()
+        {
+            return Err(Pkcs8Malformed);
+        }
+
+        let wrapped = read_tlv(body, &mut pos, TAG_OCTET_STRING)?;
+        let mut wrapped_pos = 0;
+        let private = read_tlv(wrapped, &mut wrapped_pos, TAG_OCTET_STRING)?;
+        if private.len() != 32 || wrapped_pos != wrapped.len() {
+            return Err(Pkcs8Malformed);
+        }
+
+        // Attributes are permitted but carry nothing of use to this crate.
+        if body.get(pos) == Some(&TAG_ATTRIBUTES) {
+            read_tlv(body, &mut pos, TAG_ATTRIBUTES)?;
+        }
+
+        let keypair = if body.get(pos) == Some(&TAG_PUBLIC_KEY) {
+            let public = read_tlv(body, &mut pos, TAG_PUBLIC_KEY)?;
+            if version != 2 || public.len() != 33 || public[0] != 0 {
+                return Err(Pkcs8Malformed);
+            }
+            let private = WiredPrivateKey::from_bytes(private)?;
+            let public = WiredPublicKey::from_bytes(&public[1..])?;
+            BlindKeypair::from_wired(private, public)?
+        } else {
+            let private = WiredPrivateKey::from_bytes(private).map_err(|_| Pkcs8Malformed)?;
+            BlindKeypair::from_private(SigningKey::from_wired(private).scalar())
+        };
+
+        if pos != body.len() {
+            return Err(Pkcs8Malformed);
+        }
+        Ok(keypair)
+    }
+
+    /// Encodes the keypair as a PEM document with the PRIVATE KEY label
+    pub fn to_pkcs8_pem(&self) -> String {
+        let encoded = encoding::to_base64(&self.to_pkcs8_der());
+        let mut pem = format!("-----BEGIN {}-----\n", PEM_LABEL);
+        for line in encoded.as_bytes().chunks(64) {
+            // Base64 output is ASCII, so every chunk is valid UTF-8.
+            pem.push_str(&String::from_utf8_lossy(line));
+            pem.push('\n');
+        }
+        pem.push_str(&format!("-----END {}-----\n", PEM_LABEL));
+        pem
+    }
+
+    /// Decodes a keypair from a PEM document with the PRIVATE KEY label.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlindKeypair) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the PEM document was
+    /// malformed or that the contained PKCS#8 document was invalid.
+    pub fn from_pkcs8_pem(pem: &str) -> ::Result<Self> {
+        let begin = format!("-----BEGIN {}-----", PEM_LABEL);
+        let end = format!("-----END {}-----", PEM_LABEL);
+        let pem = pem.trim();
+        if !pem.starts_with(&begin) || !pem.ends_with(&end) || pem.len() < begin.len() + end.len()
+        {
+            return Err(PemMalformed);
+        }
+        let body: String = pem[begin.len()..pem.len() - end.len()]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        BlindKeypair::from_pkcs8_der(&encoding::from_base64(&body).map_err(|_| PemMalformed)?)
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Writes a DER TLV with the minimal length encoding
+fn write_tlv(tag: u8, value: &[u8], out: &mut Vec<u8>) {
+    out.push(tag);
+    if value.len() < 0x80 {
+        out.push(value.len() as u8);
+    } else if value.len() <= 0xff {
+        out.push(0x81);
+        out.push(value.len() as u8);
+    } else {
+        out.push(0x82);
+        out.push((value.len() >> 8) as u8);
+        out.push(value.len() as u8);
+    }
+    out.extend_from_slice(value);
+}
+
+/// Reads a DER TLV with the expected tag, rejecting non-minimal lengths
+fn read_tlv<'a>(bytes: &'a [u8], pos: &mut usize, tag: u8) -> ::Result<&'a [u8]> {
+    let header = bytes.get(*pos..*pos + 2).ok_or(Pkcs8Malformed)?;
+    if header[0] != tag {
+        return Err(Pkcs8Malformed);
+    }
+    *pos += 2;
+    let len = match header[1] {
+        len @ 0..=0x7f => len as usize,
+        0x81 => {
+            let len = *bytes.get(*pos).ok_or(Pkcs8Malformed)? as usize;
+            *pos += 1;
+            if len < 0x80 {
+                return Err(Pkcs8Malformed);
+            }
+            len
+        }
+        0x82 => {
+            let len_bytes = bytes.get(*pos..*pos + 2).ok_or(Pkcs8Malformed)?;
+            *pos += 2;
+            let len = (len_bytes[0] as usize) << 8 | len_bytes[1] as usize;
+            if len <= 0xff {
+                return Err(Pkcs8Malformed);
+            }
+            len
+        }
+        _ => return Err(Pkcs8Malformed),
+    };
+    let value = bytes.get(*pos..*pos + len).ok_or(Pkcs8Malformed)?;
+    *pos += len;
+    Ok(value)
+}
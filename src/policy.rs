@@ -0,0 +1,174 @@
+//! Signing policies, checked before each blind signature is produced
+//!
+//! A SignPolicy is consulted with the metadata of a session, its id, the id
+//! of the signing key, and an opaque context blob supplied by the integrator
+//! alongside e' (such as an account id, a payment receipt or a fraud score),
+//! and either approves signing or rejects it with a PolicyRejection. The
+//! PolicySigner wraps a SessionManager, consulting its policy each time a
+//! session is finished. Closures taking the SessionInfo can be used as
+//! policies directly.
+//!
+//! # Note
+//!
+//! A rejected session is aborted, as with a session whose signing fails, so
+//! that the requester must start a new one.
+use request::WiredEPrime;
+use session::{SessionId, SessionManager, WiredRPrime, WiredSPrime};
+use std::fmt;
+use Error::{PolicyRejected, SessionUnknown};
+
+/// The metadata of a session about to be signed
+#[derive(Copy, Clone, Debug)]
+pub struct SessionInfo<'a> {
+    id: SessionId,
+    key_id: u32,
+    context: &'a [u8],
+}
+
+impl<'a> SessionInfo<'a> {
+    /// Creates the metadata of the session
+    pub fn new(id: SessionId, key_id: u32, context: &'a [u8]) -> Self {
+        SessionInfo {
+            id,
+            key_id,
+            context,
+        }
+    }
+
+    /// Returns the session id
+    pub fn id(&self) -> SessionId {
+        self.id
+    }
+
+    /// Returns the id of the signing key
+    pub fn key_id(&self) -> u32 {
+        self.key_id
+    }
+
+    /// Returns the context blob supplied alongside e'
+    pub fn context(&self) -> &'a [u8] {
+        self.context
+    }
+}
+
+/// The reason a policy rejected signing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyRejection {
+    /// The requester has exceeded a rate limit
+    RateLimited,
+    /// The requester has not paid for the signature
+    PaymentRequired,
+    /// The request was flagged as fraudulent
+    Fraudulent,
+    /// Any other reason, described by the string
+    Other(String),
+}
+
+impl fmt::Display for PolicyRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PolicyRejection::RateLimited => write!(f, "rate limited"),
+            PolicyRejection::PaymentRequired => write!(f, "payment required"),
+            PolicyRejection::Fraudulent => write!(f, "flagged as fraudulent"),
+            PolicyRejection::Other(ref reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// A policy approving or rejecting each blind signature before it is
+/// produced
+pub trait SignPolicy {
+    /// Checks whether the session may be signed.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(()) to approve signing.
+    ///
+    /// * Err(PolicyRejection) to reject signing, with the reason.
+    fn approve(&self, info: &SessionInfo) -> Result<(), PolicyRejection>;
+}
+
+impl<F> SignPolicy for F
+where
+    F: Fn(&SessionInfo) -> Result<(), PolicyRejection>,
+{
+    fn approve(&self, info: &SessionInfo) -> Result<(), PolicyRejection> {
+        self(info)
+    }
+}
+
+/// A policy approving every session
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AllowAll;
+
+impl SignPolicy for AllowAll {
+    fn approve(&self, _: &SessionInfo) -> Result<(), PolicyRejection> {
+        Ok(())
+    }
+}
+
+/// A signer wrapping a SessionManager, which consults its policy before
+/// every signature.
+pub struct PolicySigner<P> {
+    sessions: SessionManager,
+    policy: P,
+    key_id: u32,
+}
+
+impl<P> PolicySigner<P>
+where
+    P: SignPolicy,
+{
+    /// Creates a signer starting sessions with the provided manager, whose
+    /// signing key is reported to the policy as key_id.
+    pub fn new(sessions: SessionManager, policy: P, key_id: u32) -> Self {
+        PolicySigner {
+            sessions,
+            policy,
+            key_id,
+        }
+    }
+
+    /// Returns the policy
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    /// Starts a new session at the provided time, as for
+    /// SessionManager::start.
+    pub fn start(&self, now: u64) -> ::Result<(SessionId, WiredRPrime)> {
+        self.sessions.start(now)
+    }
+
+    /// Checks the session against the policy with the provided context, and
+    /// finishes it as for SessionManager::finish if approved.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(WiredSPrime) on success, representing the completed blind
+    /// signature value S'.
+    ///
+    /// * Err(::Error) on failure, which indicates that the policy rejected
+    /// the session, in which case it is aborted, or any failure of
+    /// SessionManager::finish.
+    pub fn finish(
+        &self,
+        id: &SessionId,
+        ep: &WiredEPrime,
+        context: &[u8],
+    ) -> ::Result<WiredSPrime> {
+        let info = SessionInfo::new(*id, self.key_id, context);
+        if let Err(reason) = self.policy.approve(&info) {
+            if !self.sessions.abort(id) {
+                return Err(SessionUnknown);
+            }
+            return Err(PolicyRejected(reason));
+        }
+        self.sessions.finish(id, ep)
+    }
+
+    /// Aborts the session with the provided id, as for SessionManager::abort.
+    pub fn abort(&self, id: &SessionId) -> bool {
+        self.sessions.abort(id)
+    }
+}
@@ -0,0 +1,288 @@
+//! Privacy Pass issuance and redemption message types, as specified by RFC 9578
+//!
+//! This implements the publicly verifiable Blind RSA token type (0x0002),
+//! built on the RSA blind signatures of rsa_blind, with the binary layouts of
+//! the TokenRequest, TokenResponse and Token structures, so that an issuer
+//! built on this crate interoperates with standard Privacy Pass clients.
+//!
+//! 1. The client creates a TokenRequest for a challenge with
+//!    PrivacyPassClient::new.
+//! 2. The issuer responds to the TokenRequest with issue.
+//! 3. The client finalizes the TokenResponse into a Token, which is redeemed
+//!    with the origin and verified with Token::verify.
+//!
+//! The issuer key must be a 2048 bit RSA key, and is identified by its
+//! token_key_id, the SHA-256 hash of its RSASSA-PSS SubjectPublicKeyInfo.
+//!
+//! # Note
+//!
+//! As with the ECC protocol, this **does not** include **any** networking
+//! code.
+use rand::RngCore;
+use rng;
+use rsa::{PublicKeyParts, RSAPrivateKey, RSAPublicKey};
+use rsa_blind::{self, RsaBlindRequest};
+use sha2::{Digest, Sha256};
+use Error::{PrivacyPassKeyMismatch, PrivacyPassMalformed};
+
+/// The token type of publicly verifiable Blind RSA (2048) tokens
+pub const TOKEN_TYPE_BLIND_RSA: u16 = 0x0002;
+
+/// The length in bytes of the issuer modulus, Nk
+pub const NK: usize = 256;
+
+/// The length in bytes of an encoded TokenRequest
+const TOKEN_REQUEST_LEN: usize = 3 + NK;
+
+/// The length in bytes of an encoded Token
+const TOKEN_LEN: usize = 2 + 32 + 32 + 32 + NK;
+
+/// Computes the token_key_id of an issuer key from the DER encoding of its
+/// RSASSA-PSS SubjectPublicKeyInfo.
+pub fn token_key_id(spki_der: &[u8]) -> [u8; 32] {
+    let mut id = [0; 32];
+    id.copy_from_slice(&Sha256::digest(spki_der));
+    id
+}
+
+/// The TokenRequest sent from the client to the issuer
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenRequest {
+    truncated_token_key_id: u8,
+    blinded_msg: Vec<u8>,
+}
+
+impl TokenRequest {
+    /// Encodes the TokenRequest as token_type || truncated_token_key_id ||
+    /// blinded_msg
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = TOKEN_TYPE_BLIND_RSA.to_be_bytes().to_vec();
+        bytes.push(self.truncated_token_key_id);
+        bytes.extend_from_slice(&self.blinded_msg);
+        bytes
+    }
+
+    /// Decodes the TokenRequest.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(TokenRequest) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input had the wrong
+    /// length or token type.
+    pub fn from_bytes(bytes: &[u8]) -> ::Result<Self> {
+        if bytes.len() != TOKEN_REQUEST_LEN || bytes[0..2] != TOKEN_TYPE_BLIND_RSA.to_be_bytes()
+        {
+            return Err(PrivacyPassMalformed);
+        }
+        Ok(TokenRequest {
+            truncated_token_key_id: bytes[2],
+            blinded_msg: bytes[3..].to_vec(),
+        })
+    }
+}
+
+/// The TokenResponse sent from the issuer to the client
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenResponse {
+    blind_sig: Vec<u8>,
+}
+
+impl TokenResponse {
+    /// Encodes the TokenResponse as blind_sig
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.blind_sig.clone()
+    }
+
+    /// Decodes the TokenResponse.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(TokenResponse) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input had the wrong
+    /// length.
+    pub fn from_bytes(bytes: &[u8]) -> ::Result<Self> {
+        if bytes.len() != NK {
+            return Err(PrivacyPassMalformed);
+        }
+        Ok(TokenResponse {
+            blind_sig: bytes.to_vec(),
+        })
+    }
+}
+
+/// The Token redeemed by the client with the origin
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    nonce: [u8; 32],
+    challenge_digest: [u8; 32],
+    token_key_id: [u8; 32],
+    authenticator: Vec<u8>,
+}
+
+impl Token {
+    /// Returns the SHA-256 digest of the TokenChallenge the token was issued
+    /// for, which the origin checks against the challenge it sent.
+    pub fn challenge_digest(&self) -> [u8; 32] {
+        self.challenge_digest
+    }
+
+    /// Returns the token_key_id of the issuer key the token was issued under
+    pub fn token_key_id(&self) -> [u8; 32] {
+        self.token_key_id
+    }
+
+    /// Verifies the authenticator of the token with the issuer public key.
+    ///
+    /// # Returns
+    ///
+    /// * True to indicate that the token is valid.
+    ///
+    /// * False to indicate that the token is not valid.
+    pub fn verify(&self, pub_key: &RSAPublicKey) -> bool {
+        rsa_blind::verify(pub_key, &self.token_input(), &self.authenticator)
+    }
+
+    /// Encodes the Token as token_type || nonce || challenge_digest ||
+    /// token_key_id || authenticator
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.token_input();
+        bytes.extend_from_slice(&self.authenticator);
+        bytes
+    }
+
+    /// Decodes the Token.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Token) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input had the wrong
+    /// length or token type.
+    pub fn from_bytes(bytes: &[u8]) -> ::Result<Self> {
+        if bytes.len() != TOKEN_LEN || bytes[0..2] != TOKEN_TYPE_BLIND_RSA.to_be_bytes() {
+            return Err(PrivacyPassMalformed);
+        }
+        let mut token = Token {
+            nonce: [0; 32],
+            challenge_digest: [0; 32],
+            token_key_id: [0; 32],
+            authenticator: bytes[98..].to_vec(),
+        };
+        token.nonce.copy_from_slice(&bytes[2..34]);
+        token.challenge_digest.copy_from_slice(&bytes[34..66]);
+        token.token_key_id.copy_from_slice(&bytes[66..98]);
+        Ok(token)
+    }
+
+    /// Returns the token_input that the authenticator is a signature on,
+    /// token_type || nonce || challenge_digest || token_key_id
+    fn token_input(&self) -> Vec<u8> {
+        token_input(&self.nonce, &self.challenge_digest, &self.token_key_id)
+    }
+}
+
+/// For managing the client side of issuance of one token.
+pub struct PrivacyPassClient {
+    request: RsaBlindRequest,
+    nonce: [u8; 32],
+    challenge_digest: [u8; 32],
+    token_key_id: [u8; 32],
+}
+
+impl PrivacyPassClient {
+    /// Creates the TokenRequest for the encoded TokenChallenge received from
+    /// the origin.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((TokenRequest, PrivacyPassClient)) on success, with the
+    /// TokenRequest being sent to the issuer.
+    ///
+    /// * Err(::Error) on failure, which indicates the issuer key was not a
+    /// 2048 bit key, or the failure to initiate the internal RNG.
+    pub fn new(
+        pub_key: &RSAPublicKey,
+        token_key_id: [u8; 32],
+        challenge: &[u8],
+    ) -> ::Result<(TokenRequest, Self)> {
+        if (pub_key.n().bits() + 7) / 8 != NK {
+            return Err(PrivacyPassKeyMismatch);
+        }
+        let mut nonce = [0; 32];
+        rng::try_new()?.fill_bytes(&mut nonce);
+        let mut challenge_digest = [0; 32];
+        challenge_digest.copy_from_slice(&Sha256::digest(challenge));
+        let input = token_input(&nonce, &challenge_digest, &token_key_id);
+        let (blinded_msg, request) = RsaBlindRequest::new(pub_key, &input)?;
+        let token_request = TokenRequest {
+            truncated_token_key_id: token_key_id[31],
+            blinded_msg,
+        };
+        let client = PrivacyPassClient {
+            request,
+            nonce,
+            challenge_digest,
+            token_key_id,
+        };
+        Ok((token_request, client))
+    }
+
+    /// Finalizes the TokenResponse from the issuer into the Token.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Token) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the blind signature did not
+    /// finalize into a valid authenticator.
+    pub fn finalize(self, response: &TokenResponse) -> ::Result<Token> {
+        let authenticator = self.request.gen_signed_msg(&response.blind_sig)?;
+        Ok(Token {
+            nonce: self.nonce,
+            challenge_digest: self.challenge_digest,
+            token_key_id: self.token_key_id,
+            authenticator,
+        })
+    }
+}
+
+/// Responds to a TokenRequest, for the issuer holding the private key with
+/// the provided token_key_id.
+///
+/// # Returns
+///
+/// * Ok(TokenResponse) on success.
+///
+/// * Err(::Error) on failure, which indicates the request was for a different
+/// issuer key, or that the blinded message was malformed.
+pub fn issue(
+    priv_key: &RSAPrivateKey,
+    token_key_id: &[u8; 32],
+    request: &TokenRequest,
+) -> ::Result<TokenResponse> {
+    if request.truncated_token_key_id != token_key_id[31]
+        || (priv_key.n().bits() + 7) / 8 != NK
+    {
+        return Err(PrivacyPassKeyMismatch);
+    }
+    Ok(TokenResponse {
+        blind_sig: rsa_blind::blind_sign(priv_key, &request.blinded_msg)?,
+    })
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Encodes token_type || nonce || challenge_digest || token_key_id
+fn token_input(
+    nonce: &[u8; 32],
+    challenge_digest: &[u8; 32],
+    token_key_id: &[u8; 32],
+) -> Vec<u8> {
+    let mut input = TOKEN_TYPE_BLIND_RSA.to_be_bytes().to_vec();
+    input.extend_from_slice(nonce);
+    input.extend_from_slice(challenge_digest);
+    input.extend_from_slice(token_key_id);
+    input
+}
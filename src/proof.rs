@@ -0,0 +1,138 @@
+//! Zero knowledge proofs about the ECC keys
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::RistrettoPoint,
+    scalar::Scalar,
+};
+use digest::Digest;
+use keypair::{SigningKey, VerifyingKey};
+use rng;
+use typenum::U64;
+use Error::WiredScalarMalformed;
+
+/// The domain separation prefix for proof of possession challenges
+const POSSESSION_DOMAIN: &[u8] = b"blindsign proof of possession";
+
+/// A Schnorr proof of knowledge of the private key associated with a public
+/// key, bound to a context string. Relying parties should verify such a proof
+/// before trusting a published public key, which prevents rogue key style
+/// attacks where a key is registered without knowledge of its private key.
+#[derive(Copy, Clone, Debug)]
+pub struct PossessionProof {
+    c: Scalar,
+    s: Scalar,
+}
+
+impl PossessionProof {
+    /// Proves possession of the private key of the provided signing key, bound
+    /// to the provided context (for example the name of the key directory and
+    /// the identity of the issuer).
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for generating the challenge
+    ///
+    /// # Returns
+    ///
+    /// * Ok(PossessionProof) on success.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * T = t*P
+    /// * t = A randomly generated scalar
+    /// * c = H("blindsign proof of possession" || Qs || T || context)
+    /// * s = t + c*Xs
+    pub fn prove<H>(xs: &SigningKey, context: &[u8]) -> ::Result<Self>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let t = Scalar::random(&mut rng::try_new()?);
+        let c = challenge::<H>(
+            &xs.verifying_key(),
+            t * RISTRETTO_BASEPOINT_POINT,
+            context,
+        );
+        Ok(PossessionProof {
+            c,
+            s: t + c * xs.scalar(),
+        })
+    }
+
+    /// Verifies that the proof demonstrates possession of the private key of
+    /// the provided verifying key, for the provided context.
+    ///
+    /// # Returns
+    ///
+    /// * True if the proof is valid and the public key passes validation.
+    ///
+    /// * False otherwise.
+    ///
+    /// # Mathematics
+    ///
+    /// * T = s*P - c*Qs
+    /// * c == H("blindsign proof of possession" || Qs || T || context)
+    pub fn verify<H>(&self, pub_key: &VerifyingKey, context: &[u8]) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        if pub_key.validate().is_err() {
+            return false;
+        }
+        let t = self.s * RISTRETTO_BASEPOINT_POINT - self.c * pub_key.point();
+        challenge::<H>(pub_key, t, context) == self.c
+    }
+}
+
+/// The PossessionProof in wired form, consisting of c || s with each component
+/// consisting of 32 bytes.
+pub struct WiredPossessionProof(pub [u8; 64]);
+
+impl From<PossessionProof> for WiredPossessionProof {
+    fn from(proof: PossessionProof) -> Self {
+        let mut arr = [0; 64];
+        arr[0..32].copy_from_slice(proof.c.as_bytes());
+        arr[32..64].copy_from_slice(proof.s.as_bytes());
+        WiredPossessionProof(arr)
+    }
+}
+
+impl WiredPossessionProof {
+    /// Converts WiredPossessionProof into PossessionProof.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(PossessionProof) on success
+    ///
+    /// * Err(::Error) on failure, which indicates either component of the
+    /// internal [u8; 64] was malformed.
+    pub fn to_internal_format(&self) -> ::Result<PossessionProof> {
+        let mut c_arr = [0; 32];
+        let mut s_arr = [0; 32];
+        c_arr.copy_from_slice(&self.0[0..32]);
+        s_arr.copy_from_slice(&self.0[32..64]);
+        Ok(PossessionProof {
+            c: Scalar::from_canonical_bytes(c_arr).ok_or(WiredScalarMalformed)?,
+            s: Scalar::from_canonical_bytes(s_arr).ok_or(WiredScalarMalformed)?,
+        })
+    }
+}
+
+impl_wired_bytes!(WiredPossessionProof, 64);
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Computes the proof of possession challenge
+fn challenge<H>(pub_key: &VerifyingKey, t: RistrettoPoint, context: &[u8]) -> Scalar
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut hasher = H::default();
+    hasher.input(POSSESSION_DOMAIN);
+    hasher.input(pub_key.point().compress().as_bytes());
+    hasher.input(t.compress().as_bytes());
+    hasher.input(context);
+    Scalar::from_hash(hasher)
+}
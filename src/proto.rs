@@ -0,0 +1,136 @@
+//! Protobuf message types for the protocol messages and the final signature
+//!
+//! These types mirror proto/blindsign.proto, which is the canonical
+//! definition for services integrating over gRPC. Encoding and decoding are
+//! provided by the prost::Message trait, and the conversions to the internal
+//! types validate the length and form of every field.
+use request::WiredEPrime;
+use session::{WiredRPrime, WiredSPrime};
+use signature::{UnblindedSigData, WiredUnblindedSigData};
+use std::convert::TryFrom;
+use Error::WiredLengthInvalid;
+
+/// R', sent from the signer to the requester upon protocol initiation
+#[derive(Clone, PartialEq, Message)]
+pub struct RPrime {
+    #[prost(bytes, tag = "1")]
+    pub rp: Vec<u8>,
+}
+
+/// e', sent from the requester to the signer for blind signing
+#[derive(Clone, PartialEq, Message)]
+pub struct EPrime {
+    #[prost(bytes, tag = "1")]
+    pub ep: Vec<u8>,
+}
+
+/// S', the blind signature sent from the signer to the requester
+#[derive(Clone, PartialEq, Message)]
+pub struct SPrime {
+    #[prost(bytes, tag = "1")]
+    pub sp: Vec<u8>,
+}
+
+/// The unblinded signature, consisting of e, S, and R
+#[derive(Clone, PartialEq, Message)]
+pub struct UnblindedSignature {
+    #[prost(bytes, tag = "1")]
+    pub e: Vec<u8>,
+    #[prost(bytes, tag = "2")]
+    pub s: Vec<u8>,
+    #[prost(bytes, tag = "3")]
+    pub r: Vec<u8>,
+}
+
+impl From<WiredRPrime> for RPrime {
+    fn from(rp: WiredRPrime) -> Self {
+        RPrime { rp: rp.as_bytes().to_vec() }
+    }
+}
+
+impl TryFrom<RPrime> for WiredRPrime {
+    type Error = ::Error;
+
+    fn try_from(msg: RPrime) -> ::Result<Self> {
+        WiredRPrime::from_bytes(&msg.rp)
+    }
+}
+
+impl From<WiredEPrime> for EPrime {
+    fn from(ep: WiredEPrime) -> Self {
+        EPrime { ep: ep.as_bytes().to_vec() }
+    }
+}
+
+impl TryFrom<EPrime> for WiredEPrime {
+    type Error = ::Error;
+
+    fn try_from(msg: EPrime) -> ::Result<Self> {
+        WiredEPrime::from_bytes(&msg.ep)
+    }
+}
+
+impl From<WiredSPrime> for SPrime {
+    fn from(sp: WiredSPrime) -> Self {
+        SPrime { sp: sp.as_bytes().to_vec() }
+    }
+}
+
+impl TryFrom<SPrime> for WiredSPrime {
+    type Error = ::Error;
+
+    fn try_from(msg: SPrime) -> ::Result<Self> {
+        WiredSPrime::from_bytes(&msg.sp)
+    }
+}
+
+impl From<WiredUnblindedSigData> for UnblindedSignature {
+    fn from(wired: WiredUnblindedSigData) -> Self {
+        UnblindedSignature {
+            e: wired.0[0..32].to_vec(),
+            s: wired.0[32..64].to_vec(),
+            r: wired.0[64..96].to_vec(),
+        }
+    }
+}
+
+impl From<UnblindedSigData> for UnblindedSignature {
+    fn from(usd: UnblindedSigData) -> Self {
+        UnblindedSignature::from(WiredUnblindedSigData::from(usd))
+    }
+}
+
+impl TryFrom<UnblindedSignature> for WiredUnblindedSigData {
+    type Error = ::Error;
+
+    fn try_from(msg: UnblindedSignature) -> ::Result<Self> {
+        let mut arr = [0; 96];
+        copy_field(&mut arr[0..32], &msg.e)?;
+        copy_field(&mut arr[32..64], &msg.s)?;
+        copy_field(&mut arr[64..96], &msg.r)?;
+        Ok(WiredUnblindedSigData(arr))
+    }
+}
+
+impl TryFrom<UnblindedSignature> for UnblindedSigData {
+    type Error = ::Error;
+
+    fn try_from(msg: UnblindedSignature) -> ::Result<Self> {
+        WiredUnblindedSigData::try_from(msg)?.to_internal_format()
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Copies a message field into its position in a wired array, erroring if the
+/// field is not of the expected length.
+fn copy_field(dst: &mut [u8], field: &[u8]) -> ::Result<()> {
+    if field.len() != dst.len() {
+        return Err(WiredLengthInvalid {
+            expected: dst.len(),
+            actual: field.len(),
+        });
+    }
+    dst.copy_from_slice(field);
+    Ok(())
+}
@@ -0,0 +1,376 @@
+//! Sans-IO state machines driving the blind signature protocol
+//!
+//! The ProtocolMessage enum types every message exchanged by the two roles,
+//! and the RequesterProtocol and SignerProtocol state machines accept each
+//! incoming message and return the outgoing one, if any, so that integrators
+//! only need to move messages between them and the network. Messages that
+//! arrive out of order are rejected with ProtocolUnexpectedMessage, leaving
+//! the state machine unchanged, while any other failure ends the protocol.
+//!
+//! 1. The requester calls RequesterProtocol::start, sending InitRequest.
+//! 2. The signer handles InitRequest, replying with RPrime.
+//! 3. The requester handles RPrime, replying with EPrime.
+//! 4. The signer handles EPrime, replying with SPrime, and is done.
+//! 5. The requester handles SPrime, and is done, with the signature.
+//!
+//! Either side may send Abort instead, after which the other side fails with
+//! ProtocolAborted.
+//!
+//! For streaming over sockets, each message encodes as a frame of a 4 byte
+//! big endian length, followed by that many bytes of a 1 byte type tag and
+//! the fields of the message.
+//!
+//! # Note
+//!
+//! As the name implies, this **does not** include **any** networking code.
+use digest::Digest;
+use keypair::BlindSignerKey;
+use rand::RngCore;
+use request::{BlindRequest, WiredEPrime};
+use rng;
+use session::{BlindSession, SessionId, WiredRPrime, WiredSPrime};
+use signature::UnblindedSigData;
+use std::mem;
+use typenum::U64;
+use Error::{
+    ProtocolAborted, ProtocolMessageIncomplete, ProtocolMessageTagUnknown,
+    ProtocolSessionMismatch, ProtocolUnexpectedMessage, WiredLengthInvalid,
+};
+
+/// The length of the length prefix of a frame
+const PREFIX_LEN: usize = 4;
+
+/// A message of the blind signature protocol
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProtocolMessage {
+    /// The request for protocol initiation, from the requester
+    InitRequest,
+    /// R' for the session, from the signer
+    RPrime { id: SessionId, rp: WiredRPrime },
+    /// e' for the session, from the requester
+    EPrime { id: SessionId, ep: WiredEPrime },
+    /// S' for the session, from the signer
+    SPrime { id: SessionId, sp: WiredSPrime },
+    /// Abandons the session, from either side
+    Abort { id: Option<SessionId> },
+}
+
+impl ProtocolMessage {
+    /// Appends the message to the buffer as a length prefixed frame.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) {
+        let mut body = vec![self.tag()];
+        match *self {
+            ProtocolMessage::InitRequest => (),
+            ProtocolMessage::RPrime { id, rp } => {
+                body.extend_from_slice(&id.0);
+                body.extend_from_slice(rp.as_bytes());
+            }
+            ProtocolMessage::EPrime { id, ep } => {
+                body.extend_from_slice(&id.0);
+                body.extend_from_slice(ep.as_bytes());
+            }
+            ProtocolMessage::SPrime { id, sp } => {
+                body.extend_from_slice(&id.0);
+                body.extend_from_slice(sp.as_bytes());
+            }
+            ProtocolMessage::Abort { id: Some(id) } => body.extend_from_slice(&id.0),
+            ProtocolMessage::Abort { id: None } => (),
+        }
+        buf.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&body);
+    }
+
+    /// Decodes the frame at the start of the bytes, which may be followed by
+    /// further frames.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((ProtocolMessage, usize)) on success, with the usize being the
+    /// number of bytes consumed.
+    ///
+    /// * Err(::Error) on failure, which indicates that the frame is not yet
+    /// complete, in which case decoding should be retried once more bytes are
+    /// read, that the type tag was unknown, that the length did not match
+    /// the type of message, or that R', e' or S' was malformed. Only the
+    /// first is recoverable on a stream.
+    pub fn decode(bytes: &[u8]) -> ::Result<(Self, usize)> {
+        if bytes.len() < PREFIX_LEN {
+            return Err(ProtocolMessageIncomplete);
+        }
+        let mut len = [0; PREFIX_LEN];
+        len.copy_from_slice(&bytes[..PREFIX_LEN]);
+        let len = u32::from_be_bytes(len) as usize;
+        if len == 0 {
+            return Err(WiredLengthInvalid {
+                expected: 1,
+                actual: 0,
+            });
+        }
+        if bytes.len() - PREFIX_LEN < len {
+            return Err(ProtocolMessageIncomplete);
+        }
+        let body = &bytes[PREFIX_LEN..PREFIX_LEN + len];
+        let (tag, fields) = (body[0], &body[1..]);
+        let expected = match tag {
+            0 => 0,
+            1 | 2 | 3 => 48,
+            4 if fields.is_empty() => 0,
+            4 => 16,
+            _ => return Err(ProtocolMessageTagUnknown(tag)),
+        };
+        if fields.len() != expected {
+            return Err(WiredLengthInvalid {
+                expected: expected + 1,
+                actual: len,
+            });
+        }
+        let mut id = SessionId([0; 16]);
+        if expected > 0 {
+            id.0.copy_from_slice(&fields[..16]);
+        }
+        let msg = match tag {
+            0 => ProtocolMessage::InitRequest,
+            1 => ProtocolMessage::RPrime {
+                id,
+                rp: WiredRPrime::from_bytes(&fields[16..])?,
+            },
+            2 => ProtocolMessage::EPrime {
+                id,
+                ep: WiredEPrime::from_bytes(&fields[16..])?,
+            },
+            3 => ProtocolMessage::SPrime {
+                id,
+                sp: WiredSPrime::from_bytes(&fields[16..])?,
+            },
+            _ if expected == 0 => ProtocolMessage::Abort { id: None },
+            _ => ProtocolMessage::Abort { id: Some(id) },
+        };
+        Ok((msg, PREFIX_LEN + len))
+    }
+
+    /// Returns the type tag of the message
+    fn tag(&self) -> u8 {
+        match *self {
+            ProtocolMessage::InitRequest => 0,
+            ProtocolMessage::RPrime { .. } => 1,
+            ProtocolMessage::EPrime { .. } => 2,
+            ProtocolMessage::SPrime { .. } => 3,
+            ProtocolMessage::Abort { .. } => 4,
+        }
+    }
+}
+
+/// The states of the requester
+enum RequesterState {
+    Idle,
+    AwaitingRPrime,
+    AwaitingSPrime(SessionId, BlindRequest),
+    Done(UnblindedSigData),
+    Aborted,
+}
+
+/// The requester side of the protocol, for a blind signature on a message.
+pub struct RequesterProtocol {
+    msg: Vec<u8>,
+    state: RequesterState,
+}
+
+impl RequesterProtocol {
+    /// Creates the requester for a blind signature on the message m
+    pub fn new<M>(m: M) -> Self
+    where
+        M: AsRef<[u8]>,
+    {
+        RequesterProtocol {
+            msg: m.as_ref().to_vec(),
+            state: RequesterState::Idle,
+        }
+    }
+
+    /// Starts the protocol.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(ProtocolMessage) on success, the InitRequest for sending to the
+    /// signer.
+    ///
+    /// * Err(::Error) on failure, which indicates that the protocol was
+    /// already started.
+    pub fn start(&mut self) -> ::Result<ProtocolMessage> {
+        match self.state {
+            RequesterState::Idle => {
+                self.state = RequesterState::AwaitingRPrime;
+                Ok(ProtocolMessage::InitRequest)
+            }
+            _ => Err(ProtocolUnexpectedMessage),
+        }
+    }
+
+    /// Handles a message from the signer.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for generating e
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Some(ProtocolMessage)) on success, with the message being sent to
+    /// the signer.
+    ///
+    /// * Ok(None) on success, if there is no message to send as the protocol
+    /// is done.
+    ///
+    /// * Err(::Error) on failure, which indicates that the message was out of
+    /// order or for another session, that the signer aborted, or that R' or
+    /// S' was malformed.
+    pub fn handle<H>(&mut self, msg: ProtocolMessage) -> ::Result<Option<ProtocolMessage>>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        match (mem::replace(&mut self.state, RequesterState::Aborted), msg) {
+            (RequesterState::Aborted, _) => Err(ProtocolUnexpectedMessage),
+            (RequesterState::Done(sig), _) => {
+                self.state = RequesterState::Done(sig);
+                Err(ProtocolUnexpectedMessage)
+            }
+            (_, ProtocolMessage::Abort { .. }) => Err(ProtocolAborted),
+            (RequesterState::AwaitingRPrime, ProtocolMessage::RPrime { id, rp }) => {
+                let (ep, request) = BlindRequest::new_specific_msg::<H, &[u8]>(&rp, &self.msg)?;
+                self.state = RequesterState::AwaitingSPrime(id, request);
+                Ok(Some(ProtocolMessage::EPrime { id, ep }))
+            }
+            (RequesterState::AwaitingSPrime(pending, req), ProtocolMessage::SPrime { id, sp }) => {
+                if id != pending {
+                    self.state = RequesterState::AwaitingSPrime(pending, req);
+                    return Err(ProtocolSessionMismatch);
+                }
+                self.state = RequesterState::Done(req.gen_signed_msg(&sp));
+                Ok(None)
+            }
+            (state, _) => {
+                self.state = state;
+                Err(ProtocolUnexpectedMessage)
+            }
+        }
+    }
+
+    /// Returns the message being signed
+    pub fn msg(&self) -> &[u8] {
+        &self.msg
+    }
+
+    /// Returns true if the signature has been received
+    pub fn is_done(&self) -> bool {
+        match self.state {
+            RequesterState::Done(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the signature once the protocol is done. The signature is not
+    /// verified.
+    pub fn signature(&self) -> Option<&UnblindedSigData> {
+        match self.state {
+            RequesterState::Done(ref sig) => Some(sig),
+            _ => None,
+        }
+    }
+
+    /// Aborts the protocol, returning the Abort message for sending to the
+    /// signer.
+    pub fn abort(&mut self) -> ProtocolMessage {
+        let id = match mem::replace(&mut self.state, RequesterState::Aborted) {
+            RequesterState::AwaitingSPrime(id, _) => Some(id),
+            _ => None,
+        };
+        ProtocolMessage::Abort { id }
+    }
+}
+
+/// The states of the signer
+enum SignerState {
+    AwaitingInit,
+    AwaitingEPrime(SessionId, BlindSession),
+    Done,
+    Aborted,
+}
+
+/// The signer side of the protocol, for a single session.
+pub struct SignerProtocol<'a, K: 'a + ?Sized> {
+    xs: &'a K,
+    state: SignerState,
+}
+
+impl<'a, K> SignerProtocol<'a, K>
+where
+    K: BlindSignerKey + ?Sized,
+{
+    /// Creates the signer for a session signing with the provided key
+    pub fn new(xs: &'a K) -> Self {
+        SignerProtocol {
+            xs,
+            state: SignerState::AwaitingInit,
+        }
+    }
+
+    /// Handles a message from the requester.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(ProtocolMessage) on success, with the message being sent to the
+    /// requester.
+    ///
+    /// * Err(::Error) on failure, which indicates that the message was out of
+    /// order or for another session, that the requester aborted, that e' was
+    /// malformed, or the failure to initiate the internal RNG.
+    pub fn handle(&mut self, msg: ProtocolMessage) -> ::Result<ProtocolMessage> {
+        match (mem::replace(&mut self.state, SignerState::Aborted), msg) {
+            (SignerState::Aborted, _) => Err(ProtocolUnexpectedMessage),
+            (SignerState::Done, _) => {
+                self.state = SignerState::Done;
+                Err(ProtocolUnexpectedMessage)
+            }
+            (_, ProtocolMessage::Abort { .. }) => Err(ProtocolAborted),
+            (SignerState::AwaitingInit, ProtocolMessage::InitRequest) => {
+                let mut id = SessionId([0; 16]);
+                rng::try_new()?.fill_bytes(&mut id.0);
+                let (rp, session) = BlindSession::new()?;
+                self.state = SignerState::AwaitingEPrime(id, session);
+                Ok(ProtocolMessage::RPrime { id, rp })
+            }
+            (SignerState::AwaitingEPrime(pending, session), ProtocolMessage::EPrime { id, ep }) => {
+                if id != pending {
+                    self.state = SignerState::AwaitingEPrime(pending, session);
+                    return Err(ProtocolSessionMismatch);
+                }
+                // The session is consumed even if signing fails, as with
+                // SessionManager::finish
+                self.state = SignerState::Done;
+                let sp = session.sign_ep(&ep, self.xs)?;
+                Ok(ProtocolMessage::SPrime { id, sp })
+            }
+            (state, _) => {
+                self.state = state;
+                Err(ProtocolUnexpectedMessage)
+            }
+        }
+    }
+
+    /// Aborts the session, returning the Abort message for sending to the
+    /// requester.
+    pub fn abort(&mut self) -> ProtocolMessage {
+        let id = match mem::replace(&mut self.state, SignerState::Aborted) {
+            SignerState::AwaitingEPrime(id, _) => Some(id),
+            _ => None,
+        };
+        ProtocolMessage::Abort { id }
+    }
+
+    /// Returns true if S' has been sent
+    pub fn is_done(&self) -> bool {
+        match self.state {
+            SignerState::Done => true,
+            _ => false,
+        }
+    }
+}
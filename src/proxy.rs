@@ -0,0 +1,205 @@
+//! Proxy blind signatures
+//!
+//! A key owner delegates signing rights to a proxy signer by issuing it a
+//! Delegation, which is a Schnorr style signature on a warrant describing the
+//! scope of the delegation, the proxy public key, and a commitment K. The
+//! proxy combines the delegation with its own private key into a proxy
+//! keypair, and issues blind signatures with it using an ordinary
+//! BlindSession. Verifiers derive the proxy verifying key from the owner key,
+//! the proxy key and the published ProxyWarrant, so signatures only verify
+//! for proxies the owner actually delegated to.
+//!
+//! # Mathematics
+//!
+//! * K = k*P, h = H(K || Qp || scope)
+//! * σ = k + h*Xo
+//! * Xproxy = σ + Xp
+//! * Qproxy = K + h*Qo + Qp
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+};
+use digest::Digest;
+use keypair::{BlindKeypair, VerifyingKey};
+use rng;
+use typenum::U64;
+use Error::{ProxyDelegationInvalid, WiredRistrettoPointMalformed, WiredScalarMalformed};
+
+/// The domain separation prefix for the delegation challenge
+const DELEGATION_DOMAIN: &[u8] = b"blindsign proxy delegation";
+
+/// The public half of a delegation, which is published alongside the proxy
+/// public key so that verifiers can derive the proxy verifying key.
+#[derive(Clone, Debug)]
+pub struct ProxyWarrant {
+    k: RistrettoPoint,
+    scope: Vec<u8>,
+}
+
+impl ProxyWarrant {
+    /// Returns the scope of the delegation, as set by the owner
+    pub fn scope(&self) -> &[u8] {
+        &self.scope
+    }
+
+    /// Derives the verifying key that signatures of the proxy verify under.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for the delegation challenge
+    ///
+    /// # Mathematics
+    ///
+    /// * Qproxy = K + H(K || Qp || scope)*Qo + Qp
+    pub fn verifying_key<H>(&self, owner: &VerifyingKey, proxy: &VerifyingKey) -> VerifyingKey
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let h = self.challenge::<H>(proxy);
+        VerifyingKey::from_point(self.k + h * owner.point() + proxy.point())
+    }
+
+    /// Returns the warrant in wired form, K || scope
+    pub fn wired(&self) -> Vec<u8> {
+        let mut wired = self.k.compress().to_bytes().to_vec();
+        wired.extend_from_slice(&self.scope);
+        wired
+    }
+
+    /// Loads the warrant from its wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(ProxyWarrant) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input was shorter than
+    /// 32 bytes or K was malformed.
+    pub fn from_wired(wired: &[u8]) -> ::Result<Self> {
+        if wired.len() < 32 {
+            return Err(WiredRistrettoPointMalformed);
+        }
+        let k = CompressedRistretto::from_slice(&wired[0..32])
+            .decompress()
+            .ok_or(WiredRistrettoPointMalformed)?;
+        Ok(ProxyWarrant {
+            k,
+            scope: wired[32..].to_vec(),
+        })
+    }
+
+    /// Computes the delegation challenge h = H(K || Qp || scope)
+    fn challenge<H>(&self, proxy: &VerifyingKey) -> Scalar
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut hasher = H::default();
+        hasher.input(DELEGATION_DOMAIN);
+        hasher.input(self.k.compress().as_bytes());
+        hasher.input(proxy.point().compress().as_bytes());
+        hasher.input(&self.scope);
+        Scalar::from_hash(hasher)
+    }
+}
+
+/// A delegation of signing rights from a key owner to a proxy signer, which is
+/// sent privately to the proxy.
+pub struct Delegation {
+    warrant: ProxyWarrant,
+    sigma: Scalar,
+}
+
+impl Delegation {
+    /// Delegates signing rights within the provided scope to the holder of
+    /// the proxy key, for the key owner.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for the delegation challenge
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Delegation) on success.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    pub fn new<H, W>(owner: &BlindKeypair, proxy: &VerifyingKey, scope: W) -> ::Result<Self>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        W: AsRef<[u8]>,
+    {
+        let k = Scalar::random(&mut rng::try_new()?);
+        let warrant = ProxyWarrant {
+            k: k * RISTRETTO_BASEPOINT_POINT,
+            scope: scope.as_ref().to_vec(),
+        };
+        let sigma = k + warrant.challenge::<H>(proxy) * owner.private();
+        Ok(Delegation { warrant, sigma })
+    }
+
+    /// Returns the public warrant of the delegation
+    pub fn warrant(&self) -> &ProxyWarrant {
+        &self.warrant
+    }
+
+    /// Verifies the delegation against the owner key, and combines it with
+    /// the proxy keypair into the keypair the proxy signs with. The public key
+    /// of the returned keypair is the proxy verifying key of the warrant.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlindKeypair) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the delegation was not
+    /// issued by the owner to this proxy.
+    ///
+    /// # Mathematics
+    ///
+    /// * σP == K + h*Qo
+    pub fn proxy_keypair<H>(
+        &self,
+        owner: &VerifyingKey,
+        proxy: &BlindKeypair,
+    ) -> ::Result<BlindKeypair>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let h = self.warrant.challenge::<H>(&proxy.verifying_key());
+        if self.sigma * RISTRETTO_BASEPOINT_POINT != self.warrant.k + h * owner.point() {
+            return Err(ProxyDelegationInvalid);
+        }
+        Ok(BlindKeypair::from_private(self.sigma + proxy.private()))
+    }
+
+    /// Returns the delegation in wired form, σ || K || scope
+    pub fn wired(&self) -> Vec<u8> {
+        let mut wired = self.sigma.to_bytes().to_vec();
+        wired.extend_from_slice(&self.warrant.wired());
+        wired
+    }
+
+    /// Loads the delegation from its wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Delegation) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input was too short or
+    /// σ or K was malformed.
+    pub fn from_wired(wired: &[u8]) -> ::Result<Self> {
+        if wired.len() < 32 {
+            return Err(WiredScalarMalformed);
+        }
+        let mut sigma = [0; 32];
+        sigma.copy_from_slice(&wired[0..32]);
+        Ok(Delegation {
+            warrant: ProxyWarrant::from_wired(&wired[32..])?,
+            sigma: Scalar::from_canonical_bytes(sigma).ok_or(WiredScalarMalformed)?,
+        })
+    }
+}
@@ -0,0 +1,170 @@
+//! PyO3 bindings, for prototyping and scripting issuance from Python
+//!
+//! The blindsign Python module exposes BlindKeypair, BlindSession and
+//! BlindRequest classes, and an authenticate function, with every wired value
+//! passed as bytes and every failure raised as ValueError. The hash algorithm
+//! is always SHA3-512. The module is built as an extension with, for example,
+//! `maturin build --features python`.
+//!
+//! Python objects are shared by reference, so a session or request is taken
+//! out when it is signed or unblinded, after which further use raises.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use keypair::{self, VerifyingKey, WiredPrivateKey, WiredPublicKey};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+use request::{self, WiredEPrime};
+use session::{self, WiredRPrime, WiredSPrime};
+use sha3::Sha3_512;
+use signature::WiredUnblindedSigData;
+
+/// A signer keypair
+#[pyclass(name = "BlindKeypair")]
+pub struct PyKeypair {
+    inner: keypair::BlindKeypair,
+}
+
+#[pymethods]
+impl PyKeypair {
+    /// Generates a new keypair
+    #[staticmethod]
+    fn generate() -> PyResult<Self> {
+        Ok(PyKeypair {
+            inner: keypair::BlindKeypair::generate().map_err(to_py)?,
+        })
+    }
+
+    /// Loads a keypair from its 32 byte wired private and public keys
+    #[staticmethod]
+    fn from_wired(private: &[u8], public: &[u8]) -> PyResult<Self> {
+        let private = WiredPrivateKey::from_bytes(private).map_err(to_py)?;
+        let public = WiredPublicKey::from_bytes(public).map_err(to_py)?;
+        Ok(PyKeypair {
+            inner: keypair::BlindKeypair::from_wired(private, public).map_err(to_py)?,
+        })
+    }
+
+    /// Returns the 32 byte wired public key
+    fn public<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.inner.verifying_key().wired())
+    }
+
+    /// Returns the 32 byte wired private key
+    fn private<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, self.inner.private_wired().as_bytes())
+    }
+}
+
+/// A signer side session
+#[pyclass(name = "BlindSession")]
+pub struct PySession {
+    inner: Option<session::BlindSession>,
+    rp: WiredRPrime,
+}
+
+#[pymethods]
+impl PySession {
+    /// Starts a new session
+    #[new]
+    fn new() -> PyResult<Self> {
+        let (rp, inner) = session::BlindSession::new().map_err(to_py)?;
+        Ok(PySession {
+            inner: Some(inner),
+            rp,
+        })
+    }
+
+    /// Returns the 32 byte R', for sending to the requester
+    fn rp<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, self.rp.as_bytes())
+    }
+
+    /// Signs the 32 byte e' with the keypair, returning the 32 byte S'. A
+    /// session can only be signed once.
+    fn sign_ep<'py>(
+        &mut self,
+        py: Python<'py>,
+        ep: &[u8],
+        keypair: &PyKeypair,
+    ) -> PyResult<&'py PyBytes> {
+        let ep = WiredEPrime::from_bytes(ep).map_err(to_py)?;
+        let inner = self.inner.take().ok_or_else(|| used("session was already signed"))?;
+        let sp = inner.sign_ep(&ep, &keypair.inner.signing_key()).map_err(to_py)?;
+        Ok(PyBytes::new(py, sp.as_bytes()))
+    }
+}
+
+/// The requester side of a blind signature on a message
+#[pyclass(name = "BlindRequest")]
+pub struct PyRequest {
+    inner: Option<request::BlindRequest>,
+    ep: WiredEPrime,
+}
+
+#[pymethods]
+impl PyRequest {
+    /// Blinds the message with the 32 byte R' from the signer
+    #[new]
+    fn new(rp: &[u8], msg: &[u8]) -> PyResult<Self> {
+        let rp = WiredRPrime::from_bytes(rp).map_err(to_py)?;
+        let (ep, inner) = request::BlindRequest::new_specific_msg::<Sha3_512, &[u8]>(&rp, msg)
+            .map_err(to_py)?;
+        Ok(PyRequest {
+            inner: Some(inner),
+            ep,
+        })
+    }
+
+    /// Returns the 32 byte e', for sending to the signer
+    fn ep<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, self.ep.as_bytes())
+    }
+
+    /// Unblinds the 32 byte S' from the signer, returning the 96 byte wired
+    /// signature, which is not verified. A request can only be unblinded
+    /// once.
+    fn unblind<'py>(&mut self, py: Python<'py>, sp: &[u8]) -> PyResult<&'py PyBytes> {
+        let sp = WiredSPrime::from_bytes(sp).map_err(to_py)?;
+        let inner = self.inner.take().ok_or_else(|| used("request was already unblinded"))?;
+        let sig = inner.gen_signed_msg(&sp);
+        Ok(PyBytes::new(py, &WiredUnblindedSigData::from(sig).0))
+    }
+}
+
+/// Authenticates the 96 byte wired signature on the message under the 32
+/// byte wired public key
+#[pyfunction]
+fn authenticate(public: &[u8], sig: &[u8], msg: &[u8]) -> PyResult<bool> {
+    let pub_key = WiredPublicKey::from_bytes(public)
+        .and_then(VerifyingKey::from_wired)
+        .map_err(to_py)?;
+    let sig = WiredUnblindedSigData::from_bytes(sig)
+        .and_then(|sig| sig.to_internal_format())
+        .map_err(to_py)?;
+    Ok(sig.msg_authenticate::<Sha3_512, &[u8]>(&pub_key, msg))
+}
+
+/// The blindsign Python module
+#[pymodule]
+fn blindsign(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyKeypair>()?;
+    m.add_class::<PySession>()?;
+    m.add_class::<PyRequest>()?;
+    m.add_function(wrap_pyfunction!(authenticate, m)?)?;
+    Ok(())
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Converts an error to the ValueError raised to Python
+fn to_py(e: ::Error) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// The ValueError raised when a session or request is used twice
+fn used(msg: &'static str) -> PyErr {
+    PyValueError::new_err(msg)
+}
+
@@ -0,0 +1,166 @@
+//! Rate limited issuance with per client counters
+//!
+//! Redemption of blind signatures is unlinkable to issuance, so an issuer
+//! can only limit how many tokens each client obtains at issuance. The
+//! RateLimitedIssuer counts the sessions started for each client identifier
+//! within fixed time windows, and refuses to start sessions beyond the quota.
+//! Client identifiers are opaque to the issuer, and would typically be an
+//! account id or a hash of one. The CounterStore trait allows the counters to
+//! be kept in a database shared between issuers, with MemoryCounterStore being
+//! a simple in-process implementation.
+//!
+//! # Note
+//!
+//! Windows are expressed in whatever unit of time the caller uses for `now`,
+//! as with KeySet.
+use request::WiredEPrime;
+use session::{SessionId, SessionManager, WiredRPrime, WiredSPrime};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use Error::IssuanceQuotaExceeded;
+
+/// A record of the number of sessions started per client per window
+pub trait CounterStore {
+    /// Increments the counter of the client in the window if it is below the
+    /// quota. This must be atomic, so that concurrent sessions cannot exceed
+    /// the quota.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(true) if the counter was incremented by this call.
+    ///
+    /// * Ok(false) if the counter had already reached the quota.
+    ///
+    /// * Err(::Error) if the store failed, in which case no session must be
+    /// started.
+    fn increment_if_below(&self, client: &[u8], window: u64, quota: u64) -> ::Result<bool>;
+
+    /// Decrements the counter of the client in the window, returning a unit
+    /// of quota for a session that failed to start.
+    fn decrement(&self, client: &[u8], window: u64) -> ::Result<()>;
+}
+
+/// A CounterStore kept in memory, which may be shared between threads.
+#[derive(Debug, Default)]
+pub struct MemoryCounterStore {
+    counts: Mutex<HashMap<(Vec<u8>, u64), u64>>,
+}
+
+impl MemoryCounterStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        MemoryCounterStore {
+            counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the counter of the client in the window
+    pub fn count(&self, client: &[u8], window: u64) -> u64 {
+        self.counts()
+            .get(&(client.to_vec(), window))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    /// Removes the counters of every window before the provided one, which
+    /// can never be incremented again.
+    pub fn prune(&self, before: u64) {
+        self.counts().retain(|&(_, window), _| window >= before);
+    }
+
+    /// Locks the counters. The map is never left in an inconsistent state,
+    /// so it remains usable even if another thread panicked while holding
+    /// the lock.
+    fn counts(&self) -> MutexGuard<HashMap<(Vec<u8>, u64), u64>> {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl CounterStore for MemoryCounterStore {
+    fn increment_if_below(&self, client: &[u8], window: u64, quota: u64) -> ::Result<bool> {
+        let mut counts = self.counts();
+        let count = counts.entry((client.to_vec(), window)).or_insert(0);
+        if *count >= quota {
+            return Ok(false);
+        }
+        *count += 1;
+        Ok(true)
+    }
+
+    fn decrement(&self, client: &[u8], window: u64) -> ::Result<()> {
+        if let Some(count) = self.counts().get_mut(&(client.to_vec(), window)) {
+            *count = count.saturating_sub(1);
+        }
+        Ok(())
+    }
+}
+
+/// An issuer wrapping a SessionManager, which starts at most quota sessions
+/// per client in each window.
+pub struct RateLimitedIssuer<S> {
+    sessions: SessionManager,
+    store: S,
+    quota: u64,
+    window_len: u64,
+}
+
+impl<S> RateLimitedIssuer<S>
+where
+    S: CounterStore,
+{
+    /// Creates an issuer starting sessions with the provided manager, and
+    /// allowing quota sessions per client in each window of window_len.
+    /// window_len must not be zero.
+    pub fn new(sessions: SessionManager, store: S, quota: u64, window_len: u64) -> Self {
+        RateLimitedIssuer {
+            sessions,
+            store,
+            quota,
+            window_len,
+        }
+    }
+
+    /// Returns the window containing the provided time
+    pub fn window(&self, now: u64) -> u64 {
+        now / self.window_len
+    }
+
+    /// Returns the store of the counters
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Starts a new session for the client, counting it against the quota of
+    /// the client in the current window.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((SessionId, WiredRPrime)) on success, as for SessionManager::start.
+    ///
+    /// * Err(::Error) on failure, which indicates that the client has reached
+    /// its quota, that the store failed, or any failure of
+    /// SessionManager::start, in which case the session is not counted.
+    pub fn start(&self, client: &[u8], now: u64) -> ::Result<(SessionId, WiredRPrime)> {
+        let window = self.window(now);
+        if !self.store.increment_if_below(client, window, self.quota)? {
+            return Err(IssuanceQuotaExceeded);
+        }
+        self.sessions.start(now).or_else(|e| {
+            self.store.decrement(client, window)?;
+            Err(e)
+        })
+    }
+
+    /// Finishes the session with the provided id, as for
+    /// SessionManager::finish. The session stays counted even if signing
+    /// fails.
+    pub fn finish(&self, id: &SessionId, ep: &WiredEPrime) -> ::Result<WiredSPrime> {
+        self.sessions.finish(id, ep)
+    }
+
+    /// Aborts the session with the provided id, as for SessionManager::abort.
+    /// The session stays counted.
+    pub fn abort(&self, id: &SessionId) -> bool {
+        self.sessions.abort(id)
+    }
+}
@@ -0,0 +1,227 @@
+//! Blind signatures with message recovery
+//!
+//! A short message of up to MAX_RECOVERABLE_LEN bytes is embedded in the e
+//! component of the signature, so that the signature is all that needs to be
+//! transmitted. The signer side of the protocol is the ordinary BlindSession,
+//! and the signature is e || S, the same size as a CompactSignature.
+//!
+//! The message is encoded with redundancy, being an 8 byte tag and zero
+//! padding, which is checked upon recovery. A random (e, S) pair recovers to a
+//! validly encoded message with probability below 2^-64.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::RistrettoPoint,
+    scalar::Scalar,
+};
+use digest::Digest;
+use envelope::PayloadType;
+use keypair::VerifyingKey;
+use request::WiredEPrime;
+use rng;
+use session::{WiredRPrime, WiredSPrime};
+use typenum::U64;
+use Error::{RecoveryMessageTooLong, RecoveryRedundancyInvalid, WiredScalarMalformed};
+
+/// The maximum length in bytes of a recoverable message
+pub const MAX_RECOVERABLE_LEN: usize = 22;
+
+/// The length of the redundancy tag in the encoded message
+const TAG_LEN: usize = 8;
+
+/// The domain separation prefix for the mask derived from R
+const MASK_DOMAIN: &[u8] = b"blindsign recovery mask";
+
+/// The domain separation prefix for the redundancy tag
+const TAG_DOMAIN: &[u8] = b"blindsign recovery tag";
+
+/// For managing the requester side of a session for a signature with message
+/// recovery. The signer uses an ordinary BlindSession.
+pub struct RecoveryRequest {
+    u: Scalar,
+    v: Scalar,
+    e: Scalar,
+}
+
+impl RecoveryRequest {
+    /// Blinds R' for a signature from which the message m can be recovered.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for the mask and redundancy tag
+    ///
+    /// # Returns
+    ///
+    /// * Ok((WiredEPrime, RecoveryRequest)) on success. The WiredEPrime
+    /// represents the value e', which is sent to the signer for blind signing.
+    ///
+    /// * Err(::Error) variant on error, which could be caused by m being longer
+    /// than MAX_RECOVERABLE_LEN, or the failure to initiate the RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * R = u*R' + v*P
+    /// * e = encode(m) + H(R)
+    /// * e' = e / u
+    pub fn new<H, M>(rp: &WiredRPrime, m: M) -> ::Result<(WiredEPrime, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        let encoded = encode::<H>(m.as_ref())?;
+        let mut rng = rng::try_new()?;
+        let u = Scalar::random(&mut rng);
+        let v = Scalar::random(&mut rng);
+        let r = u * rp.point() + v * RISTRETTO_BASEPOINT_POINT;
+        let e = encoded + mask::<H>(r);
+        let ep = WiredEPrime::from_bytes((u.invert() * e).as_bytes())?;
+        Ok((ep, RecoveryRequest { u, v, e }))
+    }
+
+    /// Input the blinded signature S' from the signer, consumes self and
+    /// creates the signature. This does not verify the signature.
+    ///
+    /// # Mathematics
+    ///
+    /// * S = S' * u + v
+    pub fn gen_signed_msg(self, sp: &WiredSPrime) -> RecoverableSignature {
+        RecoverableSignature {
+            e: self.e,
+            s: sp.scalar() * self.u + self.v,
+        }
+    }
+}
+
+/// A signature from which its message can be recovered, e || S
+#[derive(Copy, Clone, Debug)]
+pub struct RecoverableSignature {
+    e: Scalar,
+    s: Scalar,
+}
+
+impl RecoverableSignature {
+    /// Recovers the message from the signature, which also authenticates the
+    /// signature with the provided public key.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Vec<u8>) on success, which is the recovered message.
+    ///
+    /// * Err(::Error) on failure, which indicates that the recovered encoding
+    /// failed its redundancy check, so that the signature is not valid under
+    /// the public key.
+    ///
+    /// # Mathematics
+    ///
+    /// * R = SP - e*Qs
+    /// * encode(m) = e - H(R)
+    pub fn recover<H>(&self, pub_key: &VerifyingKey) -> ::Result<Vec<u8>>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let r = self.s * RISTRETTO_BASEPOINT_POINT - self.e * pub_key.point();
+        decode::<H>(&(self.e - mask::<H>(r)))
+    }
+}
+
+/// The RecoverableSignature in wired form, e || S
+pub struct WiredRecoverableSignature(pub [u8; 64]);
+
+impl From<RecoverableSignature> for WiredRecoverableSignature {
+    fn from(sig: RecoverableSignature) -> Self {
+        let mut arr = [0; 64];
+        arr[0..32].copy_from_slice(sig.e.as_bytes());
+        arr[32..64].copy_from_slice(sig.s.as_bytes());
+        WiredRecoverableSignature(arr)
+    }
+}
+
+impl WiredRecoverableSignature {
+    /// Converts the wired form into the internal RecoverableSignature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(RecoverableSignature) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates a malformed scalar.
+    pub fn to_internal_format(&self) -> ::Result<RecoverableSignature> {
+        let mut e_arr = [0; 32];
+        let mut s_arr = [0; 32];
+        e_arr.copy_from_slice(&self.0[0..32]);
+        s_arr.copy_from_slice(&self.0[32..64]);
+        Ok(RecoverableSignature {
+            e: Scalar::from_canonical_bytes(e_arr).ok_or(WiredScalarMalformed)?,
+            s: Scalar::from_canonical_bytes(s_arr).ok_or(WiredScalarMalformed)?,
+        })
+    }
+}
+
+impl_wired_bytes!(WiredRecoverableSignature, 64);
+impl_wired_envelope!(WiredRecoverableSignature, PayloadType::RecoverableSignature);
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Derives the mask H(R) that hides the encoded message in e
+fn mask<H>(r: RistrettoPoint) -> Scalar
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut hasher = H::default();
+    hasher.input(MASK_DOMAIN);
+    hasher.input(r.compress().as_bytes());
+    Scalar::from_hash(hasher)
+}
+
+/// Computes the redundancy tag on the message
+fn tag<H>(m: &[u8]) -> [u8; TAG_LEN]
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut hasher = H::default();
+    hasher.input(TAG_DOMAIN);
+    hasher.input(m);
+    let mut tag = [0; TAG_LEN];
+    tag.copy_from_slice(&hasher.result()[..TAG_LEN]);
+    tag
+}
+
+/// Encodes the message as tag || len || m || zero padding, with the final
+/// byte also zero so that the encoding is always a canonical scalar.
+fn encode<H>(m: &[u8]) -> ::Result<Scalar>
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    if m.len() > MAX_RECOVERABLE_LEN {
+        return Err(RecoveryMessageTooLong);
+    }
+    let mut bytes = [0; 32];
+    bytes[..TAG_LEN].copy_from_slice(&tag::<H>(m));
+    bytes[TAG_LEN] = m.len() as u8;
+    bytes[TAG_LEN + 1..TAG_LEN + 1 + m.len()].copy_from_slice(m);
+    Ok(Scalar::from_canonical_bytes(bytes).expect("encodings below 2^248 are canonical scalars"))
+}
+
+/// Decodes the message, checking the length, zero padding and tag
+fn decode<H>(encoded: &Scalar) -> ::Result<Vec<u8>>
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let bytes = encoded.as_bytes();
+    let len = bytes[TAG_LEN] as usize;
+    if len > MAX_RECOVERABLE_LEN {
+        return Err(RecoveryRedundancyInvalid);
+    }
+    let end = TAG_LEN + 1 + len;
+    if bytes[end..].iter().any(|&byte| byte != 0) {
+        return Err(RecoveryRedundancyInvalid);
+    }
+    let m = &bytes[TAG_LEN + 1..end];
+    if tag::<H>(m) != bytes[..TAG_LEN] {
+        return Err(RecoveryRedundancyInvalid);
+    }
+    Ok(m.to_vec())
+}
@@ -9,16 +9,68 @@ use curve25519_dalek::{
     constants::RISTRETTO_BASEPOINT_POINT,
     ristretto::{CompressedRistretto, RistrettoPoint},
     scalar::Scalar,
+    traits::Identity,
 };
 use digest::Digest;
-use rand::OsRng;
+use envelope::PayloadType;
+use keypair::{Redacted, VerifyingKey};
+use rand::{CryptoRng, RngCore};
+use rng;
+#[cfg(feature = "sealing")]
+use seal;
+use session::{WiredRPrime, WiredSPrime};
 use signature::UnblindedSigData;
+use std::fmt;
 use typenum::U64;
-use Error::{WiredRistrettoPointMalformed, WiredScalarMalformed};
+use wipe;
+use Error::{
+    BlindSignatureInvalid, WiredRistrettoPointIdentity, WiredRistrettoPointMalformed,
+    WiredScalarMalformed,
+};
+
+/// The kind byte identifying sealed BlindRequest state
+#[cfg(feature = "sealing")]
+const SEALED_REQUEST_KIND: u8 = 2;
+
+/// The inputs hashed into e, which the requester and every verifier of a
+/// signature must agree on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    /// e = H(R || msg), as in the original protocol
+    Plain,
+    /// e = H(Qs || R || msg), binding the signer's public key into the
+    /// signature so that it cannot be made to authenticate under a related
+    /// key of an attacker's choosing.
+    KeyBound,
+}
+
+impl Default for Scheme {
+    /// Plain, so that existing signatures keep authenticating
+    fn default() -> Self {
+        Scheme::Plain
+    }
+}
+
+impl Scheme {
+    /// Returns the key which is hashed into e under this scheme, if any.
+    ///
+    /// pub(crate) as used in signature.rs
+    pub(crate) fn bound_key(self, pub_key: &VerifyingKey) -> Option<&VerifyingKey> {
+        match self {
+            Scheme::Plain => None,
+            Scheme::KeyBound => Some(pub_key),
+        }
+    }
+}
 
 /// For managing the requester steps of the blind signature protocol. Actually
 /// initiating the protocol such that the signer knows to begin the first step
 /// (generating R') is orthogonal to this crate.
+///
+/// The blinding factors u and v are wiped from memory once the request is
+/// unblinded or dropped. Anyone who obtains them can link the unblinded
+/// signature to the session it was issued in, so retaining them (or a
+/// sealed copy) beyond unblinding breaks unlinkability.
 pub struct BlindRequest {
     u: Scalar,
     v: Scalar,
@@ -26,6 +78,26 @@ pub struct BlindRequest {
     e: Scalar,
 }
 
+impl fmt::Debug for BlindRequest {
+    // R and e are redacted as well, as they would link the request to the
+    // unblinded signature just as u and v would
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlindRequest")
+            .field("u", &Redacted)
+            .field("v", &Redacted)
+            .field("r", &Redacted)
+            .field("e", &Redacted)
+            .finish()
+    }
+}
+
+impl Drop for BlindRequest {
+    fn drop(&mut self) {
+        wipe::wipe_scalar(&mut self.u);
+        wipe::wipe_scalar(&mut self.v);
+    }
+}
+
 impl BlindRequest {
     /// Perform the first set of requester side steps toward acquiring a blindly
     /// signed message. Generates a random 32 byte scalar to use as the message,
@@ -34,10 +106,10 @@ impl BlindRequest {
     ///
     /// # Arguments
     ///
-    /// * 'rp' - A reference to a 32 byte CompressedRistrettoPoint represented
-    /// as a [u8; 32]. This is the R' value received from the siger in response
-    /// to a request for protocol initiation, though how it is requested or
-    /// received is orthogonal to this crate.
+    /// * 'rp' - A reference to the wired R' value, which was validated as a
+    /// non-identity point on construction. This is the R' value received from
+    /// the siger in response to a request for protocol initiation, though how
+    /// it is requested or received is orthogonal to this crate.
     ///
     /// # Type Paramaeters
     ///
@@ -45,12 +117,11 @@ impl BlindRequest {
     ///
     /// # Returns
     ///
-    /// * Ok(([u8; 32], BlindRequest)) on success. The [u8; 32] represents the
-    /// value e', which is sent to the server for blind signing.
+    /// * Ok((WiredEPrime, BlindRequest)) on success. The WiredEPrime represents
+    /// the value e', which is sent to the server for blind signing.
     ///
-    /// * Err(::Error) variant on error, which could be caused by the failure to
-    /// initiate the RNG, or otherwise being input a malformed R' value from the
-    /// signer.
+    /// * Err(::Error) variant on error, which can only be caused by the failure
+    /// to initiate the RNG.
     ///
     /// # Mathematics
     ///
@@ -63,87 +134,406 @@ impl BlindRequest {
     /// * m = The bytes of the message to be blindly signed
     /// _____
     /// * e' = e / u
-    pub fn new<H>(rp: &[u8; 32]) -> ::Result<([u8; 32], Self)>
+    pub fn new<H>(rp: &WiredRPrime) -> ::Result<(WiredEPrime, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        Ok(Self::new_with_rng::<H, _>(rp, &mut rng::try_new()?))
+    }
+
+    /// The same as new, but drawing m, u and v from the provided RNG rather
+    /// than the internal one.
+    ///
+    /// # Security
+    ///
+    /// The RNG must be cryptographically secure, as its CryptoRng
+    /// implementation asserts, since predictable u and v link the signature
+    /// to the session it was issued in.
+    pub fn new_with_rng<H, R>(rp: &WiredRPrime, rng: &mut R) -> (WiredEPrime, Self)
     where
         H: Digest<OutputSize = U64> + Default,
+        R: RngCore + CryptoRng,
     {
-        initiate::<H, &[u8; 32]>(rp, Scalar::random(&mut OsRng::new()?).as_bytes())
+        let m = Scalar::random(rng);
+        initiate::<H, &[u8; 32], R>(rp.point(), None, m.as_bytes(), rng)
     }
 
     /// The same as new, but allows for passing in a specific message value 'm'
     /// to be blindly signed.
-    pub fn new_specific_msg<H, M>(rp: &[u8; 32], m: M) -> ::Result<([u8; 32], Self)>
+    pub fn new_specific_msg<H, M>(rp: &WiredRPrime, m: M) -> ::Result<(WiredEPrime, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        Ok(Self::new_specific_msg_with_rng::<H, M, _>(rp, m, &mut rng::try_new()?))
+    }
+
+    /// The same as new_specific_msg, but drawing u and v from the provided
+    /// RNG, as with new_with_rng.
+    pub fn new_specific_msg_with_rng<H, M, R>(
+        rp: &WiredRPrime,
+        m: M,
+        rng: &mut R,
+    ) -> (WiredEPrime, Self)
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+        R: RngCore + CryptoRng,
+    {
+        initiate::<H, M, R>(rp.point(), None, m, rng)
+    }
+
+    /// The same as new_specific_msg, but computing e under the provided
+    /// scheme. The signature must then be authenticated with
+    /// UnblindedSigData::msg_authenticate_with under the same scheme.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((WiredEPrime, BlindRequest)) on success, as for new_specific_msg.
+    ///
+    /// * Err(::Error) on failure, which could be caused by the failure to
+    /// initiate the RNG, or under KeyBound the public key failing validation.
+    pub fn new_with_scheme<H, M>(
+        rp: &WiredRPrime,
+        pub_key: &VerifyingKey,
+        m: M,
+        scheme: Scheme,
+    ) -> ::Result<(WiredEPrime, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        Self::new_with_scheme_with_rng::<H, M, _>(rp, pub_key, m, scheme, &mut rng::try_new()?)
+    }
+
+    /// The same as new_with_scheme, but drawing u and v from the provided
+    /// RNG, as with new_with_rng.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((WiredEPrime, BlindRequest)) on success, as for new_specific_msg.
+    ///
+    /// * Err(::Error) on failure, which under KeyBound indicates the public
+    /// key failing validation.
+    pub fn new_with_scheme_with_rng<H, M, R>(
+        rp: &WiredRPrime,
+        pub_key: &VerifyingKey,
+        m: M,
+        scheme: Scheme,
+        rng: &mut R,
+    ) -> ::Result<(WiredEPrime, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+        R: RngCore + CryptoRng,
+    {
+        let key = scheme.bound_key(pub_key);
+        if let Some(key) = key {
+            key.validate()?;
+        }
+        Ok(initiate::<H, M, R>(rp.point(), key, m, rng))
+    }
+
+    /// The same as new_specific_msg, but for a session bound to a context
+    /// with BlindSession::new_with_context. The signer's public key is needed
+    /// for absorbing the context into R', so an S' from a session in another
+    /// context fails to unblind, which gen_verified_msg detects.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for hashing the context and generating e
+    ///
+    /// # Returns
+    ///
+    /// * Ok((WiredEPrime, BlindRequest)) on success, as for new_specific_msg.
+    ///
+    /// * Err(::Error) on failure, which could be caused by the failure to
+    /// initiate the RNG, or the public key failing validation.
+    ///
+    /// # Mathematics
+    ///
+    /// * t = H("blindsign context" || R' || context)
+    /// * R = u(R' + tQs) + vP, with e and e' then computed as usual
+    pub fn new_with_context<H, M>(
+        rp: &WiredRPrime,
+        pub_key: &VerifyingKey,
+        context: &[u8],
+        m: M,
+    ) -> ::Result<(WiredEPrime, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        Self::new_with_context_with_rng::<H, M, _>(rp, pub_key, context, m, &mut rng::try_new()?)
+    }
+
+    /// The same as new_with_context, but drawing u and v from the provided
+    /// RNG, as with new_with_rng.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((WiredEPrime, BlindRequest)) on success, as for new_specific_msg.
+    ///
+    /// * Err(::Error) on failure, which indicates the public key failing
+    /// validation.
+    pub fn new_with_context_with_rng<H, M, R>(
+        rp: &WiredRPrime,
+        pub_key: &VerifyingKey,
+        context: &[u8],
+        m: M,
+        rng: &mut R,
+    ) -> ::Result<(WiredEPrime, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+        R: RngCore + CryptoRng,
+    {
+        pub_key.validate()?;
+        let t = generate_context_t::<H>(rp, context);
+        Ok(initiate::<H, M, R>(rp.point() + t * pub_key.point(), None, m, rng))
+    }
+
+    /// The same as new_specific_msg, but for many (R', m) pairs at once, for
+    /// wallets blinding large numbers of messages. The RNG is initiated once,
+    /// and the inversions of every u are batched into a single inversion.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Vec<(WiredEPrime, BlindRequest)>) on success, with an (e', request)
+    /// pair for each input pair, in the same order.
+    ///
+    /// * Err(::Error) on failure, which can only be caused by the failure to
+    /// initiate the RNG.
+    pub fn new_batch<H, M>(pairs: &[(WiredRPrime, M)]) -> ::Result<Vec<(WiredEPrime, Self)>>
     where
         H: Digest<OutputSize = U64> + Default,
         M: AsRef<[u8]>,
     {
-        initiate::<H, M>(rp, m)
+        Ok(Self::new_batch_with_rng::<H, M, _>(pairs, &mut rng::try_new()?))
+    }
+
+    /// The same as new_batch, but drawing every u and v from the provided
+    /// RNG, as with new_with_rng.
+    pub fn new_batch_with_rng<H, M, R>(
+        pairs: &[(WiredRPrime, M)],
+        rng: &mut R,
+    ) -> Vec<(WiredEPrime, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+        R: RngCore + CryptoRng,
+    {
+        let mut requests = Vec::with_capacity(pairs.len());
+        for &(ref rp, ref m) in pairs {
+            let rp = rp.point();
+            let u = Scalar::random(rng);
+            let v = Scalar::random(rng);
+            let r = generate_r(u, v, rp);
+            let e = generate_e::<H>(r, m.as_ref());
+            requests.push(BlindRequest { u, v, r, e });
+        }
+        let inverses = batch_invert(requests.iter().map(|request| request.u));
+        requests
+            .into_iter()
+            .zip(inverses)
+            .map(|(request, u_inv)| (WiredEPrime((u_inv * request.e).to_bytes()), request))
+            .collect()
     }
 
     /// Input the blinded signature S' from the signer, consumes self and
     /// creates the complete blindly signed message structure. Note that this
     /// method does not actually verify that a correct sp value was received
-    /// from the signer, only that it was a valid canonical scalar.
+    /// from the signer, only that it was a valid canonical scalar, which was
+    /// checked when the WiredSPrime was constructed.
     ///
     /// # Arguments
     ///
-    /// * 'sp' - A reference to a 32 byte Scalar represented as a [u8; 32]. This
-    /// scalar is received from the signer and is the signature on the value e'.
+    /// * 'sp' - A reference to the wired S' value. This scalar is received
+    /// from the signer and is the signature on the value e'.
     ///
     /// # Returns
     ///
-    /// * Ok(UnblindedSigData) on success. The UnblindedSigData consists of
+    /// * UnblindedSigData, which consists of
     ///
     /// 1. The unblinded S' value = **S**, the signature (on unblinded e' value, which is e = H(msg||R))
     /// 2. The **e** value (which is the unblinded e' value, on which S is the signature)
     /// 3. The **R** value (which is the unblinded R' value that was originally received from the signer)
     ///
-    /// * Err(::Error) on error. The only error is if the input sp is not a
-    /// valid scalar. Note that this method **does not** actually ensure that
-    /// the signature is valid, only that the scalar of the signature is
-    /// correctly formed.
+    /// Note that this method **does not** actually ensure that the signature
+    /// is valid, only that the scalar of the signature is correctly formed.
     ///
     /// # Mathematics
     ///
     /// * S = S' * u + v
     /// * v = Random scalar previously generated by requester
     /// * u = Random scalar previously generated by requester
-    pub fn gen_signed_msg(self, sp: &[u8; 32]) -> ::Result<UnblindedSigData> {
-        let sp = Scalar::from_canonical_bytes(*sp).ok_or(WiredScalarMalformed)?;
-        Ok(UnblindedSigData::new(self.e, sp * self.u + self.v, self.r))
+    pub fn gen_signed_msg(mut self, sp: &WiredSPrime) -> UnblindedSigData {
+        let s = sp.scalar() * self.u + self.v;
+        wipe::wipe_scalar(&mut self.u);
+        wipe::wipe_scalar(&mut self.v);
+        UnblindedSigData::new(self.e, s, self.r)
+    }
+
+    /// The same as gen_signed_msg, but additionally verifies that S' was
+    /// computed with the private key of the published verifying key, so that
+    /// a signer using a different key for some requesters, to segment them,
+    /// is detected as soon as S' is received.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(UnblindedSigData) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that sp was not a signature
+    /// under the provided key.
+    ///
+    /// # Note
+    ///
+    /// No separate proof from the signer is needed for this, as S' is only
+    /// valid if S'*P = e'*Qs + R', which holds exactly when the unblinded
+    /// signature authenticates under Qs.
+    pub fn gen_verified_msg(
+        self,
+        sp: &WiredSPrime,
+        pub_key: &VerifyingKey,
+    ) -> ::Result<UnblindedSigData> {
+        let sig = self.gen_signed_msg(sp);
+        if pub_key.validate().is_err() || !sig.authenticate(pub_key) {
+            return Err(BlindSignatureInvalid);
+        }
+        Ok(sig)
+    }
+
+    /// Seals the request state, encrypting it with the provided 32 byte key so
+    /// that a requester can persist it (for example across an app restart)
+    /// while waiting for S' from the signer.
+    ///
+    /// # Warning
+    ///
+    /// The blinding factors u and v are what keep the signer from linking the
+    /// final signature to this request. Anyone holding both the blob and the
+    /// key can unblind, and so link, the signature. The key should be kept in
+    /// platform secure storage rather than alongside the blob, and the blob
+    /// should be destroyed once the signed message has been generated.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Vec<u8>) on success, which is the sealed blob.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    #[cfg(feature = "sealing")]
+    pub fn seal(&self, key: &[u8; 32]) -> ::Result<Vec<u8>> {
+        let mut state = [0; 128];
+        state[0..32].copy_from_slice(self.u.as_bytes());
+        state[32..64].copy_from_slice(self.v.as_bytes());
+        state[64..96].copy_from_slice(self.r.compress().as_bytes());
+        state[96..128].copy_from_slice(self.e.as_bytes());
+        let sealed = seal::seal_state(key, SEALED_REQUEST_KIND, &state);
+        wipe::wipe_bytes(&mut state);
+        sealed
+    }
+
+    /// Restores request state from a blob produced by seal, after which
+    /// gen_signed_msg can be called as usual.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(BlindRequest) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the blob was malformed,
+    /// that the key was wrong, or that the blob was tampered with.
+    #[cfg(feature = "sealing")]
+    pub fn restore(key: &[u8; 32], blob: &[u8]) -> ::Result<Self> {
+        let mut state = seal::open_state(key, SEALED_REQUEST_KIND, 128, blob)?;
+        let restored = {
+            let scalar = |offset: usize| {
+                let mut bytes = [0; 32];
+                bytes.copy_from_slice(&state[offset..offset + 32]);
+                let scalar = Scalar::from_canonical_bytes(bytes);
+                wipe::wipe_bytes(&mut bytes);
+                scalar.ok_or(WiredScalarMalformed)
+            };
+            let mut r = [0; 32];
+            r.copy_from_slice(&state[64..96]);
+            (scalar(0), scalar(32), decompress_non_identity(r), scalar(96))
+        };
+        wipe::wipe_bytes(&mut state);
+        Ok(BlindRequest {
+            u: restored.0?,
+            v: restored.1?,
+            r: restored.2?,
+            e: restored.3?,
+        })
     }
 }
 
+/// The e' value in wired form, which is sent from the requester to the signer
+/// for blind signing.
+///
+/// Construction fails unless the bytes are a canonical scalar.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WiredEPrime([u8; 32]);
+
+impl WiredEPrime {
+    /// Returns e' in Scalar form
+    ///
+    /// pub(crate) as used in session.rs, threshold.rs and frost.rs
+    pub(crate) fn scalar(&self) -> Scalar {
+        Scalar::from_canonical_bytes(self.0).expect("WiredEPrime is validated")
+    }
+}
+
+impl_wired_bytes!(WiredEPrime, 32, validate_ep);
+impl_wired_envelope!(WiredEPrime, PayloadType::EPrime);
+
 // Implementation internal functions, not exposed to crate users -->
 
 /// Internal code for all new variants (ie: with random or specific msg)
-#[allow(many_single_char_names)]
-fn initiate<H, M>(rp: &[u8; 32], m: M) -> ::Result<([u8; 32], BlindRequest)>
+fn initiate<H, M, R>(
+    rp: RistrettoPoint,
+    key: Option<&VerifyingKey>,
+    m: M,
+    rng: &mut R,
+) -> (WiredEPrime, BlindRequest)
 where
     H: Digest<OutputSize = U64> + Default,
     M: AsRef<[u8]>,
+    R: RngCore + CryptoRng,
 {
-    let mut rng = OsRng::new()?;
-    // Load the wired R' value into RistrettoPoint form, error if the wired
-    // form was malformed.
-    let rp = CompressedRistretto(*rp)
-        .decompress()
-        .ok_or(WiredRistrettoPointMalformed)?;
-
     // The random scalars u and v must be generated
-    let u = Scalar::random(&mut rng);
-    let v = Scalar::random(&mut rng);
+    let u = Scalar::random(rng);
+    let v = Scalar::random(rng);
+    initiate_with::<H, M>(u, v, rp, key, m)
+}
+
+/// Internal code for initiating with the provided u and v.
+///
+/// pub(crate) as used in testvectors.rs for replaying known answers
+#[allow(many_single_char_names)]
+pub(crate) fn initiate_with<H, M>(
+    u: Scalar,
+    v: Scalar,
+    rp: RistrettoPoint,
+    key: Option<&VerifyingKey>,
+    m: M,
+) -> (WiredEPrime, BlindRequest)
+where
+    H: Digest<OutputSize = U64> + Default,
+    M: AsRef<[u8]>,
+{
+    // R' was loaded from its validated wired form, which rejects the
+    // identity, as a malicious signer sending it would leave R = v*P.
 
     // R = u*R' + v*P
     let r = generate_r(u, v, rp);
 
-    // e = H(R||m)
-    let e = generate_e::<H>(r, m.as_ref());
+    // e = H(R||m), or H(Qs||R||m) if the key is bound
+    let e = generate_e_compressed::<H>(key, &r.compress(), m.as_ref());
 
     // e' = e / u
     let ep = generate_ep(u, e);
 
-    Ok((ep.to_bytes(), BlindRequest { u, v, r, e }))
+    (WiredEPrime(ep.to_bytes()), BlindRequest { u, v, r, e })
 }
 
 /// The requester, given R' from the signer, calculates R = u*R' + v*P, where
@@ -159,20 +549,100 @@ fn generate_r(u: Scalar, v: Scalar, rp: RistrettoPoint) -> RistrettoPoint {
 /// * R = the previously calculated R value
 /// * m = the message to be  signed
 ///
-/// pub(crate) as used in signature.rs
+/// pub(crate) as used in signature.rs, fuzzing.rs, clause.rs, cut_choose.rs,
+/// fair.rs and okamoto.rs
 pub(crate) fn generate_e<H>(r: RistrettoPoint, m: &[u8]) -> Scalar
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    generate_e_compressed::<H>(None, &r.compress(), m)
+}
+
+/// The same as generate_e, for an R which is already compressed, and
+/// prefixing the provided public key Qs if any, for Scheme::KeyBound.
+///
+/// pub(crate) as used in signature.rs
+pub(crate) fn generate_e_compressed<H>(
+    key: Option<&VerifyingKey>,
+    r: &CompressedRistretto,
+    m: &[u8],
+) -> Scalar
 where
     H: Digest<OutputSize = U64> + Default,
 {
     let mut hasher = H::default();
-    hasher.input(r.compress().as_bytes());
+    if let Some(key) = key {
+        hasher.input(&key.wired());
+    }
+    hasher.input(r.as_bytes());
     hasher.input(m);
     Scalar::from_hash(hasher)
 }
 
+/// Both sides generate the context tweak t = H("blindsign context" || R' ||
+/// context), where
+/// * R' = the wired R' value of the session
+/// * context = the context agreed by the requester and signer
+///
+/// pub(crate) as used in session.rs
+pub(crate) fn generate_context_t<H>(rp: &WiredRPrime, context: &[u8]) -> Scalar
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut hasher = H::default();
+    hasher.input(b"blindsign context");
+    hasher.input(rp.as_bytes());
+    hasher.input(context);
+    Scalar::from_hash(hasher)
+}
+
+/// Inverts every scalar with a single inversion, by Montgomery's trick. The
+/// scalars must be nonzero, as random scalars are with overwhelming
+/// probability.
+fn batch_invert<I>(scalars: I) -> Vec<Scalar>
+where
+    I: Iterator<Item = Scalar>,
+{
+    // prefix[i] is the product of the scalars before i
+    let scalars: Vec<Scalar> = scalars.collect();
+    let mut prefix = Vec::with_capacity(scalars.len());
+    let mut acc = Scalar::one();
+    for scalar in &scalars {
+        prefix.push(acc);
+        acc *= scalar;
+    }
+    let mut inv = acc.invert();
+    let mut inverses = vec![Scalar::zero(); scalars.len()];
+    for i in (0..scalars.len()).rev() {
+        inverses[i] = inv * prefix[i];
+        inv *= scalars[i];
+    }
+    inverses
+}
+
 /// The requester calculates e' = e / u, where
 /// * e = the previously generated e value
 /// * u = a randomly chosen number by the requester
 fn generate_ep(u: Scalar, e: Scalar) -> Scalar {
     u.invert() * e
 }
+
+/// Decompresses a wired R' or R value, rejecting the identity point.
+///
+/// pub(crate) as used in signature.rs and session.rs
+pub(crate) fn decompress_non_identity(wired: [u8; 32]) -> ::Result<RistrettoPoint> {
+    let point = CompressedRistretto(wired)
+        .decompress()
+        .ok_or(WiredRistrettoPointMalformed)?;
+    if point == RistrettoPoint::identity() {
+        return Err(WiredRistrettoPointIdentity);
+    }
+    Ok(point)
+}
+
+/// Validates the bytes of a WiredEPrime on construction
+fn validate_ep(ep: &[u8; 32]) -> ::Result<()> {
+    Scalar::from_canonical_bytes(*ep)
+        .map(|_| ())
+        .ok_or(WiredScalarMalformed)
+}
@@ -0,0 +1,170 @@
+//! Acquisition of randomness
+//!
+//! Every random value in the crate is drawn from the operating system's
+//! entropy source, through SecureRng, except by the `_with_rng` variants of
+//! the protocol constructors, such as BlindKeypair::generate_with_rng, which
+//! draw from an RNG provided by the caller instead, for targets without an
+//! operating system source. Two optional steps are provided for applications
+//! to call once at startup.
+//!
+//! * self_test checks that the entropy source is actually producing varying
+//! output, as a misconfigured sandbox or VM image can silently return
+//! constant bytes, which would reveal the signer's private key through
+//! repeated nonces. It also checks that the entropy source can be opened at
+//! all, after which acquiring the RNG does not fail.
+//!
+//! * enable_drbg seeds a ChaCha20 DRBG from the entropy source, after which
+//! random values are drawn from the DRBG rather than with a syscall per
+//! value, for platforms where those are expensive.
+use rand::prng::ChaChaRng;
+use rand::{CryptoRng, OsRng, RngCore, SeedableRng};
+use std::sync::{Mutex, MutexGuard};
+use Error::RngSelfTestFailed;
+
+/// The DRBG all randomness is drawn from once enable_drbg has been called
+static DRBG: Mutex<Option<ChaChaRng>> = Mutex::new(None);
+
+/// Checks that the operating system's entropy source is producing varying
+/// output, by drawing two 32 byte blocks and checking that neither is
+/// constant and that they differ.
+///
+/// # Returns
+///
+/// * Ok(()) on success.
+///
+/// * Err(::Error) on failure, which indicates the failure to initiate the
+/// entropy source, or that its output was constant, in which case no keys or
+/// signatures must be generated.
+pub fn self_test() -> ::Result<()> {
+    let mut os = OsRng::new()?;
+    let mut blocks = [[0; 32]; 2];
+    for block in &mut blocks {
+        os.try_fill_bytes(block)?;
+        if block.iter().all(|&byte| byte == block[0]) {
+            return Err(RngSelfTestFailed);
+        }
+    }
+    if blocks[0] == blocks[1] {
+        return Err(RngSelfTestFailed);
+    }
+    Ok(())
+}
+
+/// Runs self_test, then seeds the DRBG from the operating system's entropy
+/// source, so that randomness is drawn from the DRBG from then on. Calling
+/// this again reseeds the DRBG.
+///
+/// # Returns
+///
+/// * Ok(()) on success.
+///
+/// * Err(::Error) on failure, which indicates that self_test failed or the
+/// seeding failed, in which case the entropy source continues to be used
+/// directly.
+///
+/// # Note
+///
+/// The DRBG is shared by the whole process, and is not reseeded after a fork,
+/// so processes which fork must call this again in the child.
+pub fn enable_drbg() -> ::Result<()> {
+    self_test()?;
+    let drbg = ChaChaRng::from_rng(OsRng::new()?)?;
+    *lock_drbg() = Some(drbg);
+    Ok(())
+}
+
+/// Returns whether randomness is currently drawn from the DRBG
+pub fn drbg_enabled() -> bool {
+    lock_drbg().is_some()
+}
+
+/// The source of every random value in the crate, which is either the
+/// operating system's entropy source or the DRBG seeded from it.
+pub(crate) enum SecureRng {
+    Os(OsRng),
+    Drbg,
+}
+
+/// Acquires the source of randomness, replacing each call of OsRng::new.
+///
+/// pub(crate) as used throughout the crate
+///
+/// # Returns
+///
+/// * Ok(SecureRng) on success.
+///
+/// * Err(::Error) on failure, which indicates the failure to initiate the
+/// operating system's entropy source.
+pub(crate) fn try_new() -> ::Result<SecureRng> {
+    if drbg_enabled() {
+        Ok(SecureRng::Drbg)
+    } else {
+        Ok(SecureRng::Os(OsRng::new()?))
+    }
+}
+
+/// Acquires the source of randomness as try_new does, for the few callers
+/// which do not return a Result, such as Mnemonic::generate.
+///
+/// pub(crate) as used in mnemonic.rs
+///
+/// # Panics
+///
+/// If the operating system's entropy source cannot be opened, as no keys or
+/// signatures can be generated without it. Applications check for this at
+/// startup with self_test, as an error rather than a panic, after which this
+/// does not panic.
+pub(crate) fn new() -> SecureRng {
+    try_new().expect("the entropy source failed to open")
+}
+
+impl RngCore for SecureRng {
+    fn next_u32(&mut self) -> u32 {
+        match *self {
+            SecureRng::Os(ref mut os) => os.next_u32(),
+            SecureRng::Drbg => with_drbg(|drbg| drbg.next_u32()),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match *self {
+            SecureRng::Os(ref mut os) => os.next_u64(),
+            SecureRng::Drbg => with_drbg(|drbg| drbg.next_u64()),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match *self {
+            SecureRng::Os(ref mut os) => os.fill_bytes(dest),
+            SecureRng::Drbg => with_drbg(|drbg| drbg.fill_bytes(dest)),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), ::rand::Error> {
+        match *self {
+            SecureRng::Os(ref mut os) => os.try_fill_bytes(dest),
+            SecureRng::Drbg => with_drbg(|drbg| drbg.try_fill_bytes(dest)),
+        }
+    }
+}
+
+impl CryptoRng for SecureRng {}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Locks the DRBG. The DRBG is replaced whole, so it remains usable even if
+/// another thread panicked while holding the lock.
+fn lock_drbg() -> MutexGuard<'static, Option<ChaChaRng>> {
+    DRBG.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Draws from the DRBG, which is only called by a SecureRng::Drbg, so the
+/// DRBG has been seeded
+fn with_drbg<T, F>(f: F) -> T
+where
+    F: FnOnce(&mut ChaChaRng) -> T,
+{
+    f(lock_drbg()
+        .as_mut()
+        .expect("SecureRng::Drbg is only created once the DRBG is seeded"))
+}
@@ -0,0 +1,312 @@
+//! RSA blind signatures, as specified by RFC 9474 (RSABSSA)
+//!
+//! This implements the RSABSSA-SHA384-PSS variants, with a 48 byte salt, for
+//! interoperability with Privacy Pass issuers and other deployments that have
+//! standardized on RSA blind signatures. The signatures produced are ordinary
+//! RSASSA-PSS signatures, verifiable by any RSASSA-PSS verifier.
+//!
+//! 1. The requester prepares its message (for the randomized variant) and
+//!    blinds it with RsaBlindRequest::new, sending the blinded message.
+//! 2. The signer signs the blinded message with blind_sign.
+//! 3. The requester finalizes the blind signature with gen_signed_msg.
+//!
+//! Keys are those of the rsa crate. Only moduli of at least 2048 bits should
+//! be used.
+//!
+//! # Note
+//!
+//! As with the ECC protocol, this **does not** include **any** networking
+//! code.
+use num_bigint_dig::{BigUint, ModInverse};
+use rand::RngCore;
+use rand_core_rsa;
+use rng::{self, SecureRng};
+use rsa::{internals, PublicKeyParts, RSAPrivateKey, RSAPublicKey};
+use sha2::{Digest, Sha384};
+use subtle::ConstantTimeEq;
+use Error::{RsaEncodingFailed, RsaInputInvalid, RsaSignatureInvalid};
+
+/// The length of the SHA-384 output, which is also the length of the salt
+const HASH_LEN: usize = 48;
+
+/// The length of the random prefix added by prepare
+const PREFIX_LEN: usize = 32;
+
+/// Prepares a message for the randomized variant, RSABSSA-SHA384-PSS-Randomized,
+/// by prepending 32 random bytes. The prepared message is what is blinded and
+/// what the final signature authenticates. For the deterministic variant the
+/// message is used as is.
+///
+/// # Returns
+///
+/// * Ok(Vec<u8>) on success, which is the prepared message.
+///
+/// * Err(::Error) on failure, which can only be the failure to initiate the
+/// internal RNG.
+pub fn prepare(msg: &[u8]) -> ::Result<Vec<u8>> {
+    let mut prepared = vec![0; PREFIX_LEN];
+    rng::try_new()?.fill_bytes(&mut prepared);
+    prepared.extend_from_slice(msg);
+    Ok(prepared)
+}
+
+/// For managing the requester steps of the RSA blind signature protocol.
+pub struct RsaBlindRequest {
+    pub_key: RSAPublicKey,
+    msg: Vec<u8>,
+    inv: BigUint,
+}
+
+impl RsaBlindRequest {
+    /// Blinds the (prepared) message for signing with the provided public key.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((Vec<u8>, RsaBlindRequest)) on success. The Vec<u8> is the blinded
+    /// message, of the length of the modulus, which is sent to the signer.
+    ///
+    /// * Err(::Error) on failure, which indicates that the key was too small
+    /// for the encoding, the failure to initiate the RNG, or (with negligible
+    /// probability) that the encoded message was not invertible.
+    ///
+    /// # Mathematics
+    ///
+    /// * m = EMSA-PSS-ENCODE(msg)
+    /// * z = m * r^e mod n
+    /// * r = A randomly generated value by the requester, coprime with n
+    pub fn new(pub_key: &RSAPublicKey, msg: &[u8]) -> ::Result<(Vec<u8>, Self)> {
+        let mut rng = rng::try_new()?;
+        let n = pub_key.n();
+        let mod_len = modulus_len(n);
+        let encoded = pss_encode(msg, n.bits() - 1, &mut rng)?;
+        let m = BigUint::from_bytes_be(&encoded);
+        if invert(&m, n).is_none() {
+            return Err(RsaInputInvalid);
+        }
+
+        // Sampling 128 bits more than the modulus makes the bias negligible.
+        let mut bytes = vec![0; mod_len + 16];
+        let (r, inv) = loop {
+            rng.fill_bytes(&mut bytes);
+            let r = BigUint::from_bytes_be(&bytes) % n;
+            if let Some(inv) = invert(&r, n) {
+                break (r, inv);
+            }
+        };
+        let z = (m * r.modpow(pub_key.e(), n)) % n;
+        let request = RsaBlindRequest {
+            pub_key: pub_key.clone(),
+            msg: msg.to_vec(),
+            inv,
+        };
+        Ok((i2osp(&z, mod_len)?, request))
+    }
+
+    /// Input the blind signature from the signer, consumes self and returns
+    /// the final signature. Unlike the ECC protocol, the signature is verified
+    /// before it is returned, as required by RFC 9474.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Vec<u8>) on success, which is the RSASSA-PSS signature on the
+    /// (prepared) message.
+    ///
+    /// * Err(::Error) on failure, which indicates that the blind signature was
+    /// malformed or did not finalize into a valid signature.
+    ///
+    /// # Mathematics
+    ///
+    /// * S = z^d * r^-1 mod n
+    pub fn gen_signed_msg(self, blind_sig: &[u8]) -> ::Result<Vec<u8>> {
+        let n = self.pub_key.n();
+        let mod_len = modulus_len(n);
+        if blind_sig.len() != mod_len {
+            return Err(RsaInputInvalid);
+        }
+        let z = BigUint::from_bytes_be(blind_sig);
+        let sig = i2osp(&((z * &self.inv) % n), mod_len)?;
+        if !verify(&self.pub_key, &self.msg, &sig) {
+            return Err(RsaSignatureInvalid);
+        }
+        Ok(sig)
+    }
+}
+
+/// Signs a blinded message, for the signer. The private key operation is that
+/// of the rsa crate, with the input blinded by a fresh random factor so that
+/// its timing is independent of the attacker chosen message, and using CRT
+/// when the key has been precomputed. The signature is checked before it is
+/// returned, so that a faulty computation never leaks the private key.
+///
+/// # Returns
+///
+/// * Ok(Vec<u8>) on success, which is the blind signature sent back to the
+/// requester.
+///
+/// * Err(::Error) on failure, which indicates that the blinded message was
+/// malformed, that the signature failed its check, or the failure to
+/// initiate the internal RNG.
+pub fn blind_sign(priv_key: &RSAPrivateKey, blinded_msg: &[u8]) -> ::Result<Vec<u8>> {
+    let n = priv_key.n();
+    let mod_len = modulus_len(n);
+    if blinded_msg.len() != mod_len {
+        return Err(RsaInputInvalid);
+    }
+    let m = BigUint::from_bytes_be(blinded_msg);
+    if &m >= n {
+        return Err(RsaInputInvalid);
+    }
+    let mut rng = rng::try_new()?;
+    let s = internals::decrypt_and_check(Some(&mut RsaRng(&mut rng)), priv_key, &m)
+        .map_err(|_| RsaSignatureInvalid)?;
+    if s.modpow(priv_key.e(), n) != m {
+        return Err(RsaSignatureInvalid);
+    }
+    i2osp(&s, mod_len)
+}
+
+/// Verifies an RSASSA-PSS signature on the (prepared) message.
+///
+/// # Returns
+///
+/// * True to indicate that the signature is valid.
+///
+/// * False to indicate that the signature is not valid.
+pub fn verify(pub_key: &RSAPublicKey, msg: &[u8], sig: &[u8]) -> bool {
+    let n = pub_key.n();
+    if sig.len() != modulus_len(n) {
+        return false;
+    }
+    let s = BigUint::from_bytes_be(sig);
+    if &s >= n {
+        return false;
+    }
+    let em_bits = n.bits() - 1;
+    match i2osp(&s.modpow(pub_key.e(), n), (em_bits + 7) / 8) {
+        Ok(encoded) => pss_verify(msg, &encoded, em_bits),
+        Err(_) => false,
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Adapts SecureRng to the rand_core version the rsa crate draws its
+/// blinding factors with
+struct RsaRng<'a>(&'a mut SecureRng);
+
+impl<'a> rand_core_rsa::RngCore for RsaRng<'a> {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core_rsa::Error> {
+        self.0.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<'a> rand_core_rsa::CryptoRng for RsaRng<'a> {}
+
+/// Returns the length of the modulus in bytes
+fn modulus_len(n: &BigUint) -> usize {
+    (n.bits() + 7) / 8
+}
+
+/// Returns the inverse of x modulo n, if there is one
+fn invert(x: &BigUint, n: &BigUint) -> Option<BigUint> {
+    x.mod_inverse(n).and_then(|inv| inv.to_biguint())
+}
+
+/// Encodes the integer as a big endian byte string of the provided length,
+/// erroring if it is too large.
+fn i2osp(x: &BigUint, len: usize) -> ::Result<Vec<u8>> {
+    let bytes = x.to_bytes_be();
+    if bytes.len() > len {
+        return Err(RsaEncodingFailed);
+    }
+    let mut out = vec![0; len - bytes.len()];
+    out.extend_from_slice(&bytes);
+    Ok(out)
+}
+
+/// Returns the SHA-384 hash of the concatenated inputs
+fn hash(inputs: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = Sha384::new();
+    for input in inputs {
+        hasher.update(input);
+    }
+    hasher.finalize().to_vec()
+}
+
+/// MGF1 with SHA-384, producing a mask of the provided length
+fn mgf1(seed: &[u8], len: usize) -> Vec<u8> {
+    let mut mask = Vec::with_capacity(len + HASH_LEN);
+    let mut counter: u32 = 0;
+    while mask.len() < len {
+        mask.extend_from_slice(&hash(&[seed, &counter.to_be_bytes()]));
+        counter += 1;
+    }
+    mask.truncate(len);
+    mask
+}
+
+/// EMSA-PSS-ENCODE from RFC 8017, with SHA-384, MGF1 and a 48 byte salt
+fn pss_encode(msg: &[u8], em_bits: usize, rng: &mut SecureRng) -> ::Result<Vec<u8>> {
+    let em_len = (em_bits + 7) / 8;
+    if em_len < 2 * HASH_LEN + 2 {
+        return Err(RsaEncodingFailed);
+    }
+    let mut salt = [0; HASH_LEN];
+    rng.fill_bytes(&mut salt);
+    let h = hash(&[&[0; 8], &hash(&[msg]), &salt]);
+
+    // DB = PS || 0x01 || salt, masked with MGF1(H)
+    let mut db = vec![0; em_len - 2 * HASH_LEN - 2];
+    db.push(0x01);
+    db.extend_from_slice(&salt);
+    for (byte, mask) in db.iter_mut().zip(mgf1(&h, em_len - HASH_LEN - 1)) {
+        *byte ^= mask;
+    }
+    db[0] &= 0xff >> (8 * em_len - em_bits);
+
+    let mut encoded = db;
+    encoded.extend_from_slice(&h);
+    encoded.push(0xbc);
+    Ok(encoded)
+}
+
+/// EMSA-PSS-VERIFY from RFC 8017, with SHA-384, MGF1 and a 48 byte salt
+fn pss_verify(msg: &[u8], encoded: &[u8], em_bits: usize) -> bool {
+    let em_len = encoded.len();
+    if em_len < 2 * HASH_LEN + 2 || encoded[em_len - 1] != 0xbc {
+        return false;
+    }
+    let (masked_db, h) = encoded[..em_len - 1].split_at(em_len - HASH_LEN - 1);
+    let top_mask = 0xff >> (8 * em_len - em_bits);
+    if masked_db[0] & !top_mask != 0 {
+        return false;
+    }
+    let mut db: Vec<u8> = masked_db
+        .iter()
+        .zip(mgf1(h, masked_db.len()))
+        .map(|(byte, mask)| byte ^ mask)
+        .collect();
+    db[0] &= top_mask;
+
+    // DB must be PS || 0x01 || salt, with PS all zeros
+    let ps_len = em_len - 2 * HASH_LEN - 2;
+    if db[..ps_len].iter().any(|&byte| byte != 0) || db[ps_len] != 0x01 {
+        return false;
+    }
+    let salt = &db[ps_len + 1..];
+    let expected = hash(&[&[0; 8], &hash(&[msg]), salt]);
+    expected.ct_eq(h).unwrap_u8() == 1
+}
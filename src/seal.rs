@@ -0,0 +1,91 @@
+//! Crate internal authenticated encryption of secret state
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use rng;
+use Error::DecryptionFailed;
+#[cfg(feature = "sealing")]
+use Error::SealedStateMalformed;
+
+/// The current version of the sealed state format
+#[cfg(feature = "sealing")]
+const SEALED_VERSION: u8 = 1;
+
+/// The length of the sealed state header, which is version || kind || nonce
+#[cfg(feature = "sealing")]
+const SEALED_HEADER_LEN: usize = 2 + NONCE_LEN;
+
+/// The length of the AEAD nonce
+pub(crate) const NONCE_LEN: usize = 12;
+
+/// The length of the AEAD authentication tag
+pub(crate) const TAG_LEN: usize = 16;
+
+/// Generates a random nonce for use with seal
+pub(crate) fn random_nonce() -> ::Result<[u8; NONCE_LEN]> {
+    let mut nonce = [0; NONCE_LEN];
+    rng::try_new()?.fill_bytes(&mut nonce);
+    Ok(nonce)
+}
+
+/// Encrypts and authenticates the plaintext, additionally authenticating the
+/// associated data, returning ciphertext || tag.
+pub(crate) fn seal(
+    key: &[u8; 32],
+    nonce: &[u8; NONCE_LEN],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Vec<u8> {
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(nonce), Payload { msg: plaintext, aad })
+        .expect("encryption only fails for plaintexts far larger than any crate state")
+}
+
+/// Authenticates and decrypts ciphertext || tag, erroring if either the
+/// ciphertext or the associated data were altered or the key is wrong.
+pub(crate) fn open(
+    key: &[u8; 32],
+    nonce: &[u8; NONCE_LEN],
+    aad: &[u8],
+    sealed: &[u8],
+) -> ::Result<Vec<u8>> {
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: sealed, aad })
+        .map_err(|_| DecryptionFailed)
+}
+
+/// Seals the secret state of the provided kind, producing the self describing
+/// blob version || kind || nonce || ciphertext || tag. The header is
+/// authenticated, so a blob of one kind cannot be unsealed as another.
+#[cfg(feature = "sealing")]
+pub(crate) fn seal_state(key: &[u8; 32], kind: u8, state: &[u8]) -> ::Result<Vec<u8>> {
+    let nonce = random_nonce()?;
+    let mut blob = Vec::with_capacity(SEALED_HEADER_LEN + state.len() + TAG_LEN);
+    blob.push(SEALED_VERSION);
+    blob.push(kind);
+    blob.extend_from_slice(&nonce);
+    let sealed = seal(key, &nonce, &blob, state);
+    blob.extend_from_slice(&sealed);
+    Ok(blob)
+}
+
+/// Unseals secret state of the provided kind and length from a blob produced by
+/// seal_state.
+#[cfg(feature = "sealing")]
+pub(crate) fn open_state(
+    key: &[u8; 32],
+    kind: u8,
+    len: usize,
+    blob: &[u8],
+) -> ::Result<Vec<u8>> {
+    if blob.len() != SEALED_HEADER_LEN + len + TAG_LEN
+        || blob[0] != SEALED_VERSION
+        || blob[1] != kind
+    {
+        return Err(SealedStateMalformed);
+    }
+    let (header, sealed) = blob.split_at(SEALED_HEADER_LEN);
+    let mut nonce = [0; NONCE_LEN];
+    nonce.copy_from_slice(&header[2..]);
+    open(key, &nonce, header, sealed)
+}
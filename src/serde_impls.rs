@@ -0,0 +1,100 @@
+//! Serde support for the wired types
+//!
+//! Human readable formats such as JSON and TOML represent the wired types as
+//! lowercase hex strings, while binary formats represent them as byte strings.
+//! Deserialization from human readable formats accepts hex of either case.
+use encoding;
+use keypair::{WiredPrivateKey, WiredPublicKey};
+use request::WiredEPrime;
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+use session::{WiredRPrime, WiredSPrime};
+use signature::{CompactSignature, WiredUnblindedSigData};
+use std::fmt;
+
+/// Implements Serialize and Deserialize for a wired type
+macro_rules! impl_serde_wired {
+    ($name:ident, $len:expr) => {
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                if serializer.is_human_readable() {
+                    serializer.serialize_str(&encoding::to_hex(self.as_bytes()))
+                } else {
+                    serializer.serialize_bytes(self.as_bytes())
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let visitor = WiredVisitor { len: $len };
+                let bytes = if deserializer.is_human_readable() {
+                    deserializer.deserialize_str(visitor)?
+                } else {
+                    deserializer.deserialize_bytes(visitor)?
+                };
+                $name::from_bytes(&bytes).map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+impl_serde_wired!(WiredPublicKey, 32);
+impl_serde_wired!(WiredPrivateKey, 32);
+impl_serde_wired!(WiredRPrime, 32);
+impl_serde_wired!(WiredEPrime, 32);
+impl_serde_wired!(WiredSPrime, 32);
+impl_serde_wired!(WiredUnblindedSigData, 96);
+impl_serde_wired!(CompactSignature, 64);
+
+/// Visits either a hex string or a byte string, producing the bytes. The
+/// length is checked by the wired type after visiting.
+struct WiredVisitor {
+    len: usize,
+}
+
+impl<'de> Visitor<'de> for WiredVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} bytes as a hex string or byte string", self.len)
+    }
+
+    fn visit_str<E>(self, hex: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        encoding::from_hex(hex).map_err(E::custom)
+    }
+
+    fn visit_bytes<E>(self, bytes: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(bytes.to_vec())
+    }
+
+    fn visit_byte_buf<E>(self, bytes: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(bytes)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut bytes = Vec::with_capacity(self.len);
+        while let Some(b) = seq.next_element()? {
+            bytes.push(b);
+        }
+        Ok(bytes)
+    }
+}
@@ -5,14 +5,86 @@
 //! request for protocol initiation. Also, the request for protocol initiation
 //! is neither defined nor implemented by this crate.
 
-use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, scalar::Scalar};
-use rand::OsRng;
-use Error::WiredScalarMalformed;
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT, ristretto::RistrettoPoint, scalar::Scalar,
+};
+use digest::Digest;
+use encoding;
+use envelope::PayloadType;
+use keypair::{BlindSignerKey, Redacted, SigningKey};
+use rand::{CryptoRng, RngCore};
+use request::{self, WiredEPrime};
+use rng;
+use spent::{Nullifier, SpentStore};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use typenum::U64;
+use wipe;
+use Error::{EPrimeReplayed, SessionLimitReached, SessionUnknown, WiredScalarMalformed};
+#[cfg(feature = "async")]
+use keypair::{AsyncBlindSigner, SignFuture};
+#[cfg(feature = "sealing")]
+use seal;
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+#[cfg(feature = "sealing")]
+use Error::SessionDuplicate;
+
+/// The default cap on concurrently open sessions used by SessionManager::new.
+///
+/// Running ℓ sessions under one key concurrently allows forging ℓ + 1
+/// signatures, via Wagner's generalized birthday algorithm in roughly
+/// 2^(256 / (1 + log2 ℓ)) work, or in polynomial time once ℓ exceeds 256 (the
+/// ROS attack). Only sequential sessions avoid this entirely, so that is the
+/// default.
+pub const DEFAULT_MAX_OPEN_SESSIONS: usize = 1;
+
+/// The default time for which a session stays open before it is evicted,
+/// used by SessionManager::new. This is in the unit of time of `now`, and is
+/// one minute when `now` is in seconds.
+///
+/// As the default cap is a single open session, a session that never expired
+/// would block all signing once abandoned by its requester.
+pub const DEFAULT_SESSION_TTL: u64 = 60;
+
+/// The kind byte identifying sealed BlindSession state
+#[cfg(feature = "sealing")]
+const SEALED_SESSION_KIND: u8 = 1;
+
+/// The kind byte identifying sealed BlindSession state bound to a context
+#[cfg(feature = "sealing")]
+const SEALED_CONTEXT_SESSION_KIND: u8 = 3;
 
 /// For managing the signer side response to incoming requests for blind
 /// signatures. How the actual requests come in is orthogonal to this crate.
+///
+/// The nonce k is wiped from memory when the session is dropped, whether it
+/// was signed or abandoned, as k reveals the private key through
+/// S' = Xs*e' + k.
 pub struct BlindSession {
     k: Scalar,
+    // The context tweak t added to e' before signing, zero without a context
+    t: Scalar,
+}
+
+impl fmt::Debug for BlindSession {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlindSession")
+            .field("k", &Redacted)
+            .field("t", &encoding::to_hex(self.t.as_bytes()))
+            .finish()
+    }
+}
+
+impl Drop for BlindSession {
+    fn drop(&mut self) {
+        wipe::wipe_scalar(&mut self.k);
+    }
 }
 
 impl BlindSession {
@@ -21,8 +93,8 @@ impl BlindSession {
     ///
     /// # Returns
     ///
-    /// * Ok( ([u8; 32], BlindSession) ) on success, with the [u8; 32] being the
-    /// value R' for sending to the requester, and the BlindSession struct
+    /// * Ok( (WiredRPrime, BlindSession) ) on success, with the WiredRPrime being
+    /// the value R' for sending to the requester, and the BlindSession struct
     /// supporting the sign_ep() method for completing the protocol (upon the
     /// receipt of the E' response from the requester).
     ///
@@ -34,41 +106,543 @@ impl BlindSession {
     /// * R' = kP
     /// * k = A randomly generated scalar by the signer
     /// * P = An ECC Generator Point
-    pub fn new() -> ::Result<([u8; 32], Self)> {
-        let mut rng = OsRng::new()?;
-        let k = Scalar::random(&mut rng);
-        let rp = (k * RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
-        Ok((rp, Self { k }))
+    pub fn new() -> ::Result<(WiredRPrime, Self)> {
+        Ok(Self::new_with_rng(&mut rng::try_new()?))
+    }
+
+    /// The same as new, but drawing k from the provided RNG rather than the
+    /// internal one.
+    ///
+    /// # Security
+    ///
+    /// The RNG must be cryptographically secure, as its CryptoRng
+    /// implementation asserts, since a predictable k reveals the private key
+    /// through S' = Xs*e' + k.
+    pub fn new_with_rng<R>(rng: &mut R) -> (WiredRPrime, Self)
+    where
+        R: RngCore + CryptoRng,
+    {
+        Self::with_k(Scalar::random(rng))
+    }
+
+    /// The same as new, but with the provided k rather than a random one.
+    ///
+    /// pub(crate) as used in testvectors.rs for replaying known answers
+    pub(crate) fn with_k(k: Scalar) -> (WiredRPrime, Self) {
+        let rp = WiredRPrime((k * RISTRETTO_BASEPOINT_POINT).compress().to_bytes());
+        let t = Scalar::zero();
+        (rp, Self { k, t })
+    }
+
+    /// The same as new, but binding the session to a context agreed with the
+    /// requester, such as an application defined session or transcript id.
+    /// The requester must use BlindRequest::new_with_context with the same
+    /// context, and an S' produced in this session unblinds to a valid
+    /// signature only for a request made in the same context.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for hashing the context
+    ///
+    /// # Returns
+    ///
+    /// * Ok( (WiredRPrime, BlindSession) ) on success, as for new.
+    ///
+    /// * Err(::Error) variant on failure, which is only due to the
+    /// failure to initiate the internal random number generator.
+    ///
+    /// # Mathematics
+    ///
+    /// * t = H("blindsign context" || R' || context)
+    /// * S' = Xs(e' + t) + k, so that S'P = e'Qs + (R' + tQs)
+    pub fn new_with_context<H>(context: &[u8]) -> ::Result<(WiredRPrime, Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        Ok(Self::new_with_context_with_rng::<H, _>(context, &mut rng::try_new()?))
+    }
+
+    /// The same as new_with_context, but drawing k from the provided RNG, as
+    /// with new_with_rng.
+    pub fn new_with_context_with_rng<H, R>(context: &[u8], rng: &mut R) -> (WiredRPrime, Self)
+    where
+        H: Digest<OutputSize = U64> + Default,
+        R: RngCore + CryptoRng,
+    {
+        let (rp, mut session) = Self::new_with_rng(rng);
+        session.t = request::generate_context_t::<H>(&rp, context);
+        (rp, session)
     }
 
     /// Consumes the session and returns the generated blind signature.
     ///
     /// # Arguments
     ///
-    /// * 'ep' - A reference to a 32 byte scalar represented as a [u8; 32]. This
-    /// scalar is received from the requester in some manner.
+    /// * 'ep' - A reference to the wired e' value, which was validated as a
+    /// canonical scalar on construction. This is received from the requester
+    /// in some manner.
     ///
-    /// * 'xs' - The private key componenet of the associated BlindKeypair
-    /// component, in internal Scalar form. This is used for creating signatures
-    /// which can be authenticated with the associated public key.
+    /// * 'xs' - The signing key half of the associated BlindKeypair, or any
+    /// other BlindSignerKey holding it. This is used for creating signatures
+    /// which can be authenticated with the associated verifying key.
     ///
     /// # Returns
     ///
-    /// * Ok([u8; 32]) on success, representing the completed blind signature
+    /// * Ok(WiredSPrime) on success, representing the completed blind signature
     /// value S'.
     ///
-    /// * Err(errors::BlindErrors) variant on error. Only errors if the
-    /// requester provided a malformed scalar value ep.
+    /// * Err(::Error) variant on error. Only errors if the key backend failed.
     ///
     /// # Mathematics
     ///
     /// * S' = Xs*e' + k
     /// * e' = requester calculated e' value, received by signer
     /// * k  = randomly generated number by the signer
-    pub fn sign_ep(self, ep: &[u8; 32], xs: Scalar) -> ::Result<[u8; 32]> {
-        Ok(
-            (xs * Scalar::from_canonical_bytes(*ep).ok_or(WiredScalarMalformed)? + self.k)
-                .to_bytes(),
-        )
+    ///
+    /// # Security
+    ///
+    /// With a SigningKey, the multiplication and addition involving Xs and k
+    /// are constant time scalar arithmetic, so the time taken does not depend
+    /// on Xs, k or the attacker supplied e'. Only the canonicity check of e',
+    /// made when the WiredEPrime was constructed, may branch, and e' is
+    /// public. The timing-harness feature provides timing::measure_sign_ep for
+    /// checking this on a given platform.
+    pub fn sign_ep<K>(mut self, ep: &WiredEPrime, xs: &K) -> ::Result<WiredSPrime>
+    where
+        K: BlindSignerKey + ?Sized,
+    {
+        let sp = xs.sign_blinded(&(ep.scalar() + self.t), &self.k);
+        // k must never be used twice, so is wiped before S' is even returned
+        wipe::wipe_scalar(&mut self.k);
+        Ok(WiredSPrime(sp?.to_bytes()))
     }
+
+    /// The same as sign_ep, but signing with an AsyncBlindSigner. The copy of
+    /// k passed to the signer is only as protected as its future.
+    #[cfg(feature = "async")]
+    pub fn sign_ep_async<'a, K>(self, ep: &WiredEPrime, xs: &'a K) -> SPrimeFuture<'a>
+    where
+        K: AsyncBlindSigner + ?Sized,
+    {
+        SPrimeFuture {
+            inner: Ok(xs.sign_blinded(ep.scalar() + self.t, self.k)),
+        }
+    }
+
+    /// Seals the session state along with its session id, encrypting it with
+    /// the provided 32 byte key so that it can be persisted (for example across
+    /// a signer restart) without k ever being exposed in plaintext.
+    ///
+    /// # Warning
+    ///
+    /// A sealed session must be unsealed **at most once**, and the blob must
+    /// be destroyed once the session has been finished. Finishing the same
+    /// session twice signs two different e' values with the same k, which
+    /// reveals the private key.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Vec<u8>) on success, which is the sealed blob.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    #[cfg(feature = "sealing")]
+    pub fn seal(&self, key: &[u8; 32], id: &SessionId) -> ::Result<Vec<u8>> {
+        let mut state = [0; 80];
+        state[0..16].copy_from_slice(&id.0);
+        state[16..48].copy_from_slice(self.k.as_bytes());
+        // Sessions without a context keep the original format
+        let sealed = if self.t == Scalar::zero() {
+            seal::seal_state(key, SEALED_SESSION_KIND, &state[0..48])
+        } else {
+            state[48..80].copy_from_slice(self.t.as_bytes());
+            seal::seal_state(key, SEALED_CONTEXT_SESSION_KIND, &state)
+        };
+        wipe::wipe_bytes(&mut state);
+        sealed
+    }
+
+    /// Unseals a session and its session id from a blob produced by seal.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((SessionId, BlindSession)) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the blob was malformed,
+    /// that the key was wrong, or that the blob was tampered with.
+    #[cfg(feature = "sealing")]
+    pub fn unseal(key: &[u8; 32], blob: &[u8]) -> ::Result<(SessionId, Self)> {
+        let (kind, len) = match blob.get(1) {
+            Some(&SEALED_CONTEXT_SESSION_KIND) => (SEALED_CONTEXT_SESSION_KIND, 80),
+            _ => (SEALED_SESSION_KIND, 48),
+        };
+        let mut state = seal::open_state(key, kind, len, blob)?;
+        let mut k = [0; 32];
+        let mut t = [0; 32];
+        k.copy_from_slice(&state[16..48]);
+        if len == 80 {
+            t.copy_from_slice(&state[48..80]);
+        }
+        let id = SessionId::from_bytes(&state[0..16]);
+        let session = Scalar::from_canonical_bytes(k).and_then(|k| {
+            let t = Scalar::from_canonical_bytes(t)?;
+            Some(BlindSession { k, t })
+        });
+        wipe::wipe_bytes(&mut k);
+        wipe::wipe_bytes(&mut state);
+        Ok((id?, session.ok_or(WiredScalarMalformed)?))
+    }
+}
+
+/// An opaque identifier of a pending signer side session, which is sent to the
+/// requester alongside R' and returned by the requester alongside e'.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SessionId(pub [u8; 16]);
+
+impl_wired_bytes!(SessionId, 16);
+
+/// Manages the pending signer side sessions for a signing key, so that a
+/// server can start sessions as requests for protocol initiation come in and
+/// finish them as e' values arrive, without tracking the BlindSession state
+/// itself. The manager may be shared between threads.
+///
+/// Every session is single use: it is removed as soon as finish is called for
+/// it, whether or not signing succeeds, so k is never used for more than one
+/// signature.
+///
+/// The number of concurrently open sessions is capped, as concurrent sessions
+/// enable forgeries; see DEFAULT_MAX_OPEN_SESSIONS. A single SessionManager
+/// should be used per signing key for the cap to be meaningful. Each session
+/// stays open for at most the session ttl, after which it is evicted by the
+/// next start, so that abandoned sessions do not hold the cap forever.
+///
+/// The manager signs with a SigningKey by default, or with any other
+/// BlindSignerKey, such as one backed by an HSM.
+///
+/// Optionally, with_replay_store makes the manager refuse to sign an e' it
+/// has signed before in any session, as repeat signing of an identical
+/// challenge is almost always a client bug or an attack probe.
+pub struct SessionManager<K = SigningKey> {
+    xs: K,
+    max_open: usize,
+    ttl: u64,
+    // Each pending session along with the time after which it is evicted
+    sessions: Mutex<HashMap<SessionId, (BlindSession, u64)>>,
+    replay: Option<Box<dyn SpentStore + Send + Sync>>,
+}
+
+impl<K> SessionManager<K> {
+    /// Creates a new SessionManager with no pending sessions, which signs with
+    /// the provided signing key and allows DEFAULT_MAX_OPEN_SESSIONS open
+    /// sessions, each for DEFAULT_SESSION_TTL.
+    pub fn new(xs: K) -> Self {
+        Self::with_max_open(xs, DEFAULT_MAX_OPEN_SESSIONS)
+    }
+
+    /// The same as new, but with a custom cap on concurrently open sessions.
+    /// Raising the cap trades forgery resistance for throughput, as described
+    /// for DEFAULT_MAX_OPEN_SESSIONS.
+    pub fn with_max_open(xs: K, max_open: usize) -> Self {
+        SessionManager {
+            xs,
+            max_open,
+            ttl: DEFAULT_SESSION_TTL,
+            sessions: Mutex::new(HashMap::new()),
+            replay: None,
+        }
+    }
+
+    /// Keeps each session open for the provided ttl rather than
+    /// DEFAULT_SESSION_TTL, in the unit of time of `now`.
+    pub fn with_ttl(mut self, ttl: u64) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Records every e' signed in the provided store, refusing to sign any e'
+    /// already recorded. Each e' is recorded as a Nullifier of its wired
+    /// bytes, so the store must be dedicated to this manager's signing key
+    /// rather than shared with redemption or with managers for other keys.
+    pub fn with_replay_store<S>(mut self, store: S) -> Self
+    where
+        S: SpentStore + Send + Sync + 'static,
+    {
+        self.replay = Some(Box::new(store));
+        self
+    }
+
+    /// Returns the cap on concurrently open sessions
+    pub fn max_open(&self) -> usize {
+        self.max_open
+    }
+
+    /// Returns the time for which each session stays open
+    pub fn ttl(&self) -> u64 {
+        self.ttl
+    }
+
+    /// Starts a new session at the provided time, minting a random session id
+    /// for it. Sessions whose ttl has passed are evicted first, so do not
+    /// count towards the cap.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((SessionId, WiredRPrime)) on success, with the WiredRPrime being the
+    /// value R' for sending to the requester along with the SessionId.
+    ///
+    /// * Err(::Error) on failure, which indicates either that the cap on open
+    /// sessions has been reached, or the failure to initiate the internal
+    /// random number generator.
+    ///
+    /// # Note
+    ///
+    /// `now` is expressed in whatever unit of time the caller uses, as with
+    /// KeySet, and must be in the same unit as the ttl.
+    pub fn start(&self, now: u64) -> ::Result<(SessionId, WiredRPrime)> {
+        let mut rng = rng::try_new()?;
+        let mut sessions = self.sessions();
+        evict_expired(&mut sessions, now);
+        if sessions.len() >= self.max_open {
+            return Err(SessionLimitReached);
+        }
+        let (rp, session) = BlindSession::new()?;
+        let mut id = SessionId([0; 16]);
+        loop {
+            rng.fill_bytes(&mut id.0);
+            if !sessions.contains_key(&id) {
+                break;
+            }
+        }
+        sessions.insert(id, (session, now.saturating_add(self.ttl)));
+        Ok((id, rp))
+    }
+
+    /// Aborts the session with the provided id, returning true if there was
+    /// such a pending session.
+    pub fn abort(&self, id: &SessionId) -> bool {
+        self.sessions().remove(id).is_some()
+    }
+
+    /// Returns the number of pending sessions, including any whose ttl has
+    /// passed but which are not yet evicted
+    pub fn pending(&self) -> usize {
+        self.sessions().len()
+    }
+
+    /// Removes every pending session, returning each sealed with the provided
+    /// key, for example before the signer shuts down. The sessions are removed
+    /// so that they cannot be finished both here and after being imported
+    /// elsewhere; see the warning on BlindSession::seal.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Vec<Vec<u8>>) on success, with one sealed blob per session.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG. No sessions are removed on failure.
+    #[cfg(feature = "sealing")]
+    pub fn export_sealed(&self, key: &[u8; 32]) -> ::Result<Vec<Vec<u8>>> {
+        let mut sessions = self.sessions();
+        let blobs = sessions
+            .iter()
+            .map(|(id, &(ref session, _))| session.seal(key, id))
+            .collect::<::Result<Vec<Vec<u8>>>>()?;
+        sessions.clear();
+        Ok(blobs)
+    }
+
+    /// Imports a session sealed with the provided key as a pending session,
+    /// open for the ttl from the provided time. Sessions whose ttl has passed
+    /// are evicted first, as for start.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(SessionId) on success, with the SessionId being that of the
+    /// imported session.
+    ///
+    /// * Err(::Error) on failure, which indicates that the blob could not be
+    /// unsealed, that a session with the same id is already pending, or that
+    /// the cap on open sessions has been reached.
+    #[cfg(feature = "sealing")]
+    pub fn import_sealed(&self, key: &[u8; 32], blob: &[u8], now: u64) -> ::Result<SessionId> {
+        let (id, session) = BlindSession::unseal(key, blob)?;
+        let mut sessions = self.sessions();
+        evict_expired(&mut sessions, now);
+        if sessions.contains_key(&id) {
+            return Err(SessionDuplicate);
+        }
+        if sessions.len() >= self.max_open {
+            return Err(SessionLimitReached);
+        }
+        sessions.insert(id, (session, now.saturating_add(self.ttl)));
+        Ok(id)
+    }
+
+    /// Locks the pending sessions. The map is never left in an inconsistent
+    /// state, so it remains usable even if another thread panicked while
+    /// holding the lock.
+    fn sessions(&self) -> MutexGuard<HashMap<SessionId, (BlindSession, u64)>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Records e' in the replay store if there is one, erroring if it was
+    /// already recorded or the store failed.
+    fn record_ep(&self, ep: &WiredEPrime) -> ::Result<()> {
+        match self.replay {
+            Some(ref store) if !store.insert_if_absent(&Nullifier(ep.to_bytes()))? => {
+                Err(EPrimeReplayed)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<K> SessionManager<K>
+where
+    K: BlindSignerKey,
+{
+    /// Finishes the session with the provided id, signing the provided e'
+    /// value. The session is consumed even if signing fails.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(WiredSPrime) on success, representing the completed blind signature
+    /// value S'.
+    ///
+    /// * Err(::Error) on failure, which indicates either that there is no
+    /// pending session with the provided id (it was never started, or has
+    /// already been finished or aborted), that the key backend failed, or with
+    /// a replay store that ep was signed before or the store failed.
+    pub fn finish(&self, id: &SessionId, ep: &WiredEPrime) -> ::Result<WiredSPrime> {
+        let (session, _) = self.sessions().remove(id).ok_or(SessionUnknown)?;
+        self.record_ep(ep)?;
+        session.sign_ep(ep, &self.xs)
+    }
+
+    /// The same as finish, but calling commit once the session is removed,
+    /// and only signing if it succeeds, so that whatever commit records is
+    /// recorded exactly when S' is returned.
+    ///
+    /// pub(crate) as used in ecash.rs
+    pub(crate) fn finish_committed<F>(
+        &self,
+        id: &SessionId,
+        ep: &WiredEPrime,
+        commit: F,
+    ) -> ::Result<WiredSPrime>
+    where
+        F: FnOnce() -> ::Result<()>,
+    {
+        let (session, _) = self.sessions().remove(id).ok_or(SessionUnknown)?;
+        self.record_ep(ep)?;
+        commit()?;
+        session.sign_ep(ep, &self.xs)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<K> SessionManager<K>
+where
+    K: AsyncBlindSigner,
+{
+    /// The same as finish, but signing with an AsyncBlindSigner, so that the
+    /// thread is not blocked while a key service computes S'. The session is
+    /// consumed as soon as this is called, even if the future is dropped.
+    pub fn finish_async<'a>(&'a self, id: &SessionId, ep: &WiredEPrime) -> SPrimeFuture<'a> {
+        match self.sessions().remove(id) {
+            Some((session, _)) => match self.record_ep(ep) {
+                Ok(()) => session.sign_ep_async(ep, &self.xs),
+                Err(e) => SPrimeFuture::failed(e),
+            },
+            None => SPrimeFuture::failed(SessionUnknown),
+        }
+    }
+}
+
+/// The future of S', as returned by the async signing methods.
+#[cfg(feature = "async")]
+pub struct SPrimeFuture<'a> {
+    inner: ::std::result::Result<SignFuture<'a>, Option<::Error>>,
+}
+
+#[cfg(feature = "async")]
+impl<'a> SPrimeFuture<'a> {
+    /// Creates a future which fails immediately with the error
+    fn failed(e: ::Error) -> Self {
+        SPrimeFuture { inner: Err(Some(e)) }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<'a> Future for SPrimeFuture<'a> {
+    type Output = ::Result<WiredSPrime>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match self.get_mut().inner {
+            Ok(ref mut sign) => sign
+                .as_mut()
+                .poll(cx)
+                .map(|result| result.map(|sp| WiredSPrime(sp.to_bytes()))),
+            Err(ref mut e) => Poll::Ready(Err(e.take().expect("future polled after completion"))),
+        }
+    }
+}
+
+/// The R' value in wired form, which is sent from the signer to the requester
+/// in response to a request for protocol initiation.
+///
+/// Construction fails unless the bytes are a compressed Ristretto point other
+/// than the identity.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WiredRPrime([u8; 32]);
+
+impl WiredRPrime {
+    /// Returns R' in RistrettoPoint form
+    ///
+    /// pub(crate) as used in request.rs, recovery.rs and testvectors.rs
+    pub(crate) fn point(&self) -> RistrettoPoint {
+        request::decompress_non_identity(self.0).expect("WiredRPrime is validated")
+    }
+}
+
+impl_wired_bytes!(WiredRPrime, 32, validate_rp);
+impl_wired_envelope!(WiredRPrime, PayloadType::RPrime);
+
+/// The S' value in wired form, which is the blind signature sent from the
+/// signer to the requester upon signing e'.
+///
+/// Construction fails unless the bytes are a canonical scalar.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WiredSPrime([u8; 32]);
+
+impl WiredSPrime {
+    /// Returns S' in Scalar form
+    ///
+    /// pub(crate) as used in request.rs and recovery.rs
+    pub(crate) fn scalar(&self) -> Scalar {
+        Scalar::from_canonical_bytes(self.0).expect("WiredSPrime is validated")
+    }
+}
+
+impl_wired_bytes!(WiredSPrime, 32, validate_sp);
+impl_wired_envelope!(WiredSPrime, PayloadType::SPrime);
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Removes every session whose ttl has passed by now, wiping its k as it is
+/// dropped.
+fn evict_expired(sessions: &mut HashMap<SessionId, (BlindSession, u64)>, now: u64) {
+    sessions.retain(|_, &mut (_, deadline)| now <= deadline);
+}
+
+/// Validates the bytes of a WiredRPrime on construction
+fn validate_rp(rp: &[u8; 32]) -> ::Result<()> {
+    request::decompress_non_identity(*rp).map(|_| ())
+}
+
+/// Validates the bytes of a WiredSPrime on construction
+fn validate_sp(sp: &[u8; 32]) -> ::Result<()> {
+    Scalar::from_canonical_bytes(*sp)
+        .map(|_| ())
+        .ok_or(WiredScalarMalformed)
 }
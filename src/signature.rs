@@ -4,11 +4,19 @@ use curve25519_dalek::{
     ristretto::{CompressedRistretto, RistrettoPoint},
     scalar::Scalar,
 };
-use Error::{WiredRistrettoPointMalformed, WiredScalarMalformed};
+use Error::{
+    WiredEncodingNonCanonical, WiredFieldMalformed, WiredRistrettoPointMalformed, WiredScalarZero,
+};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
 use subtle::ConstantTimeEq;
 use typenum::U64;
 use digest::Digest;
-use request;
+use encoding;
+use envelope::PayloadType;
+use keypair::VerifyingKey;
+use request::{self, Scheme};
+use WiredField;
 
 /// The data required for authenticating the unblinded signature,
 ///
@@ -42,6 +50,37 @@ pub struct UnblindedSigData {
     s: Scalar,
     // The unblinded R value
     r: RistrettoPoint,
+    // The compressed form of R, kept so that serializing the signature and
+    // hashing R for msg_authenticate never recompress it.
+    r_compressed: CompressedRistretto,
+}
+
+/// Signatures compare and hash by their canonical wired form e || S || R, so
+/// that they can key HashMaps and BTreeMaps directly.
+impl PartialEq for UnblindedSigData {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical() == other.canonical()
+    }
+}
+
+impl Eq for UnblindedSigData {}
+
+impl Hash for UnblindedSigData {
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.canonical().hash(state)
+    }
+}
+
+impl PartialOrd for UnblindedSigData {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for UnblindedSigData {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.canonical().cmp(&other.canonical())
+    }
 }
 
 impl UnblindedSigData {
@@ -55,7 +94,40 @@ impl UnblindedSigData {
     /// * 'r' - The unblinded R' value received from the signer in step one
     /// of the protocol
     pub fn new(e: Scalar, s: Scalar, r: RistrettoPoint) -> Self {
-        Self { e, s, r }
+        Self {
+            e,
+            s,
+            r,
+            r_compressed: r.compress(),
+        }
+    }
+
+    /// Returns the e value
+    ///
+    /// pub(crate) as used in fair.rs, verifier.rs and announce.rs
+    pub(crate) fn e(&self) -> Scalar {
+        self.e
+    }
+
+    /// Returns the S value
+    ///
+    /// pub(crate) as used in batch.rs and verifier.rs
+    pub(crate) fn s(&self) -> Scalar {
+        self.s
+    }
+
+    /// Returns the R value
+    ///
+    /// pub(crate) as used in fair.rs, batch.rs, verifier.rs and announce.rs
+    pub(crate) fn r(&self) -> RistrettoPoint {
+        self.r
+    }
+
+    /// Returns the compressed R value
+    ///
+    /// pub(crate) as used in batch.rs and verifier.rs
+    pub(crate) fn r_compressed(&self) -> &CompressedRistretto {
+        &self.r_compressed
     }
 
     /// Authenticates that the signature value S on e is valid with R and the
@@ -65,7 +137,7 @@ impl UnblindedSigData {
     ///
     /// # Arguments
     ///
-    /// 'pub_key' - The public key associated with the private key that
+    /// 'pub_key' - The verifying key associated with the signing key that
     /// ostensibly created the signature value S' on e', to authenticate that
     /// S is authentic on e (given R).
     ///
@@ -94,14 +166,43 @@ impl UnblindedSigData {
     /// * This method only verifies that the signature S on e is valid given
     /// R and pub_key, it does **not** verify that e is correlated to any given
     /// msg value.
-    pub fn authenticate(&self, pub_key: RistrettoPoint) -> bool {
-        self.s * RISTRETTO_BASEPOINT_POINT == self.e * pub_key + self.r
+    pub fn authenticate(&self, pub_key: &VerifyingKey) -> bool {
+        self.s * RISTRETTO_BASEPOINT_POINT == self.e * pub_key.point() + self.r
+    }
+
+    /// Authenticates the signature against each of the provided candidate
+    /// verifying keys, reporting which key (if any) the signature is valid
+    /// under. Rather than checking the signature once per key, the only
+    /// public key the signature can be valid under is computed once and
+    /// compared against each candidate.
+    ///
+    /// # Returns
+    ///
+    /// * Some(usize) with the index of the first key in keys that the
+    /// signature authenticates under.
+    ///
+    /// * None if the signature does not authenticate under any of the keys.
+    ///
+    /// # Mathematics
+    ///
+    /// * Q = (SP - R) / e
+    /// * Q == Qi for some Qi in keys
+    /// * S = Unblinded signature value
+    /// * P = ECC generator point
+    /// * e = H(msg || R)
+    /// * R = Unblinded version of the R' value from the signer
+    pub fn authenticate_any(&self, keys: &[VerifyingKey]) -> Option<usize> {
+        if self.e == Scalar::zero() {
+            return None;
+        }
+        let q = self.e.invert() * (self.s * RISTRETTO_BASEPOINT_POINT - self.r);
+        keys.iter().position(|key| key.validate().is_ok() && key.point() == q)
     }
 
     /// The same as authenticate but with a constant time comparison.
-    pub fn const_authenticate(&self, pub_key: RistrettoPoint) -> bool {
+    pub fn const_authenticate(&self, pub_key: &VerifyingKey) -> bool {
         (self.s * RISTRETTO_BASEPOINT_POINT)
-            .ct_eq( &(self.e * pub_key + self.r) )
+            .ct_eq( &(self.e * pub_key.point() + self.r) )
             .unwrap_u8() == 1
     }
 
@@ -114,13 +215,29 @@ impl UnblindedSigData {
     ///
     /// The internal e value is not used at all, and is not guaranteed to match
     /// H(R||msg) for the provided msg.
-    pub fn msg_authenticate<H, M>(&self, pub_key: RistrettoPoint, msg: M) -> bool
+    pub fn msg_authenticate<H, M>(&self, pub_key: &VerifyingKey, msg: M) -> bool
     where
         H: Digest<OutputSize = U64> + Default,
         M: AsRef<[u8]>,
     {
-        let e = request::generate_e::<H>(self.r, msg.as_ref());
-        self.s * RISTRETTO_BASEPOINT_POINT == e * pub_key + self.r
+        self.msg_authenticate_with::<H, M>(pub_key, msg, Scheme::Plain)
+    }
+
+    /// The same as msg_authenticate, but computing e under the provided
+    /// scheme, which must be the one the request was made under.
+    pub fn msg_authenticate_with<H, M>(
+        &self,
+        pub_key: &VerifyingKey,
+        msg: M,
+        scheme: Scheme,
+    ) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        let key = scheme.bound_key(pub_key);
+        let e = request::generate_e_compressed::<H>(key, &self.r_compressed, msg.as_ref());
+        self.s * RISTRETTO_BASEPOINT_POINT == e * pub_key.point() + self.r
     }
 
     /// The same as const_authenticate, but rather than using the internal e value
@@ -132,23 +249,62 @@ impl UnblindedSigData {
     ///
     /// The internal e value is not used at all, and is not guaranteed to match
     /// H(R||msg) for the provided msg.
-    pub fn msg_const_authenticate<H, M>(&self, pub_key: RistrettoPoint, msg: M) -> bool
+    pub fn msg_const_authenticate<H, M>(&self, pub_key: &VerifyingKey, msg: M) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        self.msg_const_authenticate_with::<H, M>(pub_key, msg, Scheme::Plain)
+    }
+
+    /// The same as msg_const_authenticate, but computing e under the provided
+    /// scheme, which must be the one the request was made under.
+    pub fn msg_const_authenticate_with<H, M>(
+        &self,
+        pub_key: &VerifyingKey,
+        msg: M,
+        scheme: Scheme,
+    ) -> bool
     where
         H: Digest<OutputSize = U64> + Default,
         M: AsRef<[u8]>,
     {
-        let e = request::generate_e::<H>(self.r, msg.as_ref());
+        let key = scheme.bound_key(pub_key);
+        let e = request::generate_e_compressed::<H>(key, &self.r_compressed, msg.as_ref());
         (self.s * RISTRETTO_BASEPOINT_POINT)
-            .ct_eq( &(e * pub_key + self.r) )
+            .ct_eq( &(e * pub_key.point() + self.r) )
             .unwrap_u8() == 1
     }
 }
 
 
 
+/// How strictly wired signatures are checked when imported.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Rejects scalars which are not reduced, and any R which does not
+    /// recompress to the same bytes, so that each signature has exactly one
+    /// wired form. Systems deduplicating tokens by their bytes rely on this,
+    /// as otherwise e + l, for the group order l, would be a second encoding
+    /// of the same signature.
+    Strict,
+    /// Reduces unreduced scalars rather than rejecting them, for importing
+    /// signatures produced by implementations which do not reduce. Tokens
+    /// imported this way must not be deduplicated by their wired bytes.
+    Lenient,
+}
+
+impl Default for ParseMode {
+    /// Strict
+    fn default() -> Self {
+        ParseMode::Strict
+    }
+}
+
 /// The UnblindedSigData in wired form capable of being sent over the network.
 /// The wired form consists of e || S || R, with each component consisting of
 /// 32 bytes.
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct WiredUnblindedSigData(pub [u8; 96]);
 
 impl From<UnblindedSigData> for WiredUnblindedSigData {
@@ -156,7 +312,7 @@ impl From<UnblindedSigData> for WiredUnblindedSigData {
         let mut arr = [0; 96];
         arr[0..32].copy_from_slice(usd.e.as_bytes());
         arr[32..64].copy_from_slice(usd.s.as_bytes());
-        arr[64..96].copy_from_slice(usd.r.compress().as_bytes());
+        arr[64..96].copy_from_slice(usd.r_compressed.as_bytes());
         WiredUnblindedSigData(arr)
     }
 }
@@ -169,30 +325,230 @@ impl WiredUnblindedSigData {
     /// * Ok(UnblindedSigData) on success
     ///
     /// * Err(::Error) on failure, which could be due to any component of the
-    /// internal [u8; 96] being malformed.
+    /// internal [u8; 96] being malformed, R being the identity or S zero.
     pub fn to_internal_format(&self) -> ::Result<UnblindedSigData> {
-        let mut e_arr = [0; 32];
-        let mut s_arr = [0; 32];
-        let mut r_arr = [0; 32];
-        e_arr.copy_from_slice(&self.0[0..32]);
-        s_arr.copy_from_slice(&self.0[32..64]);
-        r_arr.copy_from_slice(&self.0[64..96]);
-        Ok(UnblindedSigData {
-            e: Scalar::from_canonical_bytes(e_arr).ok_or(WiredScalarMalformed)?,
-            s: Scalar::from_canonical_bytes(s_arr).ok_or(WiredScalarMalformed)?,
-            r: CompressedRistretto(r_arr)
-                .decompress()
-                .ok_or(WiredRistrettoPointMalformed)?,
-        })
-    }
-
-    /// Returns a reference to the internal [u8; 96]
-    pub fn as_bytes(&self) -> &[u8; 96] {
-        &self.0
-    }
-
-    /// Returns a copy of the internal [u8; 96]
-    pub fn to_bytes(&self) -> [u8; 96] {
-        self.0
+        parse_sig_data(&self.0, ParseMode::default())
     }
+
+    /// The same as to_internal_format, but with the provided parse mode.
+    pub fn to_internal_format_with(&self, mode: ParseMode) -> ::Result<UnblindedSigData> {
+        parse_sig_data(&self.0, mode)
+    }
+
+    /// Encodes the wired signature as a bech32 string with the provided human
+    /// readable prefix, so that it can be displayed to and pasted back by
+    /// users with checksumming.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(String) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the hrp was not 1 to 83
+    /// lowercase printable ASCII characters.
+    pub fn to_bech32(&self, hrp: &str) -> ::Result<String> {
+        encoding::to_bech32(hrp, &self.0)
+    }
+
+    /// Decodes the wired signature from a bech32 string, which must have the
+    /// provided human readable prefix.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(WiredUnblindedSigData) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the string was not valid
+    /// bech32, had a different prefix, or did not contain 96 bytes.
+    pub fn from_bech32(hrp: &str, encoded: &str) -> ::Result<Self> {
+        WiredUnblindedSigData::from_bytes(&encoding::from_bech32(hrp, encoded)?)
+    }
+}
+
+impl_wired_bytes!(WiredUnblindedSigData, 96);
+impl_wired_hex!(WiredUnblindedSigData);
+impl_wired_envelope!(WiredUnblindedSigData, PayloadType::UnblindedSigData);
+impl_wired_ref!(
+    /// A borrowed view of a WiredUnblindedSigData, for parsing signatures in
+    /// place within larger buffers.
+    WiredUnblindedSigDataRef,
+    WiredUnblindedSigData,
+    96
+);
+
+impl<'a> WiredUnblindedSigDataRef<'a> {
+    /// The same as WiredUnblindedSigData::to_internal_format, without copying.
+    pub fn to_internal_format(&self) -> ::Result<UnblindedSigData> {
+        parse_sig_data(self.0, ParseMode::default())
+    }
+
+    /// The same as WiredUnblindedSigData::to_internal_format_with, without
+    /// copying.
+    pub fn to_internal_format_with(&self, mode: ParseMode) -> ::Result<UnblindedSigData> {
+        parse_sig_data(self.0, mode)
+    }
+}
+
+/// A compact wired form of the blind signature consisting of e || S, with each
+/// component consisting of 32 bytes. The R component is omitted, as it can be
+/// recomputed from the other components and the public key of the signer, so
+/// the compact form is only usable for authentication when the signed message
+/// is also available.
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CompactSignature(pub [u8; 64]);
+
+impl From<UnblindedSigData> for CompactSignature {
+    fn from(usd: UnblindedSigData) -> Self {
+        let mut arr = [0; 64];
+        arr[0..32].copy_from_slice(usd.e.as_bytes());
+        arr[32..64].copy_from_slice(usd.s.as_bytes());
+        CompactSignature(arr)
+    }
+}
+
+impl CompactSignature {
+    /// Authenticates that the compact signature is valid on the provided msg
+    /// value with the provided public key, by recomputing R from the e and S
+    /// components and checking that e is the hash of R and the msg.
+    ///
+    /// # Arguments
+    ///
+    /// 'pub_key' - The verifying key associated with the signing key that
+    /// ostensibly created the signature value S' on e'.
+    ///
+    /// 'msg' - The message that the signature is ostensibly on.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm that was used for generating e
+    ///
+    /// # Returns
+    ///
+    /// * Ok(true) to indicate that the signature is valid on msg.
+    ///
+    /// * Ok(false) to indicate that the signature is invalid on msg.
+    ///
+    /// * Err(::Error) on failure, which indicates that either the e or S
+    /// component of the internal [u8; 64] was malformed.
+    ///
+    /// # Mathematics
+    ///
+    /// * R = SP - e*Qs
+    /// * e == H(R || msg)
+    /// * S = Unblinded signature value
+    /// * P = ECC generator point
+    /// * Qs = Public key of the signer
+    pub fn msg_authenticate<H, M>(&self, pub_key: &VerifyingKey, msg: M) -> ::Result<bool>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        compact_msg_authenticate::<H>(&self.0, pub_key, msg.as_ref())
+    }
+}
+
+impl_wired_bytes!(CompactSignature, 64);
+impl_wired_hex!(CompactSignature);
+impl_wired_envelope!(CompactSignature, PayloadType::CompactSignature);
+impl_wired_ref!(
+    /// A borrowed view of a CompactSignature, for authenticating signatures
+    /// in place within larger buffers.
+    CompactSignatureRef,
+    CompactSignature,
+    64
+);
+
+impl<'a> CompactSignatureRef<'a> {
+    /// The same as CompactSignature::msg_authenticate, without copying.
+    pub fn msg_authenticate<H, M>(&self, pub_key: &VerifyingKey, msg: M) -> ::Result<bool>
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        compact_msg_authenticate::<H>(self.0, pub_key, msg.as_ref())
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+impl UnblindedSigData {
+    /// The components of the wired form, borrowed for comparing and hashing
+    fn canonical(&self) -> (&[u8; 32], &[u8; 32], &[u8; 32]) {
+        (self.e.as_bytes(), self.s.as_bytes(), self.r_compressed.as_bytes())
+    }
+}
+
+/// Parses the wired form e || S || R of a signature
+fn parse_sig_data(wired: &[u8; 96], mode: ParseMode) -> ::Result<UnblindedSigData> {
+    let mut e_arr = [0; 32];
+    let mut s_arr = [0; 32];
+    let mut r_arr = [0; 32];
+    e_arr.copy_from_slice(&wired[0..32]);
+    s_arr.copy_from_slice(&wired[32..64]);
+    r_arr.copy_from_slice(&wired[64..96]);
+    let (e, s) = match mode {
+        ParseMode::Strict => (
+            Scalar::from_canonical_bytes(e_arr).ok_or_else(|| malformed(WiredField::E, 0))?,
+            Scalar::from_canonical_bytes(s_arr).ok_or_else(|| malformed(WiredField::S, 32))?,
+        ),
+        ParseMode::Lenient => (
+            Scalar::from_bytes_mod_order(e_arr),
+            Scalar::from_bytes_mod_order(s_arr),
+        ),
+    };
+    if s == Scalar::zero() {
+        return Err(WiredScalarZero);
+    }
+    let r = decompress_r(r_arr)?;
+    // Ristretto decoding already only accepts canonical encodings, but this is
+    // checked explicitly as strict mode guarantees it.
+    let r_compressed = r.compress();
+    if mode == ParseMode::Strict && r_compressed.to_bytes() != r_arr {
+        return Err(WiredEncodingNonCanonical);
+    }
+    Ok(UnblindedSigData {
+        e,
+        s,
+        r,
+        r_compressed,
+    })
+}
+
+/// Authenticates the compact form e || S of a signature on msg
+fn compact_msg_authenticate<H>(
+    compact: &[u8; 64],
+    pub_key: &VerifyingKey,
+    msg: &[u8],
+) -> ::Result<bool>
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let mut e_arr = [0; 32];
+    let mut s_arr = [0; 32];
+    e_arr.copy_from_slice(&compact[0..32]);
+    s_arr.copy_from_slice(&compact[32..64]);
+    let e = Scalar::from_canonical_bytes(e_arr).ok_or_else(|| malformed(WiredField::E, 0))?;
+    let s = non_zero_s(s_arr)?;
+    let r = s * RISTRETTO_BASEPOINT_POINT - e * pub_key.point();
+    Ok(request::generate_e::<H>(r, msg) == e)
+}
+
+/// Loads a wired S value at offset 32, rejecting zero
+fn non_zero_s(wired: [u8; 32]) -> ::Result<Scalar> {
+    let s = Scalar::from_canonical_bytes(wired).ok_or_else(|| malformed(WiredField::S, 32))?;
+    if s == Scalar::zero() {
+        return Err(WiredScalarZero);
+    }
+    Ok(s)
+}
+
+/// Decompresses a wired R value at offset 64, rejecting the identity
+fn decompress_r(wired: [u8; 32]) -> ::Result<RistrettoPoint> {
+    match request::decompress_non_identity(wired) {
+        Err(WiredRistrettoPointMalformed) => Err(malformed(WiredField::R, 64)),
+        result => result,
+    }
+}
+
+/// The error for a malformed field at the offset
+fn malformed(field: WiredField, offset: usize) -> ::Error {
+    WiredFieldMalformed { field, offset }
 }
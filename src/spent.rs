@@ -0,0 +1,154 @@
+//! Double spend detection for redeemed signatures
+//!
+//! Each redeemed signature has a nullifier, a hash of the message it signs,
+//! which is the same for every redemption of it. A verifier records the
+//! nullifier of every signature it accepts in a SpentStore, and rejects
+//! signatures whose nullifier is already recorded. The SpentStore trait allows
+//! the record to be kept in a database shared between verifiers, with
+//! MemorySpentStore being a simple in-process implementation.
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use digest::Digest;
+use keypair::VerifyingKey;
+use signature::UnblindedSigData;
+use std::collections::HashSet;
+use std::sync::{Mutex, MutexGuard};
+use typenum::U64;
+use Error::{AlreadySpent, RedemptionSignatureInvalid};
+
+/// The domain separation prefix for deriving nullifiers
+const NULLIFIER_DOMAIN: &[u8] = b"blindsign nullifier";
+
+/// The nullifier of a signed message, which identifies it for double spend
+/// detection without revealing anything about its issuance.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Nullifier(pub [u8; 32]);
+
+impl Nullifier {
+    /// Derives the nullifier of a signed message.
+    ///
+    /// The nullifier is derived from the message rather than the signature,
+    /// as the stored e of a signature is not checked by msg_authenticate, so
+    /// a holder could otherwise alter e for a fresh nullifier on every
+    /// redemption of the same signature.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for deriving the nullifier, which must be
+    /// the same for every verifier sharing a SpentStore
+    ///
+    /// # Mathematics
+    ///
+    /// * N = H(domain || msg), truncated to 32 bytes
+    pub fn from_msg<H, M>(msg: M) -> Self
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        let mut hasher = H::default();
+        hasher.input(NULLIFIER_DOMAIN);
+        hasher.input(msg.as_ref());
+        let mut nullifier = [0; 32];
+        nullifier.copy_from_slice(&hasher.result()[..32]);
+        Nullifier(nullifier)
+    }
+}
+
+/// A record of the nullifiers of redeemed signatures
+pub trait SpentStore {
+    /// Records the nullifier if it is not already recorded. This must be
+    /// atomic, so that concurrent redemptions of the same signature cannot
+    /// both succeed.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(true) if the nullifier was recorded by this call.
+    ///
+    /// * Ok(false) if the nullifier was already recorded.
+    ///
+    /// * Err(::Error) if the store failed, in which case the signature must
+    /// not be accepted.
+    fn insert_if_absent(&self, nullifier: &Nullifier) -> ::Result<bool>;
+}
+
+/// A SpentStore kept in memory, which may be shared between threads.
+#[derive(Debug, Default)]
+pub struct MemorySpentStore {
+    spent: Mutex<HashSet<Nullifier>>,
+}
+
+impl MemorySpentStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        MemorySpentStore {
+            spent: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Returns true if the nullifier is recorded
+    pub fn contains(&self, nullifier: &Nullifier) -> bool {
+        self.spent().contains(nullifier)
+    }
+
+    /// Returns the number of recorded nullifiers
+    pub fn len(&self) -> usize {
+        self.spent().len()
+    }
+
+    /// Returns true if no nullifiers are recorded
+    pub fn is_empty(&self) -> bool {
+        self.spent().is_empty()
+    }
+
+    /// Locks the recorded nullifiers. The set is never left in an
+    /// inconsistent state, so it remains usable even if another thread
+    /// panicked while holding the lock.
+    fn spent(&self) -> MutexGuard<HashSet<Nullifier>> {
+        self.spent.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SpentStore for MemorySpentStore {
+    fn insert_if_absent(&self, nullifier: &Nullifier) -> ::Result<bool> {
+        Ok(self.spent().insert(*nullifier))
+    }
+}
+
+/// Redeems a signature on msg, verifying it with the provided public key and
+/// then recording the nullifier of msg in the store, so that msg is redeemed
+/// at most once, whichever signature on it is presented.
+///
+/// # Type Parameters
+///
+/// H is the hash algorithm that was used for generating e, and which is used
+/// for deriving the nullifier
+///
+/// # Returns
+///
+/// * Ok(Nullifier) on success, the nullifier that was recorded.
+///
+/// * Err(::Error) on failure, which indicates that the signature was not
+/// valid on msg, that it was already spent, or that the store failed.
+pub fn redeem<H, M, S>(
+    store: &S,
+    pub_key: &VerifyingKey,
+    sig: &UnblindedSigData,
+    msg: M,
+) -> ::Result<Nullifier>
+where
+    H: Digest<OutputSize = U64> + Default,
+    M: AsRef<[u8]>,
+    S: SpentStore + ?Sized,
+{
+    if !sig.msg_authenticate::<H, &[u8]>(pub_key, msg.as_ref()) {
+        return Err(RedemptionSignatureInvalid);
+    }
+    let nullifier = Nullifier::from_msg::<H, M>(msg);
+    if !store.insert_if_absent(&nullifier)? {
+        return Err(AlreadySpent);
+    }
+    Ok(nullifier)
+}
@@ -0,0 +1,334 @@
+//! Known answer test vectors for the plain protocol
+//!
+//! A test vector fixes every random value of a session, the private key x,
+//! the signer's k and the requester's blinding factors u and v, along with
+//! the message, and records the values the protocol must then produce. Other
+//! implementations of the scheme can check their conformance against the
+//! same vectors, while TestVector::check replays them through this crate.
+//! Fresh vectors are produced by generate and written with to_json.
+//!
+//! Vectors are stored as a JSON array of objects, each with the fields below
+//! as lowercase hex strings, and no other fields or escapes.
+//!
+//! * "private", "public" - the wired signer keypair
+//! * "k" - the signer's nonce, with R' = kP
+//! * "u", "v" - the requester's blinding factors
+//! * "msg" - the message
+//! * "rp", "ep", "sp" - the expected R', e' and S'
+//! * "sig" - the expected wired unblinded signature, e || S || R
+//!
+//! # Note
+//!
+//! The hash algorithm is not recorded, and is chosen by the caller of check.
+use curve25519_dalek::scalar::Scalar;
+use digest::Digest;
+use encoding;
+use keypair::{BlindKeypair, VerifyingKey, WiredPublicKey};
+use request;
+use rng;
+use session::BlindSession;
+use signature::WiredUnblindedSigData;
+use typenum::U64;
+use Error::{TestVectorMalformed, TestVectorMismatch};
+
+/// The names of the fields of a vector, in the order of TestVector
+const FIELDS: [&str; 10] = [
+    "private", "public", "k", "u", "v", "msg", "rp", "ep", "sp", "sig",
+];
+
+/// A known answer test vector for a single session
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestVector {
+    /// The wired private key
+    pub private: [u8; 32],
+    /// The wired public key
+    pub public: [u8; 32],
+    /// The signer's nonce
+    pub k: [u8; 32],
+    /// The requester's first blinding factor
+    pub u: [u8; 32],
+    /// The requester's second blinding factor
+    pub v: [u8; 32],
+    /// The message
+    pub msg: Vec<u8>,
+    /// The expected R'
+    pub rp: [u8; 32],
+    /// The expected e'
+    pub ep: [u8; 32],
+    /// The expected S'
+    pub sp: [u8; 32],
+    /// The expected wired unblinded signature
+    pub sig: [u8; 96],
+}
+
+impl TestVector {
+    /// Replays the session of the vector deterministically, checking every
+    /// value against the expected one.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for generating e
+    ///
+    /// # Returns
+    ///
+    /// * Ok(()) on success, if every value matched and the signature
+    /// authenticates.
+    ///
+    /// * Err(::Error) on failure, which indicates a malformed input, or the
+    /// name of the first value which did not match.
+    pub fn check<H>(&self) -> ::Result<()>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let actual = replay::<H>(
+            scalar(self.private)?,
+            scalar(self.k)?,
+            scalar(self.u)?,
+            scalar(self.v)?,
+            &self.msg,
+        )?;
+        expect("public", &actual.public, &self.public)?;
+        expect("rp", &actual.rp, &self.rp)?;
+        expect("ep", &actual.ep, &self.ep)?;
+        expect("sp", &actual.sp, &self.sp)?;
+        expect("sig", &actual.sig, &self.sig)?;
+        let sig = WiredUnblindedSigData(self.sig).to_internal_format()?;
+        let pub_key = WiredPublicKey::from_bytes(&self.public).and_then(VerifyingKey::from_wired)?;
+        if !sig.msg_authenticate::<H, &[u8]>(&pub_key, &self.msg) {
+            return Err(TestVectorMismatch("sig"));
+        }
+        Ok(())
+    }
+}
+
+/// Generates a vector for a fresh session on the message, with every random
+/// value drawn from the internal RNG, for publishing as an interop vector.
+///
+/// # Type Parameters
+///
+/// H is the hash algorithm used for generating e
+///
+/// # Returns
+///
+/// * Ok(TestVector) on success.
+///
+/// * Err(::Error) on failure, which can only be the failure to initiate the
+/// internal RNG.
+pub fn generate<H, M>(msg: M) -> ::Result<TestVector>
+where
+    H: Digest<OutputSize = U64> + Default,
+    M: AsRef<[u8]>,
+{
+    let mut rng = rng::try_new()?;
+    let mut random = || Scalar::random(&mut rng);
+    let (x, k, u, v) = (random(), random(), random(), random());
+    replay::<H>(x, k, u, v, msg.as_ref())
+}
+
+/// Parses vectors from their JSON form.
+///
+/// # Returns
+///
+/// * Ok(Vec<TestVector>) on success.
+///
+/// * Err(::Error) on failure, which indicates that the input was not in the
+/// documented format.
+pub fn parse(json: &str) -> ::Result<Vec<TestVector>> {
+    let mut parser = Parser {
+        input: json.as_bytes(),
+        pos: 0,
+    };
+    let mut vectors = Vec::new();
+    parser.expect(b'[')?;
+    if !parser.eat(b']') {
+        loop {
+            vectors.push(parser.vector()?);
+            if parser.eat(b']') {
+                break;
+            }
+            parser.expect(b',')?;
+        }
+    }
+    parser.skip_whitespace();
+    if parser.pos != parser.input.len() {
+        return Err(TestVectorMalformed);
+    }
+    Ok(vectors)
+}
+
+/// Writes vectors in their JSON form, as read by parse.
+pub fn to_json(vectors: &[TestVector]) -> String {
+    let mut json = String::from("[");
+    for (i, vector) in vectors.iter().enumerate() {
+        let values: [&[u8]; 10] = [
+            &vector.private,
+            &vector.public,
+            &vector.k,
+            &vector.u,
+            &vector.v,
+            &vector.msg,
+            &vector.rp,
+            &vector.ep,
+            &vector.sp,
+            &vector.sig,
+        ];
+        json.push_str(if i == 0 { "\n  {" } else { ",\n  {" });
+        for (j, (field, value)) in FIELDS.iter().zip(values.iter()).enumerate() {
+            let sep = if j == 0 { "" } else { "," };
+            let hex = encoding::to_hex(value);
+            json.push_str(&format!("{}\n    \"{}\": \"{}\"", sep, field, hex));
+        }
+        json.push_str("\n  }");
+    }
+    json.push_str(if vectors.is_empty() { "]\n" } else { "\n]\n" });
+    json
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Runs the protocol with the provided private key and random values,
+/// recording every value produced
+fn replay<H>(x: Scalar, k: Scalar, u: Scalar, v: Scalar, msg: &[u8]) -> ::Result<TestVector>
+where
+    H: Digest<OutputSize = U64> + Default,
+{
+    let keypair = BlindKeypair::from_private(x);
+    let (rp, session) = BlindSession::with_k(k);
+    let (ep, request) = request::initiate_with::<H, &[u8]>(u, v, rp.point(), None, msg);
+    let sp = session.sign_ep(&ep, &keypair.signing_key())?;
+    let sig = request.gen_signed_msg(&sp);
+    Ok(TestVector {
+        private: keypair.private_wired().to_bytes(),
+        public: keypair.verifying_key().wired(),
+        k: k.to_bytes(),
+        u: u.to_bytes(),
+        v: v.to_bytes(),
+        msg: msg.to_vec(),
+        rp: rp.to_bytes(),
+        ep: ep.to_bytes(),
+        sp: sp.to_bytes(),
+        sig: WiredUnblindedSigData::from(sig).0,
+    })
+}
+
+/// A parser for the restricted JSON of vectors
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Parses a single vector object
+    fn vector(&mut self) -> ::Result<TestVector> {
+        let mut values: [Option<Vec<u8>>; 10] = Default::default();
+        self.expect(b'{')?;
+        loop {
+            let name = self.string()?;
+            self.expect(b':')?;
+            let value = from_hex(self.string()?)?;
+            let index = FIELDS
+                .iter()
+                .position(|field| field.as_bytes() == name)
+                .ok_or(TestVectorMalformed)?;
+            if values[index].replace(value).is_some() {
+                return Err(TestVectorMalformed);
+            }
+            if self.eat(b'}') {
+                break;
+            }
+            self.expect(b',')?;
+        }
+        let mut values = values.iter_mut().map(|value| value.take());
+        let mut next = || values.next().and_then(|value| value).ok_or(TestVectorMalformed);
+        Ok(TestVector {
+            private: array32(&next()?)?,
+            public: array32(&next()?)?,
+            k: array32(&next()?)?,
+            u: array32(&next()?)?,
+            v: array32(&next()?)?,
+            msg: next()?,
+            rp: array32(&next()?)?,
+            ep: array32(&next()?)?,
+            sp: array32(&next()?)?,
+            sig: WiredUnblindedSigData::from_bytes(&next()?)
+                .map_err(|_| TestVectorMalformed)?
+                .0,
+        })
+    }
+
+    /// Parses a string without escapes, returning its contents
+    fn string(&mut self) -> ::Result<&'a [u8]> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        while self.pos < self.input.len() && self.input[self.pos] != b'"' {
+            if self.input[self.pos] == b'\\' {
+                return Err(TestVectorMalformed);
+            }
+            self.pos += 1;
+        }
+        let contents = &self.input[start..self.pos];
+        self.expect(b'"')?;
+        Ok(contents)
+    }
+
+    /// Consumes the byte after any whitespace, or errors if it is another
+    fn expect(&mut self, byte: u8) -> ::Result<()> {
+        if self.eat(byte) {
+            Ok(())
+        } else {
+            Err(TestVectorMalformed)
+        }
+    }
+
+    /// Consumes the byte after any whitespace, returning whether it was there
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        if self.input.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skips any whitespace
+    fn skip_whitespace(&mut self) {
+        while self
+            .input
+            .get(self.pos)
+            .map_or(false, |b| b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+}
+
+/// Errors with the name of the value if it did not match the expected one
+fn expect(name: &'static str, actual: &[u8], expected: &[u8]) -> ::Result<()> {
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(TestVectorMismatch(name))
+    }
+}
+
+/// Loads a canonical scalar
+fn scalar(bytes: [u8; 32]) -> ::Result<Scalar> {
+    Scalar::from_canonical_bytes(bytes).ok_or(TestVectorMalformed)
+}
+
+/// Copies a 32 byte value into an array
+fn array32(bytes: &[u8]) -> ::Result<[u8; 32]> {
+    if bytes.len() != 32 {
+        return Err(TestVectorMalformed);
+    }
+    let mut arr = [0; 32];
+    arr.copy_from_slice(bytes);
+    Ok(arr)
+}
+
+/// Decodes lowercase or uppercase hex
+fn from_hex(hex: &[u8]) -> ::Result<Vec<u8>> {
+    let hex = ::std::str::from_utf8(hex).map_err(|_| TestVectorMalformed)?;
+    encoding::from_hex(hex).map_err(|_| TestVectorMalformed)
+}
@@ -0,0 +1,478 @@
+//! Threshold blind signing, where any t of n signers jointly sign
+//!
+//! The private key is Shamir secret shared between n signer nodes by a dealer,
+//! along with Feldman commitments to the sharing polynomial through which each
+//! node verifies its share, and the coordinator verifies each partial
+//! signature. The requester runs an ordinary BlindRequest, and the resulting
+//! signature is valid under the original verifying key.
+//!
+//! 1. Each of the t participating nodes starts a ThresholdSession, sending
+//!    its commitment R'i to the coordinator.
+//! 2. The coordinator combines the commitments into R', which is sent to the
+//!    requester, who responds with e'.
+//! 3. Each node signs e' with its share, sending its PartialBlindSig to the
+//!    coordinator.
+//! 4. The coordinator verifies and combines the partial signatures into S'.
+//!
+//! # Mathematics
+//!
+//! * R' = R'1 + ... + R't
+//! * S'i = ki + λi*Xi*e'
+//! * S' = S'1 + ... + S't
+//! * λi = The Lagrange coefficient of node i for the participating nodes
+//!
+//! # Note
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::Identity,
+};
+use encoding;
+use keypair::{BlindKeypair, Redacted, VerifyingKey};
+use request::WiredEPrime;
+use rng;
+use session::{WiredRPrime, WiredSPrime};
+use std::fmt;
+use wipe;
+use Error::{
+    ThresholdParamsInvalid, ThresholdPartialInvalid, ThresholdSignersInvalid,
+    WiredRistrettoPointMalformed, WiredScalarMalformed,
+};
+
+/// The share of the private key held by one signer node. Node indices start
+/// from 1.
+#[derive(Copy, Clone)]
+pub struct KeyShare {
+    index: u32,
+    x: Scalar,
+}
+
+impl fmt::Debug for KeyShare {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let public = (self.x * RISTRETTO_BASEPOINT_POINT).compress();
+        f.debug_struct("KeyShare")
+            .field("index", &self.index)
+            .field("x", &Redacted)
+            .field("public", &encoding::to_hex(public.as_bytes()))
+            .finish()
+    }
+}
+
+impl KeyShare {
+    /// Returns the index of the node holding the share
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the share Xi
+    ///
+    /// pub(crate) as used in frost.rs
+    pub(crate) fn private(&self) -> Scalar {
+        self.x
+    }
+
+    /// Verifies the share against the dealer's commitments, which each node
+    /// should do upon receipt.
+    ///
+    /// # Mathematics
+    ///
+    /// * Xi*P == C0 + i*C1 + ... + i^(t-1)*C(t-1)
+    pub fn verify(&self, commitments: &ShareCommitments) -> bool {
+        self.index != 0
+            && self.x * RISTRETTO_BASEPOINT_POINT == commitments.share_public(self.index)
+    }
+
+    /// Returns the share in wired form, index || Xi
+    pub fn wired(&self) -> [u8; 36] {
+        let mut arr = [0; 36];
+        arr[0..4].copy_from_slice(&self.index.to_be_bytes());
+        arr[4..36].copy_from_slice(self.x.as_bytes());
+        arr
+    }
+
+    /// Loads the share from its wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(KeyShare) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the index was 0 or the share
+    /// was not a canonical scalar.
+    pub fn from_wired(wired: [u8; 36]) -> ::Result<Self> {
+        let (index, x) = index_and_scalar(&wired)?;
+        Ok(KeyShare { index, x })
+    }
+}
+
+/// The dealer's Feldman commitments C0, ..., C(t-1) to the coefficients of the
+/// sharing polynomial, which are published to all nodes and the coordinator.
+/// C0 is the verifying key.
+#[derive(Clone, Debug)]
+pub struct ShareCommitments {
+    points: Vec<RistrettoPoint>,
+}
+
+impl ShareCommitments {
+    /// Returns the threshold t
+    pub fn threshold(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns the verifying key that the combined signatures are valid under
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey::from_point(self.points[0])
+    }
+
+    /// Returns the commitments in wired form, C0 || ... || C(t-1)
+    pub fn wired(&self) -> Vec<u8> {
+        self.points
+            .iter()
+            .flat_map(|point| point.compress().to_bytes().to_vec())
+            .collect()
+    }
+
+    /// Loads the commitments from their wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(ShareCommitments) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the input was empty, not a
+    /// multiple of 32 bytes, or contained a malformed point.
+    pub fn from_wired(wired: &[u8]) -> ::Result<Self> {
+        if wired.is_empty() || wired.len() % 32 != 0 {
+            return Err(WiredRistrettoPointMalformed);
+        }
+        let points = wired
+            .chunks(32)
+            .map(|chunk| {
+                CompressedRistretto::from_slice(chunk)
+                    .decompress()
+                    .ok_or(WiredRistrettoPointMalformed)
+            })
+            .collect::<::Result<Vec<_>>>()?;
+        Ok(ShareCommitments { points })
+    }
+
+    /// Computes the public key Xi*P of the share of node i from the
+    /// commitments
+    ///
+    /// pub(crate) as used in frost.rs
+    pub(crate) fn share_public(&self, index: u32) -> RistrettoPoint {
+        let i = index_scalar(index);
+        self.points
+            .iter()
+            .rev()
+            .fold(RistrettoPoint::identity(), |acc, point| acc * i + point)
+    }
+}
+
+/// Splits the private key of the keypair into n shares, any t of which can
+/// jointly sign, for the dealer. The dealer should erase the keypair after
+/// distributing the shares.
+///
+/// # Returns
+///
+/// * Ok((Vec<KeyShare>, ShareCommitments)) on success, with the share of node
+/// i at position i - 1.
+///
+/// * Err(::Error) on failure, which indicates that t was 0 or greater than n,
+/// or the failure to initiate the internal RNG.
+///
+/// # Mathematics
+///
+/// * f(z) = Xs + a1*z + ... + a(t-1)*z^(t-1)
+/// * Xi = f(i)
+/// * Cj = aj*P
+pub fn deal(
+    keypair: &BlindKeypair,
+    t: usize,
+    n: u32,
+) -> ::Result<(Vec<KeyShare>, ShareCommitments)> {
+    if t == 0 || t > n as usize {
+        return Err(ThresholdParamsInvalid);
+    }
+    let mut rng = rng::try_new()?;
+    let mut coefficients = vec![keypair.private()];
+    for _ in 1..t {
+        coefficients.push(Scalar::random(&mut rng));
+    }
+    let shares = (1..=n)
+        .map(|index| {
+            let i = index_scalar(index);
+            let x = coefficients
+                .iter()
+                .rev()
+                .fold(Scalar::zero(), |acc, coefficient| acc * i + coefficient);
+            KeyShare { index, x }
+        })
+        .collect();
+    let points = coefficients
+        .iter()
+        .map(|coefficient| coefficient * RISTRETTO_BASEPOINT_POINT)
+        .collect();
+    Ok((shares, ShareCommitments { points }))
+}
+
+/// For managing the side of one signer node in a threshold session.
+///
+/// The nonce k is wiped from memory when the session is dropped, as it
+/// reveals the share through S'i = ki + λi*Xi*e'.
+pub struct ThresholdSession {
+    k: Scalar,
+}
+
+impl Drop for ThresholdSession {
+    fn drop(&mut self) {
+        wipe::wipe_scalar(&mut self.k);
+    }
+}
+
+impl ThresholdSession {
+    /// Initiate a new session on the signer node.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], ThresholdSession)) on success, with the [u8; 32] being
+    /// the commitment R'i for sending to the coordinator.
+    ///
+    /// * Err(::Error) on failure, which is only due to the failure to initiate
+    /// the internal random number generator.
+    pub fn new() -> ::Result<([u8; 32], Self)> {
+        let k = Scalar::random(&mut rng::try_new()?);
+        let rp = (k * RISTRETTO_BASEPOINT_POINT).compress().to_bytes();
+        Ok((rp, ThresholdSession { k }))
+    }
+
+    /// Consumes the session and returns the partial blind signature on e'.
+    ///
+    /// # Arguments
+    ///
+    /// * 'signers' - The indices of all nodes participating in the session,
+    /// as announced by the coordinator, which must include this node.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(PartialBlindSig) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the signers were not
+    /// distinct nonzero indices including this node.
+    ///
+    /// # Mathematics
+    ///
+    /// * S'i = ki + λi*Xi*e'
+    pub fn sign_ep(
+        mut self,
+        ep: &WiredEPrime,
+        signers: &[u32],
+        share: &KeyShare,
+    ) -> ::Result<PartialBlindSig> {
+        let ep = ep.scalar();
+        let lambda = lagrange(share.index, signers)?;
+        let sp = self.k + lambda * share.x * ep;
+        // k must never be used twice, so is wiped before S'i is even returned
+        wipe::wipe_scalar(&mut self.k);
+        Ok(PartialBlindSig {
+            index: share.index,
+            sp,
+        })
+    }
+}
+
+/// The partial blind signature S'i of one signer node
+#[derive(Copy, Clone, Debug)]
+pub struct PartialBlindSig {
+    index: u32,
+    sp: Scalar,
+}
+
+impl PartialBlindSig {
+    /// Returns the index of the node that created the partial signature
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the partial signature in wired form, index || S'i
+    pub fn wired(&self) -> [u8; 36] {
+        let mut arr = [0; 36];
+        arr[0..4].copy_from_slice(&self.index.to_be_bytes());
+        arr[4..36].copy_from_slice(self.sp.as_bytes());
+        arr
+    }
+
+    /// Creates a partial signature
+    ///
+    /// pub(crate) as used in frost.rs
+    pub(crate) fn new(index: u32, sp: Scalar) -> Self {
+        PartialBlindSig { index, sp }
+    }
+
+    /// Returns the partial signature S'i
+    ///
+    /// pub(crate) as used in frost.rs
+    pub(crate) fn sp(&self) -> Scalar {
+        self.sp
+    }
+
+    /// Loads the partial signature from its wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(PartialBlindSig) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the index was 0 or S'i was
+    /// not a canonical scalar.
+    pub fn from_wired(wired: [u8; 36]) -> ::Result<Self> {
+        let (index, sp) = index_and_scalar(&wired)?;
+        Ok(PartialBlindSig { index, sp })
+    }
+}
+
+/// For managing the coordinator side of a threshold session, which combines
+/// the commitments and partial signatures of the participating nodes. The
+/// coordinator holds no secrets.
+pub struct ThresholdCoordinator {
+    commitments: ShareCommitments,
+    signers: Vec<(u32, RistrettoPoint)>,
+}
+
+impl ThresholdCoordinator {
+    /// Combines the commitments R'i of the participating nodes into R'.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((WiredRPrime, ThresholdCoordinator)) on success, with the
+    /// WiredRPrime being R' for sending to the requester.
+    ///
+    /// * Err(::Error) on failure, which indicates that fewer than t nodes are
+    /// participating, that the node indices were not distinct and nonzero, or
+    /// that a commitment or their sum was malformed.
+    pub fn new(
+        commitments: &ShareCommitments,
+        node_rps: &[(u32, [u8; 32])],
+    ) -> ::Result<(WiredRPrime, Self)> {
+        let indices: Vec<u32> = node_rps.iter().map(|&(index, _)| index).collect();
+        if indices.len() < commitments.threshold() {
+            return Err(ThresholdSignersInvalid);
+        }
+        check_signers(&indices)?;
+        let signers = node_rps
+            .iter()
+            .map(|&(index, rp)| {
+                CompressedRistretto(rp)
+                    .decompress()
+                    .map(|rp| (index, rp))
+                    .ok_or(WiredRistrettoPointMalformed)
+            })
+            .collect::<::Result<Vec<_>>>()?;
+        let rp = signers.iter().fold(RistrettoPoint::identity(), |acc, &(_, rp)| acc + rp);
+        let rp = WiredRPrime::from_bytes(rp.compress().as_bytes())?;
+        let coordinator = ThresholdCoordinator {
+            commitments: commitments.clone(),
+            signers,
+        };
+        Ok((rp, coordinator))
+    }
+
+    /// Returns the indices of the participating nodes, which are announced to
+    /// each node along with e'
+    pub fn signers(&self) -> Vec<u32> {
+        self.signers.iter().map(|&(index, _)| index).collect()
+    }
+
+    /// Consumes the coordinator, verifying each partial signature and
+    /// combining them into the blind signature S' for the requester.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(WiredSPrime) on success, representing the blind signature S'.
+    ///
+    /// * Err(::Error) on failure, which indicates that a partial signature
+    /// was missing or invalid, identifying the node.
+    ///
+    /// # Mathematics
+    ///
+    /// * S'i*P == R'i + λi*e'*(Xi*P)
+    pub fn combine(
+        self,
+        ep: &WiredEPrime,
+        partials: &[PartialBlindSig],
+    ) -> ::Result<WiredSPrime> {
+        let ep = ep.scalar();
+        let indices = self.signers();
+        let mut sp = Scalar::zero();
+        for &(index, rp) in &self.signers {
+            let partial = partials
+                .iter()
+                .find(|partial| partial.index == index)
+                .ok_or(ThresholdPartialInvalid(index))?;
+            let lambda = lagrange(index, &indices)?;
+            let expected = rp + lambda * ep * self.commitments.share_public(index);
+            if partial.sp * RISTRETTO_BASEPOINT_POINT != expected {
+                return Err(ThresholdPartialInvalid(index));
+            }
+            sp += partial.sp;
+        }
+        WiredSPrime::from_bytes(sp.as_bytes())
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Converts a node index into a scalar
+fn index_scalar(index: u32) -> Scalar {
+    let mut bytes = [0; 32];
+    bytes[0..4].copy_from_slice(&index.to_le_bytes());
+    Scalar::from_canonical_bytes(bytes).expect("32 bit integers are canonical scalars")
+}
+
+/// Checks that the indices of the participating nodes are distinct and
+/// nonzero
+///
+/// pub(crate) as used in frost.rs
+pub(crate) fn check_signers(signers: &[u32]) -> ::Result<()> {
+    for (i, &index) in signers.iter().enumerate() {
+        if index == 0 || signers[..i].contains(&index) {
+            return Err(ThresholdSignersInvalid);
+        }
+    }
+    Ok(())
+}
+
+/// Computes the Lagrange coefficient at zero of node i for the participating
+/// nodes, which must include node i
+///
+/// pub(crate) as used in frost.rs
+pub(crate) fn lagrange(index: u32, signers: &[u32]) -> ::Result<Scalar> {
+    check_signers(signers)?;
+    if !signers.contains(&index) {
+        return Err(ThresholdSignersInvalid);
+    }
+    let i = index_scalar(index);
+    let (num, den) = signers
+        .iter()
+        .filter(|&&other| other != index)
+        .fold((Scalar::one(), Scalar::one()), |(num, den), &other| {
+            let j = index_scalar(other);
+            (num * j, den * (j - i))
+        });
+    Ok(num * den.invert())
+}
+
+/// Loads an index || scalar pair from its wired form
+fn index_and_scalar(wired: &[u8; 36]) -> ::Result<(u32, Scalar)> {
+    let mut index = [0; 4];
+    let mut scalar = [0; 32];
+    index.copy_from_slice(&wired[0..4]);
+    scalar.copy_from_slice(&wired[4..36]);
+    let index = u32::from_be_bytes(index);
+    if index == 0 {
+        return Err(ThresholdSignersInvalid);
+    }
+    let scalar = Scalar::from_canonical_bytes(scalar).ok_or(WiredScalarMalformed)?;
+    Ok((index, scalar))
+}
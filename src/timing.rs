@@ -0,0 +1,112 @@
+//! A harness for detecting timing variability of signing
+//!
+//! This measures BlindSession::sign_ep for two classes of e', a fixed one
+//! and uniformly random ones, interleaved in random order, and compares the
+//! two distributions of timings with Welch's t-test, as in dudect. If signing
+//! is constant time with respect to e', the t statistic stays small however
+//! many samples are taken, while a leak makes it grow with the number of
+//! samples. As a rule of thumb, |t| above 4.5 over a million samples
+//! indicates a leak, and above 10 a certain leak.
+//!
+//! # Note
+//!
+//! Timings are taken with std::time::Instant, so the harness should be run
+//! in release mode on an otherwise idle machine, and the result is only as
+//! good as the resolution of the clock.
+use curve25519_dalek::scalar::Scalar;
+use keypair::BlindKeypair;
+use rand::Rng;
+use request::WiredEPrime;
+use rng;
+use session::BlindSession;
+use std::time::Instant;
+
+/// The result of measuring the two classes of e'
+#[derive(Copy, Clone, Debug)]
+pub struct TimingReport {
+    /// The number of samples in each class, fixed then random
+    pub samples: (usize, usize),
+    /// The mean time in nanoseconds of each class, fixed then random
+    pub means: (f64, f64),
+    /// Welch's t statistic of the difference in means
+    pub t: f64,
+}
+
+/// Measures signing for the provided number of samples, split randomly
+/// between the two classes of e'.
+///
+/// # Returns
+///
+/// * Ok(TimingReport) on success.
+///
+/// * Err(::Error) on failure, which indicates the failure to initiate the
+/// internal RNG.
+pub fn measure_sign_ep(samples: usize) -> ::Result<TimingReport> {
+    let mut rng = rng::try_new()?;
+    let xs = BlindKeypair::generate()?.signing_key();
+    // A low weight e', the class most likely to expose a leak
+    let mut fixed = [0; 32];
+    fixed[0] = 1;
+    let mut classes = (Stats::default(), Stats::default());
+    for _ in 0..samples {
+        let random_class = rng.gen::<bool>();
+        let ep = if random_class {
+            Scalar::random(&mut rng).to_bytes()
+        } else {
+            fixed
+        };
+        let ep = WiredEPrime::from_bytes(&ep)?;
+        let (_, session) = BlindSession::new()?;
+        let start = Instant::now();
+        let sp = session.sign_ep(&ep, &xs);
+        let elapsed = start.elapsed();
+        // Using S' keeps the signing from being optimized away
+        if sp.is_err() {
+            continue;
+        }
+        let nanos = elapsed.as_secs() as f64 * 1e9 + f64::from(elapsed.subsec_nanos());
+        if random_class {
+            classes.1.push(nanos);
+        } else {
+            classes.0.push(nanos);
+        }
+    }
+    let (fixed, random) = classes;
+    let mut t = 0.0;
+    if fixed.n > 1 && random.n > 1 {
+        let se = (fixed.variance() / fixed.n as f64 + random.variance() / random.n as f64).sqrt();
+        if se > 0.0 {
+            t = (fixed.mean - random.mean) / se;
+        }
+    }
+    Ok(TimingReport {
+        samples: (fixed.n, random.n),
+        means: (fixed.mean, random.mean),
+        t,
+    })
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Running mean and variance, by Welford's algorithm
+#[derive(Default)]
+struct Stats {
+    n: usize,
+    mean: f64,
+    m2: f64,
+}
+
+impl Stats {
+    /// Adds a sample
+    fn push(&mut self, x: f64) {
+        self.n += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.n as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// Returns the sample variance, of at least two samples
+    fn variance(&self) -> f64 {
+        self.m2 / (self.n - 1) as f64
+    }
+}
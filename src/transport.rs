@@ -0,0 +1,231 @@
+//! Transports moving protocol messages between the requester and signer
+//!
+//! The BlindTransport trait sends and receives ProtocolMessage values, and
+//! run_requester and run_signer drive the sans-IO state machines of the
+//! protocol module over any transport until they are done. StreamTransport
+//! implements the trait over any byte stream, such as a TCP or Unix socket,
+//! framing each message with ProtocolMessage::encode_into; with the
+//! tokio-transport feature, TokioTransport does the same over a tokio TCP
+//! stream.
+//!
+//! # Note
+//!
+//! Transports provide no confidentiality or authentication of the signer,
+//! which should be provided by running the protocol over TLS or similar.
+use digest::Digest;
+use keypair::BlindSignerKey;
+use protocol::{ProtocolMessage, RequesterProtocol, SignerProtocol};
+use signature::UnblindedSigData;
+use std::io::{Read, Write};
+use typenum::U64;
+use Error::{ProtocolMessageIncomplete, TransportFailed};
+
+#[cfg(feature = "tokio-transport")]
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    runtime::Handle,
+};
+
+/// The length of the length prefix of a frame
+const PREFIX_LEN: usize = 4;
+
+/// A transport for protocol messages
+pub trait BlindTransport {
+    /// Sends the message to the other side.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(()) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the transport failed.
+    fn send(&mut self, msg: &ProtocolMessage) -> ::Result<()>;
+
+    /// Receives the next message from the other side, blocking until one
+    /// arrives.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(ProtocolMessage) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the transport failed
+    /// or that the message was malformed.
+    fn recv(&mut self) -> ::Result<ProtocolMessage>;
+}
+
+/// A transport framing messages over a byte stream.
+pub struct StreamTransport<S> {
+    stream: S,
+}
+
+impl<S> StreamTransport<S>
+where
+    S: Read + Write,
+{
+    /// Creates a transport over the stream
+    pub fn new(stream: S) -> Self {
+        StreamTransport { stream }
+    }
+
+    /// Returns the stream, consuming the transport
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> BlindTransport for StreamTransport<S>
+where
+    S: Read + Write,
+{
+    fn send(&mut self, msg: &ProtocolMessage) -> ::Result<()> {
+        let mut buf = Vec::new();
+        msg.encode_into(&mut buf);
+        self.stream.write_all(&buf).map_err(|_| TransportFailed)?;
+        self.stream.flush().map_err(|_| TransportFailed)
+    }
+
+    fn recv(&mut self) -> ::Result<ProtocolMessage> {
+        let stream = &mut self.stream;
+        read_frame(|buf| stream.read_exact(buf).map_err(|_| TransportFailed))
+    }
+}
+
+/// A transport framing messages over a tokio TCP stream, for use from
+/// outside of the runtime, such as within tokio::task::spawn_blocking.
+///
+/// # Note
+///
+/// As with Handle::block_on, send and recv panic if called from within an
+/// asynchronous task of the runtime.
+#[cfg(feature = "tokio-transport")]
+pub struct TokioTransport {
+    stream: TcpStream,
+    handle: Handle,
+}
+
+#[cfg(feature = "tokio-transport")]
+impl TokioTransport {
+    /// Creates a transport over the stream, driving its IO on the runtime
+    /// of the provided handle
+    pub fn new(stream: TcpStream, handle: Handle) -> Self {
+        TokioTransport { stream, handle }
+    }
+
+    /// Returns the stream, consuming the transport
+    pub fn into_inner(self) -> TcpStream {
+        self.stream
+    }
+}
+
+#[cfg(feature = "tokio-transport")]
+impl BlindTransport for TokioTransport {
+    fn send(&mut self, msg: &ProtocolMessage) -> ::Result<()> {
+        let mut buf = Vec::new();
+        msg.encode_into(&mut buf);
+        self.handle
+            .block_on(self.stream.write_all(&buf))
+            .map_err(|_| TransportFailed)
+    }
+
+    fn recv(&mut self) -> ::Result<ProtocolMessage> {
+        let (stream, handle) = (&mut self.stream, &self.handle);
+        read_frame(|buf| {
+            handle
+                .block_on(stream.read_exact(buf))
+                .map(|_| ())
+                .map_err(|_| TransportFailed)
+        })
+    }
+}
+
+/// Runs the requester side of the protocol over the transport, for a blind
+/// signature on the message m.
+///
+/// # Type Parameters
+///
+/// H is the hash algorithm used for generating e
+///
+/// # Returns
+///
+/// * Ok(UnblindedSigData) on success. As with BlindRequest::gen_signed_msg,
+/// the signature is not verified.
+///
+/// * Err(::Error) on failure, which indicates that the transport failed, or
+/// any failure of RequesterProtocol::handle. If the transport is still
+/// usable, Abort is sent to the signer before returning.
+pub fn run_requester<H, T, M>(transport: &mut T, m: M) -> ::Result<UnblindedSigData>
+where
+    H: Digest<OutputSize = U64> + Default,
+    T: BlindTransport + ?Sized,
+    M: AsRef<[u8]>,
+{
+    let mut requester = RequesterProtocol::new(m);
+    transport.send(&requester.start()?)?;
+    while !requester.is_done() {
+        let reply = match transport.recv().and_then(|msg| requester.handle::<H>(msg)) {
+            Ok(reply) => reply,
+            Err(e) => {
+                let _ = transport.send(&requester.abort());
+                return Err(e);
+            }
+        };
+        if let Some(reply) = reply {
+            transport.send(&reply)?;
+        }
+    }
+    Ok(requester.signature().cloned().expect("done requester has a signature"))
+}
+
+/// Runs the signer side of the protocol over the transport for a single
+/// session, signing with the provided key.
+///
+/// # Returns
+///
+/// * Ok(()) on success, once S' has been sent.
+///
+/// * Err(::Error) on failure, which indicates that the transport failed, or
+/// any failure of SignerProtocol::handle. If the transport is still usable,
+/// Abort is sent to the requester before returning.
+pub fn run_signer<T, K>(transport: &mut T, xs: &K) -> ::Result<()>
+where
+    T: BlindTransport + ?Sized,
+    K: BlindSignerKey + ?Sized,
+{
+    let mut signer = SignerProtocol::new(xs);
+    while !signer.is_done() {
+        let reply = match transport.recv().and_then(|msg| signer.handle(msg)) {
+            Ok(reply) => reply,
+            Err(e) => {
+                let _ = transport.send(&signer.abort());
+                return Err(e);
+            }
+        };
+        transport.send(&reply)?;
+    }
+    Ok(())
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Reads a single frame with the provided exact read, and decodes it
+fn read_frame<F>(mut read_exact: F) -> ::Result<ProtocolMessage>
+where
+    F: FnMut(&mut [u8]) -> ::Result<()>,
+{
+    let mut frame = vec![0; PREFIX_LEN];
+    read_exact(&mut frame)?;
+    let mut len = [0; PREFIX_LEN];
+    len.copy_from_slice(&frame);
+    let len = u32::from_be_bytes(len) as usize;
+    // Every message is at most 49 bytes, so longer frames are rejected before
+    // allocating for them
+    if len <= 64 {
+        frame.resize(PREFIX_LEN + len, 0);
+        read_exact(&mut frame[PREFIX_LEN..])?;
+    }
+    match ProtocolMessage::decode(&frame) {
+        Ok((msg, _)) => Ok(msg),
+        Err(ProtocolMessageIncomplete) => Err(TransportFailed),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,222 @@
+//! Per key counts of issued signatures, with an optional cap
+//!
+//! Key rotation policies such as "rotate after 1M issuances" need the signer
+//! to know how many blind signatures each key has produced. A CountedKey
+//! wraps any BlindSignerKey, counting every S' it computes against the
+//! fingerprint of the key, and with a cap refusing to compute S' once the
+//! cap is reached, so that sign_ep fails rather than exceeding it. As it is a
+//! BlindSignerKey itself, it counts signing through BlindSession,
+//! SessionManager, batch and multi alike.
+//!
+//! The UsageStore trait allows the counts to be persisted, for example in a
+//! database shared between signers, so that they survive restarts. The
+//! MemoryUsageStore is a simple in-process implementation, which can be
+//! snapshotted and restored.
+use curve25519_dalek::scalar::Scalar;
+use digest::Digest;
+use keypair::{BlindSignerKey, KeyFingerprint, VerifyingKey};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use Error::KeyUsageCapReached;
+
+/// A record of the number of signatures produced per key
+pub trait UsageStore {
+    /// Increments the count of the key if it is below the cap. This must be
+    /// atomic, so that concurrent signing cannot exceed the cap.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(true) if the count was incremented by this call.
+    ///
+    /// * Ok(false) if the count had already reached the cap.
+    ///
+    /// * Err(::Error) if the store failed, in which case no signature must
+    /// be produced.
+    fn increment_if_below(&self, key_id: &KeyFingerprint, cap: u64) -> ::Result<bool>;
+
+    /// Decrements the count of the key, returning a unit of the cap for a
+    /// signature that failed to be produced.
+    fn decrement(&self, key_id: &KeyFingerprint) -> ::Result<()>;
+
+    /// Returns the count of the key, which is 0 for a key never used
+    fn count(&self, key_id: &KeyFingerprint) -> ::Result<u64>;
+}
+
+/// A UsageStore kept in memory, which may be shared between threads.
+#[derive(Debug, Default)]
+pub struct MemoryUsageStore {
+    counts: Mutex<HashMap<KeyFingerprint, u64>>,
+}
+
+impl MemoryUsageStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        MemoryUsageStore {
+            counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a store with the provided counts, as returned by snapshot.
+    pub fn restore<I>(counts: I) -> Self
+    where
+        I: IntoIterator<Item = (KeyFingerprint, u64)>,
+    {
+        MemoryUsageStore {
+            counts: Mutex::new(counts.into_iter().collect()),
+        }
+    }
+
+    /// Returns the count of every key used, for persisting.
+    pub fn snapshot(&self) -> Vec<(KeyFingerprint, u64)> {
+        self.counts()
+            .iter()
+            .map(|(&key_id, &count)| (key_id, count))
+            .collect()
+    }
+
+    /// Locks the counts. The map is never left in an inconsistent state, so
+    /// it remains usable even if another thread panicked while holding the
+    /// lock.
+    fn counts(&self) -> MutexGuard<HashMap<KeyFingerprint, u64>> {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl UsageStore for MemoryUsageStore {
+    fn increment_if_below(&self, key_id: &KeyFingerprint, cap: u64) -> ::Result<bool> {
+        let mut counts = self.counts();
+        let count = counts.entry(*key_id).or_insert(0);
+        if *count >= cap {
+            return Ok(false);
+        }
+        *count += 1;
+        Ok(true)
+    }
+
+    fn decrement(&self, key_id: &KeyFingerprint) -> ::Result<()> {
+        if let Some(count) = self.counts().get_mut(key_id) {
+            *count = count.saturating_sub(1);
+        }
+        Ok(())
+    }
+
+    fn count(&self, key_id: &KeyFingerprint) -> ::Result<u64> {
+        Ok(self.counts().get(key_id).cloned().unwrap_or(0))
+    }
+}
+
+/// A signing key whose every S' is counted in a UsageStore, optionally up to
+/// a cap.
+pub struct CountedKey<K, S> {
+    key: K,
+    store: S,
+    key_id: KeyFingerprint,
+    cap: Option<u64>,
+}
+
+impl<K, S> CountedKey<K, S>
+where
+    K: BlindSignerKey,
+    S: UsageStore,
+{
+    /// Wraps the key, counting its signatures without a cap.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for the fingerprint the key is counted
+    /// against
+    pub fn new<H>(key: K, store: S) -> Self
+    where
+        H: Digest + Default,
+    {
+        let key_id = key.verifying_key().fingerprint::<H>();
+        CountedKey {
+            key,
+            store,
+            key_id,
+            cap: None,
+        }
+    }
+
+    /// Wraps the key, counting its signatures and refusing to produce more
+    /// than cap of them in total, including those already in the store.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for the fingerprint the key is counted
+    /// against
+    pub fn with_cap<H>(key: K, store: S, cap: u64) -> Self
+    where
+        H: Digest + Default,
+    {
+        CountedKey {
+            cap: Some(cap),
+            ..CountedKey::new::<H>(key, store)
+        }
+    }
+
+    /// Returns the fingerprint the key is counted against
+    pub fn key_id(&self) -> KeyFingerprint {
+        self.key_id
+    }
+
+    /// Returns the cap, if there is one
+    pub fn cap(&self) -> Option<u64> {
+        self.cap
+    }
+
+    /// Returns the store of the counts
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the number of signatures produced with the key.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(u64) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the store failed.
+    pub fn count(&self) -> ::Result<u64> {
+        self.store.count(&self.key_id)
+    }
+
+    /// Returns the number of signatures the key may still produce, or None
+    /// without a cap.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Option<u64>) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the store failed.
+    pub fn remaining(&self) -> ::Result<Option<u64>> {
+        match self.cap {
+            Some(cap) => Ok(Some(cap.saturating_sub(self.count()?))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<K, S> BlindSignerKey for CountedKey<K, S>
+where
+    K: BlindSignerKey,
+    S: UsageStore,
+{
+    /// Computes S' with the wrapped key, once the signature is counted. Fails
+    /// with KeyUsageCapReached once the cap is reached, or if the store
+    /// fails, and the signature is not counted if the wrapped key fails.
+    fn sign_blinded(&self, ep: &Scalar, k: &Scalar) -> ::Result<Scalar> {
+        let cap = self.cap.unwrap_or_else(u64::max_value);
+        if !self.store.increment_if_below(&self.key_id, cap)? {
+            return Err(KeyUsageCapReached);
+        }
+        self.key.sign_blinded(ep, k).or_else(|e| {
+            self.store.decrement(&self.key_id)?;
+            Err(e)
+        })
+    }
+
+    fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+}
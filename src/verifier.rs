@@ -0,0 +1,185 @@
+//! Verification under a single key, with optional result caching
+//!
+//! A Verifier holds the verifying key signatures are authenticated under. With
+//! with_cache, it also keeps a bounded least recently used cache of results,
+//! so that repeated presentations of the same signature, such as a retried or
+//! idempotent redemption, skip the scalar multiplications.
+//!
+//! Results are cached by the canonical bytes of e, S and R, where for the msg
+//! variants e is the one computed from the message. As the result of
+//! SP == eQs + R depends on nothing else for the fixed key, a cached result
+//! is always the result verification would give, whichever message, hash
+//! algorithm or scheme produced e.
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::scalar::Scalar;
+use digest::Digest;
+use keypair::VerifyingKey;
+use request::{self, Scheme};
+use signature::UnblindedSigData;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+use typenum::U64;
+
+/// The canonical bytes of e, S and R, in that order
+type CacheKey = ([u8; 32], [u8; 32], [u8; 32]);
+
+/// Authenticates signatures under a single verifying key, optionally caching
+/// the results. The verifier may be shared between threads.
+pub struct Verifier {
+    pub_key: VerifyingKey,
+    cache: Option<Mutex<Lru>>,
+}
+
+impl Verifier {
+    /// Creates a new Verifier for the provided key, without a cache.
+    pub fn new(pub_key: VerifyingKey) -> Self {
+        Verifier {
+            pub_key,
+            cache: None,
+        }
+    }
+
+    /// Creates a new Verifier for the provided key, which caches the results
+    /// of up to capacity distinct signatures, evicting the least recently
+    /// used once full. A capacity of 0 caches nothing.
+    pub fn with_cache(pub_key: VerifyingKey, capacity: usize) -> Self {
+        Verifier {
+            pub_key,
+            cache: Some(Mutex::new(Lru::new(capacity))),
+        }
+    }
+
+    /// Returns the key signatures are authenticated under
+    pub fn pub_key(&self) -> &VerifyingKey {
+        &self.pub_key
+    }
+
+    /// Returns the number of results currently cached, which is always 0
+    /// without a cache.
+    pub fn cached(&self) -> usize {
+        self.cache.as_ref().map_or(0, |cache| lock(cache).entries.len())
+    }
+
+    /// The same as UnblindedSigData::authenticate under the verifier's key.
+    pub fn authenticate(&self, sig: &UnblindedSigData) -> bool {
+        self.check(sig.e(), sig)
+    }
+
+    /// The same as UnblindedSigData::msg_authenticate under the verifier's
+    /// key.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for generating e
+    pub fn msg_authenticate<H, M>(&self, sig: &UnblindedSigData, msg: M) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        self.msg_authenticate_with::<H, M>(sig, msg, Scheme::Plain)
+    }
+
+    /// The same as UnblindedSigData::msg_authenticate_with under the
+    /// verifier's key.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for generating e
+    pub fn msg_authenticate_with<H, M>(
+        &self,
+        sig: &UnblindedSigData,
+        msg: M,
+        scheme: Scheme,
+    ) -> bool
+    where
+        H: Digest<OutputSize = U64> + Default,
+        M: AsRef<[u8]>,
+    {
+        let key = scheme.bound_key(&self.pub_key);
+        let e = request::generate_e_compressed::<H>(key, sig.r_compressed(), msg.as_ref());
+        self.check(e, sig)
+    }
+
+    /// Verifies SP == eQs + R, through the cache if there is one
+    fn check(&self, e: Scalar, sig: &UnblindedSigData) -> bool {
+        let pub_key = &self.pub_key;
+        let verify = || sig.s() * RISTRETTO_BASEPOINT_POINT == e * pub_key.point() + sig.r();
+        let cache = match self.cache {
+            Some(ref cache) => cache,
+            None => return verify(),
+        };
+        let key = (
+            e.to_bytes(),
+            sig.s().to_bytes(),
+            sig.r_compressed().to_bytes(),
+        );
+        if let Some(valid) = lock(cache).get(&key) {
+            return valid;
+        }
+        // The lock is not held while verifying, so that verifications of
+        // distinct signatures run concurrently.
+        let valid = verify();
+        lock(cache).insert(key, valid);
+        valid
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// A least recently used cache of verification results, where every access
+/// stamps the entry with the next tick, and the entry with the lowest tick is
+/// evicted first.
+struct Lru {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<CacheKey, (bool, u64)>,
+    order: BTreeMap<u64, CacheKey>,
+}
+
+impl Lru {
+    fn new(capacity: usize) -> Self {
+        Lru {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the cached result, marking it as the most recently used
+    fn get(&mut self, key: &CacheKey) -> Option<bool> {
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(key)?;
+        self.order.remove(&entry.1);
+        self.order.insert(tick, *key);
+        entry.1 = tick;
+        Some(entry.0)
+    }
+
+    /// Caches the result, evicting the least recently used result if full
+    fn insert(&mut self, key: CacheKey, valid: bool) {
+        if self.capacity == 0 || self.get(&key).is_some() {
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            let oldest = self.order.keys().next().cloned();
+            if let Some(evicted) = oldest.and_then(|tick| self.order.remove(&tick)) {
+                self.entries.remove(&evicted);
+            }
+        }
+        let tick = self.next_tick();
+        self.entries.insert(key, (valid, tick));
+        self.order.insert(tick, key);
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+/// Locks the cache. Every operation leaves the cache consistent, so it
+/// remains usable even if another thread panicked while holding the lock.
+fn lock(cache: &Mutex<Lru>) -> MutexGuard<Lru> {
+    cache.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,446 @@
+//! Verifiable oblivious PRF, as specified by RFC 9497
+//!
+//! This implements the VOPRF mode of the ristretto255-SHA512 ciphersuite,
+//! in the same sans-IO style as the blind signature protocol. The client
+//! learns the PRF output on its input under the server key without the server
+//! learning the input, and verifies with a DLEQ proof that the server used the
+//! key matching its published public key.
+//!
+//! 1. The client blinds its input with VoprfClient::blind, sending the
+//!    blinded element.
+//! 2. The server evaluates the blinded element with blind_evaluate, sending
+//!    the evaluated element and the proof.
+//! 3. The client verifies the proof and finalizes the output.
+//!
+//! The server can recompute the output on an input with evaluate, such as for
+//! redeeming Privacy Pass style tokens.
+//!
+//! # Note
+//!
+//! As with the blind signature protocol, this **does not** include **any**
+//! networking code.
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::Identity,
+};
+use encoding;
+use keypair::Redacted;
+use rng;
+use sha2::{Digest, Sha512};
+use std::fmt;
+use Error::{
+    VoprfInputInvalid, VoprfKeyDerivationFailed, VoprfProofInvalid, WiredScalarMalformed,
+};
+
+/// The context string of the VOPRF mode of the ristretto255-SHA512 ciphersuite
+pub const CONTEXT_STRING: &[u8] = b"OPRFV1-\x01-ristretto255-SHA512";
+
+/// The length in bytes of the PRF output
+pub const OUTPUT_LEN: usize = 64;
+
+/// The server side of the VOPRF, holding the private key
+#[derive(Copy, Clone)]
+pub struct VoprfServer {
+    sk: Scalar,
+    pk: RistrettoPoint,
+}
+
+impl fmt::Debug for VoprfServer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VoprfServer")
+            .field("sk", &Redacted)
+            .field("pk", &encoding::to_hex(&self.public_key()))
+            .finish()
+    }
+}
+
+impl VoprfServer {
+    /// Generates a new random server key.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(VoprfServer) on success.
+    ///
+    /// * Err(::Error) on failure, which can only be the failure to initiate
+    /// the internal RNG.
+    pub fn generate() -> ::Result<Self> {
+        let mut rng = rng::try_new()?;
+        loop {
+            let sk = Scalar::random(&mut rng);
+            if sk != Scalar::zero() {
+                return Ok(VoprfServer::from_scalar(sk));
+            }
+        }
+    }
+
+    /// Deterministically derives the server key from a seed and key info, as
+    /// with DeriveKeyPair.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(VoprfServer) on success.
+    ///
+    /// * Err(::Error) on failure, which happens with negligible probability
+    /// when no nonzero key is derived within 256 attempts.
+    pub fn derive_key_pair(seed: &[u8; 32], info: &[u8]) -> ::Result<Self> {
+        let mut input = seed.to_vec();
+        input.extend_from_slice(&len_prefix(info));
+        input.push(0);
+        let dst = [&b"DeriveKeyPair"[..], CONTEXT_STRING].concat();
+        for counter in 0..=255u8 {
+            *input.last_mut().unwrap() = counter;
+            let sk = hash_to_scalar(&input, &dst);
+            if sk != Scalar::zero() {
+                return Ok(VoprfServer::from_scalar(sk));
+            }
+        }
+        Err(VoprfKeyDerivationFailed)
+    }
+
+    /// Loads the server key from the private key in wired form.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(VoprfServer) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates the private key was not a
+    /// canonical scalar, or was zero.
+    pub fn from_wired(sk: [u8; 32]) -> ::Result<Self> {
+        match Scalar::from_canonical_bytes(sk) {
+            Some(sk) if sk != Scalar::zero() => Ok(VoprfServer::from_scalar(sk)),
+            _ => Err(WiredScalarMalformed),
+        }
+    }
+
+    /// Returns the private key in wired form
+    pub fn private_wired(&self) -> [u8; 32] {
+        self.sk.to_bytes()
+    }
+
+    /// Returns the public key in wired form, which is published to clients
+    pub fn public_key(&self) -> [u8; 32] {
+        self.pk.compress().to_bytes()
+    }
+
+    /// Evaluates a blinded element from a client, as with BlindEvaluate.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], [u8; 64])) on success, being the evaluated element and
+    /// the proof c || s for sending to the client.
+    ///
+    /// * Err(::Error) on failure, which indicates the blinded element was
+    /// malformed or the identity, or the failure to initiate the RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * Z = sk*B
+    pub fn blind_evaluate(&self, blinded: &[u8; 32]) -> ::Result<([u8; 32], [u8; 64])> {
+        let (mut evaluated, proof) = self.blind_evaluate_batch(&[*blinded])?;
+        Ok((evaluated.remove(0), proof))
+    }
+
+    /// Evaluates several blinded elements from a client, with a single proof
+    /// covering all of them.
+    ///
+    /// # Returns
+    ///
+    /// * Ok((Vec<[u8; 32]>, [u8; 64])) on success, being the evaluated elements
+    /// in order and the proof.
+    ///
+    /// * Err(::Error) on failure, which indicates there were no blinded
+    /// elements, that one was malformed or the identity, or the failure to
+    /// initiate the RNG.
+    pub fn blind_evaluate_batch(
+        &self,
+        blinded: &[[u8; 32]],
+    ) -> ::Result<(Vec<[u8; 32]>, [u8; 64])> {
+        if blinded.is_empty() {
+            return Err(VoprfInputInvalid);
+        }
+        let c = blinded
+            .iter()
+            .map(deserialize)
+            .collect::<::Result<Vec<_>>>()?;
+        let d: Vec<RistrettoPoint> = c.iter().map(|element| self.sk * element).collect();
+        let (m, z) = composites(&self.pk, &c, &d, Some(self.sk));
+        let r = Scalar::random(&mut rng::try_new()?);
+        let proof = generate_proof(self.sk, &self.pk, &m, &z, r);
+        let evaluated = d.iter().map(|element| element.compress().to_bytes()).collect();
+        Ok((evaluated, proof))
+    }
+
+    /// Computes the PRF output on the input directly, as with Evaluate.
+    ///
+    /// # Returns
+    ///
+    /// * Ok([u8; 64]) on success, which is the PRF output.
+    ///
+    /// * Err(::Error) on failure, which indicates the input hashed to the
+    /// identity.
+    pub fn evaluate(&self, input: &[u8]) -> ::Result<[u8; OUTPUT_LEN]> {
+        let element = hash_to_group(input)?;
+        Ok(finalize_hash(input, &(self.sk * element)))
+    }
+
+    /// Creates the server from a nonzero private key
+    fn from_scalar(sk: Scalar) -> Self {
+        VoprfServer {
+            sk,
+            pk: sk * RISTRETTO_BASEPOINT_POINT,
+        }
+    }
+}
+
+/// The client side of one VOPRF evaluation
+pub struct VoprfClient {
+    input: Vec<u8>,
+    blind: Scalar,
+    blinded: RistrettoPoint,
+}
+
+impl VoprfClient {
+    /// Blinds the input, as with Blind.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], VoprfClient)) on success, with the [u8; 32] being the
+    /// blinded element for sending to the server.
+    ///
+    /// * Err(::Error) on failure, which indicates the input hashed to the
+    /// identity, or the failure to initiate the RNG.
+    ///
+    /// # Mathematics
+    ///
+    /// * B = r*H(input)
+    /// * r = A randomly generated nonzero scalar by the client
+    pub fn blind(input: &[u8]) -> ::Result<([u8; 32], Self)> {
+        let element = hash_to_group(input)?;
+        let mut rng = rng::try_new()?;
+        let blind = loop {
+            let blind = Scalar::random(&mut rng);
+            if blind != Scalar::zero() {
+                break blind;
+            }
+        };
+        let blinded = blind * element;
+        let client = VoprfClient {
+            input: input.to_vec(),
+            blind,
+            blinded,
+        };
+        Ok((blinded.compress().to_bytes(), client))
+    }
+
+    /// Verifies the proof against the server public key, and finalizes the
+    /// PRF output from the evaluated element, as with Finalize.
+    ///
+    /// # Returns
+    ///
+    /// * Ok([u8; 64]) on success, which is the PRF output.
+    ///
+    /// * Err(::Error) on failure, which indicates that the public key,
+    /// evaluated element or proof was malformed, or that the proof did not
+    /// verify.
+    ///
+    /// # Mathematics
+    ///
+    /// * N = r^-1 * Z
+    pub fn finalize(
+        self,
+        pub_key: &[u8; 32],
+        evaluated: &[u8; 32],
+        proof: &[u8; 64],
+    ) -> ::Result<[u8; OUTPUT_LEN]> {
+        let mut outputs = VoprfClient::finalize_batch(vec![self], pub_key, &[*evaluated], proof)?;
+        Ok(outputs.remove(0))
+    }
+
+    /// Verifies a single proof covering several evaluations, and finalizes
+    /// each PRF output, in the order of the clients.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Vec<[u8; 64]>) on success, which are the PRF outputs.
+    ///
+    /// * Err(::Error) on failure, which indicates that the number of evaluated
+    /// elements did not match, that an input was malformed, or that the proof
+    /// did not verify.
+    pub fn finalize_batch(
+        clients: Vec<VoprfClient>,
+        pub_key: &[u8; 32],
+        evaluated: &[[u8; 32]],
+        proof: &[u8; 64],
+    ) -> ::Result<Vec<[u8; OUTPUT_LEN]>> {
+        if clients.is_empty() || clients.len() != evaluated.len() {
+            return Err(VoprfInputInvalid);
+        }
+        let pk = deserialize(pub_key)?;
+        let c: Vec<RistrettoPoint> = clients.iter().map(|client| client.blinded).collect();
+        let d = evaluated
+            .iter()
+            .map(deserialize)
+            .collect::<::Result<Vec<_>>>()?;
+        let (m, z) = composites(&pk, &c, &d, None);
+        if !verify_proof(&pk, &m, &z, proof)? {
+            return Err(VoprfProofInvalid);
+        }
+        Ok(clients
+            .iter()
+            .zip(d)
+            .map(|(client, element)| {
+                finalize_hash(&client.input, &(client.blind.invert() * element))
+            })
+            .collect())
+    }
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Prefixes the input with its length as a 2 byte big endian integer
+fn len_prefix(input: &[u8]) -> Vec<u8> {
+    let mut prefixed = (input.len() as u16).to_be_bytes().to_vec();
+    prefixed.extend_from_slice(input);
+    prefixed
+}
+
+/// expand_message_xmd from RFC 9380 with SHA-512, for a 64 byte output
+fn expand_message_xmd(msg: &[u8], dst: &[u8]) -> [u8; 64] {
+    let dst_prime = [dst, &[dst.len() as u8][..]].concat();
+    let b0 = Sha512::new()
+        .chain(&[0u8; 128][..])
+        .chain(msg)
+        .chain(&[0u8, 64, 0])
+        .chain(&dst_prime)
+        .finalize();
+    let b1 = Sha512::new().chain(&b0).chain(&[1u8]).chain(&dst_prime).finalize();
+    let mut uniform = [0; 64];
+    uniform.copy_from_slice(&b1);
+    uniform
+}
+
+/// HashToGroup, rejecting inputs that hash to the identity
+fn hash_to_group(input: &[u8]) -> ::Result<RistrettoPoint> {
+    let dst = [&b"HashToGroup-"[..], CONTEXT_STRING].concat();
+    let element = RistrettoPoint::from_uniform_bytes(&expand_message_xmd(input, &dst));
+    if element == RistrettoPoint::identity() {
+        return Err(VoprfInputInvalid);
+    }
+    Ok(element)
+}
+
+/// HashToScalar, with the provided DST
+fn hash_to_scalar(input: &[u8], dst: &[u8]) -> Scalar {
+    Scalar::from_bytes_mod_order_wide(&expand_message_xmd(input, dst))
+}
+
+/// HashToScalar, with the default DST of the ciphersuite
+fn hash_to_scalar_default(input: &[u8]) -> Scalar {
+    hash_to_scalar(input, &[&b"HashToScalar-"[..], CONTEXT_STRING].concat())
+}
+
+/// DeserializeElement, rejecting malformed points and the identity
+fn deserialize(bytes: &[u8; 32]) -> ::Result<RistrettoPoint> {
+    match CompressedRistretto(*bytes).decompress() {
+        Some(element) if element != RistrettoPoint::identity() => Ok(element),
+        _ => Err(VoprfInputInvalid),
+    }
+}
+
+/// Computes the Finalize hash of the input and unblinded element
+fn finalize_hash(input: &[u8], element: &RistrettoPoint) -> [u8; OUTPUT_LEN] {
+    let output = Sha512::new()
+        .chain(&len_prefix(input))
+        .chain(&len_prefix(element.compress().as_bytes()))
+        .chain(b"Finalize")
+        .finalize();
+    let mut arr = [0; OUTPUT_LEN];
+    arr.copy_from_slice(&output);
+    arr
+}
+
+/// ComputeComposites, or ComputeCompositesFast when the private key is
+/// provided
+fn composites(
+    pk: &RistrettoPoint,
+    c: &[RistrettoPoint],
+    d: &[RistrettoPoint],
+    sk: Option<Scalar>,
+) -> (RistrettoPoint, RistrettoPoint) {
+    let seed_dst = [&b"Seed-"[..], CONTEXT_STRING].concat();
+    let seed = Sha512::new()
+        .chain(&len_prefix(pk.compress().as_bytes()))
+        .chain(&len_prefix(&seed_dst))
+        .finalize();
+    let mut m = RistrettoPoint::identity();
+    let mut z = RistrettoPoint::identity();
+    for (i, (ci, di)) in c.iter().zip(d).enumerate() {
+        let mut transcript = len_prefix(&seed);
+        transcript.extend_from_slice(&(i as u16).to_be_bytes());
+        transcript.extend_from_slice(&len_prefix(ci.compress().as_bytes()));
+        transcript.extend_from_slice(&len_prefix(di.compress().as_bytes()));
+        transcript.extend_from_slice(b"Composite");
+        let weight = hash_to_scalar_default(&transcript);
+        m += weight * ci;
+        z += weight * di;
+    }
+    match sk {
+        Some(sk) => (m, sk * m),
+        None => (m, z),
+    }
+}
+
+/// Computes the DLEQ challenge over the public key, composites and
+/// commitments
+fn challenge(
+    pk: &RistrettoPoint,
+    m: &RistrettoPoint,
+    z: &RistrettoPoint,
+    t2: &RistrettoPoint,
+    t3: &RistrettoPoint,
+) -> Scalar {
+    let mut transcript = Vec::new();
+    for element in &[pk, m, z, t2, t3] {
+        transcript.extend_from_slice(&len_prefix(element.compress().as_bytes()));
+    }
+    transcript.extend_from_slice(b"Challenge");
+    hash_to_scalar_default(&transcript)
+}
+
+/// GenerateProof, proving that log_G(pk) == log_M(Z)
+fn generate_proof(
+    sk: Scalar,
+    pk: &RistrettoPoint,
+    m: &RistrettoPoint,
+    z: &RistrettoPoint,
+    r: Scalar,
+) -> [u8; 64] {
+    let t2 = r * RISTRETTO_BASEPOINT_POINT;
+    let t3 = r * m;
+    let c = challenge(pk, m, z, &t2, &t3);
+    let s = r - c * sk;
+    let mut proof = [0; 64];
+    proof[0..32].copy_from_slice(c.as_bytes());
+    proof[32..64].copy_from_slice(s.as_bytes());
+    proof
+}
+
+/// VerifyProof, erroring if the proof scalars are malformed
+fn verify_proof(
+    pk: &RistrettoPoint,
+    m: &RistrettoPoint,
+    z: &RistrettoPoint,
+    proof: &[u8; 64],
+) -> ::Result<bool> {
+    let mut c = [0; 32];
+    let mut s = [0; 32];
+    c.copy_from_slice(&proof[0..32]);
+    s.copy_from_slice(&proof[32..64]);
+    let c = Scalar::from_canonical_bytes(c).ok_or(WiredScalarMalformed)?;
+    let s = Scalar::from_canonical_bytes(s).ok_or(WiredScalarMalformed)?;
+    let t2 = s * RISTRETTO_BASEPOINT_POINT + c * pk;
+    let t3 = s * m + c * z;
+    Ok(challenge(pk, m, z, &t2, &t3) == c)
+}
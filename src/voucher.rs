@@ -0,0 +1,259 @@
+//! Blind signed vouchers with public terms
+//!
+//! A voucher carries public terms, a campaign id and a value, which the
+//! issuer sees and binds into the signature as the public info of a partially
+//! blind signature, while the redemption id, a random value chosen by the
+//! requester, stays blind. The issuer can so enforce the terms of each
+//! campaign at issuance, without being able to link a redeemed voucher to its
+//! issuance.
+//!
+//! 1. The issuer starts a VoucherSession for the terms, sending the
+//!    commitment and the terms to the requester.
+//! 2. The requester blinds the commitment with VoucherRequest::new.
+//! 3. The issuer signs the challenge with VoucherSession::sign_e.
+//! 4. The requester unblinds the response into a Voucher, which is redeemed
+//!    with Voucher::redeem.
+//!
+//! # Note
+//!
+//! Redemption does not detect vouchers redeemed twice; the redemption id is
+//! unique per voucher and can be recorded for that, for example as a
+//! Nullifier. As with the plain protocol, this **does not** include **any**
+//! networking code.
+use digest::Digest;
+use envelope::PayloadType;
+use keypair::{SigningKey, VerifyingKey};
+use partially_blind::{PartialRequest, PartialSession, PartialSignature, WiredPartialSignature};
+use rand::RngCore;
+use rng;
+use typenum::U64;
+use Error::{VoucherCampaignMismatch, VoucherInvalid};
+
+/// The domain separation prefix of the terms info
+const TERMS_DOMAIN: &[u8] = b"blindsign voucher terms";
+
+/// The public terms of a voucher
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VoucherTerms {
+    campaign: u32,
+    value: u64,
+}
+
+impl VoucherTerms {
+    /// Creates the terms of a voucher of the campaign with the value
+    pub fn new(campaign: u32, value: u64) -> Self {
+        VoucherTerms { campaign, value }
+    }
+
+    /// Returns the campaign id
+    pub fn campaign(&self) -> u32 {
+        self.campaign
+    }
+
+    /// Returns the value
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// Returns the terms in wired form, campaign || value, with the integers
+    /// being big endian
+    pub fn wired(&self) -> [u8; 12] {
+        let mut arr = [0; 12];
+        arr[0..4].copy_from_slice(&self.campaign.to_be_bytes());
+        arr[4..12].copy_from_slice(&self.value.to_be_bytes());
+        arr
+    }
+
+    /// Loads the terms from their wired form
+    pub fn from_wired(wired: &[u8; 12]) -> Self {
+        let mut campaign = [0; 4];
+        campaign.copy_from_slice(&wired[0..4]);
+        let mut value = [0; 8];
+        value.copy_from_slice(&wired[4..12]);
+        VoucherTerms {
+            campaign: u32::from_be_bytes(campaign),
+            value: u64::from_be_bytes(value),
+        }
+    }
+
+    /// Encodes the terms as the public info of the partially blind signature
+    fn info(&self) -> Vec<u8> {
+        let mut info = TERMS_DOMAIN.to_vec();
+        info.extend_from_slice(&self.wired());
+        info
+    }
+}
+
+/// For managing the issuer side of issuing a voucher.
+pub struct VoucherSession {
+    session: PartialSession,
+}
+
+impl VoucherSession {
+    /// Initiate a new issuer side session for a voucher with the provided
+    /// terms, which the issuer has checked are allowed for the requester.
+    ///
+    /// # Type Parameters
+    ///
+    /// H is the hash algorithm used for hashing the terms to a point
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 64], VoucherSession)) on success, with the [u8; 64] being
+    /// the commitment for sending to the requester along with the terms.
+    ///
+    /// * Err(::Error) on failure, which is only due to the failure to
+    /// initiate the internal random number generator.
+    pub fn new<H>(terms: &VoucherTerms) -> ::Result<([u8; 64], Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let (commitment, session) = PartialSession::new::<H>(&terms.info())?;
+        Ok((commitment, VoucherSession { session }))
+    }
+
+    /// Signs the challenge e from the requester, consuming the session.
+    ///
+    /// # Returns
+    ///
+    /// * Ok([u8; 128]) on success, with the response being sent to the
+    /// requester.
+    ///
+    /// * Err(::Error) on failure, which indicates e was malformed.
+    pub fn sign_e(self, e: &[u8; 32], xs: &SigningKey) -> ::Result<[u8; 128]> {
+        self.session.sign_e(e, xs)
+    }
+}
+
+/// For managing the requester side of issuing a voucher.
+pub struct VoucherRequest {
+    terms: VoucherTerms,
+    id: [u8; 32],
+    request: PartialRequest,
+}
+
+impl VoucherRequest {
+    /// Blinds the commitment received from the issuer for the terms it sent
+    /// along with it, and a fresh redemption id.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(([u8; 32], VoucherRequest)) on success, with the [u8; 32] being
+    /// the challenge e for sending to the issuer.
+    ///
+    /// * Err(::Error) on failure, which could be caused by the failure to
+    /// initiate the RNG, or otherwise being input a malformed commitment.
+    pub fn new<H>(
+        commitment: &[u8; 64],
+        pub_key: &VerifyingKey,
+        terms: VoucherTerms,
+    ) -> ::Result<([u8; 32], Self)>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        let mut id = [0; 32];
+        rng::try_new()?.fill_bytes(&mut id);
+        let (e, request) =
+            PartialRequest::new::<H, &[u8]>(commitment, pub_key, &terms.info(), &id)?;
+        Ok((e, VoucherRequest { terms, id, request }))
+    }
+
+    /// Input the response from the issuer, consumes self and creates the
+    /// voucher. This does not verify the signature.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Voucher) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates that the response contained
+    /// a malformed scalar.
+    pub fn gen_signed_voucher(self, response: &[u8; 128]) -> ::Result<Voucher> {
+        Ok(Voucher {
+            terms: self.terms,
+            id: self.id,
+            sig: self.request.gen_signed_msg(response)?,
+        })
+    }
+}
+
+/// A voucher, a partially blind signature on a redemption id with the terms
+/// bound into it.
+#[derive(Copy, Clone, Debug)]
+pub struct Voucher {
+    terms: VoucherTerms,
+    id: [u8; 32],
+    sig: PartialSignature,
+}
+
+impl Voucher {
+    /// Returns the terms of the voucher
+    pub fn terms(&self) -> &VoucherTerms {
+        &self.terms
+    }
+
+    /// Returns the redemption id of the voucher
+    pub fn id(&self) -> [u8; 32] {
+        self.id
+    }
+
+    /// Verifies the voucher for redemption in the provided campaign.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(u64) on success, with the u64 being the value of the voucher.
+    ///
+    /// * Err(::Error) on failure, which indicates that the voucher is for a
+    /// different campaign, or that the signature was not valid on its terms.
+    pub fn redeem<H>(&self, pub_key: &VerifyingKey, campaign: u32) -> ::Result<u64>
+    where
+        H: Digest<OutputSize = U64> + Default,
+    {
+        if self.terms.campaign != campaign {
+            return Err(VoucherCampaignMismatch);
+        }
+        if !self
+            .sig
+            .authenticate::<H, &[u8]>(pub_key, &self.terms.info(), &self.id)
+        {
+            return Err(VoucherInvalid);
+        }
+        Ok(self.terms.value)
+    }
+}
+
+/// The Voucher in wired form, campaign || value || id || ρ || ω || σ || δ
+pub struct WiredVoucher(pub [u8; 172]);
+
+impl From<Voucher> for WiredVoucher {
+    fn from(voucher: Voucher) -> Self {
+        let mut arr = [0; 172];
+        arr[0..12].copy_from_slice(&voucher.terms.wired());
+        arr[12..44].copy_from_slice(&voucher.id);
+        arr[44..172].copy_from_slice(&WiredPartialSignature::from(voucher.sig).0);
+        WiredVoucher(arr)
+    }
+}
+
+impl WiredVoucher {
+    /// Converts the wired form into the internal Voucher.
+    ///
+    /// # Returns
+    ///
+    /// * Ok(Voucher) on success.
+    ///
+    /// * Err(::Error) on failure, which indicates a malformed scalar.
+    pub fn to_internal_format(&self) -> ::Result<Voucher> {
+        let mut terms = [0; 12];
+        terms.copy_from_slice(&self.0[0..12]);
+        let mut id = [0; 32];
+        id.copy_from_slice(&self.0[12..44]);
+        Ok(Voucher {
+            terms: VoucherTerms::from_wired(&terms),
+            id,
+            sig: WiredPartialSignature::from_bytes(&self.0[44..172])?.to_internal_format()?,
+        })
+    }
+}
+
+impl_wired_bytes!(WiredVoucher, 172);
+impl_wired_envelope!(WiredVoucher, PayloadType::Voucher);
@@ -0,0 +1,102 @@
+//! WebAssembly bindings for the requester and verification sides
+//!
+//! These wrap BlindRequest and UnblindedSigData with wasm-bindgen, so that a
+//! browser client can blind a message, unblind the signature received from
+//! the signer, and authenticate signatures, without a second implementation
+//! of the protocol. Every wired value crosses the boundary as a Uint8Array,
+//! and errors are thrown as strings. Keypair generation is included for
+//! testing and for signers running in JavaScript. The hash algorithm is
+//! always SHA3-512.
+//!
+//! # Note
+//!
+//! On wasm32 targets the internal RNG is backed by crypto.getRandomValues,
+//! through the wasm-bindgen support of rand.
+//!
+//! As with the plain protocol, this **does not** include **any** networking
+//! code.
+use keypair::{BlindKeypair, VerifyingKey, WiredPublicKey};
+use request::{BlindRequest, WiredEPrime};
+use session::{WiredRPrime, WiredSPrime};
+use sha3::Sha3_512;
+use signature::WiredUnblindedSigData;
+use wasm_bindgen::prelude::*;
+
+/// The requester side of a blind signature on a message
+#[wasm_bindgen(js_name = BlindRequest)]
+pub struct WasmRequest {
+    inner: BlindRequest,
+    ep: WiredEPrime,
+}
+
+#[wasm_bindgen(js_class = BlindRequest)]
+impl WasmRequest {
+    /// Blinds the message with the 32 byte R' from the signer.
+    #[wasm_bindgen(constructor)]
+    pub fn new(rp: &[u8], msg: &[u8]) -> Result<WasmRequest, JsValue> {
+        let rp = WiredRPrime::from_bytes(rp).map_err(to_js)?;
+        let (ep, inner) =
+            BlindRequest::new_specific_msg::<Sha3_512, &[u8]>(&rp, msg).map_err(to_js)?;
+        Ok(WasmRequest { inner, ep })
+    }
+
+    /// Returns the 32 byte e', for sending to the signer
+    pub fn ep(&self) -> Vec<u8> {
+        self.ep.as_bytes().to_vec()
+    }
+
+    /// Unblinds the 32 byte S' from the signer, returning the 96 byte wired
+    /// signature. The request is consumed. As with
+    /// BlindRequest::gen_signed_msg, the signature is not verified.
+    pub fn unblind(self, sp: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let sp = WiredSPrime::from_bytes(sp).map_err(to_js)?;
+        let sig = self.inner.gen_signed_msg(&sp);
+        Ok(WiredUnblindedSigData::from(sig).0.to_vec())
+    }
+}
+
+/// A signer keypair
+#[wasm_bindgen(js_name = BlindKeypair)]
+pub struct WasmKeypair {
+    inner: BlindKeypair,
+}
+
+#[wasm_bindgen(js_class = BlindKeypair)]
+impl WasmKeypair {
+    /// Generates a new keypair
+    pub fn generate() -> Result<WasmKeypair, JsValue> {
+        Ok(WasmKeypair {
+            inner: BlindKeypair::generate().map_err(to_js)?,
+        })
+    }
+
+    /// Returns the 32 byte wired public key
+    pub fn public(&self) -> Vec<u8> {
+        self.inner.verifying_key().wired().to_vec()
+    }
+
+    /// Returns the 32 byte wired private key
+    pub fn private(&self) -> Vec<u8> {
+        self.inner.private_wired().as_bytes().to_vec()
+    }
+}
+
+/// Authenticates the 96 byte wired signature on the message under the 32
+/// byte wired public key, throwing if either is malformed.
+#[wasm_bindgen]
+pub fn authenticate(public: &[u8], sig: &[u8], msg: &[u8]) -> Result<bool, JsValue> {
+    let pub_key = WiredPublicKey::from_bytes(public)
+        .and_then(VerifyingKey::from_wired)
+        .map_err(to_js)?;
+    let sig = WiredUnblindedSigData::from_bytes(sig)
+        .and_then(|sig| sig.to_internal_format())
+        .map_err(to_js)?;
+    Ok(sig.msg_authenticate::<Sha3_512, &[u8]>(&pub_key, msg))
+}
+
+// Implementation internal functions, not exposed to crate users -->
+
+/// Converts an error to the string thrown to JavaScript
+fn to_js(e: ::Error) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
@@ -0,0 +1,33 @@
+//! Wiping of secrets from memory
+//!
+//! The writes are volatile and followed by a compiler fence, so that they are
+//! not removed as dead stores when the wiped value is about to be dropped.
+use curve25519_dalek::scalar::Scalar;
+use std::ptr;
+use std::sync::atomic::{self, Ordering};
+
+/// Overwrites the scalar with zero
+pub(crate) fn wipe_scalar(scalar: &mut Scalar) {
+    // Safe as the pointer comes from a mutable reference, and Scalar is Copy
+    // so has no drop glue to skip.
+    unsafe { ptr::write_volatile(scalar, Scalar::zero()) };
+    atomic::compiler_fence(Ordering::SeqCst);
+}
+
+/// Overwrites the BLS12-381 scalar with zero
+#[cfg(feature = "ibs")]
+pub(crate) fn wipe_bls_scalar(scalar: &mut ::bls12_381::Scalar) {
+    // Safe as the pointer comes from a mutable reference, and Scalar is Copy
+    // so has no drop glue to skip.
+    unsafe { ptr::write_volatile(scalar, ::bls12_381::Scalar::zero()) };
+    atomic::compiler_fence(Ordering::SeqCst);
+}
+
+/// Overwrites the bytes with zeroes
+pub(crate) fn wipe_bytes(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        // Safe as the pointer comes from a mutable reference
+        unsafe { ptr::write_volatile(byte, 0) };
+    }
+    atomic::compiler_fence(Ordering::SeqCst);
+}